@@ -0,0 +1,14 @@
+fn main() {
+    // Without protoc, the proto is compiled with protox
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto");
+        let descriptors = match protox::compile(["c2pa_analyzer/v1/analyzer.proto"], ["proto"]) {
+            Ok(d) => d,
+            Err(e) => panic!("Error compiling the proto: {}", e)
+        };
+        if let Err(e) = tonic_build::configure().build_client(false).compile_fds(descriptors) {
+            panic!("Error generating the gRPC code: {}", e);
+        }
+    }
+}
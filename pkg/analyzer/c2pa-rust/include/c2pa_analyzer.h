@@ -0,0 +1,38 @@
+/*
+ * C ABI of the c2pa-rust analyzer, built with the ffi feature:
+ *
+ *   cargo rustc --release --lib --features ffi --crate-type staticlib
+ *
+ * Reports are returned as NUL-terminated JSON owned by the caller. Give
+ * them back with c2pa_free_string, not free. The functions are safe to
+ * call from several threads at once.
+ */
+
+#ifndef C2PA_ANALYZER_H
+#define C2PA_ANALYZER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Report JSON of the file at path, NULL if path is NULL or not UTF-8. */
+char *c2pa_analyze_file(const char *path);
+
+/*
+ * Report JSON of len bytes at data. format is a file name whose extension
+ * tells the format, ex. "upload.jpg", or an extension or MIME type, ex.
+ * "png" or "image/png". NULL if data or format is NULL.
+ */
+char *c2pa_analyze_bytes(const uint8_t *data, size_t len, const char *format);
+
+/* Releases a string returned above, NULL is ignored. */
+void c2pa_free_string(char *json);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,113 @@
+use c2pa::Manifest;
+use serde::Serialize;
+use serde_json::Value;
+
+pub(crate) const SOURCE_TYPE_PREFIX: &str = "http://cv.iptc.org/newscodes/digitalsourcetype/";
+
+/// Digital source types declaring the whole asset was made by an
+/// algorithm.
+pub(crate) const GENERATED_SOURCE_TYPES: [&str; 2] = ["trainedAlgorithmicMedia", "algorithmicMedia"];
+/// Digital source types declaring generated parts in otherwise captured
+/// content.
+pub(crate) const COMPOSITE_SOURCE_TYPES: [&str; 3] = [
+    "compositeSynthetic",
+    "compositeWithTrainedAlgorithmicMedia",
+    "algorithmicallyEnhanced"
+];
+/// Actions that change the content, as opposed to ex. `c2pa.opened` or
+/// `c2pa.published`.
+const EDIT_ACTIONS: [&str; 8] = [
+    "c2pa.edited",
+    "c2pa.color_adjustments",
+    "c2pa.cropped",
+    "c2pa.drawing",
+    "c2pa.filtered",
+    "c2pa.orientation",
+    "c2pa.resized",
+    "c2pa.removed"
+];
+
+/// An entry of a `c2pa.actions` assertion.
+#[derive(Serialize, Clone)]
+#[non_exhaustive]
+pub struct ActionData {
+    pub action: String,
+    /// Last path segment of the IPTC digital source type URI, ex.
+    /// `trainedAlgorithmicMedia`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digital_source_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub software_agent: Option<String>
+}
+
+impl ActionData {
+    /// Actions of every `c2pa.actions` assertion of a manifest, v1 and v2.
+    pub fn vec_from_manifest(manifest: &Manifest) -> Vec<ActionData> {
+        let mut actions = Vec::new();
+        for assertion in manifest.assertions() {
+            if !assertion.label().starts_with("c2pa.actions") {
+                continue;
+            }
+            let value = match assertion.value() {
+                Ok(v) => v,
+                Err(_) => continue
+            };
+            if let Some(list) = value.get("actions").and_then(|a| a.as_array()) {
+                actions.extend(list.iter().filter_map(ActionData::from_value));
+            }
+        }
+        actions
+    }
+
+    fn from_value(value: &Value) -> Option<ActionData> {
+        let action = value.get("action")?.as_str()?.to_string();
+        let digital_source_type = value.get("digitalSourceType")
+            .and_then(|t| t.as_str())
+            .map(|t| t.strip_prefix(SOURCE_TYPE_PREFIX).unwrap_or(t).to_string());
+        // v1 has the agent as a string, v2 as a claim generator info object.
+        let software_agent = match value.get("softwareAgent") {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(agent) => agent.get("name").and_then(|n| n.as_str()).map(|n| n.to_string()),
+            None => None
+        };
+        Some(ActionData { action, digital_source_type, software_agent })
+    }
+
+    pub fn declares_generated(&self) -> bool {
+        self.action == "c2pa.created" && self.digital_source_type.as_deref()
+            .is_some_and(|t| GENERATED_SOURCE_TYPES.contains(&t))
+    }
+
+    pub fn declares_composite(&self) -> bool {
+        self.digital_source_type.as_deref().is_some_and(|t| COMPOSITE_SOURCE_TYPES.contains(&t))
+    }
+
+    pub fn is_edit(&self) -> bool {
+        EDIT_ACTIONS.contains(&self.action.as_str())
+    }
+}
+
+/// What the actions of all claims declare about how the asset was made.
+#[derive(Serialize, Default)]
+#[non_exhaustive]
+pub struct ActionSummary {
+    /// A claim declares algorithmic creation, which is conclusive whatever
+    /// the generator is called.
+    pub declared_generated: bool,
+    /// A claim declares generated content composited into the asset.
+    pub declared_composite: bool,
+    /// A claim records edits of the content.
+    pub edited: bool
+}
+
+impl ActionSummary {
+    pub fn from_actions<'a>(actions: impl Iterator<Item = &'a ActionData>) -> ActionSummary {
+        let mut summary = ActionSummary::default();
+        for action in actions {
+            summary.declared_generated |= action.declares_generated();
+            summary.declared_composite |= action.declares_composite();
+            summary.edited |= action.is_edit();
+        }
+        summary
+    }
+}
@@ -0,0 +1,541 @@
+#[cfg(feature = "fs")]
+use std::{fs, path::Path};
+use std::{io::Cursor, sync::Arc};
+use c2pa::{Reader, ValidationState};
+#[cfg(feature = "fs")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "pixel")]
+use image::DynamicImage;
+use serde::Serialize;
+
+#[cfg(feature = "pdf")]
+use crate::pdf::PdfData;
+#[cfg(feature = "pixel")]
+use crate::{ela::ElaFindings, noise::NoiseFindings, spectrum::SpectrumFindings, thumbnails::ThumbnailMatch, watermark::WatermarkFindings};
+use crate::{actions::ActionSummary, audio::AudioData, bmp::BmpData, consistency::ConsistencyFindings, error::{AnalyzeError, ReportError, ReportErrorKind}, evidence::Evidence, exif::ExifData, explanation::Explanation, gif::GifData, hashbinding::HashMismatch, jpeg::JpegFindings, media::MediaData, options::AnalysisOptions, pngtext::PngTextFindings, quanttables::QuantTableFindings, remote::RemoteManifest, report::{FileData, ManifestData}, revocation::{RevocationCheck, RevocationStatus}, rules::{RuleKind, RuleMatch}, scoring::Weight, signature::SignatureData, sourcetype::SourceTypeFindings, stripped::StrippedProvenance, svg::SvgData, thumbnails::{write_thumbnails, ThumbnailFile}, timings::Timings, trust::TrustConfig, xmp::XmpFindings};
+
+/// A source of findings run on every file. Implementations are added to
+/// [`Analyzers`], the report merges the outputs of all of them.
+pub trait Analyzer: Send + Sync {
+    /// Name reported with the analyzer's results, ex. `c2pa`.
+    fn name(&self) -> &str;
+    fn analyze(&self, input: &AnalysisInput) -> AnalyzerOutput;
+}
+
+/// The file as every analyzer sees it.
+#[non_exhaustive]
+pub struct AnalysisInput<'a> {
+    /// Name of the report, ex. `upload.jpg` or `stdin`.
+    pub file_name: &'a str,
+    /// Extension or MIME type the manifest reader takes, None when the
+    /// format is unknown.
+    pub format: Option<&'a str>,
+    pub bytes: &'a [u8],
+    /// Set when the file was read by path.
+    #[cfg(feature = "fs")]
+    pub path: Option<&'a Path>,
+    /// A detached manifest store read instead of the one in `bytes`, ex.
+    /// from `image.jpg.c2pa`.
+    pub sidecar: Option<&'a [u8]>,
+    /// The decoded pixels, None when they can't be decoded.
+    #[cfg(feature = "pixel")]
+    pub image: Option<&'a DynamicImage>,
+    /// Signature of the active manifest, once an analyzer before this one
+    /// read it, ex. `C2paAnalyzer`.
+    pub signature: Option<&'a SignatureData>,
+    /// EXIF of the file, once an analyzer before this one read it, ex.
+    /// `MetadataAnalyzer`.
+    pub exif: Option<&'a ExifData>,
+    pub options: &'a AnalysisOptions
+}
+
+/// What an analyzer found.
+#[derive(Default)]
+#[non_exhaustive]
+pub struct AnalyzerOutput {
+    /// Findings that move the score, ones adding nothing are left out.
+    pub evidence: Vec<Evidence>,
+    /// Listed with the analyzer in the report's `analyzers` section.
+    pub details: Option<serde_json::Value>,
+    /// Listed in the report's `errors`.
+    pub errors: Vec<ReportError>,
+    /// The analyzer's own stages, listed after it in the report's
+    /// `timings`.
+    pub timings: Timings,
+    pub(crate) c2pa: Option<C2paFindings>,
+    pub(crate) file_data: FileData
+}
+
+impl AnalyzerOutput {
+    pub fn add(&mut self, source: &str, reason: impl Into<String>, (score, confidence): (u8, u8)) {
+        if score != 0 || confidence != 0 {
+            self.evidence.push(Evidence::new(source, reason.into(), score, confidence));
+        }
+    }
+
+    /// Like `add`, with a finding the explanation can be written from in
+    /// any locale.
+    pub fn explain(&mut self, source: &str, finding: Explanation, (score, confidence): (u8, u8)) {
+        if score != 0 || confidence != 0 {
+            self.evidence.push(Evidence::explained(source, finding, score, confidence));
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> AnalyzerOutput {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// What one analyzer contributed to a report.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct AnalyzerResult {
+    pub name: String,
+    /// Sum of the analyzer's evidence, 0-100.
+    pub score: u8,
+    pub confidence: u8,
+    /// Weight in percent of the analyzer's vote.
+    pub weight: u8,
+    /// The analyzer had too little confidence, or no weight, to vote.
+    pub abstained: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>
+}
+
+impl AnalyzerResult {
+    pub(crate) fn new(name: &str, output: &AnalyzerOutput) -> AnalyzerResult {
+        let sum = |part: fn(&Evidence) -> u8| output.evidence.iter().fold(0_u8, |sum, e| sum.saturating_add(part(e))).min(100);
+        AnalyzerResult {
+            name: name.to_string(),
+            score: sum(|e| e.score),
+            confidence: sum(|e| e.confidence),
+            weight: 100,
+            abstained: false,
+            details: output.details.clone()
+        }
+    }
+}
+
+/// The analyzers run on every file, in order, the built-in ones by
+/// default.
+#[derive(Clone)]
+pub struct Analyzers {
+    analyzers: Vec<Arc<dyn Analyzer>>
+}
+
+impl Analyzers {
+    pub fn empty() -> Analyzers {
+        Analyzers { analyzers: Vec::new() }
+    }
+
+    pub fn with_analyzer(mut self, analyzer: Arc<dyn Analyzer>) -> Analyzers {
+        self.analyzers.push(analyzer);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Analyzer> {
+        self.analyzers.iter().map(|a| a.as_ref())
+    }
+}
+
+impl Default for Analyzers {
+    fn default() -> Analyzers {
+        let analyzers = Analyzers::empty()
+            .with_analyzer(Arc::new(C2paAnalyzer))
+            .with_analyzer(Arc::new(MetadataAnalyzer))
+            .with_analyzer(Arc::new(ConsistencyAnalyzer));
+        #[cfg(feature = "pixel")]
+        let analyzers = analyzers
+            .with_analyzer(Arc::new(PixelAnalyzer))
+            .with_analyzer(Arc::new(WatermarkAnalyzer));
+        analyzers
+    }
+}
+
+/// The manifest store as scored.
+pub(crate) struct C2paFindings {
+    pub data: ManifestData,
+    pub generator_matches: Vec<RuleMatch>,
+    pub actions: ActionSummary,
+    pub thumbnails: Vec<ThumbnailFile>,
+    pub provenance_stripped: Option<StrippedProvenance>
+}
+
+/// Claims, actions and validation of the C2PA manifest store.
+pub struct C2paAnalyzer;
+
+impl Analyzer for C2paAnalyzer {
+    fn name(&self) -> &str {
+        "c2pa"
+    }
+
+    fn analyze(&self, input: &AnalysisInput) -> AnalyzerOutput {
+        let options = input.options;
+        let scoring = &options.scoring;
+        let mut output = AnalyzerOutput::default();
+        let read = match input.format {
+            Some("bmp" | "image/bmp" | "image/x-ms-bmp") => Err(ReportError::new(ReportErrorKind::JumbfNotFound, "BMP files can't hold a C2PA manifest store")),
+            Some(format) => read_c2pa(format, input.bytes, input.sidecar, options, &mut output.timings).map_err(ReportError::from),
+            None => Err(AnalyzeError::UnsupportedFormat(input.file_name.to_string()).into())
+        };
+        let ManifestData { claims, identities, mut validation, active_label, mut ingredients, thumbnails, extracted_assertions, raw, remote } = match read {
+            Ok(data) => data,
+            Err(e) => {
+                output.errors.push(e);
+                ManifestData::empty()
+            }
+        };
+        if let Some(format) = input.format {
+            validation.hash_mismatches = output.timings.time("hash_binding", || HashMismatch::vec_from_certs(&validation.certs, format, input.bytes, input.sidecar));
+        }
+        #[cfg(feature = "pixel")]
+        if let Some(image) = input.image {
+            let claim_thumbnail = thumbnails.iter().find(|t| t.ingredient.is_none() && Some(&t.claim_id) == active_label.as_ref());
+            validation.thumbnail_match = claim_thumbnail.and_then(|t| output.timings.time("thumbnail_match", || ThumbnailMatch::new(t, image)));
+        }
+        let thumbnails = match &options.thumbnail_dir {
+            Some(dir) => write_thumbnails(input.file_name, &thumbnails, dir),
+            None => Vec::new()
+        };
+        let provenance_stripped = match (claims.is_empty(), &remote) {
+            (true, None) => output.timings.time("provenance_stripped", || StrippedProvenance::from_bytes(input.bytes, &options.rules)),
+            _ => None
+        };
+        if let Some(stripped) = &provenance_stripped {
+            let kinds = stripped.signals.iter().map(|s| s.kind).collect();
+            let confidence = (scoring.provenance_stripped.confidence as u16 * stripped.confidence as u16 / 100) as u8;
+            output.explain("provenance", Explanation::ProvenanceStripped(kinds), (scoring.provenance_stripped.score, confidence));
+        }
+        let generator_matches = options.rules.match_claims(&claims);
+        let active_generators = claims.iter()
+            .find(|c| Some(&c.claim_id) == active_label.as_ref())
+            .map(|c| c.claim_generator.clone())
+            .unwrap_or_default();
+        ingredients.score(&options.rules, &active_generators);
+        let actions = ActionSummary::from_actions(claims.iter().flat_map(|c| c.actions.iter()));
+        if !claims.is_empty() {
+            output.explain("claims", Explanation::ClaimsFound(claims.len()), weight(scoring.claims_found));
+            for m in &generator_matches {
+                output.explain("claims", Explanation::ClaimGenerator(m.rule.clone(), m.generator.clone(), m.kind), (m.score, m.confidence));
+            }
+            if actions.declared_generated {
+                output.explain("actions", Explanation::DeclaredGenerated, weight(scoring.declared_generated));
+            } else if actions.declared_composite {
+                output.explain("actions", Explanation::DeclaredComposite, weight(scoring.declared_composite));
+            } else if actions.edited {
+                output.explain("actions", Explanation::DeclaredEdits, weight(scoring.edited));
+            }
+            if ingredients.analyzed_generated_count != 0 {
+                output.explain("ingredients", Explanation::IngredientsGenerated(ingredients.analyzed_generated_count), weight(scoring.ingredient_generated));
+            }
+            for claim in claims.iter().filter(|c| c.redacts_actions()) {
+                output.explain("claims", Explanation::ActionsRedacted(claim.claim_id.clone()), weight(scoring.actions_redacted));
+            }
+        }
+        if validation.certs_count != 0 {
+            output.explain("validation", Explanation::Signed, weight(scoring.signed));
+            let revoked = validation.revocation.as_ref().is_some_and(|r| r.status == RevocationStatus::Revoked);
+            let state = match validation.state {
+                _ if revoked => scoring.signature_invalid,
+                ValidationState::Valid => scoring.signature_valid,
+                ValidationState::Trusted => scoring.signature_trusted,
+                ValidationState::Invalid => scoring.signature_invalid
+            };
+            output.explain("validation", Explanation::Validation(validation.state), weight(state));
+            if revoked {
+                output.explain("validation", Explanation::Revoked, weight(scoring.signature_revoked));
+            }
+            for claim in &claims {
+                if let Some(weakness) = claim.signature.as_ref().and_then(|s| s.weaknesses.first()) {
+                    output.explain("validation", Explanation::WeakSignature(claim.claim_id.clone(), weakness.clone()), weight(scoring.weak_signature));
+                }
+            }
+            if let Some(mismatch) = validation.hash_mismatches.first() {
+                output.explain("validation", Explanation::ContentAltered(mismatch.assertion.clone()), weight(scoring.content_altered));
+            }
+        }
+        #[cfg(feature = "pixel")]
+        if let Some(thumbnail) = validation.thumbnail_match.as_ref().filter(|t| t.mismatch) {
+            output.explain("validation", Explanation::ThumbnailMismatch(thumbnail.distance), weight(scoring.thumbnail_mismatch));
+        }
+        if let Some(signer) = validation.signer(&options.rules) {
+            output.explain("validation", Explanation::Signer(signer.name.clone()), (signer.score, signer.confidence));
+        }
+        output.c2pa = Some(C2paFindings {
+            data: ManifestData { claims, identities, validation, active_label, ingredients, thumbnails: Vec::new(), extracted_assertions, raw, remote },
+            generator_matches,
+            actions,
+            thumbnails,
+            provenance_stripped
+        });
+        output
+    }
+}
+
+/// EXIF, XMP, PNG text, JPEG segments and quantization tables, and the
+/// container metadata of video, audio, GIF, BMP, SVG and PDF files.
+pub struct MetadataAnalyzer;
+
+impl Analyzer for MetadataAnalyzer {
+    fn name(&self) -> &str {
+        "metadata"
+    }
+
+    fn analyze(&self, input: &AnalysisInput) -> AnalyzerOutput {
+        let bytes = input.bytes;
+        let rules = &input.options.rules;
+        let mut output = AnalyzerOutput::default();
+        let timings = &mut output.timings;
+        let xmp = timings.time("xmp", || XmpFindings::from_bytes(bytes, rules));
+        let exif = timings.time("exif", || ExifData::from_stream(Cursor::new(bytes), rules));
+        let quant_tables = timings.time("quant_tables", || QuantTableFindings::from_bytes(bytes, exif.as_ref().and_then(|e| e.make.as_deref())));
+        let source_type = timings.time("source_type", || SourceTypeFindings::from_bytes(bytes, xmp.as_ref()));
+        let png_text = timings.time("png_text", || PngTextFindings::from_bytes(bytes, rules));
+        let jpeg = timings.time("jpeg", || JpegFindings::from_bytes(bytes, rules));
+        #[cfg_attr(not(all(feature = "pixel", feature = "fs")), allow(unused_mut))]
+        let mut media = timings.time("media", || MediaData::from_bytes(bytes));
+        let audio = timings.time("audio", || AudioData::from_bytes(bytes, rules));
+        let gif = timings.time("gif", || GifData::from_bytes(bytes, rules));
+        let bmp = timings.time("bmp", || BmpData::from_bytes(bytes));
+        let svg = timings.time("svg", || SvgData::from_bytes(bytes, input.options));
+        #[cfg(feature = "pdf")]
+        let pdf = timings.time("pdf", || PdfData::from_bytes(bytes, input.options));
+
+        if let Some(exif) = &exif {
+            let finding = match (&exif.software, exif.software_rule) {
+                (Some(software), Some(kind)) => Explanation::ExifSoftware(software.clone(), kind),
+                _ => Explanation::ExifTags
+            };
+            output.explain("exif", finding, (exif.score, exif.confidence));
+        }
+        if let Some(xmp) = &xmp {
+            let reasons = xmp.markers.iter().map(|m| m.reason.clone()).collect();
+            output.explain("xmp_findings", Explanation::Xmp(reasons), (xmp.score, xmp.confidence));
+        }
+        if let Some(source_type) = &source_type {
+            let hits = source_type.hits.iter().map(|h| (h.source_type.clone(), h.found_in.clone())).collect();
+            output.explain("digital_source_type", Explanation::SourceType(hits), (source_type.score, source_type.confidence));
+        }
+        if let Some(png_text) = &png_text {
+            let finding = match (&png_text.generator, &png_text.software) {
+                (Some(generator), _) => Explanation::PngGenerator(generator.clone()),
+                (None, Some(software)) => Explanation::PngSoftware(software.clone()),
+                (None, None) => Explanation::PngParameters
+            };
+            output.explain("png_text", finding, (png_text.score, png_text.confidence));
+        }
+        if let Some(jpeg) = &jpeg {
+            let names = jpeg.fingerprints.iter().map(|f| format!("'{}' in {}", f.rule, f.segment)).collect();
+            let kind = match jpeg.fingerprints.iter().any(|f| f.kind == RuleKind::Generated) {
+                true => RuleKind::Generated,
+                false => RuleKind::Modified
+            };
+            output.explain("jpeg_segments", Explanation::JpegSegments(names, kind), (jpeg.score, jpeg.confidence));
+        }
+        if let Some(quant_tables) = &quant_tables {
+            let finding = match (&quant_tables.signature, quant_tables.camera_mismatch) {
+                (_, true) => Explanation::QuantTablesCameraMismatch,
+                (Some(signature), false) => Explanation::QuantTablesSignature(signature.clone()),
+                (None, false) => Explanation::QuantTables
+            };
+            output.explain("quant_tables", finding, (quant_tables.score, quant_tables.confidence));
+        }
+        #[cfg(all(feature = "pixel", feature = "fs"))]
+        if let (Some(media), Some(path), true) = (&mut media, input.path, input.options.frame_samples > 0) {
+            output.timings.time("frames", || media.sample_frames(path, input.options));
+        }
+        if let Some(media) = &media {
+            output.explain("media", Explanation::VideoFrames, (media.score, media.confidence));
+        }
+        if let Some(audio) = &audio {
+            let finding = match (&audio.tool, audio.tool_rule) {
+                (Some(tool), Some(kind)) => Explanation::AudioTag(tool.clone(), kind),
+                _ => Explanation::AudioTags
+            };
+            output.explain("audio", finding, (audio.score, audio.confidence));
+        }
+        if let Some(gif) = &gif {
+            let finding = match (&gif.tool, gif.tool_rule) {
+                (Some(tool), Some(kind)) => Explanation::GifComment(tool.clone(), kind),
+                _ => Explanation::GifComments
+            };
+            output.explain("gif", finding, (gif.score, gif.confidence));
+        }
+        if let Some(svg) = &svg {
+            let tool = svg.tool.clone().zip(svg.tool_rule);
+            let image = svg.images.iter().filter(|r| r.score != 0).max_by_key(|r| (r.score, r.score_confidence));
+            output.explain("svg", Explanation::Svg(tool, image.map(|i| (i.file_name.clone(), i.score))), (svg.score, svg.confidence));
+        }
+        #[cfg(feature = "pdf")]
+        if let Some(pdf) = &pdf {
+            let xmp = pdf.xmp.as_ref().is_some_and(|x| x.score != 0 || x.confidence != 0);
+            let image = pdf.images.iter().filter(|r| r.score != 0).max_by_key(|r| (r.score, r.score_confidence));
+            output.explain("pdf", Explanation::Pdf(pdf.tool_rule, xmp, image.map(|i| (i.file_name.clone(), i.score))), (pdf.score, pdf.confidence));
+        }
+        let file_data = &mut output.file_data;
+        file_data.exif = exif;
+        file_data.xmp = xmp;
+        file_data.source_type = source_type;
+        file_data.png_text = png_text;
+        file_data.jpeg = jpeg;
+        file_data.quant_tables = quant_tables;
+        file_data.media = media;
+        file_data.audio = audio;
+        file_data.gif = gif;
+        file_data.bmp = bmp;
+        file_data.svg = svg;
+        #[cfg(feature = "pdf")]
+        {
+            file_data.pdf = pdf;
+        }
+        output
+    }
+}
+
+/// EXIF capture time against the XMP history, the C2PA signing time and
+/// the file's modification time, and the camera settings against the
+/// camera. Runs after `C2paAnalyzer` and `MetadataAnalyzer` to see their
+/// signing time and EXIF.
+pub struct ConsistencyAnalyzer;
+
+impl Analyzer for ConsistencyAnalyzer {
+    fn name(&self) -> &str {
+        "consistency"
+    }
+
+    fn analyze(&self, input: &AnalysisInput) -> AnalyzerOutput {
+        let mut output = AnalyzerOutput::default();
+        let parsed;
+        let exif = match input.exif {
+            Some(exif) => Some(exif),
+            None => {
+                parsed = ExifData::from_stream(Cursor::new(input.bytes), &input.options.rules);
+                parsed.as_ref()
+            }
+        };
+        #[cfg(feature = "fs")]
+        let modified = input.path.and_then(|p| fs::metadata(p).and_then(|m| m.modified()).ok()).map(DateTime::<Utc>::from);
+        #[cfg(not(feature = "fs"))]
+        let modified = None;
+        let signing_time = input.signature.and_then(|s| s.signing_time.as_deref());
+        let consistency = ConsistencyFindings::from_sources(exif, input.bytes, signing_time, modified, input.options.now, input.options.scoring.metadata_inconsistent);
+        if let Some(consistency) = &consistency {
+            if !consistency.inconsistencies.is_empty() {
+                let kinds = consistency.inconsistencies.iter().map(|i| i.kind).collect();
+                output.explain("consistency", Explanation::Inconsistent(kinds), (consistency.score, consistency.confidence));
+            }
+        }
+        output.file_data = FileData { consistency, ..FileData::default() };
+        output
+    }
+}
+
+/// Error levels, noise and spectrum of the decoded pixels.
+#[cfg(feature = "pixel")]
+pub struct PixelAnalyzer;
+
+#[cfg(feature = "pixel")]
+impl Analyzer for PixelAnalyzer {
+    fn name(&self) -> &str {
+        "pixel"
+    }
+
+    fn analyze(&self, input: &AnalysisInput) -> AnalyzerOutput {
+        let mut output = AnalyzerOutput::default();
+        let image = match input.image {
+            Some(i) => i,
+            None => return output
+        };
+        let timings = &mut output.timings;
+        let mut ela = timings.time("ela", || ElaFindings::from_image(image, input.options.ela_dir.is_some()));
+        let noise = timings.time("noise", || NoiseFindings::from_image(image));
+        let spectrum = timings.time("spectrum", || SpectrumFindings::from_image(image, input.bytes.starts_with(&[0xFF, 0xD8])));
+        if let Some(ela) = &mut ela {
+            if let Some(dir) = &input.options.ela_dir {
+                ela.write_visualization(input.file_name, dir);
+            }
+            output.explain("ela", Explanation::ErrorLevels, (ela.score, ela.confidence));
+        }
+        if let Some(noise) = &noise {
+            let finding = match noise.uniform {
+                true => Explanation::UniformNoise,
+                false => Explanation::InconsistentNoise
+            };
+            output.explain("pixel_noise", finding, (noise.score, noise.confidence));
+        }
+        if let Some(spectrum) = &spectrum {
+            output.explain("spectrum", Explanation::SpectrumPeaks(spectrum.peak_count), (spectrum.score, spectrum.confidence));
+        }
+        output.file_data = FileData { ela, noise, spectrum, ..FileData::default() };
+        output
+    }
+}
+
+/// The watermark decoders of the options, run on the decoded pixels.
+#[cfg(feature = "pixel")]
+pub struct WatermarkAnalyzer;
+
+#[cfg(feature = "pixel")]
+impl Analyzer for WatermarkAnalyzer {
+    fn name(&self) -> &str {
+        "watermark"
+    }
+
+    fn analyze(&self, input: &AnalysisInput) -> AnalyzerOutput {
+        let mut output = AnalyzerOutput::default();
+        let watermarks = input.image.and_then(|i| WatermarkFindings::from_image(i, &input.options.watermarks));
+        if let Some(watermarks) = &watermarks {
+            let names = watermarks.detections.iter()
+                .map(|d| d.generator.clone().unwrap_or_else(|| d.decoder.clone()))
+                .collect();
+            output.explain("watermarks", Explanation::Watermarks(names), (watermarks.score, watermarks.confidence));
+        }
+        output.file_data = FileData { watermarks, ..FileData::default() };
+        output
+    }
+}
+
+/// Times reading the store, which c2pa validates as it reads, as
+/// `read_manifest`, then the stages of `ManifestData::from_reader`.
+fn read_c2pa(format: &str, bytes: &[u8], sidecar: Option<&[u8]>, options: &AnalysisOptions, timings: &mut Timings) -> Result<ManifestData, AnalyzeError> {
+    let settings = match &options.trust {
+        Some(trust) => trust.apply(),
+        None => {
+            TrustConfig::reset();
+            Ok(())
+        }
+    };
+    if let Err(e) = settings.and_then(|_| RevocationCheck::apply(options.revocation.as_ref())) {
+        return Err(AnalyzeError::Settings(e));
+    }
+    let reader = timings.time("read_manifest", || match sidecar {
+        Some(manifest) => Reader::from_manifest_data_and_stream(manifest, format, Cursor::new(bytes)),
+        None => Reader::from_stream(format, Cursor::new(bytes))
+    });
+    match reader {
+        Ok(reader) => Ok(ManifestData::from_reader(&reader, options, timings)),
+        Err(c2pa::Error::RemoteManifestUrl(url)) => Ok(read_remote(&url, format, bytes, options, timings)),
+        Err(c2pa::Error::UnsupportedType) => Err(AnalyzeError::UnsupportedFormat(format.to_string())),
+        Err(e) => Err(AnalyzeError::C2pa(e))
+    }
+}
+
+/// Fetches the manifest store the asset links to. The report keeps the
+/// link even when it can't be fetched.
+fn read_remote(url: &str, format: &str, bytes: &[u8], options: &AnalysisOptions, timings: &mut Timings) -> ManifestData {
+    let manifest = timings.time("fetch_manifest", || options.remote.fetch(url));
+    let reader = manifest.and_then(|manifest| timings.time("read_manifest", || {
+        Reader::from_manifest_data_and_stream(&manifest, format, Cursor::new(bytes)).map_err(|e| format!("Error reading the manifest at {}: {}", url, e))
+    }));
+    let (mut data, error) = match reader {
+        Ok(reader) => (ManifestData::from_reader(&reader, options, timings), None),
+        Err(e) => (ManifestData::empty(), Some(e))
+    };
+    data.remote = Some(RemoteManifest { url: url.to_string(), fetched: error.is_none(), error });
+    data
+}
+
+fn weight(weight: Weight) -> (u8, u8) {
+    (weight.score, weight.confidence)
+}
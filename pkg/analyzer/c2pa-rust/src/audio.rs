@@ -0,0 +1,304 @@
+use serde::Serialize;
+
+use crate::rules::{GeneratorRules, RuleKind};
+
+const EDITING_TOOL: (u8, u8) = (20, 10);
+/// Tags kept per file, tag blocks of podcasts carry whole chapters.
+const MAX_TAGS: usize = 32;
+/// RIFF INFO chunks naming the software or carrying free text.
+const WAV_TAGS: [&[u8; 4]; 3] = [b"ISFT", b"ICMT", b"IENG"];
+/// ID3v2 frames naming the encoder or carrying free text.
+const ID3_TAGS: [&[u8; 4]; 4] = [b"TSSE", b"TENC", b"TXXX", b"COMM"];
+const MP3_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+/// Layer III bitrates in kbit/s of MPEG-1, and of MPEG-2 and 2.5.
+const MP3_BITRATES: [[u32; 15]; 2] = [
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160]
+];
+
+/// A software or comment tag of an audio file.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct AudioTag {
+    /// RIFF INFO or ID3v2 frame id, ex. `ISFT` or `TSSE`.
+    pub key: String,
+    pub value: String
+}
+
+/// Stream parameters and tool tags of a WAV or MP3 file. M4A files are
+/// BMFF containers and reported under `media`.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct AudioData {
+    /// `wav` or `mp3`.
+    pub format: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// For MP3, estimated from the bitrate of the first frame.
+    pub duration_seconds: Option<f64>,
+    pub tags: Vec<AudioTag>,
+    /// Tag value that matched a generator rule, and the rule's kind.
+    pub tool: Option<String>,
+    pub tool_rule: Option<RuleKind>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl AudioData {
+    /// None unless the bytes are a RIFF WAVE file or an MP3 stream.
+    pub fn from_bytes(bytes: &[u8], rules: &GeneratorRules) -> Option<AudioData> {
+        let mut audio = match bytes.get(..4)? {
+            b"RIFF" if bytes.get(8..12) == Some(b"WAVE") => wav(bytes),
+            b"ID3\x03" | b"ID3\x04" => mp3(bytes),
+            [0xFF, b, ..] if b & 0xE0 == 0xE0 => mp3(bytes),
+            _ => None
+        }?;
+        let matched = audio.tags.iter()
+            .filter_map(|t| rules.find(&t.value).map(|(kind, rule)| (kind, rule, &t.value)))
+            .min_by_key(|(kind, _, _)| *kind != RuleKind::Generated);
+        (audio.score, audio.confidence) = match matched {
+            Some((RuleKind::Generated, rule, _)) => (rule.score, rule.confidence),
+            Some((RuleKind::Modified, _, _)) => EDITING_TOOL,
+            None => (0, 0)
+        };
+        audio.tool = matched.map(|(_, _, value)| value.clone());
+        audio.tool_rule = matched.map(|(kind, _, _)| kind);
+        Some(audio)
+    }
+
+    fn new(format: &str) -> AudioData {
+        AudioData {
+            format: format.to_string(),
+            sample_rate: None,
+            channels: None,
+            duration_seconds: None,
+            tags: Vec::new(),
+            tool: None,
+            tool_rule: None,
+            score: 0,
+            confidence: 0
+        }
+    }
+}
+
+fn wav(bytes: &[u8]) -> Option<AudioData> {
+    let mut audio = AudioData::new("wav");
+    let mut byte_rate = 0;
+    for (id, chunk) in riff_chunks(bytes.get(12..)?) {
+        match &id {
+            b"fmt " => {
+                audio.channels = le_u16(chunk, 2);
+                audio.sample_rate = le_u32(chunk, 4);
+                byte_rate = le_u32(chunk, 8).unwrap_or_default();
+            },
+            b"data" if byte_rate != 0 => {
+                audio.duration_seconds = Some(round_ms(chunk.len() as f64 / byte_rate as f64));
+            },
+            b"LIST" if chunk.starts_with(b"INFO") => {
+                for (key, value) in riff_chunks(&chunk[4..]) {
+                    if WAV_TAGS.contains(&&key) {
+                        push_tag(&mut audio.tags, &key, latin1(value));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    Some(audio)
+}
+
+/// (id, payload) of the chunks laid out in `bytes`, padded to even sizes.
+fn riff_chunks(mut bytes: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut found = Vec::new();
+    while bytes.len() >= 8 {
+        let id = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        let size = le_u32(bytes, 4).unwrap_or_default() as usize;
+        // The data chunk of a streamed file may claim more than was written
+        let end = (8 + size).min(bytes.len());
+        found.push((id, &bytes[8..end]));
+        bytes = &bytes[(end + size % 2).min(bytes.len())..];
+    }
+    found
+}
+
+fn mp3(bytes: &[u8]) -> Option<AudioData> {
+    let mut audio = AudioData::new("mp3");
+    let mut start = 0;
+    if bytes.starts_with(b"ID3") {
+        let version = bytes[3];
+        let size = syncsafe(bytes.get(6..10)?) as usize;
+        start = (10 + size).min(bytes.len());
+        read_id3_frames(&bytes[10..start], version, &mut audio.tags);
+    }
+    // The first frame header after the tag, CBR files keep its bitrate
+    let frame = bytes[start..].windows(4).take(4096).find_map(mp3_header);
+    if let Some((sample_rate, channels, bitrate)) = frame {
+        audio.sample_rate = Some(sample_rate);
+        audio.channels = Some(channels);
+        audio.duration_seconds = Some(round_ms((bytes.len() - start) as f64 * 8.0 / (bitrate as f64 * 1000.0)));
+    } else if start == 0 {
+        return None;
+    }
+    Some(audio)
+}
+
+fn read_id3_frames(mut frames: &[u8], version: u8, tags: &mut Vec<AudioTag>) {
+    while frames.len() >= 10 && frames[0] != 0 {
+        let id = [frames[0], frames[1], frames[2], frames[3]];
+        let size = match version {
+            4 => syncsafe(&frames[4..8]),
+            _ => u32::from_be_bytes([frames[4], frames[5], frames[6], frames[7]])
+        } as usize;
+        let Some(body) = frames.get(10..10 + size) else { break };
+        if ID3_TAGS.contains(&&id) {
+            if let Some(value) = id3_text(&id, body) {
+                push_tag(tags, &id, value);
+            }
+        }
+        frames = &frames[10 + size..];
+    }
+}
+
+/// Text of a text, `TXXX` or `COMM` frame. For the latter two the
+/// description is kept in front of the value, it often names the tool.
+fn id3_text(id: &[u8; 4], body: &[u8]) -> Option<String> {
+    let (encoding, text) = body.split_first()?;
+    let text = match id {
+        b"COMM" => text.get(3..)?,
+        _ => text
+    };
+    let decoded = match encoding {
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                _ => (*encoding == 2, text)
+            };
+            let units: Vec<u16> = text.chunks_exact(2)
+                .map(|c| if big_endian { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+                .collect();
+            String::from_utf16_lossy(&units)
+        },
+        3 => String::from_utf8_lossy(text).to_string(),
+        _ => latin1(text)
+    };
+    let parts: Vec<&str> = decoded.split(['\0', '\u{FEFF}']).map(str::trim).filter(|p| !p.is_empty()).collect();
+    if parts.is_empty() { None } else { Some(parts.join(": ")) }
+}
+
+/// (sample rate, channels, bitrate) of a Layer III frame header.
+fn mp3_header(header: &[u8]) -> Option<(u32, u16, u32)> {
+    if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 || header[1] >> 1 & 0b11 != 0b01 {
+        return None;
+    }
+    // 3 is MPEG-1, 2 MPEG-2, 0 MPEG-2.5 and 1 reserved
+    let version = header[1] >> 3 & 0b11;
+    let bitrate = *MP3_BITRATES[(version != 3) as usize].get((header[2] >> 4) as usize)?;
+    let sample_rate = *MP3_SAMPLE_RATES.get((header[2] >> 2 & 0b11) as usize)?;
+    let sample_rate = match version {
+        3 => sample_rate,
+        2 => sample_rate / 2,
+        0 => sample_rate / 4,
+        _ => return None
+    };
+    let channels = if header[3] >> 6 == 0b11 { 1 } else { 2 };
+    if bitrate == 0 { None } else { Some((sample_rate, channels, bitrate)) }
+}
+
+fn push_tag(tags: &mut Vec<AudioTag>, key: &[u8; 4], value: String) {
+    let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+    if !value.is_empty() && tags.len() < MAX_TAGS {
+        tags.push(AudioTag { key: String::from_utf8_lossy(key).to_string(), value });
+    }
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, b| acc << 7 | (*b & 0x7F) as u32)
+}
+
+fn round_ms(seconds: f64) -> f64 {
+    (seconds * 1000.0).round() / 1000.0
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut c = id.to_vec();
+        c.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        c.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            c.push(0);
+        }
+        c
+    }
+
+    /// One second of 8 kHz mono 16 bit audio, tagged by ElevenLabs.
+    fn wav_file() -> Vec<u8> {
+        let mut fmt = vec![1, 0, 1, 0];
+        fmt.extend_from_slice(&8000_u32.to_le_bytes());
+        fmt.extend_from_slice(&16000_u32.to_le_bytes());
+        fmt.extend_from_slice(&[2, 0, 16, 0]);
+        let info = [b"INFO".to_vec(), chunk(b"ISFT", b"ElevenLabs\0")].concat();
+        let body = [b"WAVE".to_vec(), chunk(b"fmt ", &fmt), chunk(b"LIST", &info), chunk(b"data", &[0; 16000])].concat();
+        [b"RIFF".to_vec(), (body.len() as u32).to_le_bytes().to_vec(), body].concat()
+    }
+
+    /// An ID3v2.4 tag with a TSSE frame, then 128 kbit/s 44.1 kHz frames.
+    fn mp3_file() -> Vec<u8> {
+        let frame = [b"TSSE\0\0\0\x0D\0\0\x03".as_slice(), b"Lavf60.3.100"].concat();
+        let mut bytes = [b"ID3\x04\0\0".to_vec(), vec![0, 0, 0, frame.len() as u8], frame].concat();
+        for _ in 0..4 {
+            bytes.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+            bytes.extend_from_slice(&[0; 413]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_wav() {
+        let audio = AudioData::from_bytes(&wav_file(), &GeneratorRules::default()).unwrap();
+        assert_eq!((audio.sample_rate, audio.channels, audio.duration_seconds), (Some(8000), Some(1), Some(1.0)));
+        assert_eq!((audio.tags[0].key.as_str(), audio.tags[0].value.as_str()), ("ISFT", "ElevenLabs"));
+        assert_eq!(audio.tool.as_deref(), Some("ElevenLabs"));
+        assert_eq!(audio.tool_rule, Some(RuleKind::Generated));
+        assert!(audio.score > 0);
+    }
+
+    #[test]
+    fn reads_mp3() {
+        let audio = AudioData::from_bytes(&mp3_file(), &GeneratorRules::default()).unwrap();
+        assert_eq!((audio.sample_rate, audio.channels), (Some(44100), Some(2)));
+        assert_eq!((audio.tags[0].key.as_str(), audio.tags[0].value.as_str()), ("TSSE", "Lavf60.3.100"));
+        assert_eq!(audio.tool, None);
+    }
+
+    #[test]
+    fn truncated_files_are_read_without_panicking() {
+        let rules = GeneratorRules::default();
+        for file in [wav_file(), mp3_file()] {
+            for end in 0..file.len() {
+                let _ = AudioData::from_bytes(&file[..end], &rules);
+            }
+        }
+        // a tag size past the end and a frame claiming more than its tag
+        assert!(AudioData::from_bytes(b"ID3\x04\0\0\x7F\x7F\x7F\x7F", &rules).is_some());
+        let lying = [b"ID3\x03\0\0\0\0\0\x0E".to_vec(), b"TSSE\xFF\xFF\xFF\xFF\0\0abcd".to_vec()].concat();
+        assert!(AudioData::from_bytes(&lying, &rules).unwrap().tags.is_empty());
+        assert!(AudioData::from_bytes(b"RIFF\0\0\0\0WAVE", &rules).is_some());
+        assert!(AudioData::from_bytes(b"RIFF", &rules).is_none());
+    }
+}
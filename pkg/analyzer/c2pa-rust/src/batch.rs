@@ -0,0 +1,206 @@
+use std::{collections::BTreeMap, fs, io::BufRead, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, mpsc}};
+use c2pa::jumbf_io::get_supported_types;
+use rayon::{iter::{ParallelBridge, ParallelIterator}, ThreadPoolBuilder};
+
+/// Expands the command line paths into the files to analyze. Files given
+/// directly are always kept so they get a report even if unreadable,
+/// directories are walked recursively and only files with an extension the
+/// c2pa crate can read, or BMP, are picked up, sorted by path.
+pub fn collect_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut supported = get_supported_types();
+    supported.push(String::from("bmp"));
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            walk_dir(path, &supported, &mut found);
+            found.sort();
+            files.append(&mut found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+/// Newline-delimited paths, ex. the output of `find`, read as they come
+/// so a queue can keep feeding them. Empty lines are skipped, directories
+/// are walked like `collect_paths` does.
+pub fn read_paths(input: impl BufRead + Send) -> impl Iterator<Item = PathBuf> + Send {
+    input.split(b'\n')
+        .map_while(|line| line.map_err(|e| eprintln!("Error reading paths: {}", e)).ok())
+        .filter_map(|mut line| {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            match line.is_empty() {
+                true => None,
+                false => Some(path_from_bytes(line))
+            }
+        })
+        .flat_map(|path| collect_paths(&[path]))
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Runs `analyze` on every path on `jobs` threads, 0 for one per core, and
+/// hands the results to `emit` in the order of `paths` as they come in.
+/// Paths are taken as the threads get to them, so they may still be coming
+/// in. The first error of `emit` stops the batch, files already being
+/// analyzed are finished but not emitted.
+pub fn analyze_parallel<T: Send, E>(
+    paths: impl IntoIterator<Item = PathBuf, IntoIter: Send>,
+    jobs: usize,
+    analyze: impl Fn(PathBuf) -> T + Sync,
+    mut emit: impl FnMut(T) -> Result<(), E>
+) -> Result<(), E> {
+    let paths = paths.into_iter();
+    let pool = match ThreadPoolBuilder::new().num_threads(jobs).build() {
+        Ok(p) => p,
+        // Without threads the files are analyzed one after the other
+        Err(_) => return paths.map(analyze).try_for_each(emit)
+    };
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        let (pool, stop, analyze) = (&pool, &stop, &analyze);
+        scope.spawn(move || pool.install(|| {
+            paths.enumerate().par_bridge().for_each_with(sender, |sender, (i, path)| {
+                if !stop.load(Ordering::Relaxed) {
+                    // Only fails once emitting stopped
+                    let _ = sender.send((i, analyze(path)));
+                }
+            })
+        }));
+        // Results that came in before the ones ahead of them
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (i, result) in receiver {
+            pending.insert(i, result);
+            while let Some(result) = pending.remove(&next) {
+                next += 1;
+                if let Err(e) = emit(result) {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+fn walk_dir(dir: &Path, supported: &[String], files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Error reading directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            walk_dir(&path, supported, files);
+        } else if is_supported(&path, supported) && !is_sidecar(&path) {
+            files.push(path);
+        }
+    }
+}
+
+pub fn is_supported(path: &Path, supported: &[String]) -> bool {
+    match path.extension() {
+        Some(ext) => {
+            let ext = ext.to_string_lossy().to_lowercase();
+            supported.contains(&ext)
+        },
+        None => false
+    }
+}
+
+/// The detached manifest store of `path`, `image.jpg.c2pa` or `image.c2pa`
+/// next to `image.jpg`.
+pub fn find_sidecar(path: &Path) -> Option<PathBuf> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("c2pa")) {
+        return None;
+    }
+    let mut appended = path.as_os_str().to_owned();
+    appended.push(".c2pa");
+    [PathBuf::from(appended), path.with_extension("c2pa")].into_iter().find(|p| p.is_file())
+}
+
+/// True for a `.c2pa` file `find_sidecar` finds for another file next to
+/// it, `image.jpg.c2pa` or `image.c2pa` of `image.jpg`. It is read with the
+/// image rather than analyzed on its own.
+fn is_sidecar(path: &Path) -> bool {
+    if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("c2pa")) {
+        return false;
+    }
+    if path.with_extension("").is_file() {
+        return true;
+    }
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else { return false };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    fs::read_dir(dir).into_iter().flatten().flatten().any(|entry| {
+        let sibling = entry.path();
+        sibling.file_stem() == Some(stem) && sibling != path && sibling.is_file() && find_sidecar(&sibling).as_deref() == Some(path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecars_are_read_with_their_image() {
+        let dir = std::env::temp_dir().join(format!("c2pa-sidecars-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.jpg", "a.c2pa", "b.png", "b.png.c2pa", "lone.c2pa"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        assert_eq!(find_sidecar(&dir.join("a.jpg")), Some(dir.join("a.c2pa")));
+        assert_eq!(find_sidecar(&dir.join("b.png")), Some(dir.join("b.png.c2pa")));
+        assert!(is_sidecar(&dir.join("a.c2pa")));
+        assert!(is_sidecar(&dir.join("b.png.c2pa")));
+        assert!(!is_sidecar(&dir.join("lone.c2pa")));
+        assert!(!is_sidecar(&dir.join("a.jpg")));
+
+        let names: Vec<String> = collect_paths(std::slice::from_ref(&dir)).iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["a.jpg", "b.png", "lone.c2pa"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn paths_are_read_line_by_line() {
+        let dir = std::env::temp_dir().join(format!("c2pa-read-paths-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["sub/b.png", "sub/a.jpg", "sub/notes.txt"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let listed = format!("one.jpg\r\n\n\r\n{}\r\nwith space.png\nlast.jpg", dir.join("sub").display());
+        let paths: Vec<PathBuf> = read_paths(listed.as_bytes()).collect();
+        assert_eq!(paths, [
+            PathBuf::from("one.jpg"),
+            dir.join("sub").join("a.jpg"),
+            dir.join("sub").join("b.png"),
+            PathBuf::from("with space.png"),
+            PathBuf::from("last.jpg")
+        ]);
+
+        let list = dir.join("paths.txt");
+        fs::write(&list, "first.jpg\r\n\r\nsecond.jpg\r\n").unwrap();
+        let from_file: Vec<PathBuf> = read_paths(std::io::BufReader::new(fs::File::open(&list).unwrap())).collect();
+        assert_eq!(from_file, [PathBuf::from("first.jpg"), PathBuf::from("second.jpg")]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,155 @@
+use std::{fs, path::PathBuf, time::Instant};
+use serde::Serialize;
+
+use crate::{batch::{collect_paths, find_sidecar}, options::AnalysisOptions, report::Report};
+
+/// Allocations and bytes allocated so far, ex. from a counting global
+/// allocator of the binary.
+pub type AllocationCounter = dyn Fn() -> (u64, u64);
+
+/// Throughput of the pipeline over a fixture set, from the `timings` of
+/// the reports. Files are analyzed one after the other so the stages don't
+/// compete for cores.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct BenchResult {
+    pub files: usize,
+    pub iterations: usize,
+    /// Size of the fixture set, read once per iteration.
+    pub input_bytes: u64,
+    pub total_ms: f64,
+    pub files_per_second: f64,
+    pub mib_per_second: f64,
+    /// None without an allocation counter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<AllocationStats>,
+    /// In the order the stages first ran.
+    pub stages: Vec<StageThroughput>
+}
+
+/// Mean allocations of analyzing one file.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct AllocationStats {
+    pub allocations_per_file: u64,
+    pub bytes_per_file: u64
+}
+
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct StageThroughput {
+    pub stage: String,
+    /// How many analyses ran the stage.
+    pub runs: usize,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub runs_per_second: f64,
+    /// Percent of the analysis time, nested stages count in their parent's
+    /// share too.
+    pub share: f64
+}
+
+/// Analyzes the files of `paths`, walked like a batch, `iterations` times.
+/// Reports aren't cached, every iteration analyzes every file again.
+pub fn run(paths: &[PathBuf], iterations: usize, options: &AnalysisOptions, counter: Option<&AllocationCounter>) -> Result<BenchResult, String> {
+    let files = collect_paths(paths);
+    if files.is_empty() {
+        return Err(String::from("No files to benchmark"));
+    }
+    let options = options.clone().with_timings();
+    let input_bytes = files.iter().filter_map(|f| fs::metadata(f).ok()).map(|m| m.len()).sum();
+    let mut stages: Vec<StageThroughput> = Vec::new();
+    let mut analysis_ms = 0.0;
+    let allocated_before = counter.map(|c| c());
+    let start = Instant::now();
+    for _ in 0..iterations {
+        for file in &files {
+            let report = Report::from_file_lenient(file.clone(), find_sidecar(file), &options);
+            let timings = report.timings.unwrap_or_default();
+            analysis_ms += timings.total_ms;
+            for timing in timings.stages {
+                match stages.iter_mut().find(|s| s.stage == timing.stage) {
+                    Some(stage) => {
+                        stage.runs += 1;
+                        stage.total_ms += timing.ms;
+                    },
+                    None => stages.push(StageThroughput { stage: timing.stage, runs: 1, total_ms: timing.ms, mean_ms: 0.0, runs_per_second: 0.0, share: 0.0 })
+                }
+            }
+        }
+    }
+    let total_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let analyses = (files.len() * iterations) as u64;
+    let allocations = match (counter, allocated_before) {
+        (Some(counter), Some((count, bytes))) => {
+            let (count_after, bytes_after) = counter();
+            Some(AllocationStats {
+                allocations_per_file: (count_after - count) / analyses.max(1),
+                bytes_per_file: (bytes_after - bytes) / analyses.max(1)
+            })
+        },
+        _ => None
+    };
+    for stage in &mut stages {
+        stage.mean_ms = round(stage.total_ms / stage.runs as f64);
+        stage.runs_per_second = round(per_second(stage.runs as f64, stage.total_ms));
+        stage.share = round(100.0 * stage.total_ms / analysis_ms.max(f64::MIN_POSITIVE));
+        stage.total_ms = round(stage.total_ms);
+    }
+    Ok(BenchResult {
+        files: files.len(),
+        iterations,
+        input_bytes,
+        total_ms: round(total_ms),
+        files_per_second: round(per_second(analyses as f64, total_ms)),
+        mib_per_second: round(per_second((input_bytes * iterations as u64) as f64 / (1024.0 * 1024.0), total_ms)),
+        allocations,
+        stages
+    })
+}
+
+fn per_second(count: f64, ms: f64) -> f64 {
+    match ms > 0.0 {
+        true => count * 1000.0 / ms,
+        false => 0.0
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn stages_and_allocations_are_summed() {
+        let dir = std::env::temp_dir().join(format!("c2pa-bench-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.png"), b"not a png").unwrap();
+        fs::write(dir.join("b.jpg"), b"not a jpeg either").unwrap();
+        let calls = Cell::new(0);
+        let counter = move || {
+            calls.set(calls.get() + 1);
+            (calls.get() * 400, calls.get() * 40_000)
+        };
+        let fixtures = [dir.clone()];
+        let result = run(&fixtures, 2, &AnalysisOptions::default(), Some(&counter)).unwrap();
+        assert_eq!((result.files, result.iterations, result.input_bytes), (2, 2, 26));
+        // one reading before and one after the four analyses
+        let allocations = result.allocations.as_ref().unwrap();
+        assert_eq!((allocations.allocations_per_file, allocations.bytes_per_file), (100, 10_000));
+        let scoring = result.stages.iter().find(|s| s.stage == "scoring").unwrap();
+        assert_eq!(scoring.runs, 4);
+        assert!(result.stages.iter().all(|s| s.share >= 0.0 && s.share <= 100.0));
+
+        let json = serde_json::to_value(run(&fixtures, 1, &AnalysisOptions::default(), None).unwrap()).unwrap();
+        assert!(json.get("allocations").is_none());
+        assert_eq!(json["files"], 2);
+        fs::create_dir_all(dir.join("empty")).unwrap();
+        assert!(run(&[dir.join("empty")], 1, &AnalysisOptions::default(), None).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
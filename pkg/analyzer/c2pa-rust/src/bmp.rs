@@ -0,0 +1,135 @@
+use serde::Serialize;
+
+/// The headers of a BMP. BMPs have no metadata blocks and can't hold a
+/// C2PA manifest, so these are reported for context only, not scored.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct BmpData {
+    /// The DIB header, ex. `BITMAPINFOHEADER` or `BITMAPV5HEADER`.
+    pub header: String,
+    pub width: i32,
+    /// Absolute, rows are stored top down when the header's is negative.
+    pub height: u32,
+    pub top_down: bool,
+    pub bits_per_pixel: u16,
+    /// `BI_RGB`, `BI_RLE8`, ..., or the number as written.
+    pub compression: String,
+    /// A V5 header points to an ICC profile in the file.
+    pub embedded_profile: bool,
+    /// Bytes after those the file header announces, where data can be
+    /// appended without the image changing.
+    pub trailing_bytes: u64
+}
+
+impl BmpData {
+    /// None unless the bytes are a BMP with a known DIB header.
+    pub fn from_bytes(bytes: &[u8]) -> Option<BmpData> {
+        if bytes.get(..2)? != b"BM" {
+            return None;
+        }
+        let file_size = le_u32(bytes, 2)? as u64;
+        let header = match le_u32(bytes, 14)? {
+            12 => "BITMAPCOREHEADER",
+            40 => "BITMAPINFOHEADER",
+            52 => "BITMAPV2INFOHEADER",
+            56 => "BITMAPV3INFOHEADER",
+            64 => "OS22XBITMAPHEADER",
+            108 => "BITMAPV4HEADER",
+            124 => "BITMAPV5HEADER",
+            _ => return None
+        };
+        let (width, height, bits_per_pixel, compression) = match header {
+            "BITMAPCOREHEADER" => (le_u16(bytes, 18)? as i32, le_u16(bytes, 20)? as i32, le_u16(bytes, 24)?, 0),
+            _ => (le_u32(bytes, 18)? as i32, le_u32(bytes, 22)? as i32, le_u16(bytes, 28)?, le_u32(bytes, 30)?)
+        };
+        let compression = match compression {
+            0 => String::from("BI_RGB"),
+            1 => String::from("BI_RLE8"),
+            2 => String::from("BI_RLE4"),
+            3 => String::from("BI_BITFIELDS"),
+            4 => String::from("BI_JPEG"),
+            5 => String::from("BI_PNG"),
+            6 => String::from("BI_ALPHABITFIELDS"),
+            other => other.to_string()
+        };
+        // bV5CSType, PROFILE_EMBEDDED is 'MBED'
+        let embedded_profile = header == "BITMAPV5HEADER" && le_u32(bytes, 14 + 56) == Some(0x4D42_4544);
+        Some(BmpData {
+            header: header.to_string(),
+            width,
+            height: height.unsigned_abs(),
+            top_down: height < 0,
+            bits_per_pixel,
+            compression,
+            embedded_profile,
+            trailing_bytes: (bytes.len() as u64).saturating_sub(file_size)
+        })
+    }
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 24 bit BMP with a `dib_size` byte DIB header, rows top down.
+    fn bmp(dib_size: u32) -> Vec<u8> {
+        let pixels_at = 14 + dib_size;
+        let mut bytes = b"BM".to_vec();
+        bytes.extend_from_slice(&(pixels_at + 4).to_le_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(&pixels_at.to_le_bytes());
+        let mut dib = vec![0; dib_size as usize];
+        dib[..4].copy_from_slice(&dib_size.to_le_bytes());
+        dib[4..8].copy_from_slice(&1i32.to_le_bytes());
+        dib[8..12].copy_from_slice(&(-1i32).to_le_bytes());
+        dib[12..14].copy_from_slice(&1u16.to_le_bytes());
+        dib[14..16].copy_from_slice(&24u16.to_le_bytes());
+        if dib_size == 124 {
+            dib[56..60].copy_from_slice(&0x4D42_4544u32.to_le_bytes());
+        }
+        bytes.extend_from_slice(&dib);
+        bytes.extend_from_slice(&[0, 0, 255, 0]);
+        bytes
+    }
+
+    #[test]
+    fn reads_headers() {
+        let info = BmpData::from_bytes(&bmp(40)).unwrap();
+        assert_eq!((info.header.as_str(), info.width, info.height, info.top_down), ("BITMAPINFOHEADER", 1, 1, true));
+        assert_eq!((info.bits_per_pixel, info.compression.as_str()), (24, "BI_RGB"));
+        assert!(!info.embedded_profile);
+        assert_eq!(info.trailing_bytes, 0);
+
+        let mut appended = bmp(124);
+        appended.extend_from_slice(b"appended");
+        let v5 = BmpData::from_bytes(&appended).unwrap();
+        assert_eq!(v5.header, "BITMAPV5HEADER");
+        assert!(v5.embedded_profile);
+        assert_eq!(v5.trailing_bytes, 8);
+
+        let mut unknown = bmp(40);
+        unknown[14] = 41;
+        assert!(BmpData::from_bytes(&unknown).is_none());
+        assert!(BmpData::from_bytes(b"GIF89a").is_none());
+    }
+
+    #[test]
+    fn truncated_headers_are_read_without_panicking() {
+        let bytes = bmp(40);
+        for len in 0..bytes.len() {
+            let data = BmpData::from_bytes(&bytes[..len]);
+            match len {
+                0..=33 => assert!(data.is_none(), "{} bytes", len),
+                _ => assert_eq!(data.unwrap().trailing_bytes, 0)
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::{fs, io::Cursor, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+use c2pa::format_from_path;
+use sha2::{Digest, Sha256};
+
+use crate::{batch::find_sidecar, cameras, input, options::AnalysisOptions, quanttables, report::Report, schema::SCHEMA_VERSION};
+
+/// Report JSON kept on disk by the SHA-256 of the analyzed bytes, so the
+/// same content uploaded again isn't analyzed again. Entries live under a
+/// directory per crate version and their key covers the format, the
+/// options that change the report, ex. the scoring, rules and trust lists,
+/// the built-in tables and the report schema version.
+///
+/// Analyses writing thumbnails or ELA images should skip the cache, a hit
+/// writes nothing.
+pub struct ReportCache {
+    dir: PathBuf,
+    ttl: Option<Duration>
+}
+
+impl ReportCache {
+    pub fn new(dir: PathBuf) -> ReportCache {
+        ReportCache { dir: dir.join(env!("CARGO_PKG_VERSION")), ttl: None }
+    }
+
+    /// Entries older than `ttl` are analyzed again.
+    pub fn with_ttl(mut self, ttl: Duration) -> ReportCache {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// `$XDG_CACHE_HOME/c2pa-rust`, or `~/.cache/c2pa-rust`.
+    pub fn default_dir() -> Option<PathBuf> {
+        match std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+            Some(dir) => Some(PathBuf::from(dir).join("c2pa-rust")),
+            None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("c2pa-rust"))
+        }
+    }
+
+    /// `Report::from_file_with` as JSON. Unreadable files and files over
+    /// the size limit aren't cached.
+    pub fn report_file(&self, path: &Path, options: &AnalysisOptions) -> serde_json::Value {
+        self.report_file_sidecar(path, find_sidecar(path), options)
+    }
+
+    /// `Report::from_file_with_sidecar` as JSON.
+    pub fn report_file_with_sidecar(&self, path: &Path, sidecar: &Path, options: &AnalysisOptions) -> serde_json::Value {
+        self.report_file_sidecar(path, Some(sidecar.to_path_buf()), options)
+    }
+
+    fn report_file_sidecar(&self, path: &Path, sidecar: Option<PathBuf>, options: &AnalysisOptions) -> serde_json::Value {
+        let analyze = || Report::from_file_lenient(path.to_path_buf(), sidecar.clone(), options);
+        let bytes = input::read_file(path, options.max_input_size);
+        let sidecar_bytes = sidecar.as_ref().map(|s| input::read_file(s, options.max_input_size)).transpose();
+        let (bytes, sidecar_bytes) = match (bytes, sidecar_bytes) {
+            (Ok(b), Ok(s)) => (b, s),
+            _ => return to_json(&analyze())
+        };
+        let file_name = match path.file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => String::from("n/a")
+        };
+        let format = format_from_path(path).unwrap_or_default();
+        let key = ReportCache::key(&format, &bytes, sidecar_bytes.as_deref(), options);
+        let mut json = self.cached(&key, &file_name, analyze);
+        if let Some(sidecar) = &sidecar {
+            json["sidecar_manifest"] = sidecar.display().to_string().into();
+        }
+        json
+    }
+
+    /// `Report::from_stream_with` of `bytes` as JSON.
+    pub fn report_stream(&self, format: &str, bytes: Vec<u8>, options: &AnalysisOptions) -> serde_json::Value {
+        let key = ReportCache::key(format, &bytes, None, options);
+        self.cached(&key, "stdin", || Report::from_stream_lenient(format, Cursor::new(bytes), options))
+    }
+
+    fn cached(&self, key: &str, file_name: &str, analyze: impl FnOnce() -> Report) -> serde_json::Value {
+        let entry = self.dir.join(format!("{}.json", key));
+        if let Some(mut json) = self.get(&entry) {
+            // The same bytes may have been uploaded under another name
+            json["file_name"] = file_name.into();
+            return json;
+        }
+        let mut json = to_json(&analyze());
+        // Timings are of this run, a hit would report them as its own
+        let timings = json.as_object_mut().and_then(|report| report.remove("timings"));
+        self.put(&entry, &json);
+        if let Some(timings) = timings {
+            json["timings"] = timings;
+        }
+        json
+    }
+
+    fn get(&self, entry: &Path) -> Option<serde_json::Value> {
+        if let Some(ttl) = self.ttl {
+            let modified = fs::metadata(entry).and_then(|m| m.modified()).ok()?;
+            if SystemTime::now().duration_since(modified).unwrap_or_default() > ttl {
+                return None;
+            }
+        }
+        serde_json::from_slice(&fs::read(entry).ok()?).ok()
+    }
+
+    /// Failing to write only costs a later analysis, errors are ignored.
+    fn put(&self, entry: &Path, json: &serde_json::Value) {
+        let bytes = match serde_json::to_vec(json) {
+            Ok(b) => b,
+            Err(_) => return
+        };
+        // Written aside and renamed, so a reader never sees half an entry
+        let partial = entry.with_extension(format!("{}.partial", std::process::id()));
+        if fs::create_dir_all(&self.dir).is_ok() && fs::write(&partial, bytes).is_ok() && fs::rename(&partial, entry).is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+    }
+
+    fn key(format: &str, bytes: &[u8], sidecar: Option<&[u8]>, options: &AnalysisOptions) -> String {
+        #[cfg_attr(not(feature = "pixel"), allow(unused_mut))]
+        let mut settings = serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "format": format,
+            "rules": options.rules,
+            "scoring": options.scoring,
+            "locale": options.locale,
+            "trust": options.trust,
+            "include_raw_manifest": options.include_raw_manifest,
+            "assertion_filter": options.assertion_filter,
+            "extract_assertions": options.extract_assertions,
+            "analyze_ingredients": options.analyze_ingredients,
+            "now": options.now,
+            "remote": options.remote,
+            "verify_base_url": options.verify_base_url,
+            "revocation": options.revocation,
+            "max_input_size": options.max_input_size,
+            "analyzers": options.analyzers.iter().map(|a| a.name()).collect::<Vec<&str>>()
+        });
+        #[cfg(feature = "pixel")]
+        {
+            settings["frame_samples"] = options.frame_samples.into();
+            settings["watermarks"] = options.watermarks.names().into();
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(settings.to_string());
+        // a rebuild may change them without a new crate version
+        hasher.update(cameras::CAMERAS);
+        hasher.update(quanttables::SIGNATURES);
+        hasher.update(bytes);
+        if let Some(sidecar) = sidecar {
+            hasher.update(b"sidecar");
+            hasher.update(sidecar);
+        }
+        hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+fn to_json(report: &Report) -> serde_json::Value {
+    serde_json::to_value(report).unwrap_or_else(|_| serde_json::json!({}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rules::GeneratorRules, scoring::ScoringConfig};
+
+    #[test]
+    fn keys_cover_the_options() {
+        let options = AnalysisOptions::default();
+        let key = ReportCache::key("png", b"bytes", None, &options);
+        assert_eq!(key, ReportCache::key("png", b"bytes", None, &AnalysisOptions::default()));
+        assert_ne!(key, ReportCache::key("png", b"other", None, &options));
+        assert_ne!(key, ReportCache::key("png", b"bytes", Some(b"sidecar"), &options));
+
+        let scoring = ScoringConfig { generated_from: 75, ..Default::default() };
+        let changed = [
+            AnalysisOptions::default().with_scoring(scoring),
+            AnalysisOptions::default().with_rules(GeneratorRules { generated: Vec::new(), modified: Vec::new(), signers: Vec::new() }),
+            AnalysisOptions::default().with_max_input_size(1024)
+        ];
+        for options in &changed {
+            assert_ne!(key, ReportCache::key("png", b"bytes", None, options));
+        }
+    }
+
+    #[test]
+    fn timings_are_not_cached() {
+        let dir = std::env::temp_dir().join(format!("c2pa-cache-timings-{}", std::process::id()));
+        let cache = ReportCache::new(dir.clone());
+        let options = AnalysisOptions::default().with_timings();
+        let first = cache.report_stream("image/png", b"not a png".to_vec(), &options);
+        assert!(first["timings"]["total_ms"].is_number());
+        let hit = cache.report_stream("image/png", b"not a png".to_vec(), &options);
+        assert!(hit.get("timings").is_none());
+        assert_eq!(hit["verdict"], first["verdict"]);
+        let untimed = Report::from_stream_lenient("image/png", Cursor::new(b"not a png".to_vec()), &AnalysisOptions::default());
+        assert!(to_json(&untimed).get("timings").is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
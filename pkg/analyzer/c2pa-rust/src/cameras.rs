@@ -0,0 +1,149 @@
+use std::sync::OnceLock;
+use serde::Deserialize;
+
+use crate::jpeg;
+
+pub(crate) const CAMERAS: &str = include_str!("../data/cameras.toml");
+/// Images longer than this times their width are taken as panoramas,
+/// stitched larger than the sensor.
+const PANORAMA_RATIO: u32 = 2;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Deserialize, Default)]
+struct CameraFile {
+    #[serde(default)]
+    family: Vec<Family>,
+    #[serde(default)]
+    camera: Vec<Camera>
+}
+
+/// Model prefix all numbered models of which are listed.
+#[derive(Deserialize)]
+struct Family {
+    make: String,
+    prefix: String
+}
+
+impl Family {
+    /// Whether `model`, lowercase, is the prefix and a number, ex. `iphone
+    /// 15` or `nikon z6_3`.
+    fn covers(&self, model: &str) -> bool {
+        model.strip_prefix(&self.prefix.to_lowercase())
+            .is_some_and(|rest| rest.trim_start().starts_with(|c: char| c.is_ascii_digit()))
+    }
+}
+
+#[derive(Deserialize)]
+struct Camera {
+    make: String,
+    models: Vec<String>,
+    /// Largest image recorded, in pixels, None when not known.
+    #[serde(default)]
+    size: Option<(u32, u32)>
+}
+
+fn cameras() -> &'static CameraFile {
+    static PARSED: OnceLock<CameraFile> = OnceLock::new();
+    PARSED.get_or_init(|| toml::from_str(CAMERAS).unwrap_or_default())
+}
+
+fn same_make(exif_make: &str, make: &str) -> bool {
+    exif_make.trim().to_lowercase().starts_with(&make.to_lowercase())
+}
+
+/// Why no such device exists, ex. `iPhone 19 isn't a model of Apple`. None
+/// for listed models and ones the database doesn't know enough about,
+/// makes it doesn't list included.
+pub fn unknown_device(make: Option<&str>, model: &str) -> Option<String> {
+    let model = model.trim();
+    let lowered = model.to_lowercase();
+    let file = cameras();
+    let family = file.family.iter().find(|f| f.covers(&lowered))?;
+    if let Some(make) = make.filter(|m| !same_make(m, &family.make)) {
+        let known = file.family.iter().map(|f| &f.make).chain(file.camera.iter().map(|c| &c.make)).any(|known| same_make(make, known));
+        return known.then(|| format!("{} is a model of {}, not {}", model, family.make, make.trim()));
+    }
+    let listed = file.camera.iter()
+        .filter(|c| c.make == family.make)
+        .any(|c| c.models.iter().any(|m| m.to_lowercase() == lowered));
+    if listed { None } else { Some(format!("{} isn't a model of {}", model, family.make)) }
+}
+
+/// Largest image the model records, (width, height) in landscape.
+pub fn sensor_size(make: Option<&str>, model: &str) -> Option<(u32, u32)> {
+    let lowered = model.trim().to_lowercase();
+    cameras().camera.iter()
+        .filter(|c| make.is_none_or(|m| same_make(m, &c.make)))
+        .find(|c| c.models.iter().any(|m| m.to_lowercase() == lowered))
+        .and_then(|c| c.size)
+}
+
+/// Whether the model can't record an image of `size`, the sensor size it
+/// could when so. Panoramas are never beyond it.
+pub fn beyond_sensor(make: Option<&str>, model: &str, size: (u32, u32)) -> Option<(u32, u32)> {
+    let sensor = sensor_size(make, model)?;
+    let (long, short) = (size.0.max(size.1), size.0.min(size.1));
+    if short == 0 || long > short * PANORAMA_RATIO {
+        return None;
+    }
+    let (sensor_long, sensor_short) = (sensor.0.max(sensor.1), sensor.0.min(sensor.1));
+    if long > sensor_long || short > sensor_short { Some(sensor) } else { None }
+}
+
+/// (width, height) from the JPEG frame header or PNG IHDR chunk.
+pub fn image_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    if let Some(ihdr) = bytes.strip_prefix(PNG_SIGNATURE).and_then(|rest| rest.get(8..16)) {
+        let width = u32::from_be_bytes([ihdr[0], ihdr[1], ihdr[2], ihdr[3]]);
+        let height = u32::from_be_bytes([ihdr[4], ihdr[5], ihdr[6], ihdr[7]]);
+        return Some((width, height));
+    }
+    jpeg::frame_size(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn database_parses() {
+        let file: CameraFile = toml::from_str(CAMERAS).unwrap();
+        for family in &file.family {
+            assert!(file.camera.iter().any(|c| c.make == family.make && c.models.iter().any(|m| family.covers(&m.to_lowercase()))));
+        }
+    }
+
+    #[test]
+    fn devices_are_checked() {
+        assert!(unknown_device(Some("Apple"), "iPhone 15 Pro").is_none());
+        assert!(unknown_device(Some("Apple"), "iPhone 19 Ultra").is_some());
+        assert!(unknown_device(Some("samsung"), "iPhone 15").is_some());
+        assert!(unknown_device(Some("Google"), "Pixel Tablet").is_none());
+        assert!(unknown_device(Some("Canon"), "Canon EOS R100").is_none());
+        assert!(unknown_device(Some("Canon"), "Canon EOS R9 Mark IV").is_some());
+        assert!(unknown_device(Some("Canon"), "Canon EOS Rebel T7i").is_none());
+        assert!(unknown_device(Some("NIKON CORPORATION"), "NIKON Z6_3").is_none());
+        assert!(unknown_device(Some("NIKON CORPORATION"), "NIKON Z 10").is_some());
+        assert!(unknown_device(Some("SONY"), "ILCE-7M3").is_none());
+        assert!(unknown_device(Some("SONY"), "ILCE-7M9").is_some());
+        assert!(unknown_device(Some("FUJIFILM"), "X-T6").is_some());
+        assert!(unknown_device(Some("SONY"), "Canon EOS R5").is_some());
+    }
+
+    #[test]
+    fn makes_missing_from_the_database_are_unknown() {
+        assert!(unknown_device(Some("Foxconn"), "iPhone 15").is_none());
+        assert!(unknown_device(Some("Foxconn"), "iPhone 19").is_none());
+        assert!(unknown_device(None, "iPhone 19").is_some());
+    }
+
+    #[test]
+    fn sizes_are_checked() {
+        assert_eq!(beyond_sensor(Some("Apple"), "iPhone 12", (3024, 4032)), None);
+        assert_eq!(beyond_sensor(Some("Apple"), "iPhone 12", (6048, 8064)), Some((4032, 3024)));
+        assert_eq!(beyond_sensor(Some("Apple"), "iPhone 12", (16000, 3000)), None);
+        // In-camera upscaling
+        assert_eq!(beyond_sensor(Some("Canon"), "Canon EOS R5 Mark II", (16384, 10928)), None);
+        assert_eq!(beyond_sensor(Some("Canon"), "Canon EOS R5", (16384, 10928)), Some((8192, 5464)));
+        assert_eq!(beyond_sensor(Some("SONY"), "ILCE-7M5", (20000, 13000)), None);
+    }
+}
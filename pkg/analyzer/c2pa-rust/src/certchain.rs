@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use x509_parser::{certificate::X509Certificate, objects::{oid2sn, oid_registry}, oid_registry::Oid, pem::Pem};
+
+/// A certificate of the chain that signed the active manifest, signing
+/// certificate first.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ChainCertificate {
+    pub subject: String,
+    pub issuer: String,
+    /// Hex, colon separated.
+    pub serial: String,
+    /// SHA-256 of the DER certificate, hex, colon separated.
+    pub sha256: String,
+    /// ex. `ecdsa-with-SHA256`
+    pub signature_algorithm: String,
+    /// `RSA`, `EC`, `Ed25519`, or the key algorithm OID's short name.
+    pub key_type: String,
+    /// Named curve of EC keys, ex. `prime256v1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_curve: Option<String>,
+    pub key_bits: usize,
+    /// RFC 3339
+    pub not_before: String,
+    pub not_after: String,
+    pub expired: bool
+}
+
+impl ChainCertificate {
+    /// Parses a PEM chain as c2pa returns it, skipping what isn't a
+    /// certificate.
+    pub fn vec_from_pem(chain: &str) -> Vec<ChainCertificate> {
+        Pem::iter_from_buffer(chain.as_bytes())
+            .filter_map(|pem| pem.ok())
+            .filter_map(|pem| pem.parse_x509().ok().map(|cert| ChainCertificate::from_x509(&cert, &pem.contents)))
+            .collect()
+    }
+
+    fn from_x509(cert: &X509Certificate, der: &[u8]) -> ChainCertificate {
+        let key = cert.public_key();
+        let key_type = match oid_name(&key.algorithm.algorithm).as_str() {
+            "rsaEncryption" => String::from("RSA"),
+            "id-ecPublicKey" => String::from("EC"),
+            "ed25519" | "1.3.101.112" => String::from("Ed25519"),
+            other => other.to_string()
+        };
+        let key_curve = match &key.algorithm.parameters {
+            Some(params) if key_type == "EC" => params.as_oid().ok().map(|oid| oid_name(&oid)),
+            _ => None
+        };
+        let key_bits = match key.parsed() {
+            Ok(k) => k.key_size(),
+            Err(_) => 0
+        };
+        let validity = cert.validity();
+        ChainCertificate {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            serial: cert.raw_serial_as_string(),
+            sha256: Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(":"),
+            signature_algorithm: oid_name(&cert.signature_algorithm.algorithm),
+            key_type,
+            key_curve,
+            key_bits,
+            not_before: rfc3339(validity.not_before.timestamp()),
+            not_after: rfc3339(validity.not_after.timestamp()),
+            expired: validity.not_after.timestamp() < Utc::now().timestamp()
+        }
+    }
+}
+
+fn oid_name(oid: &Oid) -> String {
+    match oid2sn(oid, oid_registry()) {
+        Ok(sn) => sn.to_string(),
+        Err(_) => oid.to_id_string()
+    }
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(t) => t.to_rfc3339(),
+        None => String::from("n/a")
+    }
+}
@@ -1,19 +1,51 @@
 use std::collections::HashMap;
 use c2pa::Manifest;
 
+use crate::{actions::ActionData, signature::ClaimSignature, trainingmining::TrainingPermission};
+
+/// One manifest of the store: who signed it and which tools made it.
 #[derive(serde::Serialize)]
+#[non_exhaustive]
 pub struct ClaimData {
     pub claim_id: String,
     pub claim_issuer: String,
-    pub claim_generator: Vec<String>
+    pub claim_generator: Vec<String>,
+    /// Entries of the claim's `c2pa.actions` assertions.
+    pub actions: Vec<ActionData>,
+    /// Labels of the assertions this claim removed from the manifests it
+    /// builds on, ex. `c2pa.actions`.
+    pub redactions: Vec<String>,
+    /// Entries of the claim's training and mining assertions.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub training_mining: Vec<TrainingPermission>,
+    /// None for an unsigned claim.
+    pub signature: Option<ClaimSignature>,
+    /// Every assertion the claim references, in its order.
+    pub assertions: Vec<AssertionEntry>
+}
+
+/// An assertion of a claim.
+#[derive(serde::Serialize)]
+#[non_exhaustive]
+pub struct AssertionEntry {
+    /// As stored, with the instance of a repeated label, ex.
+    /// `c2pa.ingredient__1`.
+    pub label: String,
+    /// The assertion as JSON, only for labels of the assertion filter.
+    /// Hash, thumbnail and ingredient assertions have none, they are
+    /// reported in their own sections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>
 }
 
 impl ClaimData {
     pub fn new(claim_id: String, claim_issuer: String, claim_generator: Vec<String>) -> ClaimData {
-       ClaimData { claim_id, claim_issuer, claim_generator } 
+       ClaimData { claim_id, claim_issuer, claim_generator, actions: Vec::new(), redactions: Vec::new(), training_mining: Vec::new(), signature: None, assertions: Vec::new() }
     }
     
-    pub fn from_manifest(manifest: (&String, &Manifest)) -> ClaimData {
+    /// Includes the data of the assertions matching a label of
+    /// `assertion_filter`, see `label_matches`.
+    pub fn from_manifest(manifest: (&String, &Manifest), assertion_filter: &[String]) -> ClaimData {
         let issuer = match manifest.1.issuer() {
             Some(iss) => iss,
             None => "none".to_string()
@@ -29,23 +61,163 @@ impl ClaimData {
                 Vec::new()
             }
         };
-        ClaimData::new(manifest.0.clone(), issuer, generators)
+        let mut claim = ClaimData::new(manifest.0.clone(), issuer, generators);
+        claim.actions = ActionData::vec_from_manifest(manifest.1);
+        claim.redactions = redactions(manifest.1);
+        claim.training_mining = TrainingPermission::vec_from_manifest(manifest.1);
+        claim.signature = manifest.1.signature_info().map(ClaimSignature::from_info);
+        claim.assertions = AssertionEntry::vec_from_manifest(manifest.1, assertion_filter);
+        claim
+    }
+
+    /// True when the claim redacts an actions assertion, and with it the
+    /// record of how the content was created.
+    pub fn redacts_actions(&self) -> bool {
+        self.redactions.iter().any(|l| l.starts_with("c2pa.actions"))
     }
     
-    pub fn vec_from_manifest(manifest: &HashMap<String, Manifest>) -> Vec<ClaimData> {
+    /// Sorted by manifest label, the map's order changes between runs.
+    pub fn vec_from_manifest(manifest: &HashMap<String, Manifest>, assertion_filter: &[String]) -> Vec<ClaimData> {
         let mut vector: Vec<ClaimData> = Vec::new();
         manifest.iter().for_each(|m| {
-            vector.push(ClaimData::from_manifest(m));
+            vector.push(ClaimData::from_manifest(m, assertion_filter));
         });
+        vector.sort_by(|a, b| a.claim_id.cmp(&b.claim_id));
         vector
     }
 }
 
-pub fn print_data(data: &Vec<ClaimData>) {
+impl AssertionEntry {
+    pub fn vec_from_manifest(manifest: &Manifest, assertion_filter: &[String]) -> Vec<AssertionEntry> {
+        manifest.assertion_references()
+            .filter_map(|reference| reference.url().rsplit('/').next().map(|l| l.to_string()))
+            .map(|label| {
+                let data = match assertion_filter.iter().any(|f| label_matches(&label, f)) {
+                    true => manifest.assertions().iter()
+                        .find(|a| a.label_with_instance() == label)
+                        .and_then(|a| a.value().ok().cloned()),
+                    false => None
+                };
+                AssertionEntry { label, data }
+            })
+            .collect()
+    }
+}
+
+/// An assertion copied into the report by `--extract-assertion`, as the
+/// c2pa reader gives it, ex. for assertions without their own section.
+#[derive(serde::Serialize)]
+#[non_exhaustive]
+pub struct ExtractedAssertion {
+    pub claim_id: String,
+    /// With the instance of a repeated label, ex. `stds.exif__1`.
+    pub label: String,
+    pub data: serde_json::Value
+}
+
+impl ExtractedAssertion {
+    /// The assertions matching a label of `labels`, see `label_matches`,
+    /// sorted by manifest label and in claim order. Hash, thumbnail and
+    /// ingredient assertions aren't read as JSON and never match.
+    pub fn vec_from_manifests(manifests: &HashMap<String, Manifest>, labels: &[String]) -> Vec<ExtractedAssertion> {
+        if labels.is_empty() {
+            return Vec::new();
+        }
+        let mut claim_ids: Vec<&String> = manifests.keys().collect();
+        claim_ids.sort();
+        claim_ids.into_iter()
+            .flat_map(|claim_id| manifests[claim_id].assertions().iter().map(move |a| (claim_id, a)))
+            .filter(|(_, a)| labels.iter().any(|l| label_matches(&a.label_with_instance(), l)))
+            .filter_map(|(claim_id, a)| Some(ExtractedAssertion {
+                claim_id: claim_id.clone(),
+                label: a.label_with_instance(),
+                data: a.value().ok()?.clone()
+            }))
+            .collect()
+    }
+}
+
+/// True when `label` is `filter`, or a version or instance of it, ex.
+/// `c2pa.actions.v2` and `c2pa.actions__1` for `c2pa.actions`.
+pub fn label_matches(label: &str, filter: &str) -> bool {
+    let label = match label.rsplit_once("__") {
+        Some((base, instance)) if instance.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => label
+    };
+    let unversioned = match label.rsplit_once(".v") {
+        Some((base, version)) if !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => label
+    };
+    label == filter || unversioned == filter
+}
+
+/// The manifest has no getter for its redactions, they are read from its
+/// serialized form.
+fn redactions(manifest: &Manifest) -> Vec<String> {
+    match serde_json::to_value(manifest) {
+        Ok(json) => json["redactions"].as_array()
+            .map(|labels| labels.iter().filter_map(|l| l.as_str().map(|l| l.to_string())).collect())
+            .unwrap_or_default(),
+        Err(_) => Vec::new()
+    }
+}
+
+pub fn print_data(data: &[ClaimData]) {
     data.iter().for_each(|claim| {
         println!("=== claim ===");
         println!("claim\t{}", claim.claim_id);
         println!("issuer\t{}", claim.claim_issuer);
         claim.claim_generator.iter().for_each(|claim_gen| { println!("gen\t{}", claim_gen) });
+        claim.actions.iter().for_each(|action| { println!("action\t{}", action.action) });
+        claim.assertions.iter().for_each(|assertion| { println!("assertion\t{}", assertion.label) });
     });
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claims_are_sorted_by_label() {
+        let labels: Vec<String> = (0..32).map(|i| format!("urn:uuid:{:02}", (i * 7) % 32)).collect();
+        let manifests: HashMap<String, Manifest> = labels.iter().map(|l| (l.clone(), Manifest::new("test"))).collect();
+        let mut sorted = labels.clone();
+        sorted.sort();
+        for _ in 0..4 {
+            let ids: Vec<String> = ClaimData::vec_from_manifest(&manifests, &[]).into_iter().map(|c| c.claim_id).collect();
+            assert_eq!(ids, sorted);
+        }
+    }
+
+    #[test]
+    fn labels_match_versions_and_instances() {
+        assert!(label_matches("c2pa.actions", "c2pa.actions"));
+        assert!(label_matches("c2pa.actions.v2", "c2pa.actions"));
+        assert!(label_matches("c2pa.actions.v2__1", "c2pa.actions"));
+        assert!(label_matches("c2pa.actions.v2", "c2pa.actions.v2"));
+        assert!(!label_matches("c2pa.actions.v2", "c2pa.actions.v1"));
+        assert!(!label_matches("c2pa.actions.extra", "c2pa.actions"));
+        assert!(!label_matches("stds.exif", "stds"));
+    }
+
+    #[test]
+    fn assertions_are_extracted_by_label() {
+        let manifest = |assertions: serde_json::Value| -> Manifest {
+            serde_json::from_value(serde_json::json!({"claim_generator": "test", "assertions": assertions})).unwrap()
+        };
+        let first = manifest(serde_json::json!([
+            {"label": "c2pa.actions.v2", "data": {"actions": [{"action": "c2pa.created"}]}},
+            {"label": "stds.exif", "data": {"exif:Make": "Canon"}}
+        ]));
+        let second = manifest(serde_json::json!([{"label": "com.example.custom", "data": {"note": "kept verbatim"}}]));
+        let manifests = HashMap::from([(String::from("urn:c2pa:b"), second), (String::from("urn:c2pa:a"), first)]);
+
+        let labels = [String::from("c2pa.actions"), String::from("com.example.custom")];
+        let extracted = ExtractedAssertion::vec_from_manifests(&manifests, &labels);
+        let found: Vec<(&str, &str)> = extracted.iter().map(|a| (a.claim_id.as_str(), a.label.as_str())).collect();
+        assert_eq!(found, [("urn:c2pa:a", "c2pa.actions.v2"), ("urn:c2pa:b", "com.example.custom")]);
+        assert_eq!(extracted[0].data["actions"][0]["action"], "c2pa.created");
+        assert_eq!(extracted[1].data, serde_json::json!({"note": "kept verbatim"}));
+        assert!(ExtractedAssertion::vec_from_manifests(&manifests, &[]).is_empty());
+        assert!(ExtractedAssertion::vec_from_manifests(&manifests, &[String::from("stds")]).is_empty());
+    }
+}
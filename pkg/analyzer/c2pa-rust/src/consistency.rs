@@ -0,0 +1,277 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::Serialize;
+
+use crate::{cameras, exif::ExifData, scoring::Weight, xmp};
+
+/// EXIF capture times carry no time zone, they are compared with this
+/// much leeway, the widest UTC offset.
+const CAPTURE_LEEWAY_HOURS: i64 = 14;
+/// Leeway between the clocks of the signer and the file system.
+const CLOCK_LEEWAY_HOURS: i64 = 1;
+/// Phone makes, whose lenses are fixed and small.
+const PHONE_MAKES: [&str; 8] = ["apple", "google", "samsung", "xiaomi", "huawei", "oneplus", "oppo", "vivo"];
+/// Largest real focal length and f-number of phone cameras, periscope
+/// telephotos included.
+const PHONE_MAX_FOCAL_LENGTH: f64 = 30.0;
+const PHONE_MAX_F_NUMBER: f64 = 4.5;
+
+/// A disagreement between the file's sources of when and how it was made.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum InconsistencyKind {
+    /// The C2PA signing time is before the EXIF capture time.
+    SignedBeforeCapture,
+    /// An XMP history event is before the EXIF capture time.
+    EditedBeforeCapture,
+    /// The file's modification time is before the EXIF capture time.
+    ModifiedBeforeCapture,
+    /// The file's modification time is before the C2PA signing time.
+    ModifiedBeforeSigning,
+    /// The EXIF capture time is after `AnalysisOptions::now`.
+    CaptureInFuture,
+    /// ISO, f-number or focal length no camera, or not the named one, can
+    /// record.
+    ImpossibleSetting,
+    /// The lens make differs from the make of a phone, whose lenses can't
+    /// be changed.
+    LensMismatch,
+    /// The make and model name a device that doesn't exist, see
+    /// `data/cameras.toml`.
+    UnknownDevice,
+    /// The image is larger than the named camera records.
+    ImpossibleResolution
+}
+
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct Inconsistency {
+    pub kind: InconsistencyKind,
+    /// The values compared, ex. `signed 2024-05-01T10:00:00Z, captured
+    /// 2024:05:02 18:00:00`.
+    pub detail: String
+}
+
+/// The times and camera settings the file's sources give, compared.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ConsistencyFindings {
+    /// EXIF `DateTimeOriginal` as written.
+    pub capture_time: Option<String>,
+    /// Of the active manifest, RFC 3339.
+    pub signing_time: Option<String>,
+    /// Earliest XMP history event, as written.
+    pub history_start: Option<String>,
+    /// Modification time of the file read by path, RFC 3339.
+    pub file_modified: Option<String>,
+    pub inconsistencies: Vec<Inconsistency>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl ConsistencyFindings {
+    /// None when the file gives no capture time, camera or camera settings,
+    /// there is then nothing to compare. `bytes` are the whole file's,
+    /// captures after `now` are in the future, None to not check.
+    pub fn from_sources(
+        exif: Option<&ExifData>,
+        bytes: &[u8],
+        signing_time: Option<&str>,
+        file_modified: Option<DateTime<Utc>>,
+        now: Option<DateTime<Utc>>,
+        weight: Weight
+    ) -> Option<ConsistencyFindings> {
+        let exif = exif?;
+        let settings = exif.camera || exif.iso.is_some() || exif.f_number.is_some() || exif.focal_length.is_some();
+        if exif.capture_time.is_none() && !settings {
+            return None;
+        }
+        let history_start = history_times(bytes).into_iter().min_by_key(|(time, _)| *time);
+        let mut findings = ConsistencyFindings {
+            capture_time: exif.capture_time.clone(),
+            signing_time: signing_time.map(String::from),
+            history_start: history_start.as_ref().map(|(_, written)| written.clone()),
+            file_modified: file_modified.map(|t| t.to_rfc3339()),
+            inconsistencies: Vec::new(),
+            score: 0,
+            confidence: 0
+        };
+        let signed = signing_time.and_then(|t| DateTime::parse_from_rfc3339(t).ok()).map(|t| t.naive_utc());
+        let modified = file_modified.map(|t| t.naive_utc());
+        let captured = exif.capture_time.as_deref().and_then(|t| NaiveDateTime::parse_from_str(t, "%Y:%m:%d %H:%M:%S").ok());
+        let leeway = Duration::hours(CAPTURE_LEEWAY_HOURS);
+        if let Some(captured) = captured {
+            let captured_text = exif.capture_time.clone().unwrap_or_default();
+            if signed.is_some_and(|signed| signed + leeway < captured) {
+                let detail = format!("signed {}, captured {}", signing_time.unwrap_or_default(), captured_text);
+                findings.add(InconsistencyKind::SignedBeforeCapture, detail);
+            }
+            if let Some((_, written)) = history_start.as_ref().filter(|(edited, _)| *edited + leeway < captured) {
+                findings.add(InconsistencyKind::EditedBeforeCapture, format!("history starts {}, captured {}", written, captured_text));
+            }
+            if modified.is_some_and(|modified| modified + leeway < captured) {
+                let detail = format!("modified {}, captured {}", findings.file_modified.clone().unwrap_or_default(), captured_text);
+                findings.add(InconsistencyKind::ModifiedBeforeCapture, detail);
+            }
+            if now.is_some_and(|now| now.naive_utc() + leeway < captured) {
+                findings.add(InconsistencyKind::CaptureInFuture, format!("captured {}", captured_text));
+            }
+        }
+        if let (Some(signed), Some(modified)) = (signed, modified) {
+            if modified + Duration::hours(CLOCK_LEEWAY_HOURS) < signed {
+                let detail = format!("modified {}, signed {}", findings.file_modified.clone().unwrap_or_default(), signing_time.unwrap_or_default());
+                findings.add(InconsistencyKind::ModifiedBeforeSigning, detail);
+            }
+        }
+        findings.check_settings(exif);
+        findings.check_device(exif, cameras::image_size(bytes));
+        if !findings.inconsistencies.is_empty() {
+            (findings.score, findings.confidence) = (weight.score, weight.confidence);
+        }
+        Some(findings)
+    }
+
+    fn check_settings(&mut self, exif: &ExifData) {
+        let make = exif.make.as_deref().unwrap_or_default().to_lowercase();
+        let phone = PHONE_MAKES.iter().any(|p| make.starts_with(p));
+        let iso = exif.iso.as_deref().and_then(number);
+        let f_number = exif.f_number.as_deref().and_then(number);
+        let focal_length = exif.focal_length.as_deref().and_then(number);
+        let mut impossible = Vec::new();
+        if let Some(iso) = iso.filter(|iso| !(6.0..=4_000_000.0).contains(iso)) {
+            impossible.push(format!("ISO {}", iso));
+        }
+        match f_number {
+            Some(f) if !(0.7..=128.0).contains(&f) => impossible.push(format!("f/{}", f)),
+            Some(f) if phone && f > PHONE_MAX_F_NUMBER => impossible.push(format!("f/{} on a phone", f)),
+            _ => {}
+        }
+        match focal_length {
+            Some(mm) if mm <= 0.0 || mm > 5200.0 => impossible.push(format!("{} mm", mm)),
+            Some(mm) if phone && mm > PHONE_MAX_FOCAL_LENGTH => impossible.push(format!("{} mm on a phone", mm)),
+            _ => {}
+        }
+        if !impossible.is_empty() {
+            self.add(InconsistencyKind::ImpossibleSetting, impossible.join(", "));
+        }
+        if let (true, Some(lens_make)) = (phone, exif.lens_make.as_deref()) {
+            if !lens_make.to_lowercase().starts_with(&make) && !make.starts_with(&lens_make.to_lowercase()) {
+                self.add(InconsistencyKind::LensMismatch, format!("{} lens, {} phone", lens_make, exif.make.as_deref().unwrap_or_default()));
+            }
+        }
+    }
+
+    /// The make and model against the camera database, and the image size
+    /// unless an editor, upscaler or generator wrote the file.
+    fn check_device(&mut self, exif: &ExifData, size: Option<(u32, u32)>) {
+        let model = match exif.model.as_deref() {
+            Some(model) => model,
+            None => return
+        };
+        let make = exif.make.as_deref();
+        if let Some(detail) = cameras::unknown_device(make, model) {
+            self.add(InconsistencyKind::UnknownDevice, detail);
+            return;
+        }
+        if let (Some(size), None) = (size, exif.software_rule) {
+            if let Some(sensor) = cameras::beyond_sensor(make, model, size) {
+                let detail = format!("{}x{} from the {}, which records up to {}x{}", size.0, size.1, model, sensor.0, sensor.1);
+                self.add(InconsistencyKind::ImpossibleResolution, detail);
+            }
+        }
+    }
+
+    fn add(&mut self, kind: InconsistencyKind, detail: String) {
+        self.inconsistencies.push(Inconsistency { kind, detail });
+    }
+}
+
+/// `stEvt:when` of the XMP history events, parsed and as written. Times
+/// without an offset are taken as UTC.
+fn history_times(bytes: &[u8]) -> Vec<(NaiveDateTime, String)> {
+    xmp::find_packets(bytes).iter()
+        .flat_map(|p| xmp::parse_properties(p))
+        .filter(|(name, _)| name == "stEvt:when")
+        .filter_map(|(_, value)| {
+            let time = DateTime::parse_from_rfc3339(&value).map(|t| t.naive_utc())
+                .or_else(|_| NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S"))
+                .ok()?;
+            Some((time, value))
+        })
+        .collect()
+}
+
+/// The number at the start of an EXIF display value, ex. `2.8` of `f/2.8`
+/// or `4.25` of `4.25 mm`.
+fn number(value: &str) -> Option<f64> {
+    let value = value.trim().trim_start_matches("f/");
+    let end = value.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(make: &str, capture_time: &str) -> ExifData {
+        ExifData {
+            make: Some(make.to_string()),
+            model: None,
+            software: None,
+            capture_time: Some(capture_time.to_string()),
+            lens_make: None,
+            lens_model: None,
+            focal_length: Some(String::from("4.2")),
+            f_number: Some(String::from("f/1.8")),
+            iso: Some(String::from("100")),
+            camera: true,
+            software_rule: None,
+            score: 0,
+            confidence: 0
+        }
+    }
+
+    fn kinds(findings: &ConsistencyFindings) -> Vec<InconsistencyKind> {
+        findings.inconsistencies.iter().map(|i| i.kind).collect()
+    }
+
+    #[test]
+    fn signing_before_capture_is_inconsistent() {
+        let exif = camera("Apple", "2024:05:02 18:00:00");
+        let weight = Weight::new(40, 30);
+        let findings = ConsistencyFindings::from_sources(Some(&exif), b"", Some("2024-05-01T10:00:00+00:00"), None, None, weight).unwrap();
+        assert_eq!(kinds(&findings), vec![InconsistencyKind::SignedBeforeCapture]);
+        assert_eq!((findings.score, findings.confidence), (40, 30));
+        // Within a time zone of the capture
+        let findings = ConsistencyFindings::from_sources(Some(&exif), b"", Some("2024-05-02T08:00:00+00:00"), None, None, weight).unwrap();
+        assert!(findings.inconsistencies.is_empty());
+        assert_eq!(findings.score, 0);
+    }
+
+    #[test]
+    fn phone_settings_are_checked() {
+        let mut exif = camera("samsung", "2024:05:01 13:37:00");
+        exif.focal_length = Some(String::from("85 mm"));
+        exif.lens_make = Some(String::from("SAMSUNG"));
+        let findings = ConsistencyFindings::from_sources(Some(&exif), b"", None, None, None, Weight::new(40, 30)).unwrap();
+        assert_eq!(kinds(&findings), vec![InconsistencyKind::ImpossibleSetting]);
+        exif.make = Some(String::from("Canon"));
+        let findings = ConsistencyFindings::from_sources(Some(&exif), b"", None, None, None, Weight::new(40, 30)).unwrap();
+        assert!(findings.inconsistencies.is_empty());
+    }
+
+    #[test]
+    fn captures_are_in_the_future_of_the_given_clock_only() {
+        let exif = camera("Apple", "2030:01:01 12:00:00");
+        let weight = Weight::new(40, 30);
+        let findings = ConsistencyFindings::from_sources(Some(&exif), b"", None, None, None, weight).unwrap();
+        assert!(findings.inconsistencies.is_empty());
+        let now = DateTime::parse_from_rfc3339("2029-12-31T00:00:00+00:00").unwrap().with_timezone(&Utc);
+        let findings = ConsistencyFindings::from_sources(Some(&exif), b"", None, None, Some(now), weight).unwrap();
+        assert_eq!(kinds(&findings), vec![InconsistencyKind::CaptureInFuture]);
+        // Within a time zone of the capture
+        let now = now + Duration::hours(30);
+        let findings = ConsistencyFindings::from_sources(Some(&exif), b"", None, None, Some(now), weight).unwrap();
+        assert!(findings.inconsistencies.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// What changed between two reports of the same file, ex. before and after
+/// a scoring change. Reports are compared as JSON, so reports of older
+/// versions can be read back.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ReportDiff {
+    pub file_name: String,
+    /// `before` or `after` when only one side has a report of the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_in: Option<String>,
+    /// The verdicts, before then after, when they differ.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<(String, String)>,
+    /// After minus before.
+    pub score_delta: i64,
+    pub confidence_delta: i64,
+    /// Claim ids.
+    pub claims_added: Vec<String>,
+    pub claims_removed: Vec<String>,
+    /// Evidence reasons.
+    pub evidence_added: Vec<String>,
+    pub evidence_removed: Vec<String>
+}
+
+impl ReportDiff {
+    pub fn new(before: &Value, after: &Value) -> ReportDiff {
+        let verdict = |r: &Value| r["verdict"].as_str().unwrap_or("n/a").to_string();
+        let number = |r: &Value, key: &str| r[key].as_i64().unwrap_or_default();
+        let (before_verdict, after_verdict) = (verdict(before), verdict(after));
+        let (claims_added, claims_removed) = added_removed(&list(before, "claims", "claim_id"), &list(after, "claims", "claim_id"));
+        let (evidence_added, evidence_removed) = added_removed(&list(before, "evidence", "reason"), &list(after, "evidence", "reason"));
+        ReportDiff {
+            file_name: file_name(after),
+            only_in: None,
+            verdict: (before_verdict != after_verdict).then_some((before_verdict, after_verdict)),
+            score_delta: number(after, "score") - number(before, "score"),
+            confidence_delta: number(after, "score_confidence") - number(before, "score_confidence"),
+            claims_added,
+            claims_removed,
+            evidence_added,
+            evidence_removed
+        }
+    }
+
+    /// Pairs the reports of two runs by file name, in the order of
+    /// `before` then the files only `after` has.
+    pub fn vec_from_runs(before: &[Value], after: &[Value]) -> Vec<ReportDiff> {
+        let mut diffs = Vec::new();
+        for report in before {
+            let name = file_name(report);
+            match after.iter().find(|a| file_name(a) == name) {
+                Some(other) => diffs.push(ReportDiff::new(report, other)),
+                None => diffs.push(ReportDiff::only(name, "before"))
+            }
+        }
+        for report in after {
+            let name = file_name(report);
+            if !before.iter().any(|b| file_name(b) == name) {
+                diffs.push(ReportDiff::only(name, "after"));
+            }
+        }
+        diffs
+    }
+
+    /// True when nothing the diff looks at changed.
+    pub fn is_empty(&self) -> bool {
+        self.only_in.is_none()
+            && self.verdict.is_none()
+            && self.score_delta == 0
+            && self.confidence_delta == 0
+            && self.claims_added.is_empty()
+            && self.claims_removed.is_empty()
+            && self.evidence_added.is_empty()
+            && self.evidence_removed.is_empty()
+    }
+
+    fn only(file_name: String, side: &str) -> ReportDiff {
+        ReportDiff {
+            file_name,
+            only_in: Some(side.to_string()),
+            verdict: None,
+            score_delta: 0,
+            confidence_delta: 0,
+            claims_added: Vec::new(),
+            claims_removed: Vec::new(),
+            evidence_added: Vec::new(),
+            evidence_removed: Vec::new()
+        }
+    }
+}
+
+fn file_name(report: &Value) -> String {
+    report["file_name"].as_str().unwrap_or("n/a").to_string()
+}
+
+/// `field` of every entry of the `key` array.
+fn list(report: &Value, key: &str, field: &str) -> Vec<String> {
+    match report[key].as_array() {
+        Some(entries) => entries.iter().filter_map(|e| e[field].as_str().map(|v| v.to_string())).collect(),
+        None => Vec::new()
+    }
+}
+
+fn added_removed(before: &[String], after: &[String]) -> (Vec<String>, Vec<String>) {
+    let added = after.iter().filter(|a| !before.contains(a)).cloned().collect();
+    let removed = before.iter().filter(|b| !after.contains(b)).cloned().collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn changes_of_a_file_are_listed() {
+        let before = json!({
+            "file_name": "a.jpg", "verdict": "Modified", "score": 40, "score_confidence": 50,
+            "claims": [{"claim_id": "urn:c2pa:1"}],
+            "evidence": [{"reason": "Edited"}, {"reason": "Signed"}]
+        });
+        let after = json!({
+            "file_name": "a.jpg", "verdict": "Generated", "score": 95, "score_confidence": 45,
+            "claims": [{"claim_id": "urn:c2pa:1"}, {"claim_id": "urn:c2pa:2"}],
+            "evidence": [{"reason": "Signed"}, {"reason": "Declared generated"}]
+        });
+        let diff = ReportDiff::new(&before, &after);
+        assert_eq!(diff.verdict, Some((String::from("Modified"), String::from("Generated"))));
+        assert_eq!((diff.score_delta, diff.confidence_delta), (55, -5));
+        assert_eq!((diff.claims_added, diff.claims_removed), (vec![String::from("urn:c2pa:2")], vec![]));
+        assert_eq!(diff.evidence_added, ["Declared generated"]);
+        assert_eq!(diff.evidence_removed, ["Edited"]);
+        assert!(ReportDiff::new(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn runs_are_paired_by_file_name() {
+        let report = |name: &str, score: i64| json!({"file_name": name, "verdict": "Unknown", "score": score});
+        let before = [report("a.jpg", 0), report("b.jpg", 10), report("gone.jpg", 0)];
+        let after = [report("new.jpg", 0), report("b.jpg", 10), report("a.jpg", 5)];
+        let diffs = ReportDiff::vec_from_runs(&before, &after);
+        let summary: Vec<(&str, Option<&str>, i64)> = diffs.iter()
+            .map(|d| (d.file_name.as_str(), d.only_in.as_deref(), d.score_delta))
+            .collect();
+        assert_eq!(summary, [("a.jpg", None, 5), ("b.jpg", None, 0), ("gone.jpg", Some("before"), 0), ("new.jpg", Some("after"), 0)]);
+        assert_eq!(diffs.iter().filter(|d| !d.is_empty()).count(), 3);
+        // reports of older versions without the fields still compare
+        assert!(ReportDiff::new(&json!({}), &json!({})).is_empty());
+        let timed = |ms: f64| json!({"file_name": "a.jpg", "verdict": "Unknown", "timings": {"total_ms": ms, "stages": []}});
+        assert!(ReportDiff::new(&timed(1.5), &timed(20.0)).is_empty());
+    }
+}
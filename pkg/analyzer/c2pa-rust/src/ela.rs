@@ -0,0 +1,157 @@
+use std::{fs, io::Cursor, path::Path};
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, GrayImage, Luma, RgbImage};
+use serde::Serialize;
+
+use crate::input;
+
+/// Quality the image is re-compressed at.
+pub const ELA_QUALITY: u8 = 90;
+/// Side of the square blocks the residual is summarized over.
+const BLOCK: u32 = 16;
+/// A block stands out when its mean residual is this many times the
+/// median block's.
+const OUTLIER_RATIO: f64 = 4.0;
+/// Lowest mean residual of an outlier block, an image already saved at
+/// about `ELA_QUALITY` has a median near zero.
+const MIN_OUTLIER_RESIDUAL: f64 = 3.0;
+/// Outlier blocks covering more than this share of the image are the
+/// image's texture rather than a pasted region.
+const MAX_OUTLIER_SHARE: f64 = 0.3;
+const MIN_OUTLIER_SHARE: f64 = 0.005;
+const INCONSISTENT: (u8, u8) = (30, 10);
+
+/// Error Level Analysis: how differently the regions of the image react to
+/// one more JPEG compression. Regions saved fewer times than the rest, ex.
+/// pasted in, keep a higher residual.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ElaFindings {
+    pub quality: u8,
+    /// Mean of the per-pixel residual, the largest channel difference.
+    pub mean_residual: f64,
+    pub max_residual: u8,
+    pub block_size: u32,
+    /// Median, 99th percentile and standard deviation of the block means.
+    pub block_median: f64,
+    pub block_p99: f64,
+    pub block_stddev: f64,
+    /// Share of blocks whose mean is over `OUTLIER_RATIO` times the median
+    /// and at least `MIN_OUTLIER_RESIDUAL`.
+    pub outlier_share: f64,
+    /// A localized region stands out from the rest of the image.
+    pub inconsistent: bool,
+    /// ELA image written with `--ela-dir`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visualization: Option<String>,
+    pub score: u8,
+    pub confidence: u8,
+    #[serde(skip)]
+    residual: Option<GrayImage>
+}
+
+impl ElaFindings {
+    /// None when the image can't be re-compressed. The residual image is
+    /// kept for [`ElaFindings::write_visualization`] when `keep_residual`.
+    pub fn from_image(image: &DynamicImage, keep_residual: bool) -> Option<ElaFindings> {
+        let original = image.to_rgb8();
+        let (width, height) = original.dimensions();
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(Cursor::new(&mut encoded), ELA_QUALITY).encode_image(&original).ok()?;
+        let recompressed = image::load_from_memory(&encoded).ok()?.to_rgb8();
+
+        let residual = residual(&original, &recompressed);
+        let pixel_count = (width as u64 * height as u64) as f64;
+        let mean_residual = residual.pixels().map(|p| p.0[0] as f64).sum::<f64>() / pixel_count;
+        let max_residual = residual.pixels().map(|p| p.0[0]).max().unwrap_or(0);
+
+        let mut blocks = block_means(&residual);
+        blocks.sort_by(|a, b| a.total_cmp(b));
+        let block_median = percentile(&blocks, 0.5);
+        let block_p99 = percentile(&blocks, 0.99);
+        let block_mean = blocks.iter().sum::<f64>() / blocks.len() as f64;
+        let block_stddev = (blocks.iter().map(|b| (b - block_mean).powi(2)).sum::<f64>() / blocks.len() as f64).sqrt();
+        let threshold = (block_median * OUTLIER_RATIO).max(MIN_OUTLIER_RESIDUAL);
+        let outlier_share = blocks.iter().filter(|b| **b > threshold).count() as f64 / blocks.len() as f64;
+
+        let inconsistent = (MIN_OUTLIER_SHARE..=MAX_OUTLIER_SHARE).contains(&outlier_share);
+        let (score, confidence) = if inconsistent { INCONSISTENT } else { (0, 0) };
+        Some(ElaFindings {
+            quality: ELA_QUALITY,
+            mean_residual: round(mean_residual),
+            max_residual,
+            block_size: BLOCK,
+            block_median: round(block_median),
+            block_p99: round(block_p99),
+            block_stddev: round(block_stddev),
+            outlier_share: round(outlier_share),
+            inconsistent,
+            visualization: None,
+            score,
+            confidence,
+            residual: if keep_residual { Some(residual) } else { None }
+        })
+    }
+
+    /// Writes the residual, stretched to the full range, as
+    /// `<file_name>.ela.png` in `dir`.
+    pub(crate) fn write_visualization(&mut self, file_name: &str, dir: &Path) {
+        let residual = match self.residual.take() {
+            Some(r) => r,
+            None => return
+        };
+        let file_name = match input::bare_name(file_name) {
+            Some(name) => name,
+            None => return
+        };
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("Error creating ELA directory {}: {}", dir.display(), e);
+            return;
+        }
+        let scale = 255.0 / self.max_residual.max(1) as f64;
+        let stretched = GrayImage::from_fn(residual.width(), residual.height(), |x, y| {
+            Luma([(residual.get_pixel(x, y).0[0] as f64 * scale).min(255.0) as u8])
+        });
+        let path = dir.join(format!("{}.ela.png", file_name));
+        match stretched.save(&path) {
+            Ok(_) => self.visualization = Some(path.to_string_lossy().to_string()),
+            Err(e) => eprintln!("Error writing ELA image {}: {}", path.display(), e)
+        }
+    }
+}
+
+fn residual(original: &RgbImage, recompressed: &RgbImage) -> GrayImage {
+    GrayImage::from_fn(original.width(), original.height(), |x, y| {
+        let (a, b) = (original.get_pixel(x, y).0, recompressed.get_pixel(x, y).0);
+        Luma([(0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap_or(0)])
+    })
+}
+
+/// Mean residual of each block, partial blocks at the edges included.
+pub(crate) fn block_means(image: &GrayImage) -> Vec<f64> {
+    let mut means = Vec::new();
+    for by in (0..image.height()).step_by(BLOCK as usize) {
+        for bx in (0..image.width()).step_by(BLOCK as usize) {
+            let (w, h) = (BLOCK.min(image.width() - bx), BLOCK.min(image.height() - by));
+            let mut sum = 0_u64;
+            for y in by..by + h {
+                for x in bx..bx + w {
+                    sum += image.get_pixel(x, y).0[0] as u64;
+                }
+            }
+            means.push(sum as f64 / (w * h) as f64);
+        }
+    }
+    means
+}
+
+/// `sorted` must not be empty.
+pub(crate) fn percentile(sorted: &[f64], p: f64) -> f64 {
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+pub(crate) fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,93 @@
+//! The report's score and confidence from the results of the analyzers.
+//!
+//! Every analyzer sums its own findings into a score and confidence, both
+//! 0-100. An analyzer whose confidence is below
+//! `ScoringConfig::abstain_below`, or whose weight is 0, abstains. The
+//! others vote with their confidence scaled by their weight in percent
+//! (100 by default, see `ScoringConfig::analyzer_weights`):
+//!
+//! - score: the vote-weighted mean of their scores, or the highest score
+//!   of a vote of at least `ScoringConfig::strong_from` when higher
+//! - confidence: the sum of the votes, at most 100
+//!
+//! A report where every analyzer abstains has score and confidence 0,
+//! Unknown. Findings only add up within an analyzer, so a weak pixel
+//! result doesn't push a signed camera capture towards Generated. Strong
+//! votes set a floor the mean can't go below, so a validated claim of
+//! generated content isn't outvoted by analyzers that found little.
+
+use crate::{analyzer::AnalyzerResult, scoring::ScoringConfig};
+
+/// Sets the weight and abstention of each result and returns the
+/// combined (score, confidence).
+pub(crate) fn combine(results: &mut [AnalyzerResult], scoring: &ScoringConfig) -> (u8, u8) {
+    let mut votes = 0.0;
+    let mut weighted_score = 0.0;
+    let mut strong_score = 0;
+    for result in results.iter_mut() {
+        result.weight = scoring.analyzer_weight(&result.name);
+        result.abstained = result.weight == 0 || result.confidence == 0 || result.confidence < scoring.abstain_below;
+        if result.abstained {
+            continue;
+        }
+        let vote = result.weight as f64 / 100.0 * result.confidence as f64;
+        votes += vote;
+        weighted_score += vote * result.score as f64;
+        if vote >= scoring.strong_from as f64 {
+            strong_score = strong_score.max(result.score);
+        }
+    }
+    if votes == 0.0 {
+        return (0, 0);
+    }
+    let score = ((weighted_score / votes).round().min(100.0) as u8).max(strong_score);
+    let confidence = votes.round().min(100.0) as u8;
+    (score, confidence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, score: u8, confidence: u8) -> AnalyzerResult {
+        AnalyzerResult {
+            name: name.to_string(),
+            score,
+            confidence,
+            weight: 100,
+            abstained: false,
+            details: None
+        }
+    }
+
+    #[test]
+    fn strong_evidence_is_not_diluted() {
+        let scoring = ScoringConfig::default();
+        let c2pa = result("c2pa", 100, 100);
+        let (score, confidence) = combine(&mut [c2pa], &scoring);
+        assert_eq!((score, confidence), (100, 100));
+
+        // however many analyzers find little
+        let mut results = vec![result("c2pa", 100, 100)];
+        for name in ["exif", "xmp", "jpeg", "pixel", "noise", "spectrum"] {
+            results.push(result(name, 0, 40));
+            let (score, confidence) = combine(&mut results, &scoring);
+            assert_eq!((score, confidence), (100, 100), "with {}", name);
+        }
+    }
+
+    #[test]
+    fn weak_evidence_is_averaged() {
+        let scoring = ScoringConfig::default();
+        let mut results = [result("c2pa", 60, 40), result("pixel", 0, 40), result("exif", 30, 0)];
+        assert_eq!(combine(&mut results, &scoring), (30, 80));
+        assert!(results[2].abstained);
+
+        // a strong vote weighted below the floor is averaged too
+        let mut scoring = ScoringConfig::default();
+        scoring.analyzer_weights.insert(String::from("c2pa"), 50);
+        let mut results = [result("c2pa", 100, 100), result("pixel", 0, 50)];
+        assert_eq!(combine(&mut results, &scoring), (50, 100));
+        assert_eq!(combine(&mut [], &scoring), (0, 0));
+    }
+}
@@ -0,0 +1,124 @@
+use std::{fmt, io};
+use serde::Serialize;
+
+/// Why a file couldn't be analyzed, returned by `Report::from_file` and the
+/// other fallible constructors.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AnalyzeError {
+    /// The file or sidecar couldn't be read.
+    Io(io::Error),
+    /// No C2PA reader handles the format, named by its extension or MIME
+    /// type.
+    UnsupportedFormat(String),
+    /// The leading bytes match no known format while the given extension or
+    /// MIME type has a signature.
+    ContentMismatch(String),
+    /// The manifest store couldn't be read.
+    C2pa(c2pa::Error),
+    /// The input is over the size limit, in bytes.
+    TooLarge { size: u64, limit: u64 },
+    /// The trust or revocation settings couldn't be applied.
+    Settings(String),
+    /// The URL couldn't be fetched or isn't an image, video, audio or PDF.
+    Fetch(String)
+}
+
+impl fmt::Display for AnalyzeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalyzeError::Io(e) => write!(f, "Error reading {}", e),
+            AnalyzeError::UnsupportedFormat(format) => write!(f, "No C2PA reader for {}", format),
+            AnalyzeError::ContentMismatch(format) => write!(f, "The content isn't {} or any other known format", format),
+            AnalyzeError::C2pa(c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing) => write!(f, "No C2PA manifest store found"),
+            AnalyzeError::C2pa(e) => write!(f, "Error reading the manifest store: {}", e),
+            AnalyzeError::TooLarge { size, limit } => write!(f, "Input of {} bytes is over the limit of {}", size, limit),
+            AnalyzeError::Settings(e) => write!(f, "{}", e),
+            AnalyzeError::Fetch(e) => write!(f, "Error fetching {}", e)
+        }
+    }
+}
+
+impl std::error::Error for AnalyzeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AnalyzeError::Io(e) => Some(e),
+            AnalyzeError::C2pa(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for AnalyzeError {
+    fn from(e: io::Error) -> AnalyzeError {
+        AnalyzeError::Io(e)
+    }
+}
+
+impl From<c2pa::Error> for AnalyzeError {
+    fn from(e: c2pa::Error) -> AnalyzeError {
+        AnalyzeError::C2pa(e)
+    }
+}
+
+/// Why part of a report is missing, so an empty report of an unreadable
+/// file isn't taken for one of an image without provenance.
+#[derive(Serialize, Debug)]
+#[non_exhaustive]
+pub struct ReportError {
+    pub kind: ReportErrorKind,
+    pub message: String,
+    /// What `Report::into_result` returns, None for errors of other
+    /// analyzers.
+    #[serde(skip)]
+    pub(crate) source: Option<AnalyzeError>
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum ReportErrorKind {
+    FileNotFound,
+    /// The file exists but couldn't be read, ex. for its permissions.
+    ReadError,
+    /// No C2PA reader handles the format, only the metadata and pixels are
+    /// analyzed.
+    UnsupportedFormat,
+    /// A manifest store was found but couldn't be parsed.
+    ManifestParseError,
+    /// The file has no manifest store, not a failure by itself.
+    JumbfNotFound,
+    TooLarge,
+    /// The trust or revocation settings couldn't be applied.
+    InvalidSettings,
+    /// The URL couldn't be fetched or isn't an image, video, audio or PDF.
+    FetchError,
+    /// The classifier of an `MlAnalyzer` failed or returned no probability.
+    ClassifierError
+}
+
+impl ReportError {
+    pub fn new(kind: ReportErrorKind, message: impl Into<String>) -> ReportError {
+        ReportError { kind, message: message.into(), source: None }
+    }
+
+    /// True unless the error only tells there is no manifest store.
+    pub fn is_failure(&self) -> bool {
+        self.kind != ReportErrorKind::JumbfNotFound
+    }
+}
+
+impl From<AnalyzeError> for ReportError {
+    fn from(e: AnalyzeError) -> ReportError {
+        let kind = match &e {
+            AnalyzeError::Io(e) if e.kind() == io::ErrorKind::NotFound => ReportErrorKind::FileNotFound,
+            AnalyzeError::Io(_) => ReportErrorKind::ReadError,
+            AnalyzeError::UnsupportedFormat(_) | AnalyzeError::ContentMismatch(_) => ReportErrorKind::UnsupportedFormat,
+            AnalyzeError::C2pa(c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing) => ReportErrorKind::JumbfNotFound,
+            AnalyzeError::C2pa(_) => ReportErrorKind::ManifestParseError,
+            AnalyzeError::TooLarge { .. } => ReportErrorKind::TooLarge,
+            AnalyzeError::Settings(_) => ReportErrorKind::InvalidSettings,
+            AnalyzeError::Fetch(_) => ReportErrorKind::FetchError
+        };
+        ReportError { kind, message: e.to_string(), source: Some(e) }
+    }
+}
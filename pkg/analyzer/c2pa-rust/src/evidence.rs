@@ -0,0 +1,60 @@
+use serde::Serialize;
+
+use crate::explanation::{Explanation, Locale};
+
+/// A finding that moved the score, listed in the report so the verdict
+/// can be explained.
+#[derive(Serialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct Evidence {
+    /// Report section the finding comes from, ex. `claims`, `exif` or
+    /// `validation`.
+    pub source: String,
+    /// What was found, ex. `claim generator matched 'midjourney'`.
+    pub reason: String,
+    pub score: u8,
+    pub confidence: u8,
+    /// Reason and contribution in one line, ex.
+    /// `claim generator matched 'midjourney': +100 score, +50 confidence`.
+    pub text: String,
+    /// A sentence in the locale of the options, ex. `This image declares
+    /// it was created by Midjourney.`, the reason for evidence of
+    /// analyzers that don't give one.
+    pub explanation: String,
+    #[serde(skip)]
+    pub(crate) finding: Option<Explanation>
+}
+
+impl Evidence {
+    pub fn new(source: &str, reason: String, score: u8, confidence: u8) -> Evidence {
+        let contribution = match (score, confidence) {
+            (0, c) => format!("+{} confidence", c),
+            (s, 0) => format!("+{} score", s),
+            (s, c) => format!("+{} score, +{} confidence", s, c)
+        };
+        Evidence {
+            source: source.to_string(),
+            text: format!("{}: {}", reason, contribution),
+            explanation: reason.clone(),
+            reason,
+            score,
+            confidence,
+            finding: None
+        }
+    }
+
+    /// Evidence whose reason and explanation are written from the finding.
+    pub fn explained(source: &str, finding: Explanation, score: u8, confidence: u8) -> Evidence {
+        let mut evidence = Evidence::new(source, finding.reason(), score, confidence);
+        evidence.explanation = finding.text(Locale::En);
+        evidence.finding = Some(finding);
+        evidence
+    }
+
+    pub fn localized(mut self, locale: Locale) -> Evidence {
+        if let Some(finding) = &self.finding {
+            self.explanation = finding.text(locale);
+        }
+        self
+    }
+}
@@ -0,0 +1,84 @@
+use std::io::{BufReader, Read, Seek};
+use ::exif::{Exif, In, Tag, Value};
+use serde::Serialize;
+
+use crate::rules::{GeneratorRules, RuleKind};
+
+/// Contribution of an editing tool in `Software` when no camera wrote the
+/// file, the case C2PA-only analysis can't see.
+const EDITED_WITHOUT_CAMERA: (u8, u8) = (30, 20);
+/// Contribution of an editing tool on top of camera tags, a photo that was
+/// retouched.
+const EDITED_CAMERA_IMAGE: (u8, u8) = (20, 10);
+
+/// Camera and software tags of the EXIF block.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ExifData {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub software: Option<String>,
+    /// `DateTimeOriginal`, as written, ex. `2024:05:01 13:37:00`.
+    pub capture_time: Option<String>,
+    pub lens_make: Option<String>,
+    pub lens_model: Option<String>,
+    pub focal_length: Option<String>,
+    pub f_number: Option<String>,
+    pub iso: Option<String>,
+    /// Make or model is set, the file went through a camera.
+    pub camera: bool,
+    /// Kind of the generator rule `software` matched.
+    pub software_rule: Option<RuleKind>,
+    /// What the EXIF block added to the report's score and confidence.
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl ExifData {
+    /// None when the file has no EXIF block or it can't be parsed.
+    pub fn from_stream(stream: impl Read + Seek, rules: &GeneratorRules) -> Option<ExifData> {
+        let exif = ::exif::Reader::new().read_from_container(&mut BufReader::new(stream)).ok()?;
+        let make = ascii_field(&exif, Tag::Make);
+        let model = ascii_field(&exif, Tag::Model);
+        let software = ascii_field(&exif, Tag::Software);
+        let camera = make.is_some() || model.is_some();
+        let matched = software.as_deref().and_then(|s| rules.find(s));
+
+        let (score, confidence) = match matched {
+            Some((RuleKind::Generated, rule)) => (rule.score, rule.confidence),
+            Some((RuleKind::Modified, _)) if camera => EDITED_CAMERA_IMAGE,
+            Some((RuleKind::Modified, _)) => EDITED_WITHOUT_CAMERA,
+            None => (0, 0)
+        };
+        Some(ExifData {
+            capture_time: ascii_field(&exif, Tag::DateTimeOriginal),
+            lens_make: ascii_field(&exif, Tag::LensMake),
+            lens_model: ascii_field(&exif, Tag::LensModel),
+            focal_length: display_field(&exif, Tag::FocalLength),
+            f_number: display_field(&exif, Tag::FNumber),
+            iso: display_field(&exif, Tag::PhotographicSensitivity),
+            software_rule: matched.map(|(kind, _)| kind),
+            make,
+            model,
+            software,
+            camera,
+            score,
+            confidence
+        })
+    }
+}
+
+fn ascii_field(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let value = String::from_utf8_lossy(values.first()?);
+            let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+            if value.is_empty() { None } else { Some(value.to_string()) }
+        },
+        _ => None
+    }
+}
+
+fn display_field(exif: &Exif, tag: Tag) -> Option<String> {
+    exif.get_field(tag, In::PRIMARY).map(|f| f.display_value().to_string())
+}
@@ -0,0 +1,368 @@
+use c2pa::ValidationState;
+use serde::Serialize;
+
+use crate::{consistency::InconsistencyKind, rules::RuleKind, stripped::StrippedSignalKind};
+
+/// Language of the evidence explanations.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Locale {
+    #[default]
+    En,
+    De
+}
+
+impl Locale {
+    /// The locale of a language tag, ex. `de` or `de-AT`, None for
+    /// languages without explanations.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next().unwrap_or_default();
+        match language.to_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            _ => None
+        }
+    }
+}
+
+/// What a built-in analyzer found, from which the evidence `reason` and
+/// the sentence of its `explanation` are written.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Explanation {
+    ClaimsFound(usize),
+    /// Name of the matched rule, the claim generator as written and the
+    /// kind of the rule.
+    ClaimGenerator(String, String, RuleKind),
+    DeclaredGenerated,
+    DeclaredComposite,
+    DeclaredEdits,
+    IngredientsGenerated(usize),
+    /// Label of the claim.
+    ActionsRedacted(String),
+    Signed,
+    Validation(ValidationState),
+    Revoked,
+    /// Label of the claim and the first weakness.
+    WeakSignature(String, String),
+    /// The hash assertion that doesn't match.
+    ContentAltered(String),
+    ThumbnailMismatch(u32),
+    /// Name of the signer entry.
+    Signer(String),
+    /// The kinds of traces of a removed manifest store.
+    ProvenanceStripped(Vec<StrippedSignalKind>),
+    ExifSoftware(String, RuleKind),
+    ExifTags,
+    /// Reasons of the XMP markers.
+    Xmp(Vec<String>),
+    /// Source type codes and where each was found.
+    SourceType(Vec<(String, String)>),
+    PngGenerator(String),
+    PngSoftware(String),
+    PngParameters,
+    /// Rules named in segments, ex. `'midjourney' in COM`, and
+    /// `Generated` when any of them is a generator.
+    JpegSegments(Vec<String>, RuleKind),
+    QuantTablesCameraMismatch,
+    QuantTablesSignature(String),
+    QuantTables,
+    VideoFrames,
+    AudioTag(String, RuleKind),
+    AudioTags,
+    GifComment(String, RuleKind),
+    GifComments,
+    /// The generator or comment that matched a rule, and the embedded
+    /// image that scored highest with its score.
+    Svg(Option<(String, RuleKind)>, Option<(String, u8)>),
+    /// The kind of rule Creator or Producer matched, whether XMP markers
+    /// were found, and the embedded image that scored highest.
+    Pdf(Option<RuleKind>, bool, Option<(String, u8)>),
+    ErrorLevels,
+    UniformNoise,
+    InconsistentNoise,
+    SpectrumPeaks(usize),
+    /// Kinds of the contradictions between the file's times and camera
+    /// settings.
+    Inconsistent(Vec<InconsistencyKind>),
+    /// Generators or decoders of the detected watermarks.
+    Watermarks(Vec<String>),
+    /// Model of an `MlAnalyzer` and its probability of generated, in
+    /// percent.
+    Classifier(String, u8)
+}
+
+impl Explanation {
+    /// The technical reason listed in the evidence, ex. `claim generator
+    /// matched 'midjourney'`, the same in every locale.
+    pub fn reason(&self) -> String {
+        match self {
+            Explanation::ClaimsFound(count) => format!("C2PA claims found ({})", count),
+            Explanation::ClaimGenerator(rule, ..) => format!("claim generator matched '{}'", rule),
+            Explanation::DeclaredGenerated => String::from("actions declare generated content"),
+            Explanation::DeclaredComposite => String::from("actions declare a composite with generated content"),
+            Explanation::DeclaredEdits => String::from("actions declare edits"),
+            Explanation::IngredientsGenerated(count) => format!("ingredients analyzed as generated ({})", count),
+            Explanation::ActionsRedacted(claim) => format!("claim {} redacts the actions it builds on", claim),
+            Explanation::Signed => String::from("manifest signed"),
+            Explanation::Validation(state) => format!("validation {:?}", state),
+            Explanation::Revoked => String::from("signing certificate revoked"),
+            Explanation::WeakSignature(claim, weakness) => format!("claim {} signed with a weak key or chain: {}", claim, weakness),
+            Explanation::ContentAltered(assertion) => format!("content changed after signing, {} doesn't match", assertion),
+            Explanation::ThumbnailMismatch(distance) => format!("claim thumbnail doesn't show the image, hash distance {}", distance),
+            Explanation::Signer(name) => format!("signed with the certificate of {}", name),
+            Explanation::ProvenanceStripped(kinds) => {
+                let kinds: Vec<&str> = kinds.iter().map(|k| match k {
+                    StrippedSignalKind::JumbfFragment => "orphaned JUMBF boxes",
+                    StrippedSignalKind::XmpManifestReference => "XMP manifest reference",
+                    StrippedSignalKind::CredentialsTool => "EXIF Software of a signing tool"
+                }).collect();
+                format!("C2PA manifest stripped: {}", kinds.join(", "))
+            },
+            Explanation::ExifSoftware(software, kind) => format!("EXIF Software '{}' matched a {} rule", software, kind_name(*kind)),
+            Explanation::ExifTags => String::from("EXIF tags"),
+            Explanation::Xmp(reasons) => format!("XMP {}", reasons.join("; ")),
+            Explanation::SourceType(hits) => {
+                let hits: Vec<String> = hits.iter().map(|(source_type, found_in)| format!("{} in {}", source_type, found_in)).collect();
+                format!("digital source type {}", hits.join(", "))
+            },
+            Explanation::PngGenerator(generator) => format!("PNG text names generator '{}'", generator),
+            Explanation::PngSoftware(software) => format!("PNG text Software '{}'", software),
+            Explanation::PngParameters => String::from("PNG text holds generation parameters"),
+            Explanation::JpegSegments(names, kind) => format!("JPEG segments name {}, matching {} rules", names.join(", "), kind_name(*kind)),
+            Explanation::QuantTablesCameraMismatch => String::from("JPEG quantization tables don't fit the camera make"),
+            Explanation::QuantTablesSignature(signature) => format!("JPEG quantization tables match '{}'", signature),
+            Explanation::QuantTables => String::from("JPEG quantization tables"),
+            Explanation::VideoFrames => String::from("pixel analyses of sampled video frames"),
+            Explanation::AudioTag(tool, kind) => format!("audio tag '{}' matched a {} rule", tool, kind_name(*kind)),
+            Explanation::AudioTags => String::from("audio tags"),
+            Explanation::GifComment(comment, kind) => format!("GIF comment '{}' matched a {} rule", comment, kind_name(*kind)),
+            Explanation::GifComments => String::from("GIF comments"),
+            Explanation::Svg(tool, image) => {
+                let mut parts = Vec::new();
+                if let Some((tool, kind)) = tool {
+                    parts.push(format!("'{}' matched a {} rule", tool, kind_name(*kind)));
+                }
+                if let Some((name, score)) = image {
+                    parts.push(format!("embedded image {} scored {}", name, score));
+                }
+                format!("SVG {}", parts.join(", "))
+            },
+            Explanation::Pdf(kind, xmp, image) => {
+                let mut parts = Vec::new();
+                if let Some(kind) = kind {
+                    parts.push(format!("Creator or Producer matched a {} rule", kind_name(*kind)));
+                }
+                if *xmp {
+                    parts.push(String::from("XMP markers"));
+                }
+                if let Some((name, score)) = image {
+                    parts.push(format!("embedded image {} scored {}", name, score));
+                }
+                format!("PDF {}", parts.join(", "))
+            },
+            Explanation::ErrorLevels => String::from("error levels are inconsistent across blocks"),
+            Explanation::UniformNoise => String::from("pixel noise is unnaturally uniform"),
+            Explanation::InconsistentNoise => String::from("pixel noise is inconsistent across blocks"),
+            Explanation::SpectrumPeaks(count) => format!("{} periodic peaks in the spectrum", count),
+            Explanation::Inconsistent(kinds) => {
+                let kinds: Vec<&str> = kinds.iter().map(|k| match k {
+                    InconsistencyKind::SignedBeforeCapture => "signed before captured",
+                    InconsistencyKind::EditedBeforeCapture => "edited before captured",
+                    InconsistencyKind::ModifiedBeforeCapture => "file modified before captured",
+                    InconsistencyKind::ModifiedBeforeSigning => "file modified before signed",
+                    InconsistencyKind::CaptureInFuture => "captured in the future",
+                    InconsistencyKind::ImpossibleSetting => "impossible camera settings",
+                    InconsistencyKind::LensMismatch => "lens doesn't fit the camera",
+                    InconsistencyKind::UnknownDevice => "no such camera",
+                    InconsistencyKind::ImpossibleResolution => "larger than the camera records"
+                }).collect();
+                format!("metadata inconsistent: {}", kinds.join(", "))
+            },
+            Explanation::Watermarks(names) => format!("watermark detected: {}", names.join(", ")),
+            Explanation::Classifier(model, percent) => format!("classifier {} rates {}% generated", model, percent)
+        }
+    }
+
+    /// A sentence for people without the report's vocabulary, ex. `This
+    /// image declares it was created by Midjourney.`
+    pub fn text(&self, locale: Locale) -> String {
+        match locale {
+            Locale::En => self.english(),
+            Locale::De => self.german()
+        }
+    }
+
+    fn english(&self) -> String {
+        match self {
+            Explanation::ClaimsFound(1) => String::from("This image carries Content Credentials describing its origin."),
+            Explanation::ClaimsFound(count) => format!("This image carries Content Credentials from {} steps of its history.", count),
+            Explanation::ClaimGenerator(_, generator, RuleKind::Generated) => format!("This image declares it was created by {}, an AI generator.", generator),
+            Explanation::ClaimGenerator(_, generator, RuleKind::Modified) => format!("This image declares it was edited with {}.", generator),
+            Explanation::DeclaredGenerated => String::from("Its Content Credentials state that it was generated by AI."),
+            Explanation::DeclaredComposite => String::from("Its Content Credentials state that it combines AI-generated content with other material."),
+            Explanation::DeclaredEdits => String::from("Its Content Credentials state that it was edited."),
+            Explanation::IngredientsGenerated(1) => String::from("An image it was made from looks AI-generated."),
+            Explanation::IngredientsGenerated(count) => format!("{} images it was made from look AI-generated.", count),
+            Explanation::ActionsRedacted(_) => String::from("Part of its editing history was removed from the Content Credentials."),
+            Explanation::Signed => String::from("Its Content Credentials are digitally signed."),
+            Explanation::Validation(ValidationState::Invalid) => String::from("The signature of its Content Credentials is not valid."),
+            Explanation::Validation(ValidationState::Valid) => String::from("The signature of its Content Credentials is valid."),
+            Explanation::Validation(ValidationState::Trusted) => String::from("The signature of its Content Credentials is valid and comes from a trusted signer."),
+            Explanation::Revoked => String::from("The certificate its Content Credentials were signed with has been revoked."),
+            Explanation::WeakSignature(..) => String::from("Its Content Credentials were signed with a weak key or certificate."),
+            Explanation::ContentAltered(_) => String::from("The image was changed after its Content Credentials were signed."),
+            Explanation::ThumbnailMismatch(_) => String::from("The preview in its Content Credentials shows a different picture."),
+            Explanation::Signer(name) => format!("Its Content Credentials were signed by {}.", name),
+            Explanation::ProvenanceStripped(_) => String::from("This image shows traces of Content Credentials that were removed."),
+            Explanation::ExifSoftware(software, RuleKind::Generated) => format!("Its camera data names {}, an AI generator.", software),
+            Explanation::ExifSoftware(software, RuleKind::Modified) => format!("Its camera data names the editor {}.", software),
+            Explanation::ExifTags => String::from("Its camera data doesn't look like a camera's."),
+            Explanation::Xmp(_) => String::from("Its embedded metadata points to AI generation."),
+            Explanation::SourceType(_) => String::from("Its metadata labels it as created or altered by AI."),
+            Explanation::PngGenerator(generator) => format!("This image names {} as the AI generator that made it.", generator),
+            Explanation::PngSoftware(software) => format!("This image names the program {}.", software),
+            Explanation::PngParameters => String::from("This image contains the settings of an AI generator."),
+            Explanation::JpegSegments(_, RuleKind::Generated) => String::from("The file contains traces of an AI generator."),
+            Explanation::JpegSegments(_, RuleKind::Modified) => String::from("The file contains traces of an image editor."),
+            Explanation::QuantTablesCameraMismatch => String::from("The way it was compressed doesn't fit the camera it names."),
+            Explanation::QuantTablesSignature(signature) => format!("The way it was compressed matches {}.", signature),
+            Explanation::QuantTables => String::from("The way it was compressed is unusual for a camera."),
+            Explanation::VideoFrames => String::from("Frames of this video look AI-generated."),
+            Explanation::AudioTag(tool, RuleKind::Generated) => format!("This audio names {}, an AI generator.", tool),
+            Explanation::AudioTag(tool, RuleKind::Modified) => format!("This audio names the editor {}.", tool),
+            Explanation::AudioTags => String::from("The tags of this audio look unusual."),
+            Explanation::GifComment(comment, RuleKind::Generated) => format!("A comment in this GIF names an AI generator: {}.", comment),
+            Explanation::GifComment(comment, RuleKind::Modified) => format!("A comment in this GIF names an editor: {}.", comment),
+            Explanation::GifComments => String::from("The comments in this GIF look unusual."),
+            Explanation::Svg(Some((tool, RuleKind::Generated)), _) => format!("This drawing names {}, an AI generator.", tool),
+            Explanation::Svg(Some((tool, RuleKind::Modified)), _) => format!("This drawing was exported with {}.", tool),
+            Explanation::Svg(None, _) => String::from("An image embedded in this drawing looks AI-generated."),
+            Explanation::Pdf(Some(RuleKind::Generated), ..) => String::from("This document names an AI generator as the program that made it."),
+            Explanation::Pdf(Some(RuleKind::Modified), ..) => String::from("This document names an editor as the program that made it."),
+            Explanation::Pdf(None, true, _) => String::from("The metadata of this document points to AI generation."),
+            Explanation::Pdf(None, false, _) => String::from("An image embedded in this document looks AI-generated."),
+            Explanation::ErrorLevels => String::from("Parts of the image were compressed differently, a sign of editing."),
+            Explanation::UniformNoise => String::from("The image lacks the natural noise of a camera sensor."),
+            Explanation::InconsistentNoise => String::from("The noise differs across the image, a sign of editing."),
+            Explanation::SpectrumPeaks(_) => String::from("The image has regular patterns typical of AI generators."),
+            Explanation::Inconsistent(kinds) => sentences(kinds, |kind| match kind {
+                InconsistencyKind::SignedBeforeCapture => "It was signed before the time its camera data says it was taken.",
+                InconsistencyKind::EditedBeforeCapture => "Its editing history starts before the time it was supposedly taken.",
+                InconsistencyKind::ModifiedBeforeCapture => "The file was last changed before the time it was supposedly taken.",
+                InconsistencyKind::ModifiedBeforeSigning => "The file was last changed before its Content Credentials were signed.",
+                InconsistencyKind::CaptureInFuture => "Its camera data gives a capture time in the future.",
+                InconsistencyKind::ImpossibleSetting => "Its camera settings are impossible for the camera it names.",
+                InconsistencyKind::LensMismatch => "Its camera data names a lens that doesn't fit the camera.",
+                InconsistencyKind::UnknownDevice => "Its camera data names a camera that doesn't exist.",
+                InconsistencyKind::ImpossibleResolution => "It is larger than the camera it names can take pictures."
+            }),
+            Explanation::Watermarks(names) => format!("An invisible watermark was found: {}.", names.join(", ")),
+            Explanation::Classifier(model, percent) => format!("The classifier {} rates it {}% likely to be AI-generated.", model, percent)
+        }
+    }
+
+    fn german(&self) -> String {
+        match self {
+            Explanation::ClaimsFound(1) => String::from("Dieses Bild enthält Content Credentials, die seine Herkunft beschreiben."),
+            Explanation::ClaimsFound(count) => format!("Dieses Bild enthält Content Credentials aus {} Schritten seiner Geschichte.", count),
+            Explanation::ClaimGenerator(_, generator, RuleKind::Generated) => format!("Dieses Bild gibt an, mit {} erstellt worden zu sein, einem KI-Generator.", generator),
+            Explanation::ClaimGenerator(_, generator, RuleKind::Modified) => format!("Dieses Bild gibt an, mit {} bearbeitet worden zu sein.", generator),
+            Explanation::DeclaredGenerated => String::from("Laut seinen Content Credentials wurde es von einer KI erzeugt."),
+            Explanation::DeclaredComposite => String::from("Laut seinen Content Credentials kombiniert es KI-generierte Inhalte mit anderem Material."),
+            Explanation::DeclaredEdits => String::from("Laut seinen Content Credentials wurde es bearbeitet."),
+            Explanation::IngredientsGenerated(1) => String::from("Ein Bild, aus dem es entstand, wirkt KI-generiert."),
+            Explanation::IngredientsGenerated(count) => format!("{} Bilder, aus denen es entstand, wirken KI-generiert.", count),
+            Explanation::ActionsRedacted(_) => String::from("Ein Teil seiner Bearbeitungsgeschichte wurde aus den Content Credentials entfernt."),
+            Explanation::Signed => String::from("Seine Content Credentials sind digital signiert."),
+            Explanation::Validation(ValidationState::Invalid) => String::from("Die Signatur seiner Content Credentials ist ungültig."),
+            Explanation::Validation(ValidationState::Valid) => String::from("Die Signatur seiner Content Credentials ist gültig."),
+            Explanation::Validation(ValidationState::Trusted) => String::from("Die Signatur seiner Content Credentials ist gültig und stammt von einem vertrauenswürdigen Unterzeichner."),
+            Explanation::Revoked => String::from("Das Zertifikat, mit dem seine Content Credentials signiert wurden, wurde widerrufen."),
+            Explanation::WeakSignature(..) => String::from("Seine Content Credentials wurden mit einem schwachen Schlüssel oder Zertifikat signiert."),
+            Explanation::ContentAltered(_) => String::from("Das Bild wurde nach der Signatur seiner Content Credentials verändert."),
+            Explanation::ThumbnailMismatch(_) => String::from("Die Vorschau in seinen Content Credentials zeigt ein anderes Bild."),
+            Explanation::Signer(name) => format!("Seine Content Credentials wurden von {} signiert.", name),
+            Explanation::ProvenanceStripped(_) => String::from("Dieses Bild zeigt Spuren entfernter Content Credentials."),
+            Explanation::ExifSoftware(software, RuleKind::Generated) => format!("Seine Kameradaten nennen {}, einen KI-Generator.", software),
+            Explanation::ExifSoftware(software, RuleKind::Modified) => format!("Seine Kameradaten nennen das Bearbeitungsprogramm {}.", software),
+            Explanation::ExifTags => String::from("Seine Kameradaten sehen nicht wie die einer Kamera aus."),
+            Explanation::Xmp(_) => String::from("Seine eingebetteten Metadaten deuten auf KI-Erzeugung hin."),
+            Explanation::SourceType(_) => String::from("Seine Metadaten kennzeichnen es als von KI erzeugt oder verändert."),
+            Explanation::PngGenerator(generator) => format!("Dieses Bild nennt {} als den KI-Generator, der es erzeugt hat.", generator),
+            Explanation::PngSoftware(software) => format!("Dieses Bild nennt das Programm {}.", software),
+            Explanation::PngParameters => String::from("Dieses Bild enthält die Einstellungen eines KI-Generators."),
+            Explanation::JpegSegments(_, RuleKind::Generated) => String::from("Die Datei enthält Spuren eines KI-Generators."),
+            Explanation::JpegSegments(_, RuleKind::Modified) => String::from("Die Datei enthält Spuren eines Bildbearbeitungsprogramms."),
+            Explanation::QuantTablesCameraMismatch => String::from("Die Art der Komprimierung passt nicht zur angegebenen Kamera."),
+            Explanation::QuantTablesSignature(signature) => format!("Die Art der Komprimierung passt zu {}.", signature),
+            Explanation::QuantTables => String::from("Die Art der Komprimierung ist für eine Kamera ungewöhnlich."),
+            Explanation::VideoFrames => String::from("Einzelbilder dieses Videos wirken KI-generiert."),
+            Explanation::AudioTag(tool, RuleKind::Generated) => format!("Diese Audiodatei nennt {}, einen KI-Generator.", tool),
+            Explanation::AudioTag(tool, RuleKind::Modified) => format!("Diese Audiodatei nennt das Bearbeitungsprogramm {}.", tool),
+            Explanation::AudioTags => String::from("Die Tags dieser Audiodatei sind ungewöhnlich."),
+            Explanation::GifComment(comment, RuleKind::Generated) => format!("Ein Kommentar in diesem GIF nennt einen KI-Generator: {}.", comment),
+            Explanation::GifComment(comment, RuleKind::Modified) => format!("Ein Kommentar in diesem GIF nennt ein Bearbeitungsprogramm: {}.", comment),
+            Explanation::GifComments => String::from("Die Kommentare in diesem GIF sind ungewöhnlich."),
+            Explanation::Svg(Some((tool, RuleKind::Generated)), _) => format!("Diese Grafik nennt {}, einen KI-Generator.", tool),
+            Explanation::Svg(Some((tool, RuleKind::Modified)), _) => format!("Diese Grafik wurde mit {} exportiert.", tool),
+            Explanation::Svg(None, _) => String::from("Ein in diese Grafik eingebettetes Bild wirkt KI-generiert."),
+            Explanation::Pdf(Some(RuleKind::Generated), ..) => String::from("Dieses Dokument nennt einen KI-Generator als das Programm, das es erstellt hat."),
+            Explanation::Pdf(Some(RuleKind::Modified), ..) => String::from("Dieses Dokument nennt ein Bearbeitungsprogramm als das Programm, das es erstellt hat."),
+            Explanation::Pdf(None, true, _) => String::from("Die Metadaten dieses Dokuments deuten auf KI-Erzeugung hin."),
+            Explanation::Pdf(None, false, _) => String::from("Ein in dieses Dokument eingebettetes Bild wirkt KI-generiert."),
+            Explanation::ErrorLevels => String::from("Teile des Bildes wurden unterschiedlich komprimiert, ein Hinweis auf Bearbeitung."),
+            Explanation::UniformNoise => String::from("Dem Bild fehlt das natürliche Rauschen eines Kamerasensors."),
+            Explanation::InconsistentNoise => String::from("Das Rauschen ist im Bild ungleichmäßig, ein Hinweis auf Bearbeitung."),
+            Explanation::SpectrumPeaks(_) => String::from("Das Bild zeigt regelmäßige Muster, wie sie für KI-Generatoren typisch sind."),
+            Explanation::Inconsistent(kinds) => sentences(kinds, |kind| match kind {
+                InconsistencyKind::SignedBeforeCapture => "Es wurde signiert, bevor es laut seinen Kameradaten aufgenommen wurde.",
+                InconsistencyKind::EditedBeforeCapture => "Sein Bearbeitungsverlauf beginnt vor der angeblichen Aufnahme.",
+                InconsistencyKind::ModifiedBeforeCapture => "Die Datei wurde zuletzt vor der angeblichen Aufnahme geändert.",
+                InconsistencyKind::ModifiedBeforeSigning => "Die Datei wurde zuletzt vor der Signatur ihrer Content Credentials geändert.",
+                InconsistencyKind::CaptureInFuture => "Seine Kameradaten nennen einen Aufnahmezeitpunkt in der Zukunft.",
+                InconsistencyKind::ImpossibleSetting => "Seine Kameraeinstellungen sind für die angegebene Kamera unmöglich.",
+                InconsistencyKind::LensMismatch => "Seine Kameradaten nennen ein Objektiv, das nicht zur Kamera passt.",
+                InconsistencyKind::UnknownDevice => "Seine Kameradaten nennen eine Kamera, die es nicht gibt.",
+                InconsistencyKind::ImpossibleResolution => "Es ist größer, als die angegebene Kamera Bilder aufnimmt."
+            }),
+            Explanation::Watermarks(names) => format!("Ein unsichtbares Wasserzeichen wurde gefunden: {}.", names.join(", ")),
+            Explanation::Classifier(model, percent) => format!("Der Klassifikator {} hält es zu {}% für KI-generiert.", model, percent)
+        }
+    }
+}
+
+/// One sentence per kind, each kind once.
+fn sentences(kinds: &[InconsistencyKind], sentence: fn(&InconsistencyKind) -> &'static str) -> String {
+    let mut text: Vec<&str> = Vec::new();
+    for kind in kinds {
+        if !text.contains(&sentence(kind)) {
+            text.push(sentence(kind));
+        }
+    }
+    text.join(" ")
+}
+
+fn kind_name(kind: RuleKind) -> &'static str {
+    match kind {
+        RuleKind::Generated => "generated",
+        RuleKind::Modified => "modified"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jpeg_segments_are_worded_by_kind() {
+        let editor = Explanation::JpegSegments(vec![String::from("'photoshop' in APP13")], RuleKind::Modified);
+        assert_eq!(editor.reason(), "JPEG segments name 'photoshop' in APP13, matching modified rules");
+        assert_eq!(editor.text(Locale::En), "The file contains traces of an image editor.");
+        assert_eq!(editor.text(Locale::De), "Die Datei enthält Spuren eines Bildbearbeitungsprogramms.");
+        let generator = Explanation::JpegSegments(vec![String::from("'midjourney' in COM")], RuleKind::Generated);
+        assert_eq!(generator.text(Locale::En), "The file contains traces of an AI generator.");
+        assert_eq!(generator.text(Locale::De), "Die Datei enthält Spuren eines KI-Generators.");
+    }
+}
@@ -0,0 +1,90 @@
+//! C ABI for embedding the analyzer, declared in
+//! `include/c2pa_analyzer.h`. Build the library with
+//!
+//! ```sh
+//! cargo rustc --release --lib --features ffi --crate-type staticlib
+//! ```
+//!
+//! or `cdylib` for a shared library. Every returned string is owned by the
+//! caller and must be given back to [`c2pa_free_string`], never to `free`.
+
+use std::{ffi::{c_char, CStr, CString}, io::Cursor, panic, path::PathBuf, slice};
+
+use crate::{batch::find_sidecar, options::AnalysisOptions, report::Report};
+
+/// Analyzes the file at `path` with the default options and returns the
+/// report JSON. Files that can't be read still get a report.
+///
+/// Returns NULL when `path` is NULL or not UTF-8.
+///
+/// # Safety
+///
+/// `path` must be NULL or a NUL-terminated string valid for the call.
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_analyze_file(path: *const c_char) -> *mut c_char {
+    let path = match str_arg(path) {
+        Some(p) => PathBuf::from(p),
+        None => return std::ptr::null_mut()
+    };
+    report_json(|| Report::from_file_lenient(path.clone(), find_sidecar(&path), &AnalysisOptions::default()))
+}
+
+/// Analyzes `len` bytes at `data` and returns the report JSON. `format`
+/// is a file name whose extension tells the format, ex. `upload.jpg`, or
+/// else an extension or MIME type, ex. `png` or `image/png`.
+///
+/// Returns NULL when `data` or `format` is NULL or `format` isn't UTF-8.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `format` must be NULL or
+/// a NUL-terminated string, both valid for the call.
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_analyze_bytes(data: *const u8, len: usize, format: *const c_char) -> *mut c_char {
+    let format = match str_arg(format) {
+        Some(f) => f,
+        None => return std::ptr::null_mut()
+    };
+    if data.is_null() {
+        return std::ptr::null_mut();
+    }
+    let bytes = slice::from_raw_parts(data, len);
+    report_json(|| match format.contains('.') {
+        true => Report::from_bytes_lenient(format, bytes, &AnalysisOptions::default()),
+        false => {
+            let mut report = Report::from_stream_lenient(format, Cursor::new(bytes), &AnalysisOptions::default());
+            report.file_name = String::from("upload");
+            report
+        }
+    })
+}
+
+/// Releases a string returned by this library. NULL is ignored.
+///
+/// # Safety
+///
+/// `json` must be NULL or a pointer returned by `c2pa_analyze_file` or
+/// `c2pa_analyze_bytes` that wasn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn c2pa_free_string(json: *mut c_char) {
+    if !json.is_null() {
+        drop(CString::from_raw(json));
+    }
+}
+
+unsafe fn str_arg<'a>(arg: *const c_char) -> Option<&'a str> {
+    if arg.is_null() {
+        return None;
+    }
+    CStr::from_ptr(arg).to_str().ok()
+}
+
+/// Runs the analysis without letting a panic unwind into C, which is
+/// undefined behavior. A panic returns NULL.
+fn report_json(analyze: impl FnOnce() -> Report + panic::UnwindSafe) -> *mut c_char {
+    let json = panic::catch_unwind(|| serde_json::to_string(&analyze()));
+    match json {
+        Ok(Ok(j)) => CString::new(j).map_or(std::ptr::null_mut(), CString::into_raw),
+        _ => std::ptr::null_mut()
+    }
+}
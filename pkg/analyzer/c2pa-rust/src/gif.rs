@@ -0,0 +1,215 @@
+use serde::Serialize;
+
+use crate::rules::{GeneratorRules, RuleKind};
+
+const EDITING_TOOL: (u8, u8) = (20, 10);
+/// Comments kept per file, and the longest kept of each.
+const MAX_COMMENTS: usize = 32;
+const MAX_COMMENT_LEN: usize = 4096;
+
+/// The logical screen, frames and extensions of a GIF. GIFs carry no EXIF,
+/// tools name themselves in comment extensions, if at all.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct GifData {
+    /// `87a` or `89a`.
+    pub version: String,
+    pub width: u16,
+    pub height: u16,
+    pub frames: usize,
+    /// Repetitions of the animation, 0 for forever, None without a
+    /// `NETSCAPE2.0` extension.
+    pub loop_count: Option<u16>,
+    pub comments: Vec<String>,
+    /// Identifiers of the application extensions, ex. `NETSCAPE2.0` or
+    /// `XMP DataXMP`.
+    pub applications: Vec<String>,
+    /// The blocks ended before the trailer.
+    pub truncated: bool,
+    /// Comment that matched a generator rule, and the rule's kind.
+    pub tool: Option<String>,
+    pub tool_rule: Option<RuleKind>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl GifData {
+    /// None unless the bytes are a GIF.
+    pub fn from_bytes(bytes: &[u8], rules: &GeneratorRules) -> Option<GifData> {
+        let version = match bytes.get(..6)? {
+            b"GIF87a" => "87a",
+            b"GIF89a" => "89a",
+            _ => return None
+        };
+        let mut gif = GifData {
+            version: version.to_string(),
+            width: le_u16(bytes, 6)?,
+            height: le_u16(bytes, 8)?,
+            frames: 0,
+            loop_count: None,
+            comments: Vec::new(),
+            applications: Vec::new(),
+            truncated: true,
+            tool: None,
+            tool_rule: None,
+            score: 0,
+            confidence: 0
+        };
+        gif.read_blocks(bytes, 13 + color_table_size(*bytes.get(10)?));
+        let matched = gif.comments.iter()
+            .filter_map(|c| rules.find(c).map(|(kind, rule)| (kind, rule, c)))
+            .min_by_key(|(kind, _, _)| *kind != RuleKind::Generated);
+        (gif.score, gif.confidence) = match matched {
+            Some((RuleKind::Generated, rule, _)) => (rule.score, rule.confidence),
+            Some((RuleKind::Modified, _, _)) => EDITING_TOOL,
+            None => (0, 0)
+        };
+        gif.tool = matched.map(|(_, _, comment)| comment.clone());
+        gif.tool_rule = matched.map(|(kind, _, _)| kind);
+        Some(gif)
+    }
+
+    /// Walks the blocks from `at` until the trailer, leaving `truncated`
+    /// set when the data ends first.
+    fn read_blocks(&mut self, bytes: &[u8], mut at: usize) {
+        loop {
+            match bytes.get(at) {
+                Some(0x21) => {
+                    let label = match bytes.get(at + 1) {
+                        Some(l) => *l,
+                        None => return
+                    };
+                    let (data, next) = match sub_blocks(bytes, at + 2) {
+                        Some(read) => read,
+                        None => return
+                    };
+                    match label {
+                        0xFE => self.add_comment(&data),
+                        0xFF => self.add_application(&data),
+                        _ => {}
+                    }
+                    at = next;
+                },
+                Some(0x2C) => {
+                    let packed = match bytes.get(at + 9) {
+                        Some(p) => *p,
+                        None => return
+                    };
+                    // Descriptor, local color table, LZW code size, data
+                    at = match sub_blocks(bytes, at + 10 + color_table_size(packed) + 1) {
+                        Some((_, next)) => next,
+                        None => return
+                    };
+                    self.frames += 1;
+                },
+                Some(0x3B) => {
+                    self.truncated = false;
+                    return;
+                },
+                _ => return
+            }
+        }
+    }
+
+    fn add_comment(&mut self, data: &[u8]) {
+        if self.comments.len() < MAX_COMMENTS {
+            let text = String::from_utf8_lossy(&data[..data.len().min(MAX_COMMENT_LEN)]);
+            self.comments.push(text.trim_end_matches('\0').trim().to_string());
+        }
+    }
+
+    /// The first sub-block holds the 8 byte identifier and 3 byte
+    /// authentication code.
+    fn add_application(&mut self, data: &[u8]) {
+        let identifier = match data.get(..11) {
+            Some(id) => String::from_utf8_lossy(id).to_string(),
+            None => return
+        };
+        if identifier == "NETSCAPE2.0" || identifier == "ANIMEXTS1.0" {
+            if let (Some(1), Some(count)) = (data.get(11), le_u16(data, 12)) {
+                self.loop_count = Some(count);
+            }
+        }
+        if !self.applications.contains(&identifier) {
+            self.applications.push(identifier);
+        }
+    }
+}
+
+/// Bytes of the color table a packed field announces.
+fn color_table_size(packed: u8) -> usize {
+    match packed & 0x80 {
+        0 => 0,
+        _ => 3 << ((packed & 0x07) + 1)
+    }
+}
+
+/// The data of the sub-blocks at `at`, joined, and where the block after
+/// them starts.
+fn sub_blocks(bytes: &[u8], mut at: usize) -> Option<(Vec<u8>, usize)> {
+    let mut data = Vec::new();
+    loop {
+        let size = *bytes.get(at)? as usize;
+        if size == 0 {
+            return Some((data, at + 1));
+        }
+        data.extend_from_slice(bytes.get(at + 1..at + 1 + size)?);
+        at += 1 + size;
+    }
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x1 GIF89a with a 2 color table, a looping NETSCAPE2.0 extension,
+    /// `comment` and one frame.
+    fn gif(comment: &str) -> Vec<u8> {
+        let mut bytes = b"GIF89a\x02\x00\x01\x00\x80\x00\x00".to_vec();
+        bytes.extend_from_slice(&[0, 0, 0, 255, 255, 255]);
+        bytes.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x05\x00\x00");
+        bytes.extend_from_slice(&[0x21, 0xFE, comment.len() as u8]);
+        bytes.extend_from_slice(comment.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(b"\x2C\x00\x00\x00\x00\x02\x00\x01\x00\x00\x02\x02\x44\x01\x00");
+        bytes.push(0x3B);
+        bytes
+    }
+
+    #[test]
+    fn reads_blocks_and_comments() {
+        let rules = GeneratorRules::default();
+        let data = GifData::from_bytes(&gif("Made with Midjourney"), &rules).unwrap();
+        assert_eq!((data.version.as_str(), data.width, data.height, data.frames), ("89a", 2, 1, 1));
+        assert_eq!(data.loop_count, Some(5));
+        assert_eq!(data.applications, ["NETSCAPE2.0"]);
+        assert_eq!(data.comments, ["Made with Midjourney"]);
+        assert!(!data.truncated);
+        assert!(data.tool_rule == Some(RuleKind::Generated));
+        assert_eq!(data.score, 100);
+
+        let edited = GifData::from_bytes(&gif("GIMP 2.10"), &rules).unwrap();
+        assert!(edited.tool_rule == Some(RuleKind::Modified));
+        assert_eq!((edited.score, edited.confidence), EDITING_TOOL);
+        assert!(GifData::from_bytes(b"\x89PNG\r\n\x1a\n", &rules).is_none());
+    }
+
+    #[test]
+    fn truncated_files_are_read_without_panicking() {
+        let rules = GeneratorRules::default();
+        let bytes = gif("Made with Midjourney");
+        for len in 0..bytes.len() {
+            let data = GifData::from_bytes(&bytes[..len], &rules);
+            match len {
+                0..=10 => assert!(data.is_none(), "{} bytes", len),
+                _ => assert!(data.unwrap().truncated, "{} bytes", len)
+            }
+        }
+        // the logical screen descriptor of the report
+        assert!(GifData::from_bytes(b"GIF89a\x01\x00\x01\x00", &rules).is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use std::{io::Cursor, net::SocketAddr};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use crate::{input, options::AnalysisOptions, report::{Report, Verdict}, reportsigning::ReportSigner, rules::RuleKind};
+
+/// Types and the service trait generated from
+/// `proto/c2pa_analyzer/v1/analyzer.proto`.
+pub mod proto {
+    tonic::include_proto!("c2pa_analyzer.v1");
+}
+
+use proto::{analyzer_server::{Analyzer, AnalyzerServer}, AnalyzeChunk, AnalyzeResponse};
+
+/// Uploads above, or above the options' `max_input_size`, are refused
+/// with `RESOURCE_EXHAUSTED`.
+pub const MAX_UPLOAD_SIZE: usize = 256 * 1024 * 1024;
+/// Largest message tonic accepts, a chunk.
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// The `Analyzer` service, analyzing every upload with the same options.
+pub struct AnalyzerService {
+    options: AnalysisOptions
+}
+
+impl AnalyzerService {
+    pub fn new(options: AnalysisOptions) -> AnalyzerService {
+        AnalyzerService { options }
+    }
+}
+
+#[tonic::async_trait]
+impl Analyzer for AnalyzerService {
+    async fn analyze(&self, request: Request<Streaming<AnalyzeChunk>>) -> Result<Response<AnalyzeResponse>, Status> {
+        let mut chunks = request.into_inner();
+        let mut file_name = String::new();
+        let mut format = String::new();
+        let mut bytes = Vec::new();
+        let limit = self.options.max_input_size.map_or(MAX_UPLOAD_SIZE, |l| l.min(MAX_UPLOAD_SIZE as u64) as usize);
+        while let Some(chunk) = chunks.message().await? {
+            if file_name.is_empty() && format.is_empty() {
+                file_name = chunk.file_name;
+                format = chunk.format;
+            }
+            if bytes.len() + chunk.data.len() > limit {
+                return Err(Status::resource_exhausted(format!("Upload over {} bytes", limit)));
+            }
+            bytes.extend_from_slice(&chunk.data);
+        }
+        if bytes.is_empty() {
+            return Err(Status::invalid_argument("Empty upload"));
+        }
+        if file_name.is_empty() && format.is_empty() {
+            return Err(Status::invalid_argument("The first chunk needs a file_name or format"));
+        }
+        if !file_name.is_empty() {
+            file_name = match input::bare_name(&file_name) {
+                Some(name) => name.to_string(),
+                None => return Err(Status::invalid_argument(format!("Invalid file_name {}", file_name)))
+            };
+        }
+
+        // Parsing and the pixel analyses block, keep them off the runtime
+        let options = self.options.clone();
+        let report = tokio::task::spawn_blocking(move || match file_name.is_empty() {
+            true => {
+                let mut report = Report::from_stream_lenient(&format, Cursor::new(bytes), &options);
+                report.file_name = String::from("upload");
+                report
+            },
+            false => Report::from_bytes_lenient(&file_name, &bytes, &options)
+        }).await;
+        match report {
+            Ok(report) => Ok(Response::new(AnalyzeResponse { report: Some(to_proto(&report, self.options.report_signer.as_ref())) })),
+            Err(e) => Err(Status::internal(format!("Analysis failed: {}", e)))
+        }
+    }
+}
+
+/// Serves the `Analyzer` service on `listen`, ex. `0.0.0.0:9091`, until
+/// the process ends.
+pub fn serve(listen: &str, options: AnalysisOptions) -> Result<(), String> {
+    let address: SocketAddr = match listen.parse() {
+        Ok(a) => a,
+        Err(e) => return Err(format!("Invalid address {}: {}", listen, e))
+    };
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Error starting the runtime: {}", e))
+    };
+    let service = AnalyzerServer::new(AnalyzerService::new(options)).max_decoding_message_size(MAX_CHUNK_SIZE);
+    runtime.block_on(async {
+        let listener = match tokio::net::TcpListener::bind(address).await {
+            Ok(l) => l,
+            Err(e) => return Err(format!("Error listening on {}: {}", listen, e))
+        };
+        eprintln!("Listening on {}", listen);
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .map_err(|e| format!("Error serving on {}: {}", listen, e))
+    })
+}
+
+/// `report_json` is signed with `signer`, the other fields aren't.
+fn to_proto(report: &Report, signer: Option<&ReportSigner>) -> proto::Report {
+    let verdict = match report.verdict {
+        Verdict::Generated => proto::Verdict::Generated,
+        Verdict::Modified => proto::Verdict::Modified,
+        Verdict::Genuine => proto::Verdict::Genuine,
+        Verdict::Unknown => proto::Verdict::Unknown
+    };
+    let probabilities = &report.probabilities;
+    proto::Report {
+        file_name: report.file_name.clone(),
+        file_type: report.file_type.clone(),
+        verdict: verdict.into(),
+        score: report.score as u32,
+        score_confidence: report.score_confidence as u32,
+        probabilities: Some(proto::VerdictProbabilities {
+            generated: probabilities.generated,
+            modified: probabilities.modified,
+            genuine: probabilities.genuine,
+            evidence_strength: probabilities.evidence_strength
+        }),
+        evidence: report.evidence.iter()
+            .map(|e| proto::Evidence {
+                source: e.source.clone(),
+                reason: e.reason.clone(),
+                score: e.score as u32,
+                confidence: e.confidence as u32,
+                text: e.text.clone(),
+                explanation: e.explanation.clone()
+            })
+            .collect(),
+        claims_found: report.claims_found,
+        claims_count: report.claims_count as u32,
+        generator_matches: report.generator_matches.iter()
+            .map(|m| proto::GeneratorMatch {
+                claim_id: m.claim_id.clone(),
+                generator: m.generator.clone(),
+                kind: String::from(match m.kind {
+                    RuleKind::Generated => "generated",
+                    RuleKind::Modified => "modified"
+                }),
+                rule: m.rule.clone(),
+                score: m.score as u32,
+                confidence: m.confidence as u32
+            })
+            .collect(),
+        validation_state: format!("{:?}", report.validation.state),
+        report_json: match signer {
+            Some(signer) => signer.sign_report(report).map(|r| r.to_string()).unwrap_or_default(),
+            None => serde_json::to_string(report).unwrap_or_default()
+        }
+    }
+}
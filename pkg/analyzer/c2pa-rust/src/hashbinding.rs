@@ -0,0 +1,212 @@
+use ciborium::Value;
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::validation::Certificate;
+
+/// A hard binding of the manifest that doesn't match the asset: the
+/// content changed after it was signed.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct HashMismatch {
+    /// Label of the hash assertion, ex. `c2pa.hash.data`.
+    pub assertion: String,
+    /// Validation status code, ex. `assertion.dataHash.mismatch`.
+    pub code: String,
+    pub explanation: String,
+    pub alg: Option<String>,
+    /// Hex digest the manifest signed, None for BMFF Merkle trees.
+    pub expected: Option<String>,
+    /// Hex digest of the asset as it is now, only computed for data
+    /// hashes.
+    pub computed: Option<String>,
+    /// What the hash covers: the byte ranges of a data hash, the boxes of
+    /// a box hash or the excluded boxes of a BMFF hash.
+    pub regions: Vec<HashRegion>
+}
+
+/// Part of the asset a hard binding covers or leaves out.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct HashRegion {
+    /// `bytes` for a range, the box names of a box hash, ex. `SOI`, or the
+    /// xpath of a BMFF exclusion, ex. `/uuid`.
+    pub name: String,
+    pub start: Option<u64>,
+    pub length: Option<u64>,
+    /// Hex digest the manifest signed for this region alone.
+    pub expected: Option<String>,
+    /// True for BMFF exclusions, left out of the hash.
+    pub excluded: bool
+}
+
+impl HashMismatch {
+    /// The mismatches among the active manifest's status entries, with the
+    /// hash assertions read from the `sidecar` manifest store, or else the
+    /// one embedded in `bytes`.
+    pub fn vec_from_certs(certs: &[Certificate], format: &str, bytes: &[u8], sidecar: Option<&[u8]>) -> Vec<HashMismatch> {
+        let mismatches: Vec<&Certificate> = certs.iter().filter(|c| is_hash_mismatch(&c.cert_code)).collect();
+        if mismatches.is_empty() {
+            return Vec::new();
+        }
+        let store = match sidecar {
+            Some(store) => store.to_vec(),
+            None => c2pa::jumbf_io::load_jumbf_from_memory(format, bytes).unwrap_or_default()
+        };
+        mismatches.into_iter()
+            .map(|cert| {
+                let assertion = cert.cert_id.rsplit('/').next().unwrap_or_default().to_string();
+                let cbor = assertion_cbor(&store, &cert.cert_id);
+                HashMismatch::new(assertion, cert, cbor.as_ref(), bytes)
+            })
+            .collect()
+    }
+
+    fn new(assertion: String, cert: &Certificate, cbor: Option<&Value>, bytes: &[u8]) -> HashMismatch {
+        let alg = cbor.and_then(|c| field(c, "alg")).and_then(Value::as_text).map(|a| a.to_string());
+        let expected = cbor.and_then(|c| field(c, "hash")).and_then(Value::as_bytes).map(|h| hex(h));
+        let (computed, regions) = match cert.cert_code.as_str() {
+            "assertion.dataHash.mismatch" => {
+                let exclusions: Vec<(u64, u64)> = cbor.and_then(|c| field(c, "exclusions"))
+                    .and_then(Value::as_array)
+                    .map(|list| list.iter().filter_map(|e| Some((uint(field(e, "start")?)?, uint(field(e, "length")?)?))).collect())
+                    .unwrap_or_default();
+                let ranges = hashed_ranges(&exclusions, bytes.len() as u64);
+                let regions = ranges.iter()
+                    .map(|&(start, length)| HashRegion { name: String::from("bytes"), start: Some(start), length: Some(length), expected: None, excluded: false })
+                    .collect();
+                (digest(alg.as_deref().unwrap_or("sha256"), bytes, &ranges), regions)
+            },
+            "assertion.boxesHash.mismatch" => {
+                let boxes = cbor.and_then(|c| field(c, "boxes")).and_then(Value::as_array).cloned().unwrap_or_default();
+                let regions = boxes.iter()
+                    .map(|b| {
+                        let names: Vec<&str> = field(b, "names").and_then(Value::as_array)
+                            .map(|n| n.iter().filter_map(Value::as_text).collect())
+                            .unwrap_or_default();
+                        HashRegion {
+                            name: names.join(", "),
+                            start: None,
+                            length: None,
+                            expected: field(b, "hash").and_then(Value::as_bytes).map(|h| hex(h)),
+                            excluded: false
+                        }
+                    })
+                    .collect();
+                (None, regions)
+            },
+            _ => {
+                let exclusions = cbor.and_then(|c| field(c, "exclusions")).and_then(Value::as_array).cloned().unwrap_or_default();
+                let regions = exclusions.iter()
+                    .filter_map(|e| field(e, "xpath").and_then(Value::as_text))
+                    .map(|xpath| HashRegion { name: xpath.to_string(), start: None, length: None, expected: None, excluded: true })
+                    .collect();
+                (None, regions)
+            }
+        };
+        HashMismatch {
+            assertion,
+            code: cert.cert_code.clone(),
+            explanation: cert.cert_explanation.clone(),
+            alg,
+            expected,
+            computed,
+            regions
+        }
+    }
+}
+
+fn is_hash_mismatch(code: &str) -> bool {
+    matches!(code, "assertion.dataHash.mismatch" | "assertion.boxesHash.mismatch" | "assertion.bmffHash.mismatch")
+}
+
+/// The CBOR of the assertion `uri` points to, ex.
+/// `self#jumbf=/c2pa/urn:uuid:.../c2pa.assertions/c2pa.hash.data`.
+fn assertion_cbor(store: &[u8], uri: &str) -> Option<Value> {
+    let path = uri.split_once('=')?.1.trim_start_matches('/');
+    let mut content = store;
+    for label in path.split('/') {
+        content = jumbf_children(content).into_iter().find(|(l, _)| l == label)?.1;
+    }
+    let (_, cbor) = boxes(content).into_iter().find(|(kind, _)| kind == b"cbor")?;
+    ciborium::from_reader(cbor).ok()
+}
+
+/// The labeled superboxes in `data`, with their contents after the
+/// description box.
+fn jumbf_children(data: &[u8]) -> Vec<(String, &[u8])> {
+    boxes(data).into_iter()
+        .filter(|(kind, _)| kind == b"jumb")
+        .filter_map(|(_, jumb)| {
+            let mut inner = boxes(jumb).into_iter();
+            let (kind, description) = inner.next()?;
+            if &kind != b"jumd" || description.len() < 17 || description[16] & 0x02 == 0 {
+                return None;
+            }
+            let label = description[17..].split(|&b| b == 0).next()?;
+            let header = 8 + description.len();
+            Some((String::from_utf8_lossy(label).to_string(), jumb.get(header..)?))
+        })
+        .collect()
+}
+
+/// ISO BMFF style boxes, the type and payload of each.
+fn boxes(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut found = Vec::new();
+    while data.len() >= 8 {
+        let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64;
+        let kind = [data[4], data[5], data[6], data[7]];
+        let (header, size) = match size {
+            0 => (8, data.len() as u64),
+            1 if data.len() >= 16 => (16, u64::from_be_bytes(data[8..16].try_into().unwrap_or_default())),
+            _ => (8, size)
+        };
+        if size < header || size > data.len() as u64 {
+            break;
+        }
+        found.push((kind, &data[header as usize..size as usize]));
+        data = &data[size as usize..];
+    }
+    found
+}
+
+/// The (start, length) ranges left between the exclusions.
+fn hashed_ranges(exclusions: &[(u64, u64)], len: u64) -> Vec<(u64, u64)> {
+    let mut exclusions = exclusions.to_vec();
+    exclusions.sort();
+    let mut ranges = Vec::new();
+    let mut position = 0;
+    for (start, length) in exclusions {
+        if start > position && position < len {
+            ranges.push((position, start.min(len) - position));
+        }
+        position = position.max(start.saturating_add(length));
+    }
+    if position < len {
+        ranges.push((position, len - position));
+    }
+    ranges
+}
+
+fn digest(alg: &str, bytes: &[u8], ranges: &[(u64, u64)]) -> Option<String> {
+    let parts = ranges.iter().map(|&(start, length)| &bytes[start as usize..(start + length) as usize]);
+    let hash = match alg {
+        "sha256" => parts.fold(Sha256::new(), |h, p| h.chain_update(p)).finalize().to_vec(),
+        "sha384" => parts.fold(Sha384::new(), |h, p| h.chain_update(p)).finalize().to_vec(),
+        "sha512" => parts.fold(Sha512::new(), |h, p| h.chain_update(p)).finalize().to_vec(),
+        _ => return None
+    };
+    Some(hex(&hash))
+}
+
+fn field<'a>(map: &'a Value, name: &str) -> Option<&'a Value> {
+    map.as_map()?.iter().find(|(k, _)| k.as_text() == Some(name)).map(|(_, v)| v)
+}
+
+fn uint(value: &Value) -> Option<u64> {
+    value.as_integer().and_then(|i| u64::try_from(i).ok())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use c2pa::Manifest;
+use ciborium::Value as Cbor;
+use serde::Serialize;
+use serde_json::Value;
+use x509_parser::parse_x509_certificate;
+
+/// COSE header label of the X.509 certificate chain.
+const X5CHAIN: i64 = 33;
+
+/// A CAWG identity assertion, `cawg.identity`, or the authors of a
+/// schema.org `CreativeWork` assertion: who vouches for the content beyond
+/// the certificate that signed the claim.
+///
+/// The identity's own COSE signature isn't verified, see `verified`. The
+/// claim signature covers the assertion, so it is only as trustworthy as
+/// the claim's signer.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct IdentityAssertion {
+    pub claim_id: String,
+    pub label: String,
+    /// Whether the identity's own signature was checked, always false: the
+    /// identities are what the assertion claims.
+    pub verified: bool,
+    /// `cawg.x509.cose` or `cawg.identity_claims_aggregation`, None for
+    /// `CreativeWork` authors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig_type: Option<String>,
+    /// Subject of the certificate in the identity's COSE signature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Who issued the credential, ex. the identity claims aggregator.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// The credential subject's id, ex. a DID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub identities: Vec<Identity>,
+    /// Labels of the assertions the identity is bound to, ex.
+    /// `c2pa.actions`.
+    pub referenced_assertions: Vec<String>
+}
+
+/// A social media account, website or other identity of the subject.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct Identity {
+    /// ex. `cawg.social_media` or `Person`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// The service the identity is on, ex. `Instagram`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// When the credential says the issuer verified it, None for
+    /// identities only declared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claimed_verified_at: Option<String>
+}
+
+impl IdentityAssertion {
+    /// The identity assertions of every manifest, sorted by manifest label.
+    pub fn vec_from_manifests(manifests: &HashMap<String, Manifest>) -> Vec<IdentityAssertion> {
+        let mut labels: Vec<&String> = manifests.keys().collect();
+        labels.sort();
+        let mut assertions = Vec::new();
+        for label in labels {
+            for assertion in manifests[label].assertions() {
+                let value = match assertion.value() {
+                    Ok(v) => v,
+                    Err(_) => continue
+                };
+                let found = match assertion.label() {
+                    l if l.starts_with("cawg.identity") => IdentityAssertion::from_cawg(label, l, value),
+                    l if l.starts_with("stds.schema-org.CreativeWork") => IdentityAssertion::from_creative_work(label, l, value),
+                    _ => None
+                };
+                assertions.extend(found);
+            }
+        }
+        assertions
+    }
+
+    fn new(claim_id: &str, label: &str) -> IdentityAssertion {
+        IdentityAssertion {
+            claim_id: claim_id.to_string(),
+            label: label.to_string(),
+            verified: false,
+            sig_type: None,
+            signer: None,
+            issuer: None,
+            subject: None,
+            identities: Vec::new(),
+            referenced_assertions: Vec::new()
+        }
+    }
+
+    fn from_cawg(claim_id: &str, label: &str, value: &Value) -> Option<IdentityAssertion> {
+        let payload = value.get("signer_payload")?;
+        let mut identity = IdentityAssertion::new(claim_id, label);
+        identity.sig_type = payload.get("sig_type").and_then(|t| t.as_str()).map(|t| t.to_string());
+        identity.referenced_assertions = payload.get("referenced_assertions")
+            .and_then(|r| r.as_array())
+            .map(|refs| refs.iter().filter_map(|r| r.get("url")?.as_str()).map(|u| u.rsplit('/').next().unwrap_or(u).to_string()).collect())
+            .unwrap_or_default();
+        // Byte strings come out of the reader's JSON as arrays of numbers
+        let signature: Vec<u8> = match value.get("signature") {
+            Some(Value::Array(bytes)) => bytes.iter().filter_map(|b| b.as_u64()).map(|b| b as u8).collect(),
+            _ => return Some(identity)
+        };
+        let (headers, payload) = match cose_sign1(&signature) {
+            Some(parts) => parts,
+            None => return Some(identity)
+        };
+        identity.signer = header(&headers, X5CHAIN).and_then(leaf_subject);
+        if identity.sig_type.as_deref() == Some("cawg.identity_claims_aggregation") {
+            if let Some(credential) = payload.and_then(|p| serde_json::from_slice::<Value>(&p).ok()) {
+                identity.add_credential(&credential);
+            }
+        }
+        Some(identity)
+    }
+
+    /// A W3C verifiable credential of an identity claims aggregator.
+    fn add_credential(&mut self, credential: &Value) {
+        self.issuer = match credential.get("issuer") {
+            Some(Value::String(id)) => Some(id.clone()),
+            Some(issuer) => issuer.get("name").or_else(|| issuer.get("id")).and_then(|n| n.as_str()).map(|n| n.to_string()),
+            None => None
+        };
+        let subject = match credential.get("credentialSubject") {
+            Some(Value::Array(subjects)) => subjects.first(),
+            subject => subject
+        };
+        let subject = match subject {
+            Some(s) => s,
+            None => return
+        };
+        self.subject = subject.get("id").and_then(|i| i.as_str()).map(|i| i.to_string());
+        let claimed = subject.get("verifiedIdentities").and_then(|v| v.as_array());
+        self.identities = claimed.into_iter().flatten().filter_map(|v| {
+            let text = |key: &str| v.get(key).and_then(|t| t.as_str()).map(|t| t.to_string());
+            Some(Identity {
+                kind: text("type")?,
+                name: text("name"),
+                username: text("username"),
+                uri: text("uri"),
+                provider: v.get("provider").and_then(|p| p.get("name").or_else(|| p.get("id"))).and_then(|n| n.as_str()).map(|n| n.to_string()),
+                claimed_verified_at: text("verifiedAt")
+            })
+        }).collect();
+    }
+
+    fn from_creative_work(claim_id: &str, label: &str, value: &Value) -> Option<IdentityAssertion> {
+        let authors = match value.get("author")? {
+            Value::Array(authors) => authors.iter().collect(),
+            author => vec![author]
+        };
+        let mut identity = IdentityAssertion::new(claim_id, label);
+        identity.identities = authors.into_iter().filter_map(|a| {
+            let text = |key: &str| a.get(key).and_then(|t| t.as_str()).map(|t| t.to_string());
+            Some(Identity {
+                kind: text("@type").unwrap_or_else(|| String::from("Person")),
+                name: text("name"),
+                username: None,
+                uri: text("identifier").or_else(|| text("url")),
+                provider: None,
+                claimed_verified_at: None
+            }).filter(|i| i.name.is_some() || i.uri.is_some())
+        }).collect();
+        Some(identity).filter(|i| !i.identities.is_empty())
+    }
+}
+
+type CoseHeaders = Vec<(Cbor, Cbor)>;
+
+/// The protected and unprotected headers, and the payload unless it is
+/// detached, of a COSE_Sign1 structure.
+fn cose_sign1(bytes: &[u8]) -> Option<(CoseHeaders, Option<Vec<u8>>)> {
+    let value: Cbor = ciborium::from_reader(bytes).ok()?;
+    let value = match value {
+        Cbor::Tag(18, inner) => *inner,
+        other => other
+    };
+    let mut parts = value.into_array().ok()?.into_iter();
+    let protected = match parts.next()? {
+        Cbor::Bytes(b) if !b.is_empty() => ciborium::from_reader::<Cbor, _>(&b[..]).ok()?.into_map().ok()?,
+        _ => Vec::new()
+    };
+    let unprotected = parts.next()?.into_map().unwrap_or_default();
+    let payload = match parts.next() {
+        Some(Cbor::Bytes(b)) => Some(b),
+        _ => None
+    };
+    Some((protected.into_iter().chain(unprotected).collect(), payload))
+}
+
+fn header(headers: &[(Cbor, Cbor)], label: i64) -> Option<&Cbor> {
+    headers.iter().find(|(key, _)| key.as_integer().is_some_and(|k| i128::from(k) == i128::from(label))).map(|(_, v)| v)
+}
+
+/// Subject of the first certificate of an x5chain, a single certificate or
+/// an array of them.
+fn leaf_subject(chain: &Cbor) -> Option<String> {
+    let der = match chain {
+        Cbor::Bytes(der) => der,
+        Cbor::Array(certs) => certs.first()?.as_bytes()?,
+        _ => return None
+    };
+    let (_, cert) = parse_x509_certificate(der).ok()?;
+    Some(cert.subject().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cose(payload: &Value) -> Vec<u8> {
+        let mut protected = Vec::new();
+        ciborium::into_writer(&Cbor::Map(vec![(Cbor::from(1), Cbor::from(-7))]), &mut protected).unwrap();
+        let sign1 = Cbor::Tag(18, Box::new(Cbor::Array(vec![
+            Cbor::Bytes(protected),
+            Cbor::Map(Vec::new()),
+            Cbor::Bytes(serde_json::to_vec(payload).unwrap()),
+            Cbor::Bytes(vec![0; 64])
+        ])));
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&sign1, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn aggregated_identities_are_claimed() {
+        let credential = json!({
+            "issuer": {"id": "did:web:connected-identities.identity.adobe.com", "name": "Adobe"},
+            "credentialSubject": {
+                "id": "did:key:z6Mk",
+                "verifiedIdentities": [
+                    {"type": "cawg.social_media", "username": "jdoe", "uri": "https://www.instagram.com/jdoe", "provider": {"name": "Instagram"}, "verifiedAt": "2026-01-02T03:04:05Z"},
+                    {"name": "no type"}
+                ]
+            }
+        });
+        let value = json!({
+            "signer_payload": {
+                "sig_type": "cawg.identity_claims_aggregation",
+                "referenced_assertions": [{"url": "self#jumbf=c2pa.assertions/c2pa.actions.v2"}]
+            },
+            "signature": cose(&credential)
+        });
+        let identity = IdentityAssertion::from_cawg("urn:c2pa:1", "cawg.identity", &value).unwrap();
+        assert!(!identity.verified);
+        assert_eq!(identity.issuer.as_deref(), Some("Adobe"));
+        assert_eq!(identity.subject.as_deref(), Some("did:key:z6Mk"));
+        assert_eq!(identity.referenced_assertions, vec![String::from("c2pa.actions.v2")]);
+        assert_eq!(identity.identities.len(), 1);
+        let account = &identity.identities[0];
+        assert_eq!((account.username.as_deref(), account.provider.as_deref()), (Some("jdoe"), Some("Instagram")));
+        assert_eq!(account.claimed_verified_at.as_deref(), Some("2026-01-02T03:04:05Z"));
+        let report = serde_json::to_value(&identity).unwrap();
+        assert_eq!(report["verified"], json!(false));
+        assert!(report["identities"][0].get("verified_at").is_none());
+    }
+
+    #[test]
+    fn unreadable_signatures_keep_the_payload() {
+        let value = json!({
+            "signer_payload": {"sig_type": "cawg.x509.cose", "referenced_assertions": []},
+            "signature": [1, 2, 3]
+        });
+        let identity = IdentityAssertion::from_cawg("urn:c2pa:1", "cawg.identity", &value).unwrap();
+        assert_eq!(identity.sig_type.as_deref(), Some("cawg.x509.cose"));
+        assert!(identity.signer.is_none() && identity.identities.is_empty());
+        assert!(IdentityAssertion::from_cawg("urn:c2pa:1", "cawg.identity", &json!({})).is_none());
+    }
+
+    #[test]
+    fn creative_work_authors_are_read() {
+        let value = json!({
+            "@context": "https://schema.org",
+            "@type": "CreativeWork",
+            "author": [
+                {"@type": "Person", "name": "Jane Doe", "identifier": "https://example.com/jane"},
+                {"@type": "Person"}
+            ]
+        });
+        let identity = IdentityAssertion::from_creative_work("urn:c2pa:1", "stds.schema-org.CreativeWork", &value).unwrap();
+        assert_eq!(identity.identities.len(), 1);
+        assert_eq!(identity.identities[0].uri.as_deref(), Some("https://example.com/jane"));
+        assert!(identity.identities[0].claimed_verified_at.is_none());
+        assert!(IdentityAssertion::from_creative_work("urn:c2pa:1", "stds.schema-org.CreativeWork", &json!({"author": {"@type": "Person"}})).is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use c2pa::{Ingredient, Reader, Relationship};
+use serde::Serialize;
+
+use crate::{options::AnalysisOptions, report::{Report, Verdict}, rules::{GeneratorRules, RuleKind}};
+
+/// One node of the ingredient graph: an asset a manifest was made from.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct IngredientData {
+    pub title: String,
+    pub relationship: Relationship,
+    pub format: Option<String>,
+    /// Label of the ingredient's own manifest in the store, None if it had
+    /// no C2PA data.
+    pub manifest: Option<String>,
+    pub claim_generator: Vec<String>,
+    /// Whether this ingredient or one of its own ingredients was made by a
+    /// generator of the `generated` rules, or analyzed as Generated.
+    pub generated: bool,
+    /// The analysis of the ingredient's embedded data, or else its
+    /// thumbnail, with `analyze_ingredients`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<Box<Report>>,
+    pub ingredients: Vec<IngredientData>
+}
+
+impl IngredientData {
+    fn from_ingredient(reader: &Reader, ingredient: &Ingredient, visited: &mut HashSet<String>, analysis: Option<&AnalysisOptions>) -> IngredientData {
+        let manifest = ingredient.active_manifest().and_then(|label| reader.get_manifest(label).map(|m| (label, m)));
+        let (claim_generator, ingredients) = match manifest {
+            // A label seen before on the path is a cycle, its node is kept
+            // but not walked again.
+            Some((label, m)) if visited.insert(label.to_string()) => {
+                let generators = match &m.claim_generator_info {
+                    Some(list) => list.iter().map(|ci| ci.name.clone()).collect(),
+                    None => Vec::new()
+                };
+                let children = m.ingredients().iter()
+                    .map(|i| IngredientData::from_ingredient(reader, i, visited, analysis))
+                    .collect();
+                visited.remove(label);
+                (generators, children)
+            },
+            _ => (Vec::new(), Vec::new())
+        };
+        let title = ingredient.title().unwrap_or("n/a").to_string();
+        let report = analysis.and_then(|options| {
+            let (format, bytes) = asset(ingredient)?;
+            Some(Box::new(Report::from_ingredient(&title, &format, &bytes, options)))
+        });
+        IngredientData {
+            title,
+            relationship: ingredient.relationship().clone(),
+            format: ingredient.format().map(|f| f.to_string()),
+            manifest: ingredient.active_manifest().map(|l| l.to_string()),
+            claim_generator,
+            generated: false,
+            report,
+            ingredients
+        }
+    }
+
+    fn mark_generated(&mut self, rules: &GeneratorRules) -> bool {
+        let mut generated = self.claim_generator.iter()
+            .any(|g| matches!(rules.find(g), Some((RuleKind::Generated, _))))
+            || self.analyzed_generated();
+        for child in self.ingredients.iter_mut() {
+            generated |= child.mark_generated(rules);
+        }
+        self.generated = generated;
+        generated
+    }
+
+    fn analyzed_generated(&self) -> bool {
+        self.report.as_ref().is_some_and(|r| matches!(r.verdict, Verdict::Generated))
+    }
+
+    /// (ingredients, generated, analyzed as Generated) of this node and
+    /// the ones below.
+    fn count(&self) -> (usize, usize, usize) {
+        let own = (1, self.generated as usize, self.analyzed_generated() as usize);
+        self.ingredients.iter().fold(own, |(total, generated, analyzed), child| {
+            let (t, g, a) = child.count();
+            (total + t, generated + g, analyzed + a)
+        })
+    }
+}
+
+/// MIME type and bytes of the ingredient's embedded data, or else of its
+/// thumbnail.
+fn asset(ingredient: &Ingredient) -> Option<(String, Vec<u8>)> {
+    let data = ingredient.data_ref().and_then(|data| {
+        let bytes = ingredient.resources().get(&data.identifier).ok()?;
+        Some((data.format.clone(), bytes.into_owned()))
+    });
+    data.or_else(|| ingredient.thumbnail().map(|(format, bytes)| (format.to_string(), bytes.into_owned())))
+}
+
+/// The ingredient graph below the active manifest.
+#[derive(Serialize, Default)]
+#[non_exhaustive]
+pub struct IngredientSummary {
+    /// Ingredients at any depth.
+    pub count: usize,
+    pub generated_count: usize,
+    /// Ingredients whose own analysis is Generated, with
+    /// `analyze_ingredients`.
+    pub analyzed_generated_count: usize,
+    /// The active manifest's own generator isn't a generated one but some
+    /// ingredient is, ex. a photo with a generated element pasted in.
+    pub composited_with_generated: bool,
+    pub ingredients: Vec<IngredientData>
+}
+
+impl IngredientSummary {
+    /// With `analyze_ingredients` every ingredient's data or thumbnail is
+    /// analyzed too.
+    pub fn from_reader(reader: &Reader, options: &AnalysisOptions) -> IngredientSummary {
+        let (label, manifest) = match (reader.active_label(), reader.active_manifest()) {
+            (Some(l), Some(m)) => (l, m),
+            _ => return IngredientSummary::default()
+        };
+        // Ingredients are analyzed once, the graph is already walked here
+        let analysis = options.analyze_ingredients.then(|| AnalysisOptions {
+            analyze_ingredients: false,
+            thumbnail_dir: None,
+            ela_dir: None,
+            include_raw_manifest: false,
+            ..options.clone()
+        });
+        let mut visited = HashSet::from([label.to_string()]);
+        let ingredients = manifest.ingredients().iter()
+            .map(|i| IngredientData::from_ingredient(reader, i, &mut visited, analysis.as_ref()))
+            .collect();
+        IngredientSummary { ingredients, ..Default::default() }
+    }
+
+    /// Fills in the generated flags and counts, `active_generators` being
+    /// the claim generators of the active manifest.
+    pub fn score(&mut self, rules: &GeneratorRules, active_generators: &[String]) {
+        let (mut count, mut generated_count, mut analyzed_generated_count) = (0, 0, 0);
+        for ingredient in self.ingredients.iter_mut() {
+            ingredient.mark_generated(rules);
+            let (t, g, a) = ingredient.count();
+            count += t;
+            generated_count += g;
+            analyzed_generated_count += a;
+        }
+        self.count = count;
+        self.generated_count = generated_count;
+        self.analyzed_generated_count = analyzed_generated_count;
+        let active_generated = active_generators.iter()
+            .any(|g| matches!(rules.find(g), Some((RuleKind::Generated, _))));
+        self.composited_with_generated = generated_count > 0 && !active_generated;
+    }
+}
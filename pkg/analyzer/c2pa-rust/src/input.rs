@@ -0,0 +1,131 @@
+use std::path::Path;
+#[cfg(feature = "fs")]
+use std::{fs::File, ops::Deref};
+#[cfg(feature = "fs")]
+use memmap2::Mmap;
+
+use crate::error::AnalyzeError;
+
+/// Files from this size on are mapped rather than read.
+#[cfg(feature = "fs")]
+const MAP_FROM_SIZE: u64 = 1024 * 1024;
+
+/// The bytes of an input, mapped for large files so only the pages the
+/// analyses touch are loaded.
+#[cfg(feature = "fs")]
+pub(crate) enum InputBytes {
+    Read(Vec<u8>),
+    Mapped(Mmap)
+}
+
+#[cfg(feature = "fs")]
+impl Deref for InputBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            InputBytes::Read(bytes) => bytes,
+            InputBytes::Mapped(map) => map
+        }
+    }
+}
+
+/// Reads or maps the file at `path`, failing before reading anything when
+/// it is over `limit`.
+///
+/// A mapped file changed by another process while it is analyzed gives
+/// inconsistent findings, analyze copies of files that may be written to.
+#[cfg(feature = "fs")]
+pub(crate) fn read_file(path: &Path, limit: Option<u64>) -> Result<InputBytes, AnalyzeError> {
+    let with_path = |e: std::io::Error| AnalyzeError::Io(std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)));
+    let file = File::open(path).map_err(with_path)?;
+    let size = file.metadata().map_err(with_path)?.len();
+    check_size(size, limit)?;
+    if size < MAP_FROM_SIZE {
+        return std::fs::read(path).map(InputBytes::Read).map_err(with_path);
+    }
+    // Safety: the map is only read, a concurrent writer can change what is
+    // read but not make it invalid memory for the lifetime of the map
+    match unsafe { Mmap::map(&file) } {
+        Ok(map) => Ok(InputBytes::Mapped(map)),
+        Err(_) => std::fs::read(path).map(InputBytes::Read).map_err(with_path)
+    }
+}
+
+pub(crate) fn check_size(size: u64, limit: Option<u64>) -> Result<(), AnalyzeError> {
+    match limit {
+        Some(limit) if size > limit => Err(AnalyzeError::TooLarge { size, limit }),
+        _ => Ok(())
+    }
+}
+
+/// The last component of an uploaded file name, None when there is none,
+/// ex. for `..`, so it can't point out of a directory it is joined to.
+pub(crate) fn bare_name(name: &str) -> Option<&str> {
+    Path::new(name).file_name()?.to_str()
+}
+
+/// Rejects content that can't be any format with a known signature while
+/// its `format`, an extension or MIME type, has one, ex. zeros named
+/// `upload.png`. Misnamed files of another known format pass, as do
+/// formats without a reliable signature such as MP3 and SVG.
+pub(crate) fn check_magic(format: &str, bytes: &[u8]) -> Result<(), AnalyzeError> {
+    let format = format.rsplit('/').next().unwrap_or(format).to_lowercase();
+    if !SIGNATURES.iter().any(|(formats, _)| formats.contains(&format.as_str())) {
+        return Ok(());
+    }
+    match SIGNATURES.iter().any(|(_, matches)| matches(bytes)) {
+        true => Ok(()),
+        false => Err(AnalyzeError::ContentMismatch(format))
+    }
+}
+
+type Signature = (&'static [&'static str], fn(&[u8]) -> bool);
+
+/// Extensions and MIME subtypes with the test of their leading bytes.
+const SIGNATURES: &[Signature] = &[
+    (&["jpg", "jpeg"], |b| b.starts_with(&[0xFF, 0xD8, 0xFF])),
+    (&["png"], |b| b.starts_with(b"\x89PNG\r\n\x1a\n")),
+    (&["gif"], |b| b.starts_with(b"GIF87a") || b.starts_with(b"GIF89a")),
+    (&["tif", "tiff", "dng"], |b| b.starts_with(b"II*\0") || b.starts_with(b"MM\0*")),
+    (&["webp", "wav", "x-wav", "wave", "avi", "x-msvideo"], |b| b.starts_with(b"RIFF") && b.len() >= 12),
+    (&["heic", "heif", "avif", "mp4", "m4a", "3gp"], |b| b.get(4..8) == Some(b"ftyp")),
+    // Readers accept a PDF header within the first KiB
+    (&["pdf"], |b| b[..b.len().min(1024)].windows(5).any(|w| w == b"%PDF-")),
+    (&["bmp"], |b| b.starts_with(b"BM")),
+    (&["c2pa"], |b| b.get(4..8) == Some(b"jumb"))
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_stay_in_their_directory() {
+        assert_eq!(bare_name("upload.jpg"), Some("upload.jpg"));
+        assert_eq!(bare_name("../../etc/cron.d/x.jpg"), Some("x.jpg"));
+        assert_eq!(bare_name("/tmp/thumbs/a.png"), Some("a.png"));
+        for name in ["", ".", "..", "a/..", "/"] {
+            assert_eq!(bare_name(name), None, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn content_must_fit_its_format() {
+        assert!(check_magic("jpg", &[0xFF, 0xD8, 0xFF, 0xE0]).is_ok());
+        assert!(check_magic("image/png", b"\x89PNG\r\n\x1a\n").is_ok());
+        // misnamed files of another known format pass
+        assert!(check_magic("png", b"GIF89a").is_ok());
+        // formats without a reliable signature aren't checked
+        assert!(check_magic("mp3", &[0; 16]).is_ok());
+        assert!(check_magic("svg", b"").is_ok());
+        for (format, bytes) in [("png", &[0_u8; 16][..]), ("JPEG", b"not an image"), ("image/webp", b"RIFF"), ("mp4", b"")] {
+            match check_magic(format, bytes) {
+                Err(AnalyzeError::ContentMismatch(f)) => assert_eq!(f, format.rsplit('/').next().unwrap().to_lowercase()),
+                _ => panic!("{} accepted {:?}", format, bytes)
+            }
+        }
+        assert!(check_size(10, Some(10)).is_ok());
+        assert!(matches!(check_size(11, Some(10)), Err(AnalyzeError::TooLarge { size: 11, limit: 10 })));
+    }
+}
@@ -0,0 +1,184 @@
+use serde::Serialize;
+
+use crate::rules::{GeneratorRules, RuleKind};
+
+const COM: u8 = 0xFE;
+const APP0: u8 = 0xE0;
+const APP1: u8 = 0xE1;
+const APP11: u8 = 0xEB;
+const APP13: u8 = 0xED;
+const APP14: u8 = 0xEE;
+const APP15: u8 = 0xEF;
+const EDITING_TOOL: (u8, u8) = (20, 10);
+/// Identifiers the format puts at the start of a segment, written by any
+/// tool. The Photoshop IRB of APP13 and Adobe DCT header of APP14 would
+/// otherwise match the editor.
+const SEGMENT_HEADERS: [(u8, &[u8]); 3] = [(APP0, b"JFIF\0"), (APP13, b"Photoshop 3.0\0"), (APP14, b"Adobe")];
+/// Shortest printable run treated as text.
+const MIN_TEXT_LEN: usize = 4;
+
+/// A generator or editing tool named in an APPn or COM segment.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct JpegFingerprint {
+    /// Segment name, ex. `APP12` or `COM`.
+    pub segment: String,
+    /// Text of the segment the rule matched.
+    pub text: String,
+    pub rule: String,
+    pub kind: RuleKind,
+    pub score: u8,
+    pub confidence: u8
+}
+
+/// Generator fingerprints in the APPn and COM segments of a JPEG. APP1 and
+/// APP11 are left to the EXIF, XMP and C2PA readers.
+#[derive(Serialize, Default)]
+#[non_exhaustive]
+pub struct JpegFindings {
+    /// Names of the segments scanned, in file order.
+    pub segments: Vec<String>,
+    pub fingerprints: Vec<JpegFingerprint>,
+    /// Sum of the fingerprints, at most 100.
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl JpegFindings {
+    /// None when the file isn't a JPEG.
+    pub fn from_bytes(bytes: &[u8], rules: &GeneratorRules) -> Option<JpegFindings> {
+        if !bytes.starts_with(&[0xFF, 0xD8]) {
+            return None;
+        }
+        let mut findings = JpegFindings::default();
+        for (marker, payload) in segments(bytes) {
+            if !(marker == COM || (APP0..=APP15).contains(&marker)) || marker == APP1 || marker == APP11 {
+                continue;
+            }
+            let segment = segment_name(marker);
+            let payload = SEGMENT_HEADERS.iter()
+                .find(|(m, _)| *m == marker)
+                .and_then(|(_, header)| payload.strip_prefix(*header))
+                .unwrap_or(payload);
+            for text in text_runs(payload) {
+                findings.add_fingerprint(&segment, text, rules);
+            }
+            findings.segments.push(segment);
+        }
+        Some(findings)
+    }
+
+    fn add_fingerprint(&mut self, segment: &str, text: &str, rules: &GeneratorRules) {
+        let (kind, rule) = match rules.find(text) {
+            Some(found) => found,
+            None => return
+        };
+        // a tool counts once however many segments name it
+        if self.fingerprints.iter().any(|f| f.rule == rule.name) {
+            return;
+        }
+        let (score, confidence) = match kind {
+            RuleKind::Generated => (rule.score, rule.confidence),
+            RuleKind::Modified => EDITING_TOOL
+        };
+        self.score = self.score.saturating_add(score).min(100);
+        self.confidence = self.confidence.saturating_add(confidence).min(100);
+        self.fingerprints.push(JpegFingerprint {
+            segment: segment.to_string(),
+            text: text.to_string(),
+            rule: rule.name.clone(),
+            kind,
+            score,
+            confidence
+        });
+    }
+}
+
+fn segment_name(marker: u8) -> String {
+    match marker {
+        COM => String::from("COM"),
+        m => format!("APP{}", m - APP0)
+    }
+}
+
+/// Runs of printable ASCII, segments mix text with binary fields.
+fn text_runs(payload: &[u8]) -> Vec<&str> {
+    payload.split(|b| !(b.is_ascii_graphic() || *b == b' '))
+        .filter(|run| run.len() >= MIN_TEXT_LEN)
+        .filter_map(|run| std::str::from_utf8(run).ok())
+        .map(|run| run.trim())
+        .collect()
+}
+
+/// (marker, payload) of the segments before the image data.
+pub(crate) fn segments(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut segments = Vec::new();
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return segments;
+    }
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let kind = bytes[pos + 1];
+        // start of scan, the rest is entropy-coded data
+        if kind == 0xDA {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > bytes.len() {
+            break;
+        }
+        segments.push((kind, &bytes[pos + 4..end]));
+        pos = end;
+    }
+    segments
+}
+
+/// (width, height) of the frame header, the first SOF segment.
+pub(crate) fn frame_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let (_, payload) = segments(bytes).into_iter()
+        // DHT, JPG and DAC share the range
+        .find(|(kind, _)| (0xC0..=0xCF).contains(kind) && ![0xC4, 0xC8, 0xCC].contains(kind))?;
+    let height = u16::from_be_bytes([*payload.get(1)?, *payload.get(2)?]);
+    let width = u16::from_be_bytes([*payload.get(3)?, *payload.get(4)?]);
+    Some((width as u32, height as u32))
+}
+
+/// Payloads of the JPEG segments with the given marker.
+pub(crate) fn jpeg_segments(bytes: &[u8], marker: u8) -> Vec<&[u8]> {
+    segments(bytes).into_iter()
+        .filter(|(kind, _)| *kind == marker)
+        .map(|(_, payload)| payload)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg(segments: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        for (marker, payload) in segments {
+            bytes.extend_from_slice(&[0xFF, *marker]);
+            bytes.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            bytes.extend_from_slice(payload);
+        }
+        bytes.extend_from_slice(&[0xFF, 0xD9]);
+        bytes
+    }
+
+    #[test]
+    fn standard_headers_name_no_tool() {
+        let rules = GeneratorRules::default();
+        let irb = b"Photoshop 3.0\08BIM\x04\x04\0\0\0\0\0\0";
+        let bytes = jpeg(&[(APP0, b"JFIF\0\x01\x01"), (APP13, irb), (APP14, b"Adobe\0d\0\0\0\0\x01")]);
+        let findings = JpegFindings::from_bytes(&bytes, &rules).unwrap();
+        assert_eq!(findings.segments, ["APP0", "APP13", "APP14"]);
+        assert!(findings.fingerprints.is_empty());
+
+        let bytes = jpeg(&[(COM, b"Edited with Adobe Photoshop 2024")]);
+        let findings = JpegFindings::from_bytes(&bytes, &rules).unwrap();
+        assert_eq!(findings.fingerprints[0].rule, "photoshop");
+        assert_eq!(findings.fingerprints[0].kind, RuleKind::Modified);
+    }
+}
@@ -0,0 +1,362 @@
+use std::{fs, path::Path};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::rules::{normalize, normalize_fingerprint, GeneratorRule, GeneratorRules};
+
+/// The built-in knowledge base, the default generator rules.
+pub const BUILTIN: &str = include_str!("../data/generators.toml");
+
+/// What a tool does to the images it writes.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum GeneratorCategory {
+    /// Creates them, matches score as generated.
+    Generator,
+    /// Edits them, matches score as modified.
+    Editor,
+    /// Enlarges them, matches score as modified.
+    Upscaler
+}
+
+/// A tool of the knowledge base.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct GeneratorEntry {
+    /// Matched like `GeneratorRule::name`, and the key merges replace
+    /// entries by.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    pub category: GeneratorCategory,
+    /// First release, `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<String>,
+    /// The tool signs what it writes with Content Credentials, so a file
+    /// naming it without a manifest likely lost one.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub content_credentials: bool,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl GeneratorEntry {
+    fn rule(&self) -> Result<GeneratorRule, String> {
+        let mut rule = match &self.pattern {
+            Some(pattern) => GeneratorRule::with_pattern(&self.name, pattern, self.score, self.confidence)?,
+            None => GeneratorRule::new(&self.name, self.score, self.confidence)
+        };
+        rule.vendor = self.vendor.clone();
+        rule.category = Some(self.category);
+        rule.content_credentials = self.content_credentials;
+        Ok(rule)
+    }
+
+    fn from_rule(rule: GeneratorRule, category: GeneratorCategory) -> GeneratorEntry {
+        GeneratorEntry {
+            name: rule.name,
+            pattern: rule.pattern,
+            vendor: rule.vendor,
+            category,
+            first_seen: None,
+            content_credentials: rule.content_credentials,
+            score: rule.score,
+            confidence: rule.confidence
+        }
+    }
+}
+
+/// A signing certificate only an AI service signs with. A manifest it
+/// signed scores by the entry whatever its claim generator says.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct SignerEntry {
+    /// The service, ex. `OpenAI DALL-E`.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    /// SHA-256 of the DER certificate, hex with or without colons, as in
+    /// the `sha256` of a report's `cert_chain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Organization (`O=`) of the certificate's subject, for services
+    /// whose certificates are renewed often. Both must match when both are
+    /// given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+/// Generator rules with what is known of each tool, ex.
+///
+/// ```toml
+/// [[generator]]
+/// name = "midjourney"
+/// vendor = "Midjourney"
+/// category = "generator"
+/// first_seen = "2022-07-12"
+/// score = 100
+/// confidence = 50
+/// ```
+///
+/// `generator` entries score as generated, `editor` and `upscaler` ones as
+/// modified, and earlier entries win, see `GeneratorRules::find`. `signer`
+/// entries list the certificates of AI services.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[non_exhaustive]
+pub struct KnowledgeBase {
+    #[serde(default, rename = "generator")]
+    pub entries: Vec<GeneratorEntry>,
+    #[serde(default, rename = "signer", skip_serializing_if = "Vec::is_empty")]
+    pub signers: Vec<SignerEntry>
+}
+
+/// A knowledge base or rules file, which may hold both layouts.
+#[derive(Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    generator: Vec<GeneratorEntry>,
+    #[serde(default)]
+    signer: Vec<SignerEntry>,
+    #[serde(default)]
+    generated: Vec<GeneratorRule>,
+    #[serde(default)]
+    modified: Vec<GeneratorRule>
+}
+
+impl KnowledgeBase {
+    pub fn builtin() -> KnowledgeBase {
+        toml::from_str(BUILTIN).unwrap_or_default()
+    }
+
+    /// Reads a knowledge base, TOML if the extension is `.toml` and JSON
+    /// otherwise. The `generated` and `modified` lists of a rules file are
+    /// read as generators and editors.
+    pub fn from_file(path: &Path) -> Result<KnowledgeBase, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Error reading rules file {}: {}", path.display(), e))
+        };
+        let is_toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        let file: Result<RulesFile, String> = if is_toml {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        };
+        match file {
+            Ok(f) => {
+                let generated = f.generated.into_iter().map(|r| GeneratorEntry::from_rule(r, GeneratorCategory::Generator));
+                let modified = f.modified.into_iter().map(|r| GeneratorEntry::from_rule(r, GeneratorCategory::Editor));
+                Ok(KnowledgeBase { entries: f.generator.into_iter().chain(generated).chain(modified).collect(), signers: f.signer })
+            },
+            Err(e) => Err(format!("Error parsing rules file {}: {}", path.display(), e))
+        }
+    }
+
+    /// The rules of the entries, in their order.
+    pub fn rules(&self) -> Result<GeneratorRules, String> {
+        let mut rules = GeneratorRules { generated: Vec::new(), modified: Vec::new(), signers: self.signers.clone() };
+        for entry in &self.entries {
+            let rule = entry.rule()?;
+            match entry.category {
+                GeneratorCategory::Generator => rules.generated.push(rule),
+                GeneratorCategory::Editor | GeneratorCategory::Upscaler => rules.modified.push(rule)
+            }
+        }
+        Ok(rules)
+    }
+
+    /// What is wrong with the entries, empty when they can be shipped.
+    /// Unlike loading, this also rejects duplicate names, out of range
+    /// weights, malformed dates and fingerprints.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let name = normalize(&entry.name);
+            if name.is_empty() {
+                problems.push(format!("Entry {} has no name", i + 1));
+                continue;
+            }
+            if self.entries[..i].iter().any(|other| normalize(&other.name) == name) {
+                problems.push(format!("'{}' is listed twice", entry.name));
+            }
+            if let Err(e) = entry.rule() {
+                problems.push(e);
+            }
+            if entry.score > 100 || entry.confidence > 100 {
+                problems.push(format!("'{}' has a score or confidence over 100", entry.name));
+            }
+            if entry.vendor.as_deref().is_some_and(|v| v.trim().is_empty()) {
+                problems.push(format!("'{}' has an empty vendor", entry.name));
+            }
+            if let Some(date) = &entry.first_seen {
+                if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                    problems.push(format!("'{}' has first_seen '{}', not YYYY-MM-DD", entry.name, date));
+                }
+            }
+        }
+        for (i, signer) in self.signers.iter().enumerate() {
+            if let Some(sha256) = &signer.sha256 {
+                let fingerprint = normalize_fingerprint(sha256);
+                if fingerprint.len() != 64 || !fingerprint.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    problems.push(format!("Signer '{}' has sha256 '{}', not 32 hex bytes", signer.name, sha256));
+                }
+            }
+            match &signer.organization {
+                Some(o) if o.trim().is_empty() => problems.push(format!("Signer '{}' has an empty organization", signer.name)),
+                None if signer.sha256.is_none() => problems.push(format!("Signer '{}' has neither sha256 nor organization", signer.name)),
+                _ => ()
+            }
+            if let Some(other) = self.signers[..i].iter().find(|other| signer_key(other) == signer_key(signer)) {
+                problems.push(format!("Signer '{}' repeats the certificate of '{}'", signer.name, other.name));
+            }
+            if signer.score > 100 || signer.confidence > 100 {
+                problems.push(format!("Signer '{}' has a score or confidence over 100", signer.name));
+            }
+        }
+        problems
+    }
+
+    /// Adds the entries of `update`, replacing those of the same normalized
+    /// name, or signers of the same certificate, in place so the order of
+    /// matching is kept. Returns the number of entries added and replaced.
+    pub fn merge(&mut self, update: KnowledgeBase) -> (usize, usize) {
+        let (mut added, mut replaced) = (0, 0);
+        for signer in update.signers {
+            let key = signer_key(&signer);
+            match self.signers.iter_mut().find(|s| signer_key(s) == key) {
+                Some(existing) => {
+                    *existing = signer;
+                    replaced += 1;
+                },
+                None => {
+                    self.signers.push(signer);
+                    added += 1;
+                }
+            }
+        }
+        for entry in update.entries {
+            let name = normalize(&entry.name);
+            match self.entries.iter_mut().find(|e| normalize(&e.name) == name) {
+                Some(existing) => {
+                    *existing = entry;
+                    replaced += 1;
+                },
+                None => {
+                    self.entries.push(entry);
+                    added += 1;
+                }
+            }
+        }
+        (added, replaced)
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| e.to_string())
+    }
+}
+
+/// What a signer entry matches, to find repeated entries.
+fn signer_key(signer: &SignerEntry) -> (Option<String>, Option<String>) {
+    (signer.sha256.as_deref().map(normalize_fingerprint), signer.organization.as_deref().map(|o| o.trim().to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_is_valid() {
+        let kb: KnowledgeBase = toml::from_str(BUILTIN).unwrap();
+        assert!(!kb.entries.is_empty());
+        assert_eq!(kb.validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn merge_replaces_by_name_and_appends() {
+        let mut kb = KnowledgeBase::builtin();
+        let count = kb.entries.len();
+        let update: KnowledgeBase = toml::from_str(r#"
+            [[generator]]
+            name = "Midjourney"
+            category = "generator"
+            score = 90
+            confidence = 60
+
+            [[generator]]
+            name = "ideogram"
+            category = "generator"
+            score = 100
+            confidence = 50
+        "#).unwrap();
+        assert_eq!(kb.merge(update), (1, 1));
+        assert_eq!(kb.entries.len(), count + 1);
+        assert_eq!(kb.entries.iter().find(|e| e.name == "Midjourney").map(|e| e.score), Some(90));
+        assert_eq!(kb.entries.last().map(|e| e.name.as_str()), Some("ideogram"));
+    }
+
+    #[test]
+    fn validate_reports_problems() {
+        let kb: KnowledgeBase = toml::from_str(r#"
+            [[generator]]
+            name = "tool"
+            category = "editor"
+            first_seen = "2024"
+            score = 120
+            confidence = 50
+
+            [[generator]]
+            name = "Tool"
+            pattern = "("
+            category = "upscaler"
+            score = 50
+            confidence = 50
+
+            [[signer]]
+            name = "service"
+            sha256 = "0f:20:12"
+            score = 100
+            confidence = 90
+        "#).unwrap();
+        assert_eq!(kb.validate().len(), 5);
+    }
+
+    #[test]
+    fn signers_name_a_certificate() {
+        let kb: KnowledgeBase = toml::from_str(r#"
+            [[signer]]
+            name = "service"
+            score = 100
+            confidence = 90
+
+            [[signer]]
+            name = "renamed"
+            organization = " "
+            score = 100
+            confidence = 90
+
+            [[signer]]
+            name = "first"
+            organization = "Service"
+            score = 100
+            confidence = 90
+
+            [[signer]]
+            name = "again"
+            organization = "service"
+            score = 100
+            confidence = 90
+        "#).unwrap();
+        assert_eq!(kb.validate(), vec![
+            String::from("Signer 'service' has neither sha256 nor organization"),
+            String::from("Signer 'renamed' has an empty organization"),
+            String::from("Signer 'again' repeats the certificate of 'first'")
+        ]);
+    }
+}
@@ -0,0 +1,182 @@
+//! C2PA provenance analysis for the image detector.
+//!
+//! [`Report::from_file`], [`Report::from_bytes`] and [`Report::from_stream`]
+//! read the manifest store of an image, validate it and score how likely the
+//! image is generated or modified. The report merges the findings of the
+//! [`Analyzer`]s of [`AnalysisOptions`], the built-in C2PA, metadata,
+//! consistency, pixel and watermark analyzers unless others are added. The
+//! `c2pa-rust` binary prints the same [`Report`] as JSON, one line per file
+//! when given several paths or a directory. [`Report::from_url`] fetches the
+//! image first, with the `remote` feature.
+//!
+//! The constructors fail with an [`AnalyzeError`] when the file can't be
+//! read or its manifest store can't be parsed. Their `_lenient` variants,
+//! which the binary uses, return the report of what could be read instead,
+//! listing the failures in its `errors`.
+//!
+//! # Stability
+//!
+//! The crate follows semver from 0.1 on, with the 0.x convention that a
+//! minor bump may break and a patch bump may not:
+//!
+//! - the items re-exported here and their serialized JSON field names are
+//!   the public API; new fields and verdicts can appear in patch releases,
+//!   which is why the structs and [`Verdict`] are `#[non_exhaustive]`
+//! - every report carries a [`SCHEMA_VERSION`]; within its major version the
+//!   JSON only grows, see [`REPORT_SCHEMA`]
+//! - changes to scores produced for a given input are not breaking, the
+//!   scoring rules are expected to be tuned
+//! - [`ValidationState`] is re-exported from the `c2pa` crate and follows
+//!   its versioning
+
+pub mod actions;
+pub mod analyzer;
+pub mod audio;
+#[cfg(feature = "fs")]
+pub mod batch;
+#[cfg(feature = "fs")]
+pub mod bench;
+pub mod bmp;
+#[cfg(feature = "fs")]
+pub mod cache;
+pub mod cameras;
+pub mod certchain;
+pub mod claimdata;
+pub mod consistency;
+pub mod diff;
+#[cfg(feature = "pixel")]
+pub mod ela;
+pub mod ensemble;
+pub mod error;
+pub mod evidence;
+pub mod exif;
+pub mod explanation;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod gif;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hashbinding;
+pub mod identity;
+pub mod ingredients;
+mod input;
+pub mod jpeg;
+pub mod knowledgebase;
+pub mod media;
+#[cfg(feature = "ml")]
+pub mod ml;
+#[cfg(feature = "pixel")]
+pub mod noise;
+pub mod options;
+#[cfg(feature = "ml")]
+pub mod onnx;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+#[cfg(feature = "pixel")]
+pub mod phash;
+pub mod pngtext;
+pub mod probability;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quanttables;
+pub mod remote;
+pub mod revocation;
+pub mod report;
+pub mod reportsigning;
+pub mod rules;
+pub mod schema;
+pub mod scoring;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod signature;
+pub mod sourcetype;
+#[cfg(feature = "pixel")]
+pub mod spectrum;
+pub mod stripped;
+pub mod svg;
+pub mod thumbnails;
+pub mod timings;
+pub mod trainingmining;
+pub mod trust;
+#[cfg(feature = "fs")]
+pub mod trustlist;
+pub mod validation;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "fs")]
+pub mod watch;
+#[cfg(feature = "pixel")]
+pub mod watermark;
+pub mod xmp;
+
+pub use actions::{ActionData, ActionSummary};
+#[cfg(feature = "pixel")]
+pub use analyzer::{PixelAnalyzer, WatermarkAnalyzer};
+pub use analyzer::{AnalysisInput, Analyzer, AnalyzerOutput, AnalyzerResult, Analyzers, C2paAnalyzer, ConsistencyAnalyzer, MetadataAnalyzer};
+pub use audio::{AudioData, AudioTag};
+#[cfg(feature = "fs")]
+pub use bench::BenchResult;
+pub use bmp::BmpData;
+#[cfg(feature = "fs")]
+pub use cache::ReportCache;
+pub use diff::ReportDiff;
+pub use c2pa::ValidationState;
+pub use certchain::ChainCertificate;
+pub use claimdata::{AssertionEntry, ClaimData, ExtractedAssertion};
+pub use consistency::{ConsistencyFindings, Inconsistency, InconsistencyKind};
+#[cfg(feature = "pixel")]
+pub use ela::ElaFindings;
+pub use error::{AnalyzeError, ReportError, ReportErrorKind};
+pub use evidence::Evidence;
+pub use exif::ExifData;
+pub use explanation::{Explanation, Locale};
+pub use gif::GifData;
+pub use hashbinding::{HashMismatch, HashRegion};
+pub use identity::{Identity, IdentityAssertion};
+pub use ingredients::{IngredientData, IngredientSummary};
+pub use jpeg::{JpegFindings, JpegFingerprint};
+pub use knowledgebase::{GeneratorCategory, GeneratorEntry, KnowledgeBase, SignerEntry};
+#[cfg(feature = "pixel")]
+pub use media::FrameSample;
+pub use media::{MediaData, TrackData};
+#[cfg(feature = "ml")]
+pub use ml::{ImageClassifier, MlAnalyzer, MlFindings, ModelInput};
+#[cfg(feature = "pixel")]
+pub use noise::NoiseFindings;
+#[cfg(feature = "ml")]
+pub use onnx::OnnxClassifier;
+pub use options::AnalysisOptions;
+#[cfg(feature = "pdf")]
+pub use pdf::PdfData;
+#[cfg(feature = "pixel")]
+pub use phash::PerceptualHash;
+pub use pngtext::{PngTextChunk, PngTextFindings};
+pub use probability::VerdictProbabilities;
+pub use quanttables::{QuantTableFindings, SignatureKind};
+pub use remote::{RemoteManifest, RemoteManifests};
+pub use revocation::{RevocationCheck, RevocationData, RevocationStatus};
+pub use report::{Report, Verdict};
+pub use reportsigning::{ReportSignature, ReportSigner};
+pub use rules::{GeneratorRule, GeneratorRules, RuleKind, RuleMatch};
+pub use schema::{REPORT_SCHEMA, SCHEMA_VERSION};
+pub use scoring::{ScoringConfig, Weight};
+pub use signature::{SignatureData, SignatureFlag};
+pub use sourcetype::{SourceTypeFindings, SourceTypeHit};
+#[cfg(feature = "pixel")]
+pub use spectrum::SpectrumFindings;
+pub use stripped::{StrippedProvenance, StrippedSignal, StrippedSignalKind};
+pub use svg::SvgData;
+pub use thumbnails::ThumbnailFile;
+#[cfg(feature = "pixel")]
+pub use thumbnails::ThumbnailMatch;
+pub use timings::{StageTiming, Timings};
+pub use trainingmining::{TrainingMining, TrainingPermission};
+pub use trust::TrustConfig;
+#[cfg(feature = "fs")]
+pub use trustlist::TrustListSource;
+pub use validation::{Certificate, ValidationData};
+#[cfg(feature = "fs")]
+pub use watch::WatchFolder;
+#[cfg(feature = "pixel")]
+pub use watermark::{DwtDctDecoder, WatermarkDecoder, WatermarkDecoders, WatermarkDetection, WatermarkFindings};
+pub use xmp::{XmpFindings, XmpMarker};
@@ -1,28 +1,1056 @@
-mod claimdata;
-mod report;
-mod validation;
-use std::io::Error;
-
-use report::*;
-
-fn main() -> Result<(), Error> {
-    let args: Vec<String> = std::env::args().collect();
-    match args.len() {
-        1 => {
-            return Err(Error::new(std::io::ErrorKind::InvalidInput, "Specify a path"));
+#[cfg(feature = "bench-alloc")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{Cursor, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+#[cfg(feature = "bench-alloc")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "ml")]
+use std::sync::Arc;
+use std::time::Duration;
+
+use c2pa_rust::{batch, bench, reportsigning, watch::WatchFolder, trustlist, AnalysisOptions, GeneratorRules, KnowledgeBase, Locale, RemoteManifests, Report, ReportCache, ReportDiff, ReportSigner, RevocationCheck, ScoringConfig, REPORT_SCHEMA, TrustConfig, TrustListSource, Verdict};
+#[cfg(feature = "ml")]
+use c2pa_rust::{MlAnalyzer, OnnxClassifier};
+use serde::Serialize;
+
+const USAGE: &str = "Usage: c2pa-rust [--rules <file>] [--extract-thumbnails <dir>]
+                 [--trust-config <file>] [--ela-dir <dir>] [--sample-frames <n>]
+                 [--scoring <file>] [--scoring-set <key>=<value>]... [--quiet]
+                 [--locale <en|de>] [--ml-model <file.onnx>]
+                 [--include-raw-manifest] [--analyze-ingredients] [--timings]
+                 [--assertion-filter <label>[,<label>]...]
+                 [--extract-assertion <label>]...
+                 [--remote-manifests <host>[,<host>]...]
+                 [--remote-timeout <seconds>] [--sidecar <file.c2pa>]
+                 [--verify-url <base url>]
+                 [--check-revocation] [--jobs <n>] [--max-input-size <bytes>]
+                 [--sign-key <private key pem>]
+                 [--output-format <json|json-pretty|yaml|cbor>]
+                 [--cache] [--no-cache] [--cache-dir <dir>] [--cache-ttl <seconds>]
+                 <path>... | - | --stdin --format <jpeg|png|...> | --url <url>...
+       c2pa-rust serve|grpc [--listen <address>] [options]
+       c2pa-rust update-trust --trust-key <public key pem> [--trust-url <url>]
+                 [--cache-dir <dir>]
+       c2pa-rust diff <before.json> <after.json> [--output-format <format>]
+       c2pa-rust kb list|validate|merge [<file>...] [--rules <file>]
+       c2pa-rust verify-report <report.json>... --public-key <pem>
+       c2pa-rust bench <dir>... [--iterations <n>] [options]
+       c2pa-rust watch <dir> --out <dir> [--interval <seconds>] [--once]
+                 [options]
+       c2pa-rust schema
+
+The rules file defaults to $C2PA_RULES, or the built-in generator
+knowledge base.
+The scoring file defaults to $C2PA_SCORING, --scoring-set overrides one
+of its values, ex. generated_from=75, declared_generated.score=90 or
+analyzer_weights.pixel=50.
+
+serve answers POST /analyze, with the file as multipart/form-data or as
+the raw body, with the report JSON. It listens on 127.0.0.1:9090 unless
+--listen is given, ex. 0.0.0.0:9090. grpc serves the Analyzer service of
+proto/c2pa_analyzer/v1/analyzer.proto, on 127.0.0.1:9091 by default.
+
+update-trust downloads the C2PA trust lists, anchors.pem, allowed.sb.pem
+and store.cfg under --trust-url or https://contentcredentials.org/trust/,
+to the trust directory of the cache, which later runs use unless
+--trust-config is given. Every list must parse and verify against its
+detached signature, <url>.sig, with the key pinned by --trust-key or
+$C2PA_TRUST_KEY, or nothing is replaced.
+
+diff compares the JSON reports of two runs, ex. before and after a scoring
+change, by file name and prints the changed verdicts, score and confidence
+deltas, and added or removed claims and evidence of each file that changed.
+It exits 0 when nothing changed and 1 otherwise.
+
+kb works on generator knowledge bases, the rules files that also name the
+vendor, category (generator, editor or upscaler) and first release of
+each tool, and the SHA-256 of signing certificates only AI services use,
+scored whatever the claim generator says. list prints the entries of
+--rules or the built-in one.
+validate checks the files given, or --rules or the built-in one, printing
+duplicate names, bad patterns, weights over 100 and malformed dates, and
+exits 1 on any. merge adds the entries of the given updates to --rules or
+the built-in one, replacing those of the same name, and prints the merged
+TOML unless it doesn't validate.
+
+--sign-key or $C2PA_SIGN_KEY, the key itself or its file, an Ed25519 PEM
+PRIVATE KEY, signs the reports of the analysis and the servers, adding a
+signature with the key's id over the compact JSON of the rest of the
+report. verify-report checks the signature of every report in the JSON
+files with --public-key, printing the key id or the failure, and exits 1
+when a report doesn't verify.
+
+watch analyzes the files dropped in the directory, and its subdirectories,
+once they stop changing, polling every --interval seconds, 2 by default.
+Each report is written to --out as <relative path>.json and appended to
+reports.ndjson there, and printed. Files with a newer report in --out are
+skipped, and a replaced file is analyzed again. --once analyzes the files
+there are and exits.
+
+bench analyzes the files of the directories --iterations times, 3 by
+default, one file after the other and without the cache, and prints the
+files and MiB per second, the total, mean and share of the time of each
+stage of the analysis, and in builds with the bench-alloc feature the
+mean allocations of a file.
+
+schema prints the JSON Schema of the reports. Each report names its
+schema_version; within a major version fields are only added.
+
+--timings adds how many milliseconds each stage of the analysis took to
+the reports. They change from run to run, so reports with them aren't
+cached.
+
+--ml-model runs an ONNX image classifier on the decoded pixels, in builds
+with the ml feature, voting as ml. Its input is a 1x3xHxW image normalized
+with the ImageNet mean and standard deviation, its output the probability
+the image is generated or the logits of real and generated, and its input
+at most 4096 pixels a side. Models run with tract and must use ONNX opset
+9 to 18.
+
+Manifests the file only links to are fetched from the hosts given with
+--remote-manifests, ex. *.example.com for its subdomains, waiting 10
+seconds unless --remote-timeout is given. Other links are only reported.
+
+--url fetches and analyzes an http or https image, ex. a CDN link, with
+the URL as file name. Its content type must be an image, video, audio or
+PDF type, and it is read up to --max-input-size or 256M, waiting 10
+seconds unless --remote-timeout is given. Fetched images aren't cached.
+
+--analyze-ingredients analyzes the embedded data or thumbnail of every
+ingredient and nests the reports in the ingredient graph.
+
+Claims list the labels of all their assertions. --assertion-filter adds
+the data of the ones given, ex. c2pa.actions,stds.exif, which also takes
+versions and instances such as c2pa.actions.v2 and c2pa.actions__1.
+--extract-assertion copies the assertions with a label, matched the same
+way, into extracted_assertions as the manifest holds them, ex. to read
+ones the report doesn't model.
+
+Each evidence item has an explanation, a sentence for people without the
+report's vocabulary, in English unless --locale gives another language.
+English and German (de) are available.
+
+Reports of files whose manifests validate link to a public verification
+page in verify_url, https://contentcredentials.org/verify unless
+--verify-url is given. For --url reports the link names the image, ex.
+<base url>?source=https%3A%2F%2Fcdn.example.com%2Fa.jpg.
+
+A manifest store in image.jpg.c2pa or image.c2pa next to image.jpg is read
+instead of the embedded one. --sidecar gives it for a single path.
+
+--check-revocation fetches OCSP responses and CRLs for the signing chain,
+keeping CRLs in the crl directory of the cache until their next update.
+Without it only OCSP responses stapled to the signature are checked.
+
+Files over --max-input-size, ex. 512M or 2G, and files whose leading
+bytes match no known format while their extension has one are reported
+with an error before being read. Files from 1 MiB on are memory-mapped.
+
+Several paths or a directory are analyzed on --jobs threads, one per core
+unless given, and reported in the order of the paths, directories sorted.
+A - path reads newline-delimited paths from stdin in its place, ex. from
+find, analyzing them as they come in.
+
+With --cache, reports are cached by the SHA-256 of the file, the options,
+the rules, trust lists and built-in tables, in --cache-dir,
+$C2PA_CACHE_DIR or ~/.cache/c2pa-rust, for a week unless --cache-ttl is
+given. Every file is analyzed again without it, with --no-cache, with
+--extract-thumbnails and --ela-dir, which write files, and with
+--timings and --ml-model.
+
+JSON gives one line per report, json-pretty indented reports, yaml one
+document per report and cbor a CBOR sequence, one data item per report.
+
+Reports list what couldn't be read in errors, ex. FileNotFound,
+UnsupportedFormat or ManifestParseError. JumbfNotFound only tells the file
+has no manifest store.
+
+Exit codes: 0 Genuine, 10 Modified, 20 Generated, 30 Unknown, 64 invalid
+arguments, 66 no files to analyze, ex. an empty directory or - list, 74
+read or write errors, including reports with errors other than
+JumbfNotFound. Several files exit with the code of the most telling
+report, errors over Generated over Modified over Unknown over Genuine.
+--quiet prints no reports, only the exit code tells the verdict.";
+
+/// How reports are written to stdout.
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Json,
+    JsonPretty,
+    Yaml,
+    Cbor
+}
+
+impl OutputFormat {
+    fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "json" => Some(OutputFormat::Json),
+            "json-pretty" => Some(OutputFormat::JsonPretty),
+            "yaml" => Some(OutputFormat::Yaml),
+            "cbor" => Some(OutputFormat::Cbor),
+            _ => None
+        }
+    }
+}
+
+/// Counts allocations for bench, a relaxed add per allocation of every
+/// run, so only with the bench-alloc feature.
+#[cfg(feature = "bench-alloc")]
+struct CountingAllocator;
+
+#[cfg(feature = "bench-alloc")]
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "bench-alloc")]
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "bench-alloc")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[cfg(feature = "bench-alloc")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Allocations and bytes allocated since the start.
+#[cfg(feature = "bench-alloc")]
+fn allocated() -> (u64, u64) {
+    (ALLOCATIONS.load(Ordering::Relaxed), ALLOCATED_BYTES.load(Ordering::Relaxed))
+}
+
+const EXIT_USAGE: u8 = 64;
+const EXIT_NO_INPUT: u8 = 66;
+const EXIT_IO: u8 = 74;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.kind() {
+                ErrorKind::InvalidInput => ExitCode::from(EXIT_USAGE),
+                _ => ExitCode::from(EXIT_IO)
+            }
+        }
+    }
+}
+
+/// Prints one report per line. A single file gives the same single JSON
+/// line as before, several paths or a directory give NDJSON.
+fn run() -> Result<ExitCode, Error> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut stdin = false;
+    let mut urls: Vec<String> = Vec::new();
+    let mut quiet = false;
+    let mut output_format = OutputFormat::Json;
+    let mut format: Option<String> = None;
+    let mut thumbnail_dir: Option<PathBuf> = None;
+    let mut trust_path: Option<PathBuf> = None;
+    let mut ela_dir: Option<PathBuf> = None;
+    let mut frame_samples: usize = 0;
+    let mut ml_model: Option<PathBuf> = None;
+    let mut jobs: usize = 0;
+    let mut max_input_size: Option<u64> = None;
+    let mut include_raw_manifest = false;
+    let mut timings = false;
+    let mut analyze_ingredients = false;
+    let mut check_revocation = false;
+    let mut sidecar: Option<PathBuf> = None;
+    let mut remote_hosts: Vec<String> = Vec::new();
+    let mut assertion_filter: Vec<String> = Vec::new();
+    let mut extract_assertions: Vec<String> = Vec::new();
+    let mut verify_url: Option<String> = None;
+    let mut locale = Locale::En;
+    let mut remote_timeout: Option<Duration> = None;
+    let mut rules_path: Option<PathBuf> = std::env::var_os("C2PA_RULES").map(PathBuf::from);
+    let mut scoring_path: Option<PathBuf> = std::env::var_os("C2PA_SCORING").map(PathBuf::from);
+    let mut scoring_sets: Vec<String> = Vec::new();
+    let mut use_cache = false;
+    let mut cache_dir: Option<PathBuf> = std::env::var_os("C2PA_CACHE_DIR").map(PathBuf::from).or_else(ReportCache::default_dir);
+    let mut cache_ttl = Duration::from_secs(7 * 24 * 60 * 60);
+    let mut args = std::env::args_os().skip(1).peekable();
+    let command = args.next_if(|a| ["serve", "grpc", "update-trust", "diff", "schema", "kb", "verify-report", "bench", "watch"].iter().any(|c| a == c)).map(|a| a.to_string_lossy().to_string());
+    let server = command.clone().filter(|c| c == "serve" || c == "grpc");
+    let update_trust = command.as_deref() == Some("update-trust");
+    let mut trust_key: Option<PathBuf> = std::env::var_os("C2PA_TRUST_KEY").map(PathBuf::from);
+    let mut trust_url: Option<String> = None;
+    let mut sign_key: Option<String> = std::env::var("C2PA_SIGN_KEY").ok();
+    let mut public_key: Option<PathBuf> = None;
+    let mut iterations: usize = 3;
+    let mut watch_out: Option<PathBuf> = None;
+    let mut watch_interval: Option<Duration> = None;
+    let mut watch_once = false;
+    let mut listen: Option<String> = None;
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--stdin") => stdin = true,
+            Some("-q") | Some("--quiet") => quiet = true,
+            Some("--cache") => use_cache = true,
+            Some("--no-cache") => use_cache = false,
+            Some("--include-raw-manifest") => include_raw_manifest = true,
+            Some("--timings") => timings = true,
+            Some("--analyze-ingredients") => analyze_ingredients = true,
+            Some("--check-revocation") => check_revocation = true,
+            Some("--url") => {
+                match args.next() {
+                    Some(u) => urls.push(u.to_string_lossy().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--url needs a URL"))
+                }
+            },
+            Some("--sidecar") => {
+                sidecar = match args.next() {
+                    Some(s) => Some(PathBuf::from(s)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--sidecar needs a path"))
+                };
+            },
+            Some("--remote-manifests") => {
+                match args.next() {
+                    Some(h) => remote_hosts.extend(h.to_string_lossy().split(',').map(|h| h.trim().to_string()).filter(|h| !h.is_empty())),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--remote-manifests needs hosts"))
+                };
+            },
+            Some("--assertion-filter") => {
+                match args.next() {
+                    Some(l) => assertion_filter.extend(l.to_string_lossy().split(',').map(|l| l.trim().to_string()).filter(|l| !l.is_empty())),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--assertion-filter needs assertion labels"))
+                };
+            },
+            Some("--extract-assertion") => {
+                match args.next() {
+                    Some(l) => extract_assertions.push(l.to_string_lossy().trim().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--extract-assertion needs an assertion label"))
+                };
+            },
+            Some("--locale") => {
+                locale = match args.next().and_then(|l| l.to_str().and_then(Locale::from_tag)) {
+                    Some(l) => l,
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--locale needs en or de"))
+                };
+            },
+            Some("--verify-url") => {
+                verify_url = match args.next() {
+                    Some(u) => Some(u.to_string_lossy().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--verify-url needs a URL"))
+                };
+            },
+            Some("--remote-timeout") => {
+                remote_timeout = match args.next().and_then(|t| t.to_str().and_then(|t| t.parse().ok())) {
+                    Some(t) => Some(Duration::from_secs(t)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--remote-timeout needs a number of seconds"))
+                };
+            },
+            Some("--cache-dir") => {
+                cache_dir = match args.next() {
+                    Some(d) => Some(PathBuf::from(d)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--cache-dir needs a directory"))
+                };
+            },
+            Some("--cache-ttl") => {
+                cache_ttl = match args.next().and_then(|t| t.to_str().and_then(|t| t.parse().ok())) {
+                    Some(t) => Duration::from_secs(t),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--cache-ttl needs a number of seconds"))
+                };
+            },
+            Some("--format") => {
+                format = match args.next() {
+                    Some(f) => Some(f.to_string_lossy().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--format needs a value"))
+                };
+            },
+            Some("--output-format") => {
+                output_format = match args.next().and_then(|f| f.to_str().and_then(OutputFormat::from_name)) {
+                    Some(f) => f,
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--output-format needs json, json-pretty, yaml or cbor"))
+                };
+            },
+            Some("--listen") if server.is_some() => {
+                listen = match args.next() {
+                    Some(l) => Some(l.to_string_lossy().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--listen needs an address"))
+                };
+            },
+            Some("--trust-url") if update_trust => {
+                trust_url = match args.next() {
+                    Some(u) => Some(u.to_string_lossy().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--trust-url needs a URL"))
+                };
+            },
+            Some("--trust-key") if update_trust => {
+                trust_key = match args.next() {
+                    Some(k) => Some(PathBuf::from(k)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--trust-key needs a PEM file"))
+                };
+            },
+            Some("--sign-key") => {
+                sign_key = match args.next() {
+                    Some(k) => Some(k.to_string_lossy().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--sign-key needs a PEM file"))
+                };
+            },
+            Some("--public-key") if command.as_deref() == Some("verify-report") => {
+                public_key = match args.next() {
+                    Some(k) => Some(PathBuf::from(k)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--public-key needs a PEM file"))
+                };
+            },
+            Some("--iterations") if command.as_deref() == Some("bench") => {
+                iterations = match args.next().and_then(|n| n.to_str().and_then(|n| n.parse().ok())).filter(|n| *n > 0) {
+                    Some(n) => n,
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--iterations needs a number over 0"))
+                };
+            },
+            Some("--out") if command.as_deref() == Some("watch") => {
+                watch_out = match args.next() {
+                    Some(d) => Some(PathBuf::from(d)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--out needs a directory"))
+                };
+            },
+            Some("--interval") if command.as_deref() == Some("watch") => {
+                watch_interval = match args.next().and_then(|t| t.to_str().and_then(|t| t.parse().ok())) {
+                    Some(t) => Some(Duration::from_secs(t)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--interval needs a number of seconds"))
+                };
+            },
+            Some("--once") if command.as_deref() == Some("watch") => watch_once = true,
+            Some("--rules") => {
+                rules_path = match args.next() {
+                    Some(r) => Some(PathBuf::from(r)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--rules needs a value"))
+                };
+            },
+            Some("--scoring") => {
+                scoring_path = match args.next() {
+                    Some(s) => Some(PathBuf::from(s)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--scoring needs a value"))
+                };
+            },
+            Some("--scoring-set") => {
+                match args.next() {
+                    Some(s) => scoring_sets.push(s.to_string_lossy().to_string()),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--scoring-set needs <key>=<value>"))
+                };
+            },
+            Some("--extract-thumbnails") => {
+                thumbnail_dir = match args.next() {
+                    Some(d) => Some(PathBuf::from(d)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--extract-thumbnails needs a directory"))
+                };
+            },
+            Some("--ela-dir") => {
+                ela_dir = match args.next() {
+                    Some(d) => Some(PathBuf::from(d)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--ela-dir needs a directory"))
+                };
+            },
+            Some("--max-input-size") => {
+                max_input_size = match args.next().and_then(|n| n.to_str().and_then(parse_size)) {
+                    Some(n) => Some(n),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--max-input-size needs a size, ex. 500000000 or 512M"))
+                };
+            },
+            Some("--jobs") => {
+                jobs = match args.next().and_then(|n| n.to_str().and_then(|n| n.parse().ok())) {
+                    Some(n) => n,
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--jobs needs a number"))
+                };
+            },
+            Some("--sample-frames") => {
+                frame_samples = match args.next().and_then(|n| n.to_str().and_then(|n| n.parse().ok())) {
+                    Some(n) => n,
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--sample-frames needs a number"))
+                };
+            },
+            Some("--ml-model") => {
+                ml_model = match args.next() {
+                    Some(m) => Some(PathBuf::from(m)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--ml-model needs an ONNX file"))
+                };
+            },
+            Some("--trust-config") => {
+                trust_path = match args.next() {
+                    Some(t) => Some(PathBuf::from(t)),
+                    None => return Err(Error::new(ErrorKind::InvalidInput, "--trust-config needs a value"))
+                };
+            },
+            Some("-h") | Some("--help") => {
+                println!("{}", USAGE);
+                return Ok(ExitCode::SUCCESS);
+            },
+            _ => paths.push(PathBuf::from(arg))
+        }
+    }
+
+    if command.as_deref() == Some("schema") {
+        if stdin || !paths.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "schema takes no paths or --stdin"));
+        }
+        print!("{}", REPORT_SCHEMA);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if command.as_deref() == Some("diff") {
+        return run_diff(&paths, output_format, quiet);
+    }
+    if command.as_deref() == Some("verify-report") {
+        return run_verify_report(&paths, public_key.as_deref());
+    }
+    if command.as_deref() == Some("kb") {
+        return run_kb(&paths, rules_path.as_deref(), output_format);
+    }
+    if update_trust {
+        if stdin || !paths.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "update-trust takes no paths or --stdin"));
+        }
+        return run_update_trust(cache_dir, trust_url, trust_key);
+    }
+
+    let mut options = AnalysisOptions::default();
+    if let Some(path) = rules_path {
+        match GeneratorRules::from_file(&path) {
+            Ok(rules) => options = options.with_rules(rules),
+            Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e))
+        }
+    }
+    let mut scoring = match scoring_path.map(|path| ScoringConfig::from_file(&path)) {
+        Some(Ok(scoring)) => scoring,
+        Some(Err(e)) => return Err(Error::new(ErrorKind::InvalidInput, e)),
+        None => ScoringConfig::default()
+    };
+    for set in scoring_sets {
+        let (key, value) = match set.split_once('=') {
+            Some(kv) => kv,
+            None => return Err(Error::new(ErrorKind::InvalidInput, "--scoring-set needs <key>=<value>"))
+        };
+        if let Err(e) = scoring.set(key, value) {
+            return Err(Error::new(ErrorKind::InvalidInput, e));
+        }
+    }
+    options = options.with_scoring(scoring);
+    // Lists from update-trust apply unless a policy is given
+    let trust_path = trust_path.or_else(|| cache_dir.as_ref().and_then(|dir| trustlist::updated_config(&dir.join("trust"))));
+    if let Some(path) = trust_path {
+        match TrustConfig::from_file(&path) {
+            Ok(trust) => options = options.with_trust(trust),
+            Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e))
+        }
+    }
+    if check_revocation {
+        let check = match &cache_dir {
+            Some(dir) => RevocationCheck::default().with_cache_dir(dir.join("crl")),
+            None => RevocationCheck::default()
+        };
+        options = options.with_revocation(check);
+    }
+    let cache = match (use_cache && thumbnail_dir.is_none() && ela_dir.is_none() && !timings && ml_model.is_none(), cache_dir) {
+        (true, Some(dir)) => Some(ReportCache::new(dir).with_ttl(cache_ttl)),
+        _ => None
+    };
+    if let Some(dir) = thumbnail_dir {
+        options = options.with_thumbnail_dir(dir);
+    }
+    if let Some(dir) = ela_dir {
+        options = options.with_ela_dir(dir);
+    }
+    if include_raw_manifest {
+        options = options.with_raw_manifest();
+    }
+    if timings {
+        options = options.with_timings();
+    }
+    if analyze_ingredients {
+        options = options.with_ingredient_analysis();
+    }
+    if !assertion_filter.is_empty() {
+        options = options.with_assertion_filter(assertion_filter);
+    }
+    for label in &extract_assertions {
+        options = options.with_extracted_assertion(label);
+    }
+    options = options.with_locale(locale);
+    if let Some(url) = &verify_url {
+        options = options.with_verify_base_url(url);
+    }
+    let mut remote = RemoteManifests::new(remote_hosts);
+    if let Some(timeout) = remote_timeout {
+        remote = remote.with_timeout(timeout);
+    }
+    options = options.with_remote(remote);
+    if let Some(size) = max_input_size {
+        options = options.with_max_input_size(size);
+    }
+    if let Some(key) = sign_key {
+        // The key itself, ex. from a secret store, or its file
+        let pem = match key.trim_start().starts_with("-----BEGIN") {
+            true => key,
+            false => std::fs::read_to_string(&key)?
+        };
+        match ReportSigner::from_pem(&pem) {
+            Ok(signer) => options = options.with_report_signer(signer),
+            Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e))
+        }
+    }
+    if frame_samples > 0 {
+        #[cfg(feature = "pixel")]
+        {
+            options = options.with_frame_samples(frame_samples);
+        }
+        #[cfg(not(feature = "pixel"))]
+        return Err(Error::new(ErrorKind::InvalidInput, "--sample-frames needs the pixel feature"));
+    }
+    if let Some(path) = ml_model {
+        #[cfg(feature = "ml")]
+        match OnnxClassifier::from_file(&path) {
+            Ok(classifier) => options = options.with_analyzer(Arc::new(MlAnalyzer::new(Arc::new(classifier)))),
+            Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e))
+        }
+        #[cfg(not(feature = "ml"))]
+        return Err(Error::new(ErrorKind::InvalidInput, format!("--ml-model {} needs the ml feature", path.display())));
+    }
+
+    if command.as_deref() == Some("bench") {
+        if stdin || !urls.is_empty() || paths.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "bench needs fixture paths, not --stdin or --url"));
+        }
+        #[cfg(feature = "bench-alloc")]
+        let counter: Option<&bench::AllocationCounter> = Some(&allocated);
+        #[cfg(not(feature = "bench-alloc"))]
+        let counter = None;
+        return match bench::run(&paths, iterations, &options, counter) {
+            Ok(result) => {
+                write_report(&mut std::io::stdout().lock(), &result, output_format)?;
+                Ok(ExitCode::SUCCESS)
+            },
+            Err(e) => Err(Error::new(ErrorKind::InvalidInput, e))
+        };
+    }
+    if let Some(server) = server {
+        if stdin || !paths.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("{} takes no paths or --stdin", server)));
+        }
+        if !urls.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("{} takes no --url", server)));
+        }
+        return run_server(&server, listen, options);
+    }
+
+    if command.as_deref() == Some("watch") {
+        let (dir, out_dir) = match (paths.as_slice(), watch_out) {
+            ([dir], Some(out_dir)) if !stdin && urls.is_empty() => (dir.clone(), out_dir),
+            _ => return Err(Error::new(ErrorKind::InvalidInput, "watch needs a directory and --out <dir>"))
+        };
+        let mut folder = WatchFolder::new(dir, out_dir);
+        if let Some(interval) = watch_interval {
+            folder = folder.with_interval(interval);
+        }
+        if let Err(e) = folder.check_dirs() {
+            return Err(Error::new(ErrorKind::InvalidInput, e));
+        }
+        let analyze = |path: PathBuf| {
+            let report = match &cache {
+                Some(cache) => cache.report_file(&path, &options),
+                None => {
+                    let sidecar = batch::find_sidecar(&path);
+                    report_json(Report::from_file_lenient(path, sidecar, &options))
+                }
+            };
+            // Unsigned rather than lost when signing fails
+            signed(report.clone(), &options).unwrap_or(report)
+        };
+        let stdout = std::io::stdout();
+        let emit = |path: &Path, report: Result<&serde_json::Value, String>| match report {
+            Ok(report) if !quiet => {
+                let _ = write_report(&mut stdout.lock(), report, output_format);
+            },
+            Ok(_) => (),
+            Err(e) => eprintln!("{}: {}", path.display(), e)
+        };
+        if watch_once {
+            folder.poll(jobs, analyze, emit, true);
+            return Ok(ExitCode::SUCCESS);
+        }
+        return match folder.run(jobs, analyze, emit) {
+            Ok(()) => Ok(ExitCode::SUCCESS),
+            Err(e) => Err(Error::other(e))
+        };
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if stdin {
+        let format = match format {
+            Some(f) => f,
+            None => return Err(Error::new(ErrorKind::InvalidInput, "--stdin needs --format"))
+        };
+        if !paths.is_empty() || !urls.is_empty() || sidecar.is_some() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Paths, - and --url can't be combined with --stdin"));
+        }
+        // Readers seek around the asset, stdin can't, so it is buffered.
+        let mut bytes = Vec::new();
+        let limit = options.max_input_size;
+        std::io::stdin().lock().take(limit.map_or(u64::MAX, |l| l + 1)).read_to_end(&mut bytes)?;
+        if let Some(limit) = limit.filter(|l| bytes.len() as u64 > *l) {
+            return Err(Error::new(ErrorKind::InvalidData, format!("stdin is over --max-input-size, {} bytes", limit)));
+        }
+        let report = match &cache {
+            Some(cache) => cache.report_stream(&format, bytes, &options),
+            None => report_json(Report::from_stream_lenient(&format, Cursor::new(bytes), &options))
+        };
+        let exit = report_exit(&report).1;
+        if !quiet {
+            write_report(&mut out, &signed(report, &options)?, output_format)?;
+        }
+        return Ok(ExitCode::from(exit));
+    }
+    if format.is_some() {
+        return Err(Error::new(ErrorKind::InvalidInput, "--format is only used with --stdin"));
+    }
+    if !urls.is_empty() {
+        if !paths.is_empty() || sidecar.is_some() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Paths can't be combined with --url"));
+        }
+        let mut exit = (0, 0);
+        for url in &urls {
+            let report = report_json(Report::from_url_lenient(url, &options));
+            exit = exit.max(report_exit(&report));
+            if !quiet {
+                write_report(&mut out, &signed(report, &options)?, output_format)?;
+            }
+        }
+        return Ok(ExitCode::from(exit.1));
+    }
+    if paths.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("Specify a path\n{}", USAGE)));
+    }
+
+    if let Some(sidecar) = sidecar {
+        if paths.len() != 1 || paths[0].is_dir() || paths[0].as_os_str() == "-" {
+            return Err(Error::new(ErrorKind::InvalidInput, "--sidecar takes a single file path"));
+        }
+        // Reported as a read error, the report would only show no claims
+        std::fs::metadata(&sidecar)?;
+        let path = paths.remove(0);
+        let report = match &cache {
+            Some(cache) => cache.report_file_with_sidecar(&path, &sidecar, &options),
+            None => report_json(Report::from_file_lenient(path, Some(sidecar), &options))
+        };
+        let exit = report_exit(&report).1;
+        if !quiet {
+            write_report(&mut out, &signed(report, &options)?, output_format)?;
+        }
+        return Ok(ExitCode::from(exit));
+    }
+
+    let analyze = |path: PathBuf| match &cache {
+        Some(cache) => cache.report_file(&path, &options),
+        None => {
+            let sidecar = batch::find_sidecar(&path);
+            report_json(Report::from_file_lenient(path, sidecar, &options))
+        }
+    };
+    // (rank, code) of the most telling report so far, None before the first
+    let mut exit = None;
+    // `-` reads paths from stdin in its place, as they come in
+    let files = paths.into_iter().flat_map(|path| -> Box<dyn Iterator<Item = PathBuf> + Send> {
+        match path.as_os_str() == "-" {
+            true => Box::new(batch::read_paths(std::io::BufReader::new(std::io::stdin()))),
+            false => Box::new(batch::collect_paths(&[path]).into_iter())
+        }
+    });
+    batch::analyze_parallel(files, jobs, analyze, |report| {
+        exit = exit.max(Some(report_exit(&report)));
+        if !quiet {
+            write_report(&mut out, &signed(report, &options)?, output_format)?;
+        }
+        Ok::<(), Error>(())
+    })?;
+    Ok(ExitCode::from(batch_exit(exit)))
+}
+
+/// Prints what changed between the reports in the `before` and `after`
+/// files, JSON as the analysis prints it.
+fn run_diff(paths: &[PathBuf], output_format: OutputFormat, quiet: bool) -> Result<ExitCode, Error> {
+    let (before, after) = match paths {
+        [before, after] => (read_reports(before)?, read_reports(after)?),
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "diff needs a before and an after report file"))
+    };
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let mut changed = false;
+    for diff in ReportDiff::vec_from_runs(&before, &after).into_iter().filter(|d| !d.is_empty()) {
+        changed = true;
+        if !quiet {
+            write_report(&mut out, &diff, output_format)?;
+        }
+    }
+    Ok(ExitCode::from(changed as u8))
+}
+
+/// The reports of a JSON or json-pretty run.
+fn read_reports(path: &Path) -> Result<Vec<serde_json::Value>, Error> {
+    let content = std::fs::read_to_string(path)?;
+    match serde_json::Deserializer::from_str(&content).into_iter().collect() {
+        Ok(reports) => Ok(reports),
+        Err(e) => Err(Error::new(ErrorKind::InvalidData, format!("Error parsing reports in {}: {}", path.display(), e)))
+    }
+}
+
+/// Lists, validates or merges generator knowledge bases.
+fn run_kb(args: &[PathBuf], rules_path: Option<&Path>, output_format: OutputFormat) -> Result<ExitCode, Error> {
+    let load = |path: &Path| KnowledgeBase::from_file(path).map_err(|e| Error::new(ErrorKind::InvalidData, e));
+    let base = || match rules_path {
+        Some(path) => load(path),
+        None => Ok(KnowledgeBase::builtin())
+    };
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match args.split_first().map(|(action, files)| (action.to_str(), files)) {
+        Some((Some("list"), [])) => {
+            let kb = base()?;
+            for entry in &kb.entries {
+                write_report(&mut out, entry, output_format)?;
+            }
+            for signer in &kb.signers {
+                write_report(&mut out, signer, output_format)?;
+            }
+            Ok(ExitCode::SUCCESS)
+        },
+        Some((Some("validate"), files)) => {
+            let bases = match files {
+                [] => vec![(rules_path.map_or(String::from("built-in"), |p| p.display().to_string()), base())],
+                files => files.iter().map(|f| (f.display().to_string(), load(f))).collect()
+            };
+            let mut valid = true;
+            for (name, kb) in bases {
+                let problems = match kb {
+                    Ok(kb) => kb.validate(),
+                    Err(e) => vec![e.to_string()]
+                };
+                for problem in &problems {
+                    writeln!(out, "{}: {}", name, problem)?;
+                }
+                valid &= problems.is_empty();
+            }
+            Ok(ExitCode::from(!valid as u8))
+        },
+        Some((Some("merge"), updates)) if !updates.is_empty() => {
+            let mut kb = base()?;
+            let (mut added, mut replaced) = (0, 0);
+            for update in updates {
+                let (a, r) = kb.merge(load(update)?);
+                (added, replaced) = (added + a, replaced + r);
+            }
+            let problems = kb.validate();
+            if !problems.is_empty() {
+                for problem in problems {
+                    eprintln!("{}", problem);
+                }
+                return Ok(ExitCode::from(1));
+            }
+            match kb.to_toml() {
+                Ok(toml) => write!(out, "{}", toml)?,
+                Err(e) => return Err(Error::other(e))
+            }
+            eprintln!("{} added, {} replaced", added, replaced);
+            Ok(ExitCode::SUCCESS)
         },
-        2 => {
-            let path = std::path::PathBuf::from(&args[1]);
-            let report = Report::from_file(path);
-            let json = match serde_json::to_string(&report) { 
+        _ => Err(Error::new(ErrorKind::InvalidInput, "kb needs list, validate [<file>...] or merge <update>..."))
+    }
+}
+
+/// Checks the signature of every report in the files with the public key.
+fn run_verify_report(paths: &[PathBuf], public_key: Option<&Path>) -> Result<ExitCode, Error> {
+    let public_key = match public_key {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => return Err(Error::new(ErrorKind::InvalidInput, "verify-report needs --public-key"))
+    };
+    if paths.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "verify-report needs a report file"));
+    }
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let mut verified = true;
+    for path in paths {
+        for report in read_reports(path)? {
+            let name = report["file_name"].as_str().unwrap_or_default();
+            match reportsigning::verify_report(&report, &public_key) {
+                Ok(key_id) => writeln!(out, "{}: {}: verified with key {}", path.display(), name, key_id)?,
+                Err(e) => {
+                    writeln!(out, "{}: {}: {}", path.display(), name, e)?;
+                    verified = false;
+                }
+            }
+        }
+    }
+    Ok(ExitCode::from(!verified as u8))
+}
+
+/// Downloads the trust lists into the `trust` directory of the cache.
+fn run_update_trust(cache_dir: Option<PathBuf>, trust_url: Option<String>, trust_key: Option<PathBuf>) -> Result<ExitCode, Error> {
+    let dir = match cache_dir {
+        Some(dir) => dir.join("trust"),
+        None => return Err(Error::new(ErrorKind::InvalidInput, "update-trust needs --cache-dir"))
+    };
+    let mut source = match trust_url {
+        Some(url) => TrustListSource::from_base_url(&url),
+        None => TrustListSource::default()
+    };
+    let key = match trust_key {
+        Some(path) => std::fs::read(&path)?,
+        None => return Err(Error::new(ErrorKind::InvalidInput, "update-trust needs --trust-key or $C2PA_TRUST_KEY"))
+    };
+    source = match source.with_verify_key_pem(&key) {
+        Ok(s) => s,
+        Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e))
+    };
+    match source.update(&dir) {
+        Ok(files) => {
+            for file in files {
+                println!("{}", file.display());
+            }
+            Ok(ExitCode::SUCCESS)
+        },
+        Err(e) => Err(Error::other(e))
+    }
+}
+
+/// Runs the `serve` or `grpc` server until the process ends.
+#[cfg_attr(not(all(feature = "serve", feature = "grpc")), allow(unused_variables))]
+fn run_server(server: &str, listen: Option<String>, options: AnalysisOptions) -> Result<ExitCode, Error> {
+    let served = |result: Result<(), String>| match result {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(e) => Err(Error::new(ErrorKind::AddrInUse, e))
+    };
+    match server {
+        #[cfg(feature = "serve")]
+        "serve" => served(c2pa_rust::serve::serve(listen.as_deref().unwrap_or("127.0.0.1:9090"), options)),
+        #[cfg(feature = "grpc")]
+        "grpc" => served(c2pa_rust::grpc::serve(listen.as_deref().unwrap_or("127.0.0.1:9091"), options)),
+        other => Err(Error::new(ErrorKind::InvalidInput, format!("{} needs the {} feature", other, other)))
+    }
+}
+
+/// Bytes, or KiB, MiB or GiB with a K, M or G suffix.
+fn parse_size(size: &str) -> Option<u64> {
+    let (number, unit) = match size.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&size[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&size[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&size[..i], 1 << 30),
+        _ => (size, 1)
+    };
+    number.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// (rank, exit code) of a report, the highest rank of a batch decides.
+/// Errors other than a missing manifest store outrank every verdict.
+fn report_exit(report: &serde_json::Value) -> (u8, u8) {
+    let failed = report["errors"].as_array().is_some_and(|errors| errors.iter().any(|e| e["kind"] != "JumbfNotFound"));
+    if failed {
+        return (4, EXIT_IO);
+    }
+    match serde_json::from_value(report["verdict"].clone()) {
+        Ok(Verdict::Genuine) => (0, 0),
+        Ok(Verdict::Modified) => (2, 10),
+        Ok(Verdict::Generated) => (3, 20),
+        _ => (1, 30)
+    }
+}
+
+/// Exit code of a batch from its most telling report, None when it found
+/// no files.
+fn batch_exit(exit: Option<(u8, u8)>) -> u8 {
+    exit.map_or(EXIT_NO_INPUT, |(_, code)| code)
+}
+
+/// The report with the signature of the options' signer, if any.
+fn signed(mut report: serde_json::Value, options: &AnalysisOptions) -> Result<serde_json::Value, Error> {
+    if let Some(signer) = &options.report_signer {
+        signer.sign(&mut report).map_err(Error::other)?;
+    }
+    Ok(report)
+}
+
+fn report_json(report: Report) -> serde_json::Value {
+    serde_json::to_value(report).unwrap_or_else(|_| serde_json::json!({}))
+}
+
+fn write_report(out: &mut impl Write, report: &impl Serialize, format: OutputFormat) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => {
+            let json = match serde_json::to_string(report) {
+                Ok(j) => j,
+                Err(_) => String::from("{}")
+            };
+            writeln!(out, "{}", json)
+        },
+        OutputFormat::JsonPretty => {
+            let json = match serde_json::to_string_pretty(report) {
                 Ok(j) => j,
                 Err(_) => String::from("{}")
             };
-            println!("{}", json);
+            writeln!(out, "{}", json)
+        },
+        OutputFormat::Yaml => {
+            let yaml = serde_yaml::to_string(report).map_err(Error::other)?;
+            write!(out, "---\n{}", yaml)
         },
-        _ => {
-            return Err(Error::new(std::io::ErrorKind::InvalidInput, "Too many arguments"));
+        OutputFormat::Cbor => ciborium::into_writer(report, &mut *out).map_err(Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_and_empty_batches_have_their_own_codes() {
+        let unreadable = serde_json::json!({"verdict": "Unknown", "errors": [{"kind": "FileNotFound"}]});
+        let unsigned = serde_json::json!({"verdict": "Unknown", "errors": [{"kind": "JumbfNotFound"}]});
+        let generated = serde_json::json!({"verdict": "Generated", "errors": []});
+        assert_eq!(report_exit(&unreadable), (4, EXIT_IO));
+        assert_eq!(report_exit(&unsigned), (1, 30));
+        let batch = [&unsigned, &unreadable, &generated].into_iter().map(|r| Some(report_exit(r))).max().flatten();
+        assert_eq!(batch_exit(batch), EXIT_IO);
+        assert_eq!(batch_exit(None), EXIT_NO_INPUT);
+        assert_eq!(batch_exit(Some(report_exit(&serde_json::json!({"verdict": "Genuine"})))), 0);
+    }
+
+    #[test]
+    fn reports_of_json_runs_are_read() {
+        let path = std::env::temp_dir().join(format!("c2pa-reports-{}.json", std::process::id()));
+        let report = serde_json::json!({"file_name": "a.jpg", "verdict": "Genuine"});
+        let pretty = serde_json::to_string_pretty(&report).unwrap();
+        std::fs::write(&path, format!("{}\n{}\n", report, pretty)).unwrap();
+        assert_eq!(read_reports(&path).unwrap(), [report.clone(), report]);
+        std::fs::write(&path, "{\"file_name\": ").unwrap();
+        assert_eq!(read_reports(&path).unwrap_err().kind(), ErrorKind::InvalidData);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn sizes_take_unit_suffixes() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("4k"), Some(4096));
+        assert_eq!(parse_size("512M"), Some(512 << 20));
+        assert_eq!(parse_size("2G"), Some(2 << 30));
+        for invalid in ["", "G", "1.5G", "-1", "12T", "0x10"] {
+            assert_eq!(parse_size(invalid), None, "{:?}", invalid);
         }
-    };
-    Ok(())
-}
\ No newline at end of file
+        assert_eq!(parse_size(&format!("{}G", u64::MAX >> 30)), Some((u64::MAX >> 30) << 30));
+        assert_eq!(parse_size(&format!("{}G", (u64::MAX >> 30) + 1)), None);
+        assert_eq!(parse_size("18446744073709551616"), None);
+    }
+}
@@ -0,0 +1,282 @@
+#[cfg(all(feature = "pixel", feature = "fs"))]
+use std::{path::Path, process::Command};
+use serde::Serialize;
+
+#[cfg(all(feature = "pixel", feature = "fs"))]
+use crate::options::AnalysisOptions;
+#[cfg(feature = "pixel")]
+use crate::{noise::NoiseFindings, spectrum::SpectrumFindings, watermark::WatermarkFindings};
+
+/// A track of a BMFF (MP4, MOV, M4A) container.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct TrackData {
+    pub id: u32,
+    /// `video`, `audio`, or the handler type as written, ex. `meta`.
+    pub kind: String,
+    /// Sample entry type, ex. `avc1` or `mp4a`.
+    pub codec: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Claim binding the track. The manifest of a BMFF file hashes the
+    /// whole container, so every track carries the active claim.
+    pub claim_id: Option<String>
+}
+
+/// Pixel analyses of a frame decoded with ffmpeg.
+#[cfg(feature = "pixel")]
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct FrameSample {
+    pub time_seconds: f64,
+    pub noise: Option<NoiseFindings>,
+    pub spectrum: Option<SpectrumFindings>,
+    pub watermarks: Option<WatermarkFindings>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+/// Tracks of a BMFF container and the frames sampled from it.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct MediaData {
+    /// `ftyp` major brand, ex. `isom` or `qt  `.
+    pub major_brand: String,
+    pub duration_seconds: Option<f64>,
+    pub tracks: Vec<TrackData>,
+    /// Frames sampled with `--sample-frames`.
+    #[cfg(feature = "pixel")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<FrameSample>,
+    /// Why no frames could be sampled, ex. ffmpeg isn't installed.
+    #[cfg(feature = "pixel")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frames_error: Option<String>,
+    /// Highest score and confidence of the sampled frames.
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl MediaData {
+    /// None unless the file is a BMFF container with a `moov` box.
+    pub fn from_bytes(bytes: &[u8]) -> Option<MediaData> {
+        let top = boxes(bytes);
+        let ftyp = top.iter().find(|(kind, _)| kind == b"ftyp")?.1;
+        let moov = top.iter().find(|(kind, _)| kind == b"moov")?.1;
+        let major_brand = String::from_utf8_lossy(ftyp.get(..4)?).to_string();
+
+        let moov = boxes(moov);
+        let duration_seconds = child(&moov, b"mvhd").and_then(header_duration);
+        let tracks = moov.iter()
+            .filter(|(kind, _)| kind == b"trak")
+            .filter_map(|(_, trak)| track(trak))
+            .collect();
+        Some(MediaData {
+            major_brand,
+            duration_seconds,
+            tracks,
+            #[cfg(feature = "pixel")]
+            frames: Vec::new(),
+            #[cfg(feature = "pixel")]
+            frames_error: None,
+            score: 0,
+            confidence: 0
+        })
+    }
+
+    pub(crate) fn set_claim(&mut self, claim_id: Option<&String>) {
+        for track in &mut self.tracks {
+            track.claim_id = claim_id.cloned();
+        }
+    }
+
+    /// Decodes `options.frame_samples` frames spread over the duration with
+    /// ffmpeg and runs the pixel analyzers on them.
+    #[cfg(all(feature = "pixel", feature = "fs"))]
+    pub(crate) fn sample_frames(&mut self, path: &Path, options: &AnalysisOptions) {
+        if !self.tracks.iter().any(|t| t.kind == "video") {
+            return;
+        }
+        let duration = self.duration_seconds.unwrap_or(0.0);
+        for i in 0..options.frame_samples {
+            let time_seconds = duration * (i as f64 + 0.5) / options.frame_samples as f64;
+            let image = match decode_frame(path, time_seconds) {
+                Ok(image) => image,
+                Err(e) => {
+                    self.frames_error = Some(e);
+                    return;
+                }
+            };
+            let noise = NoiseFindings::from_image(&image);
+            let spectrum = SpectrumFindings::from_image(&image, false);
+            let watermarks = WatermarkFindings::from_image(&image, &options.watermarks);
+            let mut score = 0_u8;
+            let mut confidence = 0_u8;
+            let parts = [
+                noise.as_ref().map(|n| (n.score, n.confidence)),
+                spectrum.as_ref().map(|s| (s.score, s.confidence)),
+                watermarks.as_ref().map(|w| (w.score, w.confidence))
+            ];
+            for (s, c) in parts.into_iter().flatten() {
+                score = score.saturating_add(s).min(100);
+                confidence = confidence.saturating_add(c).min(100);
+            }
+            self.score = self.score.max(score);
+            self.confidence = self.confidence.max(confidence);
+            self.frames.push(FrameSample { time_seconds, noise, spectrum, watermarks, score, confidence });
+        }
+    }
+}
+
+#[cfg(all(feature = "pixel", feature = "fs"))]
+fn decode_frame(path: &Path, time_seconds: f64) -> Result<image::DynamicImage, String> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-ss", &format!("{:.3}", time_seconds), "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"])
+        .output()
+        .map_err(|e| format!("Error running ffmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    image::load_from_memory(&output.stdout).map_err(|e| format!("Error decoding frame: {}", e))
+}
+
+fn track(trak: &[u8]) -> Option<TrackData> {
+    let trak = boxes(trak);
+    let tkhd = child(&trak, b"tkhd")?;
+    let mdia = boxes(child(&trak, b"mdia")?);
+    let handler = child(&mdia, b"hdlr").and_then(|h| h.get(8..12)).unwrap_or_default();
+    let kind = match handler {
+        b"vide" => String::from("video"),
+        b"soun" => String::from("audio"),
+        other => String::from_utf8_lossy(other).to_string()
+    };
+    let codec = child(&mdia, b"minf")
+        .and_then(|minf| child(&boxes(minf), b"stbl"))
+        .and_then(|stbl| child(&boxes(stbl), b"stsd"))
+        .and_then(|stsd| stsd.get(12..16))
+        .map(|c| String::from_utf8_lossy(c).to_string());
+
+    let (id, width, height) = match tkhd.first()? {
+        0 => (be_u32(tkhd, 12)?, be_u32(tkhd, 76)?, be_u32(tkhd, 80)?),
+        _ => (be_u32(tkhd, 20)?, be_u32(tkhd, 88)?, be_u32(tkhd, 92)?)
+    };
+    // 16.16 fixed point, zero for tracks without pixels
+    let dimension = |v: u32| if v >> 16 == 0 { None } else { Some(v >> 16) };
+    Some(TrackData {
+        id,
+        kind,
+        codec,
+        duration_seconds: child(&mdia, b"mdhd").and_then(header_duration),
+        width: dimension(width),
+        height: dimension(height),
+        claim_id: None
+    })
+}
+
+/// Duration of an `mvhd` or `mdhd` box, which share the layout up to it.
+fn header_duration(header: &[u8]) -> Option<f64> {
+    let (timescale, duration) = match header.first()? {
+        0 => (be_u32(header, 12)?, be_u32(header, 16)? as u64),
+        _ => (be_u32(header, 20)?, be_u64(header, 24)?)
+    };
+    if timescale == 0 {
+        return None;
+    }
+    Some((duration as f64 / timescale as f64 * 1000.0).round() / 1000.0)
+}
+
+/// (type, payload) of the boxes laid out in `bytes`.
+fn boxes(mut bytes: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut found = Vec::new();
+    while bytes.len() >= 8 {
+        let kind = [bytes[4], bytes[5], bytes[6], bytes[7]];
+        let (header, size) = match be_u32(bytes, 0).unwrap_or_default() {
+            0 => (8, bytes.len()),
+            1 => match be_u64(bytes, 8) {
+                Some(size) => (16, size as usize),
+                None => break
+            },
+            size => (8, size as usize)
+        };
+        if size < header || size > bytes.len() {
+            break;
+        }
+        found.push((kind, &bytes[header..size]));
+        bytes = &bytes[size..];
+    }
+    found
+}
+
+fn child<'a>(boxes: &[([u8; 4], &'a [u8])], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes.iter().find(|(k, _)| k == kind).map(|(_, payload)| *payload)
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut b = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        b.extend_from_slice(kind);
+        b.extend_from_slice(payload);
+        b
+    }
+
+    /// A 2 second video track of 640x480 in a 3 second movie.
+    fn mp4() -> Vec<u8> {
+        let mut mvhd = vec![0; 20];
+        mvhd[12..16].copy_from_slice(&1000_u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&3000_u32.to_be_bytes());
+        let mut tkhd = vec![0; 84];
+        tkhd[12..16].copy_from_slice(&1_u32.to_be_bytes());
+        tkhd[76..80].copy_from_slice(&(640_u32 << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(480_u32 << 16).to_be_bytes());
+        let mut mdhd = vec![0; 20];
+        mdhd[12..16].copy_from_slice(&600_u32.to_be_bytes());
+        mdhd[16..20].copy_from_slice(&1200_u32.to_be_bytes());
+        let mut hdlr = vec![0; 12];
+        hdlr[8..12].copy_from_slice(b"vide");
+        let mut stsd = vec![0; 16];
+        stsd[12..16].copy_from_slice(b"avc1");
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+        let minf = mp4_box(b"minf", &stbl);
+        let mdia = mp4_box(b"mdia", &[mp4_box(b"mdhd", &mdhd), mp4_box(b"hdlr", &hdlr), minf].concat());
+        let trak = mp4_box(b"trak", &[mp4_box(b"tkhd", &tkhd), mdia].concat());
+        let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &mvhd), trak].concat());
+        [mp4_box(b"ftyp", b"isom\0\0\0\0"), moov].concat()
+    }
+
+    #[test]
+    fn reads_tracks() {
+        let media = MediaData::from_bytes(&mp4()).unwrap();
+        assert_eq!(media.major_brand, "isom");
+        assert_eq!(media.duration_seconds, Some(3.0));
+        let track = &media.tracks[0];
+        assert_eq!((track.id, track.kind.as_str(), track.codec.as_deref()), (1, "video", Some("avc1")));
+        assert_eq!((track.width, track.height, track.duration_seconds), (Some(640), Some(480), Some(2.0)));
+    }
+
+    #[test]
+    fn truncated_boxes_are_skipped() {
+        let bytes = mp4();
+        for len in 0..bytes.len() {
+            // the moov box is cut, so only a complete file has one
+            assert!(MediaData::from_bytes(&bytes[..len]).is_none(), "{} bytes", len);
+        }
+        let mut huge = mp4_box(b"ftyp", b"isom");
+        huge.extend_from_slice(&[0, 0, 0, 1, b'm', b'o', b'o', b'v', 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert!(MediaData::from_bytes(&huge).is_none());
+        assert!(track(&mp4_box(b"tkhd", &[0; 10])).is_none());
+    }
+}
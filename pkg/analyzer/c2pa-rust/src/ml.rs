@@ -0,0 +1,190 @@
+//! A trained AI-image classifier run on the decoded pixels.
+//!
+//! The crate ships no model. A deployment loads its own, ex. an ONNX model
+//! in an [`OnnxClassifier`](crate::onnx::OnnxClassifier) or a model of
+//! another runtime in its own [`ImageClassifier`], and adds an
+//! [`MlAnalyzer`] of it with `AnalysisOptions::with_analyzer`. The analyzer
+//! resizes and normalizes the pixels as the classifier's [`ModelInput`]
+//! says and votes in the ensemble as `ml` with the probability it returns.
+
+use std::sync::Arc;
+use image::imageops::FilterType;
+use serde::Serialize;
+
+use crate::{analyzer::{AnalysisInput, Analyzer, AnalyzerOutput}, error::{ReportError, ReportErrorKind}, explanation::Explanation, scoring::Weight};
+
+/// Channel mean and standard deviation of the ImageNet training set, what
+/// most classifiers fine-tuned from ImageNet backbones expect.
+const IMAGENET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const IMAGENET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+/// Largest width and height of a model input, a 4096x4096 tensor takes
+/// 192 MiB.
+pub const MAX_INPUT_SIDE: u32 = 4096;
+
+/// The tensor a classifier takes, a single RGB image in NCHW order,
+/// `(value / 255 - mean) / std` per channel.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ModelInput {
+    pub width: u32,
+    pub height: u32,
+    pub mean: [f32; 3],
+    pub std: [f32; 3]
+}
+
+impl ModelInput {
+    /// Normalized with the ImageNet mean and standard deviation.
+    pub fn imagenet(width: u32, height: u32) -> ModelInput {
+        ModelInput { width, height, mean: IMAGENET_MEAN, std: IMAGENET_STD }
+    }
+
+    pub fn with_normalization(mut self, mean: [f32; 3], std: [f32; 3]) -> ModelInput {
+        self.mean = mean;
+        self.std = std;
+        self
+    }
+
+    /// The pixels resized to the input, stretched, as the 1x3xHxW tensor.
+    /// `MlAnalyzer` checks the size against [`MAX_INPUT_SIDE`] first.
+    pub fn tensor(&self, image: &image::DynamicImage) -> Vec<f32> {
+        let rgb = image.resize_exact(self.width, self.height, FilterType::Triangle).to_rgb8();
+        let plane = (self.width * self.height) as usize;
+        let mut tensor = vec![0.0; plane * 3];
+        for (i, pixel) in rgb.pixels().enumerate() {
+            for channel in 0..3 {
+                tensor[channel * plane + i] = (pixel[channel] as f32 / 255.0 - self.mean[channel]) / self.std[channel];
+            }
+        }
+        tensor
+    }
+}
+
+/// A model rating how likely an image is AI-generated.
+pub trait ImageClassifier: Send + Sync {
+    /// Name reported with the results, ex. `detector-v3.onnx`.
+    fn name(&self) -> &str;
+    fn input(&self) -> ModelInput;
+    /// Probability, 0-1, that the image of the tensor of `input` is
+    /// generated.
+    fn classify(&self, tensor: &[f32]) -> Result<f32, String>;
+}
+
+/// What the classifier made of the image.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct MlFindings {
+    pub model: String,
+    /// That the image is generated, 0-1.
+    pub probability: f32,
+    pub score: u8,
+    /// Grows with the distance of the probability from 0.5.
+    pub confidence: u8
+}
+
+impl MlFindings {
+    pub fn from_probability(model: &str, probability: f32, weight: Weight) -> MlFindings {
+        let certainty = (probability - 0.5).abs() * 2.0;
+        MlFindings {
+            model: model.to_string(),
+            probability,
+            score: (probability * weight.score as f32).round() as u8,
+            confidence: (certainty * weight.confidence as f32).round() as u8
+        }
+    }
+}
+
+/// Runs a classifier on the decoded pixels, listed in the report's
+/// `analyzers` with the findings as details.
+pub struct MlAnalyzer {
+    classifier: Arc<dyn ImageClassifier>
+}
+
+impl MlAnalyzer {
+    pub fn new(classifier: Arc<dyn ImageClassifier>) -> MlAnalyzer {
+        MlAnalyzer { classifier }
+    }
+}
+
+impl Analyzer for MlAnalyzer {
+    fn name(&self) -> &str {
+        "ml"
+    }
+
+    fn analyze(&self, input: &AnalysisInput) -> AnalyzerOutput {
+        let mut output = AnalyzerOutput::default();
+        let image = match input.image {
+            Some(i) => i,
+            None => return output
+        };
+        let classifier = &self.classifier;
+        let size = classifier.input();
+        if !(1..=MAX_INPUT_SIDE).contains(&size.width) || !(1..=MAX_INPUT_SIDE).contains(&size.height) {
+            let message = format!("{} takes {}x{} images, not 1-{} pixels a side", classifier.name(), size.width, size.height, MAX_INPUT_SIDE);
+            output.errors.push(ReportError::new(ReportErrorKind::ClassifierError, message));
+            return output;
+        }
+        let tensor = output.timings.time("preprocess", || classifier.input().tensor(image));
+        let probability = match output.timings.time("classify", || classifier.classify(&tensor)) {
+            Ok(p) if (0.0..=1.0).contains(&p) => p,
+            Ok(p) => {
+                let message = format!("{} returned {}, not a probability", classifier.name(), p);
+                output.errors.push(ReportError::new(ReportErrorKind::ClassifierError, message));
+                return output;
+            },
+            Err(e) => {
+                output.errors.push(ReportError::new(ReportErrorKind::ClassifierError, format!("{}: {}", classifier.name(), e)));
+                return output;
+            }
+        };
+        let findings = MlFindings::from_probability(classifier.name(), probability, input.options.scoring.ml_classifier);
+        let percent = (probability * 100.0).round() as u8;
+        output.explain("ml", Explanation::Classifier(findings.model.clone(), percent), (findings.score, findings.confidence));
+        match serde_json::to_value(&findings) {
+            Ok(details) => output.with_details(details),
+            Err(_) => output
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidence_follows_certainty() {
+        let weight = Weight::new(100, 60);
+        let sure = MlFindings::from_probability("test", 0.95, weight);
+        assert_eq!((sure.score, sure.confidence), (95, 54));
+        let unsure = MlFindings::from_probability("test", 0.5, weight);
+        assert_eq!((unsure.score, unsure.confidence), (50, 0));
+        let tensor = ModelInput::imagenet(4, 2).tensor(&image::DynamicImage::new_rgb8(8, 8));
+        assert_eq!(tensor.len(), 24);
+        assert!((tensor[0] + IMAGENET_MEAN[0] / IMAGENET_STD[0]).abs() < 1e-6);
+    }
+
+    struct Huge;
+
+    impl ImageClassifier for Huge {
+        fn name(&self) -> &str {
+            "huge"
+        }
+
+        fn input(&self) -> ModelInput {
+            ModelInput::imagenet(65536, 65536)
+        }
+
+        fn classify(&self, _: &[f32]) -> Result<f32, String> {
+            unreachable!("the input is refused before")
+        }
+    }
+
+    #[test]
+    fn inputs_over_the_cap_are_refused() {
+        let mut png = Vec::new();
+        image::RgbImage::new(4, 4).write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        let options = crate::options::AnalysisOptions::default().with_analyzer(Arc::new(MlAnalyzer::new(Arc::new(Huge))));
+        let report = crate::report::Report::from_bytes_with("black.png", &png, &options).unwrap();
+        let error = report.errors.iter().find(|e| e.kind == ReportErrorKind::ClassifierError).unwrap();
+        assert!(error.message.contains("65536x65536"), "{}", error.message);
+    }
+}
@@ -0,0 +1,123 @@
+use image::{DynamicImage, GrayImage};
+use serde::Serialize;
+
+use crate::ela::{percentile, round};
+
+/// Side of the square blocks noise is estimated over.
+const BLOCK: u32 = 32;
+/// Blocks whose mean brightness is this close to black or white clip the
+/// noise and are left out.
+const CLIPPED_MARGIN: f64 = 8.0;
+/// Coefficient of variation of the block noise below which it is more even
+/// than a sensor leaves it.
+const UNIFORM_CV: f64 = 0.15;
+/// A block is out of line when its noise is this many times above or below
+/// the median.
+const OUTLIER_FACTOR: f64 = 3.0;
+const MIN_OUTLIER_SHARE: f64 = 0.02;
+const MAX_OUTLIER_SHARE: f64 = 0.3;
+const UNIFORM: (u8, u8) = (20, 10);
+const INCONSISTENT: (u8, u8) = (25, 10);
+
+/// Per-block noise estimated from the high-pass residual of the luma.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct NoiseFindings {
+    pub block_size: u32,
+    /// Blocks estimated, clipped ones left out.
+    pub blocks: usize,
+    /// Median and spread of the block noise, in 8-bit levels.
+    pub median_sigma: f64,
+    pub p10_sigma: f64,
+    pub p90_sigma: f64,
+    /// Standard deviation over mean of the block noise.
+    pub variation: f64,
+    /// Share of blocks `OUTLIER_FACTOR` above or below the median.
+    pub outlier_share: f64,
+    /// Noise is unnaturally even over the image, typical of diffusion
+    /// output.
+    pub uniform: bool,
+    /// Some regions carry different noise than the rest, typical of
+    /// splicing.
+    pub inconsistent: bool,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl NoiseFindings {
+    /// None when the image is too small for a single block.
+    pub fn from_image(image: &DynamicImage) -> Option<NoiseFindings> {
+        let luma = image.to_luma8();
+        let mut sigmas = block_sigmas(&luma);
+        if sigmas.is_empty() {
+            return None;
+        }
+        sigmas.sort_by(|a, b| a.total_cmp(b));
+        let median = percentile(&sigmas, 0.5);
+        let mean = sigmas.iter().sum::<f64>() / sigmas.len() as f64;
+        let stddev = (sigmas.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / sigmas.len() as f64).sqrt();
+        let variation = if mean > 0.0 { stddev / mean } else { 0.0 };
+        let outliers = sigmas.iter()
+            .filter(|s| **s > median * OUTLIER_FACTOR || **s * OUTLIER_FACTOR < median)
+            .count();
+        let outlier_share = outliers as f64 / sigmas.len() as f64;
+
+        // a flat synthetic image has no noise to be uniform
+        let uniform = median > 0.5 && variation < UNIFORM_CV;
+        let inconsistent = (MIN_OUTLIER_SHARE..=MAX_OUTLIER_SHARE).contains(&outlier_share);
+        let mut score = 0_u8;
+        let mut confidence = 0_u8;
+        for (flag, (s, c)) in [(uniform, UNIFORM), (inconsistent, INCONSISTENT)] {
+            if flag {
+                score += s;
+                confidence += c;
+            }
+        }
+        Some(NoiseFindings {
+            block_size: BLOCK,
+            blocks: sigmas.len(),
+            median_sigma: round(median),
+            p10_sigma: round(percentile(&sigmas, 0.1)),
+            p90_sigma: round(percentile(&sigmas, 0.9)),
+            variation: round(variation),
+            outlier_share: round(outlier_share),
+            uniform,
+            inconsistent,
+            score,
+            confidence
+        })
+    }
+}
+
+/// Noise of each full block by Immerkær's estimator: the mean absolute
+/// response to a Laplacian difference kernel, which cancels smooth image
+/// structure.
+fn block_sigmas(luma: &GrayImage) -> Vec<f64> {
+    let mut sigmas = Vec::new();
+    let (width, height) = luma.dimensions();
+    let scale = (std::f64::consts::PI / 2.0).sqrt() / (6.0 * ((BLOCK - 2) * (BLOCK - 2)) as f64);
+    let at = |x: u32, y: u32| luma.get_pixel(x, y).0[0] as i32;
+    for by in (0..height.saturating_sub(BLOCK - 1)).step_by(BLOCK as usize) {
+        for bx in (0..width.saturating_sub(BLOCK - 1)).step_by(BLOCK as usize) {
+            let mut brightness = 0_u64;
+            let mut response = 0_u64;
+            for y in by..by + BLOCK {
+                for x in bx..bx + BLOCK {
+                    brightness += at(x, y) as u64;
+                }
+            }
+            for y in by + 1..by + BLOCK - 1 {
+                for x in bx + 1..bx + BLOCK - 1 {
+                    let corners = at(x - 1, y - 1) + at(x + 1, y - 1) + at(x - 1, y + 1) + at(x + 1, y + 1);
+                    let sides = at(x, y - 1) + at(x - 1, y) + at(x + 1, y) + at(x, y + 1);
+                    response += (corners - 2 * sides + 4 * at(x, y)).unsigned_abs() as u64;
+                }
+            }
+            let brightness = brightness as f64 / (BLOCK * BLOCK) as f64;
+            if (CLIPPED_MARGIN..=255.0 - CLIPPED_MARGIN).contains(&brightness) {
+                sigmas.push(response as f64 * scale);
+            }
+        }
+    }
+    sigmas
+}
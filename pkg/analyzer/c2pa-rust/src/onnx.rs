@@ -0,0 +1,229 @@
+//! ONNX image classifiers, run with `tract`.
+//!
+//! tract runs the models in pure Rust on the CPU, with the operators of
+//! the opset versions it's tested against. Models of other versions, or
+//! with operators it lacks, are refused when loaded.
+
+use std::{fs, ops::RangeInclusive, path::Path};
+use tract_onnx::{pb::{tensor_shape_proto::dimension, type_proto, ModelProto}, prelude::*};
+
+use crate::ml::{ImageClassifier, ModelInput, MAX_INPUT_SIDE};
+
+/// Versions of the default ONNX opset tract is tested against.
+pub const OPSETS: RangeInclusive<i64> = 9..=18;
+
+/// An ONNX model taking a 1x3xHxW image and giving the probability that
+/// it's generated, or the logits or probabilities of real and generated.
+pub struct OnnxClassifier {
+    name: String,
+    input: ModelInput,
+    model: TypedRunnableModel<TypedModel>
+}
+
+impl OnnxClassifier {
+    /// Named by the file name, ex. `detector-v3.onnx`.
+    pub fn from_file(path: &Path) -> Result<OnnxClassifier, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        OnnxClassifier::from_bytes(&name, &bytes)
+    }
+
+    /// The input is normalized with the ImageNet mean and standard
+    /// deviation unless `with_input` gives other values.
+    pub fn from_bytes(name: &str, bytes: &[u8]) -> Result<OnnxClassifier, String> {
+        let onnx = tract_onnx::onnx();
+        let proto = onnx.proto_model_for_read(&mut &*bytes).map_err(|e| format!("{} isn't an ONNX model: {}", name, e))?;
+        match proto.opset_import.iter().find(|o| matches!(o.domain.as_str(), "" | "ai.onnx")).map(|o| o.version) {
+            Some(version) if OPSETS.contains(&version) => {},
+            Some(version) => return Err(format!("{} uses opset {}, not {}-{}", name, version, OPSETS.start(), OPSETS.end())),
+            None => return Err(format!("{} names no opset", name))
+        }
+        let (width, height) = input_size(&proto).ok_or_else(|| format!("the input of {} isn't a 1x3xHxW image", name))?;
+        if width > MAX_INPUT_SIDE || height > MAX_INPUT_SIDE {
+            return Err(format!("{} takes {}x{} images, over {} pixels a side", name, width, height, MAX_INPUT_SIDE));
+        }
+        let model = onnx.model_for_proto_model(&proto)
+            .and_then(|m| m.with_input_fact(0, f32::fact([1, 3, height as usize, width as usize]).into()))
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(|e| format!("{} can't be run: {}", name, e))?;
+        Ok(OnnxClassifier { name: name.to_string(), input: ModelInput::imagenet(width, height), model })
+    }
+
+    /// Keeps the size of the model's input, ex. to normalize differently.
+    pub fn with_input(mut self, input: ModelInput) -> OnnxClassifier {
+        self.input = ModelInput { width: self.input.width, height: self.input.height, ..input };
+        self
+    }
+}
+
+impl ImageClassifier for OnnxClassifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn input(&self) -> ModelInput {
+        self.input
+    }
+
+    fn classify(&self, tensor: &[f32]) -> Result<f32, String> {
+        let shape = [1, 3, self.input.height as usize, self.input.width as usize];
+        let input = Tensor::from_shape(&shape, tensor).map_err(|e| e.to_string())?;
+        let outputs = self.model.run(tvec!(input.into())).map_err(|e| e.to_string())?;
+        let output = outputs.first().ok_or("the model gave no output")?;
+        match output.as_slice::<f32>().map_err(|e| e.to_string())? {
+            [probability] => Ok(*probability),
+            [real, generated] if (0.0..=1.0).contains(real) && (0.0..=1.0).contains(generated) && (real + generated - 1.0).abs() < 1e-3 => Ok(*generated),
+            [real, generated] => Ok(1.0 / (1.0 + (real - generated).exp())),
+            values => Err(format!("the output has {} values, not 1 or 2", values.len()))
+        }
+    }
+}
+
+/// (width, height) of the model's input, None unless it's a 1x3xHxW
+/// tensor, the batch size 1 or unnamed.
+fn input_size(proto: &ModelProto) -> Option<(u32, u32)> {
+    let graph = proto.graph.as_ref()?;
+    let input = graph.input.iter().find(|i| !graph.initializer.iter().any(|t| t.name == i.name))?;
+    let Some(type_proto::Value::TensorType(tensor)) = input.r#type.as_ref()?.value.as_ref() else {
+        return None;
+    };
+    let dims: Vec<Option<i64>> = tensor.shape.as_ref()?.dim.iter()
+        .map(|d| match d.value {
+            Some(dimension::Value::DimValue(v)) => Some(v),
+            _ => None
+        })
+        .collect();
+    match dims.as_slice() {
+        [None | Some(1), Some(3), Some(h), Some(w)] if *h > 0 && *w > 0 => Some((u32::try_from(*w).ok()?, u32::try_from(*h).ok()?)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, sync::Arc};
+    use onnx_prost::Message;
+    use super::*;
+    use crate::{ml::MlAnalyzer, options::AnalysisOptions, report::Report};
+    use tract_onnx::pb::{attribute_proto::AttributeType, tensor_proto::DataType, tensor_shape_proto::Dimension, AttributeProto, GraphProto, NodeProto, OperatorSetIdProto, TensorProto, TensorShapeProto, TypeProto, ValueInfoProto};
+
+    fn tensor(name: &str, dims: &[i64], values: &[f32]) -> TensorProto {
+        TensorProto { dims: dims.to_vec(), data_type: DataType::Float as i32, float_data: values.to_vec(), name: name.to_string(), ..Default::default() }
+    }
+
+    fn node(op: &str, inputs: &[&str], output: &str, attribute: Vec<AttributeProto>) -> NodeProto {
+        NodeProto {
+            input: inputs.iter().map(|i| i.to_string()).collect(),
+            output: vec![output.to_string()],
+            op_type: op.to_string(),
+            attribute,
+            ..Default::default()
+        }
+    }
+
+    fn int(name: &str, value: i64) -> AttributeProto {
+        AttributeProto { name: name.to_string(), r#type: AttributeType::Int as i32, i: value, ..Default::default() }
+    }
+
+    fn value(name: &str, dims: &[Option<i64>]) -> ValueInfoProto {
+        let dim = dims.iter().map(|d| Dimension {
+            value: Some(match d {
+                Some(d) => dimension::Value::DimValue(*d),
+                None => dimension::Value::DimParam(String::from("N"))
+            }),
+            ..Default::default()
+        }).collect();
+        let tensor_type = type_proto::Tensor { elem_type: DataType::Float as i32, shape: Some(TensorShapeProto { dim }) };
+        let r#type = TypeProto { value: Some(type_proto::Value::TensorType(tensor_type)), ..Default::default() };
+        ValueInfoProto { name: name.to_string(), r#type: Some(r#type), ..Default::default() }
+    }
+
+    /// A model of `pixels` to `probability` in the default opset
+    /// `version`.
+    fn model(version: i64, node: Vec<NodeProto>, initializer: Vec<TensorProto>, input: &[Option<i64>]) -> Vec<u8> {
+        let graph = GraphProto {
+            node,
+            initializer,
+            input: vec![value("pixels", input)],
+            output: vec![value("probability", &[])],
+            ..Default::default()
+        };
+        let opset = OperatorSetIdProto { domain: String::new(), version };
+        ModelProto { ir_version: 7, opset_import: vec![opset], graph: Some(graph), ..Default::default() }.encode_to_vec()
+    }
+
+    #[test]
+    fn tiny_models_classify_images() {
+        let mut bias = tensor("bias", &[1], &[]);
+        bias.raw_data = 0.25f32.to_le_bytes().to_vec();
+        let bytes = model(
+            13,
+            vec![
+                node("GlobalAveragePool", &["pixels"], "pooled", Vec::new()),
+                node("Flatten", &["pooled"], "flat", Vec::new()),
+                node("Gemm", &["flat", "weights", "bias"], "logit", vec![int("transB", 1)]),
+                node("Sigmoid", &["logit"], "probability", Vec::new())
+            ],
+            vec![tensor("weights", &[1, 3], &[1.0, -1.0, 0.5]), bias],
+            &[None, Some(3), Some(2), Some(2)]
+        );
+        let classifier = OnnxClassifier::from_bytes("tiny.onnx", &bytes).unwrap();
+        assert_eq!(classifier.input(), ModelInput::imagenet(2, 2));
+        let pixels: Vec<f32> = [1.0, 2.0, 4.0].iter().flat_map(|&v| [v; 4]).collect();
+        // sigmoid(1 - 2 + 2 + 0.25)
+        assert!((classifier.classify(&pixels).unwrap() - 0.777_300_9).abs() < 1e-5);
+
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0])).write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        let options = AnalysisOptions::default().with_analyzer(Arc::new(MlAnalyzer::new(Arc::new(classifier))));
+        let report = Report::from_bytes_with("red.png", &png, &options).unwrap();
+        let details = report.analyzers.iter().find(|a| a.name == "ml").and_then(|a| a.details.as_ref()).unwrap();
+        assert_eq!(details["model"], "tiny.onnx");
+        // The normalized channels are 2.249, -2.036 and -1.804
+        assert!((details["probability"].as_f64().unwrap() - 0.9742).abs() < 1e-3);
+    }
+
+    #[test]
+    fn two_class_outputs_are_probabilities() {
+        // Softmax defaults to axis 1 before opset 13 and to the last axis
+        // from it, the same axis of these 1x2 logits
+        for version in [11, 13] {
+            let classify = |softmax: bool| {
+                let mut nodes = vec![
+                    node("Flatten", &["pixels"], "flat", Vec::new()),
+                    node("Gemm", &["flat", "weights", "bias"], if softmax { "logits" } else { "probability" }, Vec::new())
+                ];
+                if softmax {
+                    nodes.push(node("Softmax", &["logits"], "probability", Vec::new()));
+                }
+                let initializers = vec![tensor("weights", &[3, 2], &[0.0; 6]), tensor("bias", &[2], &[0.0, 3f32.ln()])];
+                let bytes = model(version, nodes, initializers, &[Some(1), Some(3), Some(1), Some(1)]);
+                OnnxClassifier::from_bytes("two.onnx", &bytes).unwrap().classify(&[0.5, 0.5, 0.5]).unwrap()
+            };
+            assert!((classify(false) - 0.75).abs() < 1e-6, "opset {}", version);
+            assert!((classify(true) - 0.75).abs() < 1e-6, "opset {}", version);
+        }
+    }
+
+    #[test]
+    fn unsupported_models_are_rejected() {
+        let rejected = |bytes: Vec<u8>, error: &str| match OnnxClassifier::from_bytes("model.onnx", &bytes) {
+            Ok(_) => panic!("accepted a model for {}", error),
+            Err(e) => assert!(e.contains(error), "{}", e)
+        };
+        let identity = || vec![node("Identity", &["pixels"], "probability", Vec::new())];
+        let image = [Some(1), Some(3), Some(2), Some(2)];
+        rejected(b"not a model".to_vec(), "isn't an ONNX model");
+        rejected(model(7, identity(), Vec::new(), &image), "opset 7");
+        rejected(model(19, identity(), Vec::new(), &image), "opset 19");
+        rejected(model(13, identity(), Vec::new(), &[Some(1), Some(1), Some(2), Some(2)]), "1x3xHxW");
+        // Refused before anything of its size is allocated
+        rejected(model(13, identity(), Vec::new(), &[Some(1), Some(3), Some(65536), Some(65536)]), "over 4096");
+        rejected(model(13, vec![node("NoSuchOperator", &["pixels"], "probability", Vec::new())], Vec::new(), &image), "can't be run");
+
+        let wide = model(13, identity(), Vec::new(), &[Some(1), Some(3), Some(1), Some(1)]);
+        let wide = OnnxClassifier::from_bytes("wide.onnx", &wide).unwrap();
+        assert!(wide.classify(&[0.1, 0.2, 0.3]).unwrap_err().contains("3 values"));
+    }
+}
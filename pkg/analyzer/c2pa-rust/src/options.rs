@@ -0,0 +1,172 @@
+use std::{path::PathBuf, sync::Arc};
+
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "pixel")]
+use crate::watermark::{WatermarkDecoder, WatermarkDecoders};
+use crate::{analyzer::{Analyzer, Analyzers}, explanation::Locale, remote::RemoteManifests, reportsigning::ReportSigner, revocation::RevocationCheck, rules::GeneratorRules, scoring::ScoringConfig, trust::TrustConfig};
+
+/// Settings for an analysis, loaded once at startup and shared by every
+/// file of a run. The `Report::from_*` constructors without `_with` use the
+/// defaults.
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub struct AnalysisOptions {
+    /// Analyzers merged into the report, the built-in ones by default.
+    pub analyzers: Analyzers,
+    pub rules: GeneratorRules,
+    pub scoring: ScoringConfig,
+    /// Language of the evidence explanations.
+    pub locale: Locale,
+    /// Where claim and ingredient thumbnails are written, None to skip them.
+    pub thumbnail_dir: Option<PathBuf>,
+    /// Trust policy for validation, None for the c2pa defaults.
+    pub trust: Option<TrustConfig>,
+    /// Where ELA images are written, None to skip them.
+    pub ela_dir: Option<PathBuf>,
+    /// Adds the reader's JSON of the manifest store to the report.
+    pub include_raw_manifest: bool,
+    /// Adds how long each stage took to the report. They differ from run
+    /// to run, so they are left out by default and never cached.
+    pub timings: bool,
+    /// Labels of the assertions whose data the claims include, ex.
+    /// `c2pa.actions`, the others are only listed.
+    pub assertion_filter: Vec<String>,
+    /// Labels of the assertions copied into the report's
+    /// `extracted_assertions`.
+    pub extract_assertions: Vec<String>,
+    /// Analyzes the data or thumbnail of every ingredient and nests the
+    /// reports in the ingredient graph.
+    pub analyze_ingredients: bool,
+    /// The time of the analysis, EXIF capture times after it are flagged.
+    /// None to skip the check, so a report only depends on the file.
+    pub now: Option<DateTime<Utc>>,
+    /// Where manifests the asset only links to may be fetched from.
+    pub remote: RemoteManifests,
+    /// Verification page of `verify_url`, None for
+    /// `remote::VERIFY_URL`.
+    pub verify_base_url: Option<String>,
+    /// Online revocation checking of the signing chain, None to only check
+    /// stapled OCSP responses.
+    pub revocation: Option<RevocationCheck>,
+    /// Inputs over this many bytes are rejected before they are read, None
+    /// for no limit.
+    pub max_input_size: Option<u64>,
+    /// Signs the reports the binary and servers emit, None to leave them
+    /// unsigned.
+    pub report_signer: Option<ReportSigner>,
+    /// Watermark decoders run on the pixels, the built-in ones by default.
+    #[cfg(feature = "pixel")]
+    pub watermarks: WatermarkDecoders,
+    /// Video frames decoded with ffmpeg for the pixel analyzers, 0 to skip
+    /// them.
+    #[cfg(feature = "pixel")]
+    pub frame_samples: usize
+}
+
+impl AnalysisOptions {
+    /// Adds an analyzer after the built-in ones.
+    pub fn with_analyzer(mut self, analyzer: Arc<dyn Analyzer>) -> AnalysisOptions {
+        self.analyzers = self.analyzers.with_analyzer(analyzer);
+        self
+    }
+
+    pub fn with_rules(mut self, rules: GeneratorRules) -> AnalysisOptions {
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_scoring(mut self, scoring: ScoringConfig) -> AnalysisOptions {
+        self.scoring = scoring;
+        self
+    }
+
+    pub fn with_locale(mut self, locale: Locale) -> AnalysisOptions {
+        self.locale = locale;
+        self
+    }
+
+    pub fn with_thumbnail_dir(mut self, dir: PathBuf) -> AnalysisOptions {
+        self.thumbnail_dir = Some(dir);
+        self
+    }
+
+    pub fn with_ela_dir(mut self, dir: PathBuf) -> AnalysisOptions {
+        self.ela_dir = Some(dir);
+        self
+    }
+
+    pub fn with_raw_manifest(mut self) -> AnalysisOptions {
+        self.include_raw_manifest = true;
+        self
+    }
+
+    pub fn with_timings(mut self) -> AnalysisOptions {
+        self.timings = true;
+        self
+    }
+
+    pub fn with_now(mut self, now: DateTime<Utc>) -> AnalysisOptions {
+        self.now = Some(now);
+        self
+    }
+
+    pub fn with_assertion_filter(mut self, labels: Vec<String>) -> AnalysisOptions {
+        self.assertion_filter = labels;
+        self
+    }
+
+    /// Adds a label to `extract_assertions`.
+    pub fn with_extracted_assertion(mut self, label: &str) -> AnalysisOptions {
+        self.extract_assertions.push(label.to_string());
+        self
+    }
+
+    pub fn with_ingredient_analysis(mut self) -> AnalysisOptions {
+        self.analyze_ingredients = true;
+        self
+    }
+
+    pub fn with_remote(mut self, remote: RemoteManifests) -> AnalysisOptions {
+        self.remote = remote;
+        self
+    }
+
+    /// Adds a decoder to the built-in ones.
+    #[cfg(feature = "pixel")]
+    pub fn with_watermark_decoder(mut self, decoder: Arc<dyn WatermarkDecoder>) -> AnalysisOptions {
+        self.watermarks = self.watermarks.with_decoder(decoder);
+        self
+    }
+
+    #[cfg(feature = "pixel")]
+    pub fn with_frame_samples(mut self, frames: usize) -> AnalysisOptions {
+        self.frame_samples = frames;
+        self
+    }
+
+    pub fn with_verify_base_url(mut self, url: &str) -> AnalysisOptions {
+        self.verify_base_url = Some(url.to_string());
+        self
+    }
+
+    pub fn with_revocation(mut self, check: RevocationCheck) -> AnalysisOptions {
+        self.revocation = Some(check);
+        self
+    }
+
+    pub fn with_max_input_size(mut self, bytes: u64) -> AnalysisOptions {
+        self.max_input_size = Some(bytes);
+        self
+    }
+
+    pub fn with_report_signer(mut self, signer: ReportSigner) -> AnalysisOptions {
+        self.report_signer = Some(signer);
+        self
+    }
+
+    pub fn with_trust(mut self, trust: TrustConfig) -> AnalysisOptions {
+        self.trust = Some(trust);
+        self
+    }
+}
@@ -0,0 +1,178 @@
+use lopdf::{Dictionary, Document, Object, Stream};
+use serde::Serialize;
+
+use crate::{options::AnalysisOptions, report::Report, rules::RuleKind, xmp::XmpFindings};
+
+/// Embedded images analyzed at most, flyers rarely have more.
+const MAX_IMAGES: usize = 32;
+/// Images with fewer pixels are icons and rules, not content.
+const MIN_IMAGE_PIXELS: i64 = 64 * 64;
+const EDITING_TOOL: (u8, u8) = (20, 10);
+
+/// What a PDF says about how it was made, and the reports of its
+/// embedded images.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct PdfData {
+    pub version: String,
+    pub page_count: usize,
+    /// `Creator` and `Producer` of the document information dictionary.
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+    /// Kind of the generator rule the creator or producer matched.
+    pub tool_rule: Option<RuleKind>,
+    /// The catalog's XMP metadata, when compressed and so out of reach of
+    /// the file-level XMP scan.
+    pub xmp: Option<XmpFindings>,
+    /// Embedded images analyzed like standalone files, named
+    /// `<object number>.<jpg|png>`.
+    pub images: Vec<Report>,
+    /// Images over `MAX_IMAGES` that were not analyzed.
+    pub images_skipped: usize,
+    /// The tool rule, the compressed XMP and the highest image score
+    /// combined.
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl PdfData {
+    /// None unless the bytes are a PDF lopdf can load.
+    pub fn from_bytes(bytes: &[u8], options: &AnalysisOptions) -> Option<PdfData> {
+        if !bytes.starts_with(b"%PDF-") {
+            return None;
+        }
+        let document = Document::load_mem(bytes).ok()?;
+        let info = document.trailer.get(b"Info").ok()
+            .and_then(|i| document.dereference(i).ok())
+            .and_then(|(_, i)| i.as_dict().ok());
+        let creator = info.and_then(|i| text(i, b"Creator"));
+        let producer = info.and_then(|i| text(i, b"Producer"));
+        let tool = [&creator, &producer].into_iter()
+            .flatten()
+            .filter_map(|t| options.rules.find(t))
+            .min_by_key(|(kind, _)| *kind != RuleKind::Generated);
+        let xmp = document.catalog().ok()
+            .and_then(|c| c.get(b"Metadata").ok())
+            .and_then(|m| document.dereference(m).ok())
+            .and_then(|(_, m)| m.as_stream().ok())
+            .filter(|m| m.dict.has(b"Filter"))
+            .and_then(|m| m.decompressed_content().ok())
+            .and_then(|m| XmpFindings::from_bytes(&m, &options.rules));
+
+        let mut images = Vec::new();
+        let mut images_skipped = 0;
+        for (id, object) in &document.objects {
+            let stream = match object.as_stream() {
+                Ok(s) if is_image(s) => s,
+                _ => continue
+            };
+            if images.len() == MAX_IMAGES {
+                images_skipped += 1;
+                continue;
+            }
+            if let Some((extension, bytes)) = image_file(stream) {
+                images.push(Report::from_bytes_lenient(&format!("{}.{}", id.0, extension), &bytes, options));
+            }
+        }
+
+        let (mut score, mut confidence) = match tool {
+            Some((RuleKind::Generated, rule)) => (rule.score, rule.confidence),
+            Some((RuleKind::Modified, _)) => EDITING_TOOL,
+            None => (0, 0)
+        };
+        if let Some(xmp) = &xmp {
+            score = score.saturating_add(xmp.score);
+            confidence = confidence.saturating_add(xmp.confidence);
+        }
+        if let Some(image) = images.iter().max_by_key(|r| (r.score, r.score_confidence)) {
+            score = score.saturating_add(image.score);
+            confidence = confidence.saturating_add(image.score_confidence);
+        }
+        Some(PdfData {
+            version: document.version.clone(),
+            page_count: document.get_pages().len(),
+            creator,
+            producer,
+            tool_rule: tool.map(|(kind, _)| kind),
+            xmp,
+            images,
+            images_skipped,
+            score: score.min(100),
+            confidence: confidence.min(100)
+        })
+    }
+}
+
+fn text(dictionary: &Dictionary, key: &[u8]) -> Option<String> {
+    let bytes = dictionary.get(key).ok()?.as_str().ok()?;
+    // UTF-16BE with a byte order mark, else PDFDocEncoding, close enough
+    // to Latin-1 for tool names
+    let value = match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units)
+        },
+        None => bytes.iter().map(|b| *b as char).collect()
+    };
+    let value = value.trim();
+    if value.is_empty() { None } else { Some(value.to_string()) }
+}
+
+fn is_image(stream: &Stream) -> bool {
+    let subtype = stream.dict.get(b"Subtype").and_then(Object::as_name);
+    let width = stream.dict.get(b"Width").and_then(Object::as_i64).unwrap_or(0);
+    let height = stream.dict.get(b"Height").and_then(Object::as_i64).unwrap_or(0);
+    subtype.is_ok_and(|s| s == b"Image") && width * height >= MIN_IMAGE_PIXELS
+}
+
+fn filters(stream: &Stream) -> Vec<&[u8]> {
+    match stream.dict.get(b"Filter") {
+        Ok(Object::Name(name)) => vec![name.as_slice()],
+        Ok(Object::Array(names)) => names.iter().filter_map(|n| n.as_name().ok()).collect(),
+        _ => Vec::new()
+    }
+}
+
+/// The image as a file the analyzers read: JPEG streams as they are, and
+/// with the pixel analyses, 8-bit RGB and gray images as PNG.
+fn image_file(stream: &Stream) -> Option<(&'static str, Vec<u8>)> {
+    match filters(stream).as_slice() {
+        [b"DCTDecode"] => Some(("jpg", stream.content.clone())),
+        #[cfg(feature = "pixel")]
+        [] | [b"FlateDecode"] => raw_image(stream).map(|png| ("png", png)),
+        _ => None
+    }
+}
+
+#[cfg(feature = "pixel")]
+fn raw_image(stream: &Stream) -> Option<Vec<u8>> {
+    use std::io::Read;
+    use image::{ColorType, ImageEncoder, codecs::png::PngEncoder};
+
+    let width = stream.dict.get(b"Width").ok()?.as_i64().ok()? as u32;
+    let height = stream.dict.get(b"Height").ok()?.as_i64().ok()? as u32;
+    if stream.dict.get(b"BitsPerComponent").ok()?.as_i64().ok()? != 8 {
+        return None;
+    }
+    let color = match stream.dict.get(b"ColorSpace").ok()?.as_name().ok()? {
+        b"DeviceRGB" => ColorType::Rgb8,
+        b"DeviceGray" => ColorType::L8,
+        _ => return None
+    };
+    // lopdf won't decompress images, and predictors aren't undone here
+    let pixels = match filters(stream).is_empty() {
+        true => stream.content.clone(),
+        false if stream.dict.has(b"DecodeParms") => return None,
+        false => {
+            let mut pixels = Vec::new();
+            flate2::read::ZlibDecoder::new(stream.content.as_slice()).read_to_end(&mut pixels).ok()?;
+            pixels
+        }
+    };
+    if pixels.len() != (width * height) as usize * color.bytes_per_pixel() as usize {
+        return None;
+    }
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).write_image(&pixels, width, height, color.into()).ok()?;
+    Some(png)
+}
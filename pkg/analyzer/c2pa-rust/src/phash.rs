@@ -0,0 +1,144 @@
+use image::{imageops, DynamicImage, GrayImage};
+use serde::Serialize;
+
+/// Side of the luma the DCT runs on, and of the low frequencies kept.
+const SIZE: usize = 32;
+const HASH_SIZE: usize = 8;
+
+/// Perceptual hashes of the decoded pixels, 16 hex digits each, to find
+/// near-duplicates of an image without decoding it again. Compare them by
+/// the bits they differ in, see `distance`: up to about 10 is the same
+/// picture resized or re-encoded.
+#[derive(Serialize, Clone)]
+#[non_exhaustive]
+pub struct PerceptualHash {
+    /// DCT hash, robust to resizing, recompression and color changes.
+    pub phash: String,
+    /// Gradient hash, cheaper and more sensitive to local edits.
+    pub dhash: String
+}
+
+impl PerceptualHash {
+    pub fn from_image(image: &DynamicImage) -> PerceptualHash {
+        let luma = image.to_luma8();
+        PerceptualHash { phash: format!("{:016x}", luma_phash(&luma)), dhash: format!("{:016x}", luma_dhash(&luma)) }
+    }
+}
+
+/// 64 bit DCT perceptual hash: the lowest 8x8 frequencies of the 32x32
+/// luma, each bit set when above their median. Resizing, recompression
+/// and small edits move few bits, unrelated images differ in about half.
+pub fn phash(image: &DynamicImage) -> u64 {
+    luma_phash(&image.to_luma8())
+}
+
+/// 64 bit difference hash: each bit tells whether a pixel of the 9x8
+/// luma is brighter than its right neighbor.
+pub fn dhash(image: &DynamicImage) -> u64 {
+    luma_dhash(&image.to_luma8())
+}
+
+/// pHashes of the image and of it turned by 90, 180 and 270 degrees.
+pub(crate) fn rotated_phashes(image: &DynamicImage) -> [u64; 4] {
+    let grid = imageops::thumbnail(&image.to_luma8(), SIZE as u32, SIZE as u32);
+    [dct_hash(&grid), dct_hash(&imageops::rotate90(&grid)), dct_hash(&imageops::rotate180(&grid)), dct_hash(&imageops::rotate270(&grid))]
+}
+
+fn luma_phash(luma: &GrayImage) -> u64 {
+    dct_hash(&imageops::thumbnail(luma, SIZE as u32, SIZE as u32))
+}
+
+/// The hash of a `SIZE` square luma.
+fn dct_hash(luma: &GrayImage) -> u64 {
+    let pixels: Vec<f64> = luma.pixels().map(|p| p.0[0] as f64).collect();
+    let cosines: Vec<Vec<f64>> = (0..HASH_SIZE)
+        .map(|u| (0..SIZE).map(|x| ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * SIZE) as f64).cos()).collect())
+        .collect();
+    let mut coefficients = Vec::with_capacity(HASH_SIZE * HASH_SIZE);
+    for v in 0..HASH_SIZE {
+        for u in 0..HASH_SIZE {
+            let mut sum = 0.0;
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    sum += pixels[y * SIZE + x] * cosines[u][x] * cosines[v][y];
+                }
+            }
+            coefficients.push(sum);
+        }
+    }
+    // The DC term is the mean brightness, not structure
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    coefficients.iter().enumerate().fold(0, |hash, (i, c)| match *c > median {
+        true => hash | 1 << (63 - i),
+        false => hash
+    })
+}
+
+fn luma_dhash(luma: &GrayImage) -> u64 {
+    let luma = imageops::thumbnail(luma, HASH_SIZE as u32 + 1, HASH_SIZE as u32);
+    let mut hash = 0;
+    for y in 0..HASH_SIZE as u32 {
+        for x in 0..HASH_SIZE as u32 {
+            hash = hash << 1 | (luma.get_pixel(x, y).0[0] > luma.get_pixel(x + 1, y).0[0]) as u64;
+        }
+    }
+    hash
+}
+
+/// Bits two hashes differ in, 0-64.
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma};
+
+    fn luma(width: u32, height: u32, f: impl Fn(u32, u32) -> u8) -> DynamicImage {
+        DynamicImage::ImageLuma8(ImageBuffer::from_fn(width, height, |x, y| Luma([f(x, y)])))
+    }
+
+    /// Two soft blobs on a gradient, something with structure at low
+    /// frequencies like a photo.
+    fn scene(width: u32, height: u32) -> DynamicImage {
+        luma(width, height, |x, y| {
+            let (u, v) = (x as f64 / width as f64, y as f64 / height as f64);
+            let blob = |cx: f64, cy: f64| (-((u - cx).powi(2) + (v - cy).powi(2)) * 20.0).exp();
+            (60.0 + 60.0 * v + 120.0 * blob(0.3, 0.35) - 50.0 * blob(0.75, 0.7)).clamp(0.0, 255.0) as u8
+        })
+    }
+
+    #[test]
+    fn gradients_have_known_hashes() {
+        let rightwards = luma(90, 80, |x, _| (x * 255 / 89) as u8);
+        let leftwards = luma(90, 80, |x, _| 255 - (x * 255 / 89) as u8);
+        assert_eq!(dhash(&rightwards), 0);
+        assert_eq!(dhash(&leftwards), u64::MAX);
+        assert_eq!(distance(0, u64::MAX), 64);
+        assert_eq!(distance(0b1011, 0b0001), 2);
+    }
+
+    #[test]
+    fn scenes_have_known_hashes() {
+        let hash = PerceptualHash::from_image(&scene(256, 192));
+        assert_eq!((hash.phash.as_str(), hash.dhash.as_str()), ("c2453d3bb542c63d", "3e3f3e3e3c3c3c3c"));
+        assert_eq!(format!("{:016x}", phash(&scene(256, 192))), hash.phash);
+    }
+
+    #[test]
+    fn copies_stay_near_and_other_images_far() {
+        let original = phash(&scene(256, 192));
+        let thumbnail = phash(&scene(256, 192).thumbnail(64, 48));
+        let brighter = phash(&scene(256, 192).brighten(30));
+        assert!(distance(original, thumbnail) <= 10, "{}", distance(original, thumbnail));
+        assert!(distance(original, brighter) <= 10, "{}", distance(original, brighter));
+        let flipped = phash(&scene(256, 192).flipv());
+        assert!(distance(original, flipped) > 22, "{}", distance(original, flipped));
+        // turned pictures match one of the rotations
+        let turned = rotated_phashes(&scene(256, 192).rotate90());
+        assert!(turned.iter().any(|h| distance(*h, original) <= 10));
+    }
+}
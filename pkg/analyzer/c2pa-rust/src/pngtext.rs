@@ -0,0 +1,282 @@
+use std::io::Read;
+use flate2::read::ZlibDecoder;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::rules::{GeneratorRules, RuleKind};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// Generation parameters are only written by the generating tool, so they
+/// are as conclusive as a declared source type.
+const GENERATION_PARAMETERS: (u8, u8) = (100, 90);
+/// Keywords of text chunks holding generation parameters, and the tool
+/// writing them.
+const PARAMETER_KEYWORDS: [(&str, &str); 7] = [
+    ("parameters", "Stable Diffusion web UI"),
+    ("prompt", "ComfyUI"),
+    ("workflow", "ComfyUI"),
+    ("invokeai_metadata", "InvokeAI"),
+    ("sd-metadata", "InvokeAI"),
+    ("Dream", "InvokeAI"),
+    ("fooocus_scheme", "Fooocus")
+];
+/// Longest text kept from a chunk, prompts and workflows can be large.
+const MAX_TEXT_LEN: usize = 1 << 20;
+
+/// A `tEXt`, `zTXt` or `iTXt` chunk.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct PngTextChunk {
+    pub chunk_type: String,
+    pub keyword: String,
+    /// Length of the decoded text, the text itself is not reported.
+    pub length: usize
+}
+
+/// Generation parameters found in the text chunks of a PNG.
+#[derive(Serialize, Default)]
+#[non_exhaustive]
+pub struct PngTextFindings {
+    pub chunks: Vec<PngTextChunk>,
+    /// Value of the `Software` chunk.
+    pub software: Option<String>,
+    /// Tool the parameter chunks are from, ex. `ComfyUI`.
+    pub generator: Option<String>,
+    pub prompt: Option<String>,
+    pub negative_prompt: Option<String>,
+    pub sampler: Option<String>,
+    pub model: Option<String>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl PngTextFindings {
+    /// None when the file isn't a PNG or has no text chunks.
+    pub fn from_bytes(bytes: &[u8], rules: &GeneratorRules) -> Option<PngTextFindings> {
+        let texts = text_chunks(bytes.strip_prefix(PNG_SIGNATURE.as_slice())?);
+        if texts.is_empty() {
+            return None;
+        }
+
+        let mut findings = PngTextFindings::default();
+        for (chunk_type, keyword, text) in &texts {
+            findings.chunks.push(PngTextChunk {
+                chunk_type: chunk_type.clone(),
+                keyword: keyword.clone(),
+                length: text.len()
+            });
+            match keyword.as_str() {
+                "Software" => {
+                    findings.software.get_or_insert_with(|| text.trim().to_string());
+                },
+                "parameters" if !text.trim_start().starts_with('{') => findings.read_webui(text),
+                "prompt" => findings.read_comfyui(text),
+                _ => findings.read_json(text)
+            }
+            if let Some((_, tool)) = PARAMETER_KEYWORDS.iter().find(|(k, _)| k == keyword) {
+                findings.generator.get_or_insert_with(|| tool.to_string());
+            }
+        }
+        // NovelAI writes its parameters to `Comment` and names itself only
+        // in `Software`.
+        let software = findings.software.clone().unwrap_or_default();
+        if software.starts_with("NovelAI") && findings.sampler.is_some() {
+            findings.generator.get_or_insert(software.clone());
+        }
+        (findings.score, findings.confidence) = match (&findings.generator, rules.find(&software)) {
+            (Some(_), _) => GENERATION_PARAMETERS,
+            (None, Some((RuleKind::Generated, rule))) => (rule.score, rule.confidence),
+            _ => (0, 0)
+        };
+        Some(findings)
+    }
+
+    /// AUTOMATIC1111 and forks: the prompt, an optional `Negative prompt:`
+    /// line and a last line of `Key: value` settings.
+    fn read_webui(&mut self, text: &str) {
+        let (body, settings) = match text.rfind("\nSteps: ") {
+            Some(i) => (&text[..i], &text[i + 1..]),
+            None => (text, "")
+        };
+        let (prompt, negative) = match body.split_once("\nNegative prompt:") {
+            Some((p, n)) => (p, Some(n)),
+            None => (body, None)
+        };
+        set_text(&mut self.prompt, prompt);
+        if let Some(negative) = negative {
+            set_text(&mut self.negative_prompt, negative);
+        }
+        for setting in settings.split(", ") {
+            match setting.split_once(": ") {
+                Some(("Sampler", v)) => set_text(&mut self.sampler, v),
+                Some(("Model", v)) => set_text(&mut self.model, v),
+                _ => {}
+            }
+        }
+    }
+
+    /// ComfyUI's API graph, a map of node id to `class_type` and `inputs`.
+    fn read_comfyui(&mut self, text: &str) {
+        let graph = match serde_json::from_str::<Value>(text) {
+            Ok(Value::Object(graph)) => graph,
+            _ => return
+        };
+        for node in graph.values() {
+            let inputs = match node.get("inputs") {
+                Some(i) => i,
+                None => continue
+            };
+            let class_type = node.get("class_type").and_then(|c| c.as_str()).unwrap_or_default();
+            if class_type.starts_with("KSampler") {
+                set_json(&mut self.sampler, inputs.get("sampler_name"));
+            } else if class_type.starts_with("CheckpointLoader") {
+                set_json(&mut self.model, inputs.get("ckpt_name"));
+            } else if class_type.starts_with("CLIPTextEncode") {
+                set_json(&mut self.prompt, inputs.get("text"));
+            }
+        }
+    }
+
+    /// Flat JSON settings, as written by InvokeAI, Fooocus and NovelAI.
+    fn read_json(&mut self, text: &str) {
+        let value = match serde_json::from_str::<Value>(text) {
+            Ok(v @ Value::Object(_)) => v,
+            _ => return
+        };
+        set_json(&mut self.prompt, value.get("positive_prompt").or(value.get("prompt")));
+        set_json(&mut self.negative_prompt, value.get("negative_prompt").or(value.get("uc")));
+        set_json(&mut self.sampler, value.get("sampler").or(value.get("scheduler")));
+        set_json(&mut self.model, value.get("base_model").or(value.get("model")));
+    }
+}
+
+fn set_text(field: &mut Option<String>, value: &str) {
+    let value = value.trim();
+    if field.is_none() && !value.is_empty() {
+        *field = Some(value.to_string());
+    }
+}
+
+fn set_json(field: &mut Option<String>, value: Option<&Value>) {
+    if let Some(Value::String(s)) = value {
+        set_text(field, s);
+    }
+}
+
+/// (chunk type, keyword, text) of the text chunks, decompressed.
+fn text_chunks(mut bytes: &[u8]) -> Vec<(String, String, String)> {
+    let mut texts = Vec::new();
+    while bytes.len() >= 12 {
+        let len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        let chunk_type = &bytes[4..8];
+        if len > bytes.len() - 12 || chunk_type == b"IEND" {
+            break;
+        }
+        let data = &bytes[8..8 + len];
+        let text = match chunk_type {
+            b"tEXt" => split_keyword(data).map(|(k, t)| (k, latin1(t))),
+            b"zTXt" => split_keyword(data).and_then(|(k, t)| Some((k, inflate(t.get(1..)?)?))),
+            b"iTXt" => split_keyword(data).and_then(|(k, t)| Some((k, international_text(t)?))),
+            _ => None
+        };
+        if let Some((keyword, text)) = text {
+            texts.push((String::from_utf8_lossy(chunk_type).to_string(), keyword, text));
+        }
+        bytes = &bytes[12 + len..];
+    }
+    texts
+}
+
+fn split_keyword(data: &[u8]) -> Option<(String, &[u8])> {
+    let end = data.iter().position(|b| *b == 0)?;
+    Some((latin1(&data[..end]), &data[end + 1..]))
+}
+
+/// Rest of an `iTXt` chunk after the keyword: compression flag and method,
+/// language tag, translated keyword and the UTF-8 text.
+fn international_text(data: &[u8]) -> Option<String> {
+    let compressed = *data.first()? == 1;
+    let rest = data.get(2..)?;
+    let language_end = rest.iter().position(|b| *b == 0)?;
+    let rest = &rest[language_end + 1..];
+    let translated_end = rest.iter().position(|b| *b == 0)?;
+    let text = &rest[translated_end + 1..];
+    if compressed {
+        inflate(text)
+    } else {
+        Some(String::from_utf8_lossy(&text[..text.len().min(MAX_TEXT_LEN)]).to_string())
+    }
+}
+
+fn inflate(data: &[u8]) -> Option<String> {
+    let mut text = Vec::new();
+    ZlibDecoder::new(data).take(MAX_TEXT_LEN as u64).read_to_end(&mut text).ok()?;
+    Some(String::from_utf8_lossy(&text).to_string())
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes[..bytes.len().min(MAX_TEXT_LEN)].iter().map(|b| *b as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use flate2::{write::ZlibEncoder, Compression};
+    use super::*;
+
+    /// Chunk CRCs aren't checked, they are left 0.
+    fn png(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        for (chunk_type, data) in chunks {
+            png.extend((data.len() as u32).to_be_bytes());
+            png.extend(*chunk_type);
+            png.extend(data);
+            png.extend([0; 4]);
+        }
+        png
+    }
+
+    fn webui_png() -> Vec<u8> {
+        let parameters = b"parameters\0a cat\nNegative prompt: blurry\nSteps: 20, Sampler: Euler a, Model: sdxl".to_vec();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"Photoshop").unwrap();
+        let mut ztxt = b"Software\0\0".to_vec();
+        ztxt.extend(encoder.finish().unwrap());
+        png(&[(b"tEXt", parameters), (b"zTXt", ztxt), (b"iTXt", b"Title\0\0\0en\0\0Cat".to_vec()), (b"IEND", Vec::new())])
+    }
+
+    #[test]
+    fn reads_webui_parameters() {
+        let findings = PngTextFindings::from_bytes(&webui_png(), &GeneratorRules::default()).unwrap();
+        assert_eq!(findings.chunks.len(), 3);
+        assert_eq!(findings.generator.as_deref(), Some("Stable Diffusion web UI"));
+        assert_eq!(findings.prompt.as_deref(), Some("a cat"));
+        assert_eq!(findings.negative_prompt.as_deref(), Some("blurry"));
+        assert_eq!(findings.sampler.as_deref(), Some("Euler a"));
+        assert_eq!(findings.model.as_deref(), Some("sdxl"));
+        assert_eq!(findings.software.as_deref(), Some("Photoshop"));
+        assert_eq!((findings.score, findings.confidence), GENERATION_PARAMETERS);
+    }
+
+    #[test]
+    fn reads_comfyui_graph() {
+        let graph = br#"prompt{"3":{"class_type":"KSampler","inputs":{"sampler_name":"euler"}},"4":{"class_type":"CheckpointLoaderSimple","inputs":{"ckpt_name":"flux.safetensors"}}}"#;
+        let mut text = graph.to_vec();
+        text.insert(6, 0);
+        let findings = PngTextFindings::from_bytes(&png(&[(b"tEXt", text)]), &GeneratorRules::default()).unwrap();
+        assert_eq!(findings.generator.as_deref(), Some("ComfyUI"));
+        assert_eq!(findings.sampler.as_deref(), Some("euler"));
+        assert_eq!(findings.model.as_deref(), Some("flux.safetensors"));
+    }
+
+    #[test]
+    fn truncated_chunks_are_skipped() {
+        let png = webui_png();
+        for end in 0..png.len() {
+            let _ = PngTextFindings::from_bytes(&png[..end], &GeneratorRules::default());
+        }
+        assert!(PngTextFindings::from_bytes(b"GIF89a", &GeneratorRules::default()).is_none());
+        let lying = [PNG_SIGNATURE.as_slice(), &[0xFF, 0xFF, 0xFF, 0xFF], b"tEXt", b"a\0b"].concat();
+        assert!(PngTextFindings::from_bytes(&lying, &GeneratorRules::default()).is_none());
+    }
+}
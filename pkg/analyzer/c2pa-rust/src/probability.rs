@@ -0,0 +1,111 @@
+//! Verdict probabilities from the findings' scores and confidences.
+//!
+//! Every finding is a score `s` (0-100, how generated or modified it says
+//! the file is) and a confidence `c` (0-100, how much it says so). The
+//! score is spread over the verdicts:
+//!
+//! - generated: `(s - 50) / 40`, full from 90 on
+//! - genuine: `(20 - s) / 20`, full at 0
+//! - modified: what is left, full at 20 to 50
+//!
+//! each clamped to 0-1. A verdict's logit is the sum over the findings of
+//! its share times `c / 100` times [`EVIDENCE_SCALE`], and the
+//! probabilities are the softmax of the logits, a third each without
+//! findings. Confidences combine as independent evidence into the
+//! strength, `1 - Π(1 - c / 100)`.
+//!
+//! Unlike the legacy `score`, nothing saturates: a second generated
+//! finding still moves the probabilities, and genuine evidence such as a
+//! trusted signature weighs against the generated findings instead of
+//! being added to them.
+
+use serde::Serialize;
+
+use crate::evidence::Evidence;
+
+/// Logit a finding of full confidence adds to its verdict, 3 makes one
+/// such finding alone about 90 % likely against two empty verdicts.
+pub const EVIDENCE_SCALE: f64 = 3.0;
+
+/// Probability of each verdict, summing to 1.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct VerdictProbabilities {
+    pub generated: f64,
+    pub modified: f64,
+    pub genuine: f64,
+    /// 0-1, how much evidence the probabilities rest on. 0 means no
+    /// finding had any confidence and the probabilities are the prior.
+    pub evidence_strength: f64
+}
+
+impl VerdictProbabilities {
+    pub fn from_evidence(evidence: &[Evidence]) -> VerdictProbabilities {
+        let mut logits = [0.0; 3];
+        let mut doubt = 1.0;
+        for finding in evidence {
+            let (score, weight) = (finding.score as f64, finding.confidence.min(100) as f64 / 100.0);
+            let generated = ((score - 50.0) / 40.0).clamp(0.0, 1.0);
+            let genuine = ((20.0 - score) / 20.0).clamp(0.0, 1.0);
+            let shares = [generated, 1.0 - generated - genuine, genuine];
+            for (logit, share) in logits.iter_mut().zip(shares) {
+                *logit += share * weight * EVIDENCE_SCALE;
+            }
+            doubt *= 1.0 - weight;
+        }
+        let max = logits.iter().cloned().fold(f64::MIN, f64::max);
+        let exp = logits.map(|l| (l - max).exp());
+        let total: f64 = exp.iter().sum();
+        VerdictProbabilities {
+            generated: round(exp[0] / total),
+            modified: round(exp[1] / total),
+            genuine: round(exp[2] / total),
+            evidence_strength: round(1.0 - doubt)
+        }
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(score: u8, confidence: u8) -> Evidence {
+        Evidence::new("test", String::from("finding"), score, confidence)
+    }
+
+    fn verdicts(p: VerdictProbabilities) -> [f64; 4] {
+        [p.generated, p.modified, p.genuine, p.evidence_strength]
+    }
+
+    #[test]
+    fn no_evidence_is_the_prior() {
+        assert_eq!(verdicts(VerdictProbabilities::from_evidence(&[])), [0.333, 0.333, 0.333, 0.0]);
+        // Findings without confidence move nothing
+        assert_eq!(verdicts(VerdictProbabilities::from_evidence(&[finding(100, 0)])), [0.333, 0.333, 0.333, 0.0]);
+    }
+
+    #[test]
+    fn findings_have_known_probabilities() {
+        // e^3 / (e^3 + 2)
+        assert_eq!(verdicts(VerdictProbabilities::from_evidence(&[finding(100, 100)])), [0.909, 0.045, 0.045, 1.0]);
+        // e^6 / (e^6 + 2), a second finding still counts
+        assert_eq!(VerdictProbabilities::from_evidence(&[finding(100, 100), finding(100, 100)]).generated, 0.995);
+        // A trusted signature, score 0, weighs for genuine: logits 3, 0, 1.8
+        let signed = VerdictProbabilities::from_evidence(&[finding(100, 100), finding(0, 60)]);
+        assert_eq!(verdicts(signed), [0.74, 0.037, 0.223, 1.0]);
+        // Score 35 is all modified
+        assert_eq!(verdicts(VerdictProbabilities::from_evidence(&[finding(35, 100)])), [0.045, 0.909, 0.045, 1.0]);
+    }
+
+    #[test]
+    fn strength_combines_confidences_independently() {
+        // 1 - 0.5 * 0.6
+        assert_eq!(VerdictProbabilities::from_evidence(&[finding(100, 50), finding(0, 40)]).evidence_strength, 0.7);
+        // 1 - 0.9^3
+        assert_eq!(VerdictProbabilities::from_evidence(&[finding(50, 10), finding(50, 10), finding(50, 10)]).evidence_strength, 0.271);
+    }
+}
@@ -0,0 +1,62 @@
+//! The `c2pa_detector` Python module. Install it with maturin, configured
+//! in `pyproject.toml`, from the crate directory:
+//!
+//! ```sh
+//! pip install .
+//! ```
+//!
+//! or build it by hand with
+//!
+//! ```sh
+//! cargo rustc --release --lib --features python,pyo3/extension-module --crate-type cdylib
+//! cp target/release/libc2pa_rust.so c2pa_detector.so
+//! ```
+//!
+//! (`.dylib` on macOS, `c2pa_detector.pyd` from `c2pa_rust.dll` on Windows)
+//! and import it from the directory holding it:
+//!
+//! ```python
+//! import c2pa_detector
+//! report = c2pa_detector.analyze_file("upload.jpg")
+//! report["verdict"], report["score"]
+//! ```
+//!
+//! Reports are the dicts of the report JSON. The analysis releases the
+//! GIL, so a thread pool analyzes files in parallel.
+
+use std::path::PathBuf;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{batch::find_sidecar, options::AnalysisOptions, report::Report};
+
+/// Report of the file at `path`. Files that can't be read still get a
+/// report.
+#[pyfunction]
+fn analyze_file(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let report = py.allow_threads(|| Report::from_file_lenient(path.clone(), find_sidecar(&path), &AnalysisOptions::default()));
+    to_dict(py, &report)
+}
+
+/// Report of `data`. `file_name` tells the format by its extension, ex.
+/// `upload.jpg`.
+#[pyfunction]
+fn analyze_bytes(py: Python<'_>, data: &[u8], file_name: &str) -> PyResult<PyObject> {
+    let report = py.allow_threads(|| Report::from_bytes_lenient(file_name, data, &AnalysisOptions::default()));
+    to_dict(py, &report)
+}
+
+fn to_dict(py: Python<'_>, report: &Report) -> PyResult<PyObject> {
+    let json = match serde_json::to_string(report) {
+        Ok(j) => j,
+        Err(e) => return Err(PyValueError::new_err(format!("Error serializing the report: {}", e)))
+    };
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+#[pymodule]
+fn c2pa_detector(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(analyze_file, module)?)?;
+    module.add_function(wrap_pyfunction!(analyze_bytes, module)?)?;
+    Ok(())
+}
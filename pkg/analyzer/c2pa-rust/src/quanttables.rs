@@ -0,0 +1,217 @@
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+
+use crate::jpeg::jpeg_segments;
+
+const DQT: u8 = 0xDB;
+pub(crate) const SIGNATURES: &str = include_str!("../data/qtables.toml");
+/// Tables that don't match any signature of the camera maker the EXIF
+/// names. Makers without signatures aren't checked.
+const CAMERA_MISMATCH: (u8, u8) = (40, 30);
+/// A quality setting some generation tools export with, common enough
+/// elsewhere to only nudge the score.
+const AI_PIPELINE: (u8, u8) = (10, 5);
+
+/// Natural (row-major) index of each zigzag position.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5,
+    12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63
+];
+/// Annex K tables of the JPEG standard, in natural order.
+const STD_LUMINANCE: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61,
+    12, 12, 14, 19, 26, 58, 60, 55,
+    14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62,
+    18, 22, 37, 56, 68, 109, 103, 77,
+    24, 35, 55, 64, 81, 104, 113, 92,
+    49, 64, 78, 87, 103, 121, 120, 101,
+    72, 92, 95, 98, 112, 100, 103, 99
+];
+const STD_CHROMINANCE: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99,
+    18, 21, 26, 66, 99, 99, 99, 99,
+    24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99
+];
+
+#[derive(Deserialize, Default)]
+struct SignatureFile {
+    #[serde(default)]
+    signature: Vec<Signature>
+}
+
+#[derive(Deserialize)]
+struct Signature {
+    name: String,
+    kind: SignatureKind,
+    make: Option<String>,
+    ijg_quality: Option<u8>,
+    /// Luminance table in zigzag order.
+    luminance: Option<Vec<u16>>
+}
+
+impl Signature {
+    fn matches(&self, tables: &[QuantTable], ijg_quality: Option<u8>) -> bool {
+        match (&self.luminance, self.ijg_quality) {
+            (Some(luminance), _) => tables.iter().any(|t| t.id == 0 && &t.values == luminance),
+            (None, Some(quality)) => ijg_quality == Some(quality),
+            (None, None) => false
+        }
+    }
+}
+
+/// Who a quantization table signature belongs to.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureKind {
+    Library,
+    AiPipeline,
+    Camera
+}
+
+/// A DQT table, values in zigzag order as stored.
+struct QuantTable {
+    id: u8,
+    values: Vec<u16>
+}
+
+/// What the quantization tables of a JPEG say about its encoder.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct QuantTableFindings {
+    pub table_count: usize,
+    /// Quality the tables are libjpeg's standard tables scaled to, None
+    /// when they are custom.
+    pub ijg_quality: Option<u8>,
+    /// Nearest libjpeg quality of the luminance table.
+    pub estimated_quality: Option<u8>,
+    /// Name of the matching signature of the bundled database.
+    pub signature: Option<String>,
+    pub signature_kind: Option<SignatureKind>,
+    /// The EXIF names a camera maker the tables don't fit.
+    pub camera_mismatch: bool,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl QuantTableFindings {
+    /// None when the file has no DQT segment. `camera_make` is the EXIF
+    /// `Make`.
+    pub fn from_bytes(bytes: &[u8], camera_make: Option<&str>) -> Option<QuantTableFindings> {
+        let tables: Vec<QuantTable> = jpeg_segments(bytes, DQT).into_iter().flat_map(parse_dqt).collect();
+        if tables.is_empty() {
+            return None;
+        }
+        let ijg_quality = (1..=100).find(|q| tables.iter().all(|t| t.values == ijg_table(t.id, *q)));
+        let estimated_quality = tables.iter()
+            .find(|t| t.id == 0)
+            .and_then(|t| (1..=100).min_by_key(|q| distance(&t.values, &ijg_table(0, *q))));
+
+        let signatures = signatures();
+        let signature = signatures.iter().find(|s| s.matches(&tables, ijg_quality));
+        let camera_mismatch = match camera_make.map(|m| m.trim().to_lowercase()) {
+            Some(make) if !make.is_empty() => {
+                let of_make: Vec<&Signature> = signatures.iter()
+                    .filter(|s| s.make.as_ref().is_some_and(|m| make.starts_with(&m.to_lowercase())))
+                    .collect();
+                !of_make.is_empty() && !of_make.iter().any(|s| s.matches(&tables, ijg_quality))
+            },
+            _ => false
+        };
+
+        let (score, confidence) = if camera_mismatch {
+            CAMERA_MISMATCH
+        } else if signature.is_some_and(|s| s.kind == SignatureKind::AiPipeline) {
+            AI_PIPELINE
+        } else {
+            (0, 0)
+        };
+        Some(QuantTableFindings {
+            table_count: tables.len(),
+            ijg_quality,
+            estimated_quality,
+            signature: signature.map(|s| s.name.clone()),
+            signature_kind: signature.map(|s| s.kind),
+            camera_mismatch,
+            score,
+            confidence
+        })
+    }
+}
+
+fn signatures() -> &'static [Signature] {
+    static PARSED: OnceLock<SignatureFile> = OnceLock::new();
+    &PARSED.get_or_init(|| toml::from_str(SIGNATURES).unwrap_or_default()).signature
+}
+
+/// Tables of a DQT segment, which can hold several.
+fn parse_dqt(mut payload: &[u8]) -> Vec<QuantTable> {
+    let mut tables = Vec::new();
+    while let Some(&header) = payload.first() {
+        let (precision, id) = (header >> 4, header & 0x0F);
+        let size = if precision == 0 { 64 } else { 128 };
+        let data = match payload.get(1..1 + size) {
+            Some(d) => d,
+            None => break
+        };
+        let values = match precision {
+            0 => data.iter().map(|v| *v as u16).collect(),
+            _ => data.chunks(2).map(|v| u16::from_be_bytes([v[0], v[1]])).collect()
+        };
+        tables.push(QuantTable { id, values });
+        payload = &payload[1 + size..];
+    }
+    tables
+}
+
+/// libjpeg's standard table for `id` at `quality`, in zigzag order.
+fn ijg_table(id: u8, quality: u8) -> Vec<u16> {
+    let base = if id == 0 { &STD_LUMINANCE } else { &STD_CHROMINANCE };
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 { 5000 / quality } else { 200 - quality * 2 };
+    ZIGZAG.iter()
+        .map(|i| ((base[*i] as u32 * scale + 50) / 100).clamp(1, 255) as u16)
+        .collect()
+}
+
+fn distance(a: &[u16], b: &[u16]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| x.abs_diff(*y) as u32).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A JPEG with the libjpeg tables of `quality`.
+    fn jpeg(quality: u8) -> Vec<u8> {
+        let mut dqt = Vec::new();
+        for id in 0..2 {
+            dqt.push(id);
+            dqt.extend(ijg_table(id, quality).iter().map(|v| *v as u8));
+        }
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, DQT];
+        bytes.extend_from_slice(&((dqt.len() + 2) as u16).to_be_bytes());
+        bytes.extend_from_slice(&dqt);
+        bytes
+    }
+
+    #[test]
+    fn unlisted_makes_are_not_checked() {
+        let findings = QuantTableFindings::from_bytes(&jpeg(92), Some("Canon")).unwrap();
+        assert_eq!((findings.table_count, findings.ijg_quality), (2, Some(92)));
+        assert!(!findings.camera_mismatch);
+        assert_eq!((findings.score, findings.confidence), (0, 0));
+
+        let findings = QuantTableFindings::from_bytes(&jpeg(80), Some("Canon")).unwrap();
+        assert!(findings.signature_kind == Some(SignatureKind::AiPipeline));
+        assert_eq!((findings.score, findings.confidence), AI_PIPELINE);
+        assert!(QuantTableFindings::from_bytes(&jpeg(92)[..40], None).is_none());
+    }
+}
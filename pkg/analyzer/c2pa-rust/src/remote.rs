@@ -0,0 +1,298 @@
+use std::time::Duration;
+use serde::Serialize;
+
+use crate::error::AnalyzeError;
+
+/// Manifests and CRLs larger than this aren't fetched.
+pub const MAX_MANIFEST_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Images fetched by `Report::from_url` larger than this are rejected
+/// unless `max_input_size` is given.
+pub const MAX_MEDIA_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Public verification page of `verify_url`, unless
+/// `AnalysisOptions::verify_base_url` names another.
+pub const VERIFY_URL: &str = "https://contentcredentials.org/verify";
+
+/// Redirects `RemoteManifests::fetch` follows, each to an allowed host.
+const MAX_REDIRECTS: usize = 5;
+
+/// Content types `fetch_media` accepts, prefixes of the MIME type.
+#[cfg(feature = "remote")]
+const MEDIA_TYPES: &[&str] = &["image/", "video/", "audio/", "application/pdf"];
+
+/// A manifest store the asset points to by URL, ex. in the XMP
+/// `dcterms:provenance`, instead of embedding it.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct RemoteManifest {
+    pub url: String,
+    /// True when the manifest was fetched and read, the report's claims
+    /// and validation come from it.
+    pub fetched: bool,
+    /// Why it wasn't, ex. the host isn't allowed.
+    pub error: Option<String>
+}
+
+/// Hosts remote manifests are fetched from. Nothing is fetched by
+/// default.
+#[derive(Serialize, Clone)]
+#[non_exhaustive]
+pub struct RemoteManifests {
+    /// Host names, ex. `cai-manifests.adobe.com`, or `*.` and a domain for
+    /// its subdomains.
+    pub allowed_hosts: Vec<String>,
+    pub timeout: Duration
+}
+
+impl Default for RemoteManifests {
+    fn default() -> RemoteManifests {
+        RemoteManifests { allowed_hosts: Vec::new(), timeout: Duration::from_secs(10) }
+    }
+}
+
+impl RemoteManifests {
+    pub fn new(allowed_hosts: Vec<String>) -> RemoteManifests {
+        RemoteManifests { allowed_hosts, ..RemoteManifests::default() }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> RemoteManifests {
+        self.timeout = timeout;
+        self
+    }
+
+    /// True for http and https URLs on an allowed host.
+    pub fn allows(&self, url: &str) -> bool {
+        let host = match host(url) {
+            Some(h) => h.to_ascii_lowercase(),
+            None => return false
+        };
+        self.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host.ends_with(&format!(".{}", domain)),
+                None => host == allowed
+            }
+        })
+    }
+
+    /// The manifest store at `url`. Redirects are followed one at a time,
+    /// each only to an allowed host.
+    pub fn fetch(&self, url: &str) -> Result<Vec<u8>, String> {
+        let mut url = url.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            if !self.allows(&url) {
+                return Err(format!("{} isn't an allowed host", host(&url).unwrap_or(&url)));
+            }
+            match fetch_hop(&url, self.timeout)? {
+                Hop::Body(bytes) => return Ok(bytes),
+                Hop::Redirect(location) => url = location
+            }
+        }
+        Err(format!("Over {} redirects fetching {}", MAX_REDIRECTS, url))
+    }
+}
+
+/// The body of a response, or the URL it redirects to.
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+enum Hop {
+    Body(Vec<u8>),
+    Redirect(String)
+}
+
+/// GETs `url`, up to `MAX_MANIFEST_SIZE` bytes.
+#[cfg(feature = "remote")]
+pub(crate) fn fetch(url: &str, timeout: Duration) -> Result<Vec<u8>, String> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    match agent.get(url).call() {
+        Ok(response) => read_manifest(url, response),
+        Err(e) => Err(format!("Error fetching {}: {}", url, e))
+    }
+}
+
+/// GETs `url` without following a redirect.
+#[cfg(feature = "remote")]
+fn fetch_hop(url: &str, timeout: Duration) -> Result<Hop, String> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).redirects(0).build();
+    let response = match agent.get(url).call() {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Error fetching {}: {}", url, e))
+    };
+    if !(300..400).contains(&response.status()) {
+        return read_manifest(url, response).map(Hop::Body);
+    }
+    match response.header("Location") {
+        Some(location) => Ok(Hop::Redirect(resolve(url, location.trim()))),
+        None => Err(format!("{} redirects without a Location", url))
+    }
+}
+
+#[cfg(feature = "remote")]
+fn read_manifest(url: &str, response: ureq::Response) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    if let Err(e) = response.into_reader().take(MAX_MANIFEST_SIZE + 1).read_to_end(&mut bytes) {
+        return Err(format!("Error reading {}: {}", url, e));
+    }
+    if bytes.len() as u64 > MAX_MANIFEST_SIZE {
+        return Err(format!("{} is over {} bytes", url, MAX_MANIFEST_SIZE));
+    }
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "remote"))]
+pub(crate) fn fetch(url: &str, _timeout: Duration) -> Result<Vec<u8>, String> {
+    Err(format!("Fetching {} needs the remote feature", url))
+}
+
+#[cfg(not(feature = "remote"))]
+fn fetch_hop(url: &str, timeout: Duration) -> Result<Hop, String> {
+    fetch(url, timeout).map(Hop::Body)
+}
+
+/// The URL a `Location` of a response to `base` names, absolute or
+/// relative to it.
+#[cfg(feature = "remote")]
+fn resolve(base: &str, location: &str) -> String {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return location.to_string();
+    }
+    let (scheme, rest) = base.split_once("://").unwrap_or(("https", base));
+    if let Some(authority_path) = location.strip_prefix("//") {
+        return format!("{}://{}", scheme, authority_path);
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if location.starts_with('/') {
+        return format!("{}://{}{}", scheme, authority, location);
+    }
+    let path = rest[authority.len()..].split(['?', '#']).next().unwrap_or_default();
+    let directory = path.rsplit_once('/').map_or("", |(directory, _)| directory);
+    format!("{}://{}{}/{}", scheme, authority, directory, location)
+}
+
+/// GETs the image, video, audio or PDF at `url`, failing on other content
+/// types and on bodies over `limit` bytes. Returns its MIME type and bytes.
+#[cfg(feature = "remote")]
+pub(crate) fn fetch_media(url: &str, timeout: Duration, limit: u64) -> Result<(String, Vec<u8>), AnalyzeError> {
+    use std::io::Read;
+
+    if host(url).is_none() {
+        return Err(AnalyzeError::Fetch(format!("{}: not an http or https URL", url)));
+    }
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let response = match agent.get(url).call() {
+        Ok(r) => r,
+        Err(e) => return Err(AnalyzeError::Fetch(e.to_string()))
+    };
+    let content_type = response.content_type().to_ascii_lowercase();
+    if !MEDIA_TYPES.iter().any(|t| content_type.starts_with(t)) {
+        return Err(AnalyzeError::Fetch(format!("{}: {} isn't an image, video, audio or PDF type", url, content_type)));
+    }
+    // Refused before the body when the server tells its size
+    if let Some(size) = response.header("Content-Length").and_then(|l| l.trim().parse().ok()) {
+        crate::input::check_size(size, Some(limit))?;
+    }
+    let mut bytes = Vec::new();
+    if let Err(e) = response.into_reader().take(limit + 1).read_to_end(&mut bytes) {
+        return Err(AnalyzeError::Fetch(format!("{}: {}", url, e)));
+    }
+    crate::input::check_size(bytes.len() as u64, Some(limit))?;
+    Ok((content_type, bytes))
+}
+
+#[cfg(not(feature = "remote"))]
+pub(crate) fn fetch_media(url: &str, _timeout: Duration, _limit: u64) -> Result<(String, Vec<u8>), AnalyzeError> {
+    Err(AnalyzeError::Fetch(format!("Fetching {} needs the remote feature", url)))
+}
+
+/// The verification page of the asset at `source`, the page with
+/// `source=` and the URL for http and https sources, else the page itself
+/// to drop the file on.
+pub fn verify_url(base: &str, source: &str) -> String {
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        return base.to_string();
+    }
+    let separator = match base.contains('?') {
+        true => '&',
+        false => '?'
+    };
+    let encoded: String = source.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b)
+    }).collect();
+    format!("{}{}source={}", base, separator, encoded)
+}
+
+/// Host of an http or https URL, without user info and port.
+fn host(url: &str) -> Option<&str> {
+    let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => host.split(':').next()?
+    };
+    Some(host).filter(|h| !h.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_are_percent_encoded() {
+        let base = "https://contentcredentials.org/verify";
+        assert_eq!(verify_url(base, "https://cdn.example/a b/ü.jpg?w=1&h=2"), "https://contentcredentials.org/verify?source=https%3A%2F%2Fcdn.example%2Fa%20b%2F%C3%BC.jpg%3Fw%3D1%26h%3D2");
+        assert_eq!(verify_url("https://verify.example/?lang=de", "http://a.example/~x_y-z.png"), "https://verify.example/?lang=de&source=http%3A%2F%2Fa.example%2F~x_y-z.png");
+        // local files are dropped on the page instead
+        assert_eq!(verify_url(base, "photo.jpg"), base);
+    }
+
+    #[cfg(feature = "remote")]
+    #[test]
+    fn locations_resolve_against_the_request() {
+        let base = "https://cai.example/store/a/manifest.c2pa?v=1";
+        assert_eq!(resolve(base, "http://other.example/m"), "http://other.example/m");
+        assert_eq!(resolve(base, "//other.example/m"), "https://other.example/m");
+        assert_eq!(resolve(base, "/m"), "https://cai.example/m");
+        assert_eq!(resolve(base, "b.c2pa"), "https://cai.example/store/a/b.c2pa");
+        assert_eq!(resolve("http://cai.example", "m"), "http://cai.example/m");
+    }
+
+    /// Serves `responses` on 127.0.0.1, one per connection, and returns
+    /// its address.
+    #[cfg(feature = "remote")]
+    fn serve(responses: Vec<String>) -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0; 4096]);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        address
+    }
+
+    #[cfg(feature = "remote")]
+    #[test]
+    fn redirects_stay_on_allowed_hosts() {
+        let redirect = |location: &str| format!("HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", location);
+        let remote = RemoteManifests::new(vec![String::from("127.0.0.1")]).with_timeout(Duration::from_secs(5));
+
+        let address = serve(vec![redirect("http://169.254.169.254/latest/meta-data/")]);
+        let error = remote.fetch(&format!("http://{}/manifest.c2pa", address)).unwrap_err();
+        assert_eq!(error, "169.254.169.254 isn't an allowed host");
+
+        let ok = String::from("HTTP/1.1 200 OK\r\nContent-Length: 8\r\nConnection: close\r\n\r\nmanifest");
+        let address = serve(vec![redirect("/moved.c2pa"), ok]);
+        assert_eq!(remote.fetch(&format!("http://{}/manifest.c2pa", address)).unwrap(), b"manifest");
+
+        let address = serve(vec![redirect("/again"); MAX_REDIRECTS + 1]);
+        assert!(remote.fetch(&format!("http://{}/loop", address)).unwrap_err().starts_with("Over 5 redirects"));
+    }
+}
@@ -1,106 +1,451 @@
-use std::{fs::File, io::Error, path::PathBuf};
+use std::io::{Read, Seek, SeekFrom};
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::path::Path;
 use c2pa::{format_from_path, Reader, ValidationState};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use tracing::info_span;
+use web_time::Instant;
 
-use crate::{claimdata::ClaimData, validation::ValidationData};
+#[cfg(feature = "fs")]
+use crate::batch::find_sidecar;
+#[cfg(feature = "pdf")]
+use crate::pdf::PdfData;
+#[cfg(feature = "pixel")]
+use crate::{ela::ElaFindings, noise::NoiseFindings, phash::PerceptualHash, spectrum::SpectrumFindings, watermark::WatermarkFindings};
+use crate::{actions::ActionSummary, analyzer::{AnalysisInput, AnalyzerResult, C2paFindings}, audio::AudioData, bmp::BmpData, consistency::ConsistencyFindings, certchain::ChainCertificate, claimdata::{ClaimData, ExtractedAssertion}, ensemble, error::{AnalyzeError, ReportError}, evidence::Evidence, identity::IdentityAssertion, input, exif::ExifData, gif::GifData, ingredients::IngredientSummary, jpeg::JpegFindings, media::MediaData, options::AnalysisOptions, pngtext::PngTextFindings, probability::VerdictProbabilities, quanttables::QuantTableFindings, remote::{self, RemoteManifest}, revocation::RevocationData, rules::RuleMatch, schema::SCHEMA_VERSION, scoring::ScoringConfig, signature::SignatureData, sourcetype::SourceTypeFindings, stripped::StrippedProvenance, svg::SvgData, thumbnails::{Thumbnail, ThumbnailFile}, timings::Timings, trainingmining::TrainingMining, validation::ValidationData, xmp::XmpFindings};
 
+/// Outcome of analyzing one file, serialized as the analyzer's JSON output.
 #[derive(serde::Serialize)]
+#[non_exhaustive]
 pub struct Report {
-    file_name: String,
-    file_type: String,
-    verdict: Verdict,
-    score: u8,
-    score_confidence: u8,
-    claims_found: bool,
-    claims_count: usize,
-    claims: Vec<ClaimData>,
-    validation: ValidationData
+    /// `schema::SCHEMA_VERSION`
+    pub schema_version: &'static str,
+    pub file_name: String,
+    pub file_type: String,
+    pub verdict: Verdict,
+    /// 0-100, higher means more likely generated or modified. The
+    /// analyzers' results combined as in `ensemble`.
+    pub score: u8,
+    /// 0-100
+    pub score_confidence: u8,
+    /// Probability of each verdict and the strength of the evidence, from
+    /// the same findings as `score`.
+    pub probabilities: VerdictProbabilities,
+    /// Every finding that moved `score` or `score_confidence`, in the order
+    /// of the analyzers.
+    pub evidence: Vec<Evidence>,
+    /// What each analyzer of the pipeline contributed.
+    pub analyzers: Vec<AnalyzerResult>,
+    /// What couldn't be read, empty when nothing failed.
+    pub errors: Vec<ReportError>,
+    pub claims_found: bool,
+    pub claims_count: usize,
+    /// The manifest store the asset links to instead of embedding it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_manifest: Option<RemoteManifest>,
+    /// Path of the sidecar `.c2pa` file read instead of an embedded
+    /// manifest store.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sidecar_manifest: Option<String>,
+    /// Public page verifying the Content Credentials, for manifests that
+    /// validate. It takes the image's URL when it was fetched with
+    /// `from_url`, others have to be dropped on the page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_url: Option<String>,
+    /// Traces of a manifest store removed from a file without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance_stripped: Option<StrippedProvenance>,
+    pub claims: Vec<ClaimData>,
+    /// Who the manifests name as the creator, from CAWG identity and
+    /// `CreativeWork` assertions.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub identities: Vec<IdentityAssertion>,
+    /// Claim generators that matched a generator rule.
+    pub generator_matches: Vec<RuleMatch>,
+    pub ingredients: IngredientSummary,
+    pub actions: ActionSummary,
+    /// Whether the active manifest permits AI training and data mining,
+    /// None when it doesn't say.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub training_mining: Option<TrainingMining>,
+    /// Thumbnails written with `--extract-thumbnails`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub thumbnails: Vec<ThumbnailFile>,
+    /// None when the file has no EXIF block.
+    pub exif: Option<ExifData>,
+    /// None when the file has no XMP packet.
+    pub xmp_findings: Option<XmpFindings>,
+    /// IPTC digital source types from XMP and IPTC, None when neither has
+    /// one.
+    pub digital_source_type: Option<SourceTypeFindings>,
+    /// None unless the file is a PNG with text chunks.
+    pub png_text: Option<PngTextFindings>,
+    /// None unless the file is a JPEG.
+    pub jpeg_segments: Option<JpegFindings>,
+    /// None when the file has no JPEG quantization tables.
+    pub quant_tables: Option<QuantTableFindings>,
+    /// Tracks of MP4, MOV and other BMFF files, None for other formats.
+    pub media: Option<MediaData>,
+    /// Stream parameters and tool tags of WAV and MP3 files, None for
+    /// other formats.
+    pub audio: Option<AudioData>,
+    /// Screen, frames and comments of a GIF, None for other formats.
+    pub gif: Option<GifData>,
+    /// Headers of a BMP, None for other formats.
+    pub bmp: Option<BmpData>,
+    /// Export tool and embedded raster images of an SVG, None for other
+    /// formats.
+    pub svg: Option<SvgData>,
+    /// Capture, history, signing and file times and camera settings
+    /// compared, None without EXIF capture time or settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencyFindings>,
+    /// Document metadata and embedded images of a PDF, None for other
+    /// formats.
+    #[cfg(feature = "pdf")]
+    pub pdf: Option<PdfData>,
+    /// None when the pixels can't be decoded.
+    #[cfg(feature = "pixel")]
+    pub ela: Option<ElaFindings>,
+    /// None when the pixels can't be decoded or are smaller than a block.
+    #[cfg(feature = "pixel")]
+    pub pixel_noise: Option<NoiseFindings>,
+    /// None when the pixels can't be decoded or the image is too small.
+    #[cfg(feature = "pixel")]
+    pub spectrum: Option<SpectrumFindings>,
+    /// None when the pixels can't be decoded or no decoder is set.
+    #[cfg(feature = "pixel")]
+    pub watermarks: Option<WatermarkFindings>,
+    /// None when the pixels can't be decoded.
+    #[cfg(feature = "pixel")]
+    pub perceptual_hash: Option<PerceptualHash>,
+    pub validation: ValidationData,
+    /// How long each stage of the analysis took, with
+    /// `AnalysisOptions::timings`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+    /// The assertions asked for with `--extract-assertion`, as stored.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extracted_assertions: Vec<ExtractedAssertion>,
+    /// The manifest store as the c2pa reader prints it, with
+    /// `--include-raw-manifest`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_manifest: Option<serde_json::Value>
 }
 
 impl Report {
-    pub fn new(
-        file_name: String,
-        file_type: String,
-        verdict: Verdict,
-        score: u8,
-        score_confidence: u8,
-        claims_found: bool,
-        claims_count: usize,
-        claims: Vec<ClaimData>,
-        validation: ValidationData
-    ) -> Report {
-        Report { file_name, file_type, verdict, score, score_confidence, claims_found, claims_count, claims, validation }
+    /// Fails when the file can't be read or its manifest store can't be
+    /// parsed, see `from_file_lenient` for a report of what could be read.
+    #[cfg(feature = "fs")]
+    pub fn from_file(path: PathBuf) -> Result<Report, AnalyzeError> {
+        Report::from_file_with(path, &AnalysisOptions::default())
+    }
+
+    /// Reads the sidecar manifest store next to `path` if there is one, see
+    /// `batch::find_sidecar`.
+    #[cfg(feature = "fs")]
+    pub fn from_file_with(path: PathBuf, options: &AnalysisOptions) -> Result<Report, AnalyzeError> {
+        let sidecar = find_sidecar(&path);
+        Report::from_file_lenient(path, sidecar, options).into_result()
     }
-    
-    pub fn from_file(path: PathBuf) -> Report {
+
+    /// Validates the detached manifest store in `sidecar` against the file
+    /// at `path`, whatever the file embeds.
+    #[cfg(feature = "fs")]
+    pub fn from_file_with_sidecar(path: PathBuf, sidecar: PathBuf, options: &AnalysisOptions) -> Result<Report, AnalyzeError> {
+        Report::from_file_lenient(path, Some(sidecar), options).into_result()
+    }
+
+    /// Never fails, what couldn't be read is listed in `errors` and an
+    /// unreadable file gets the report of no data. Only `sidecar` is read
+    /// as a sidecar manifest store.
+    #[cfg(feature = "fs")]
+    pub fn from_file_lenient(path: PathBuf, sidecar: Option<PathBuf>, options: &AnalysisOptions) -> Report {
         let file_name = match path.file_name() {
-            Some(n) => String::from(n.to_str().unwrap()),
+            Some(n) => n.to_string_lossy().to_string(),
             None => String::from("n/a")
         };
-        let file_type = file_name.split(".")
-            .last()
-            .unwrap()
-            .to_string();
-        let (claims, validation_data) = handle_file(path);
-        let mut score = 0_u8;
-        let mut score_confidence = 0_u8;
-        let mut claims_found = false;
-        let iterator = claims.iter();
-        let claims_count = iterator.clone().count();
-        if claims_count != 0 {
-            score = 1_u8;
-            score_confidence = 1_u8;
-            claims_found = true;
-            let suspicious_generators = [
-                "chatgpt",
-                "gpt",
-                "gpt-3",
-                "gpt-4",
-                "gpt-4o",
-                "microsoft responsible ai image provenance",
-                "midjourney",
-                "stable diffusion",
-                "adobe firefly",
-                "leonardo",
-                "dall-e"
-            ]; // TODO: test this
-            let manipulation_generators = ["photoshop", "gimp"]; //TODO: see above
-            iterator.for_each(|claim| {
-                claim.claim_generator.iter().for_each(|generator| {
-                    if suspicious_generators.contains(&generator.to_lowercase().as_str()) {
-                        score += 100_u8;
-                        score_confidence += 50_u8;
-                    } else if manipulation_generators.contains(&generator.to_lowercase().as_str()) {
-                        score += 50_u8;
-                        score_confidence += 50_u8;
-                    }
-                });
-            });    
+        let file_type = file_type_from_name(&file_name);
+        let format = format_from_path(&path);
+        let limit = options.max_input_size;
+        let bytes = input::read_file(&path, limit).and_then(|bytes| match &format {
+            Some(format) => input::check_magic(format, &bytes).map(|_| bytes),
+            None => Ok(bytes)
+        });
+        let sidecar = sidecar.map(|s| {
+            let bytes = input::read_file(&s, limit).and_then(|bytes| input::check_magic("c2pa", &bytes).map(|_| bytes));
+            bytes.map(|b| (s, b))
+        }).transpose();
+        let (bytes, sidecar) = match (bytes, sidecar) {
+            (Ok(bytes), Ok(sidecar)) => (bytes, sidecar),
+            (Err(e), _) | (_, Err(e)) => return Report::rejected(&file_name, file_type, format.as_deref(), e, options)
         };
-        if validation_data.certs_count != 0 {
-            score += 20_u8;
-            score_confidence += 20_u8;
-            match validation_data.state {
-                ValidationState::Valid => {
-                    score_confidence += 40_u8;
-                },
-                ValidationState::Trusted => {
-                    score_confidence += 60_u8;
-                }
-                ValidationState::Invalid => {
-                    score += 60_u8;
-                    score_confidence += 20_u8;
-                }
+        let sidecar = sidecar.as_ref().map(|(s, bytes)| (s.as_path(), &bytes[..]));
+        Report::analyze(&file_name, file_type, format.as_deref(), &bytes, Some(&path), sidecar, options)
+    }
+
+    /// Analyzes an image held in memory. `file_name` is only used for the
+    /// report and to tell the format by its extension, ex. `upload.jpg`.
+    pub fn from_bytes(file_name: &str, bytes: &[u8]) -> Result<Report, AnalyzeError> {
+        Report::from_bytes_with(file_name, bytes, &AnalysisOptions::default())
+    }
+
+    pub fn from_bytes_with(file_name: &str, bytes: &[u8], options: &AnalysisOptions) -> Result<Report, AnalyzeError> {
+        Report::from_bytes_lenient(file_name, bytes, options).into_result()
+    }
+
+    /// Never fails, see `from_file_lenient`.
+    pub fn from_bytes_lenient(file_name: &str, bytes: &[u8], options: &AnalysisOptions) -> Report {
+        let file_type = file_type_from_name(file_name);
+        let format = format_from_path(file_name);
+        let checked = input::check_size(bytes.len() as u64, options.max_input_size).and_then(|_| match &format {
+            Some(format) => input::check_magic(format, bytes),
+            None => Ok(())
+        });
+        match checked {
+            Ok(_) => Report::analyze(file_name, file_type, format.as_deref(), bytes, None, None, options),
+            Err(e) => Report::rejected(file_name, file_type, format.as_deref(), e, options)
+        }
+    }
+
+    /// Analyzes an image read from `stream` without a file name, `format` is
+    /// an extension or MIME type, ex. `jpeg` or `image/png`. The report's
+    /// `file_name` is `stdin`.
+    pub fn from_stream(format: &str, stream: impl Read + Seek + Send) -> Result<Report, AnalyzeError> {
+        Report::from_stream_with(format, stream, &AnalysisOptions::default())
+    }
+
+    pub fn from_stream_with(format: &str, stream: impl Read + Seek + Send, options: &AnalysisOptions) -> Result<Report, AnalyzeError> {
+        Report::from_stream_lenient(format, stream, options).into_result()
+    }
+
+    /// Never fails, see `from_file_lenient`.
+    pub fn from_stream_lenient(format: &str, mut stream: impl Read + Seek + Send, options: &AnalysisOptions) -> Report {
+        let file_type = match format.rsplit_once('/') {
+            Some((_, subtype)) => subtype.to_string(),
+            None => format.to_string()
+        };
+        // The size is checked before anything is read
+        let mut bytes = Vec::new();
+        let read = stream.seek(SeekFrom::End(0))
+            .and_then(|size| stream.rewind().map(|_| size))
+            .map_err(|e| AnalyzeError::Io(std::io::Error::new(e.kind(), format!("the stream: {}", e))))
+            .and_then(|size| input::check_size(size, options.max_input_size))
+            .and_then(|_| match stream.read_to_end(&mut bytes) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(AnalyzeError::Io(std::io::Error::new(e.kind(), format!("the stream: {}", e))))
+            })
+            .and_then(|_| input::check_magic(format, &bytes));
+        match read {
+            Ok(_) => Report::analyze("stdin", file_type, Some(format), &bytes, None, None, options),
+            Err(e) => Report::rejected("stdin", file_type, Some(format), e, options)
+        }
+    }
+
+    /// Fetches and analyzes the image at an http or https URL, ex. a CDN
+    /// link, waiting up to `options.remote.timeout`. Bodies over
+    /// `max_input_size`, or `remote::MAX_MEDIA_SIZE` without one, are
+    /// rejected. The report's `file_name` is the URL.
+    pub fn from_url(url: &str, options: &AnalysisOptions) -> Result<Report, AnalyzeError> {
+        Report::from_url_lenient(url, options).into_result()
+    }
+
+    /// Never fails, see `from_file_lenient`. The format is the response's
+    /// content type.
+    pub fn from_url_lenient(url: &str, options: &AnalysisOptions) -> Report {
+        let limit = options.max_input_size.unwrap_or(remote::MAX_MEDIA_SIZE);
+        let (format, bytes) = match remote::fetch_media(url, options.remote.timeout, limit) {
+            Ok(fetched) => fetched,
+            Err(e) => return Report::rejected(url, String::from("n/a"), None, e, options)
+        };
+        let file_type = format.rsplit('/').next().unwrap_or_default().to_string();
+        match input::check_magic(&format, &bytes) {
+            Ok(_) => Report::analyze(url, file_type, Some(&format), &bytes, None, None, options),
+            Err(e) => Report::rejected(url, file_type, Some(&format), e, options)
+        }
+    }
+
+    /// The report of no data, failed on `error`.
+    fn rejected(file_name: &str, file_type: String, format: Option<&str>, error: AnalyzeError, options: &AnalysisOptions) -> Report {
+        let mut report = Report::analyze(file_name, file_type, format, &[], None, None, options);
+        report.errors = vec![ReportError::from(error)];
+        report
+    }
+
+    /// The first error the analysis failed on, if any. Errors of added
+    /// analyzers are only listed.
+    pub fn into_result(mut self) -> Result<Report, AnalyzeError> {
+        let failure = self.errors.iter().position(|e| e.is_failure() && e.source.is_some());
+        match failure.and_then(|i| self.errors.remove(i).source) {
+            Some(e) => Err(e),
+            None => Ok(self)
+        }
+    }
+
+    /// Analyzes an ingredient's data, `format` is its MIME type.
+    pub(crate) fn from_ingredient(title: &str, format: &str, bytes: &[u8], options: &AnalysisOptions) -> Report {
+        let file_type = match format.rsplit_once('/') {
+            Some((_, subtype)) => subtype.to_string(),
+            None => format.to_string()
+        };
+        Report::analyze(title, file_type, Some(format), bytes, None, None, options)
+    }
+
+    /// Runs the analyzers of `options` and merges their outputs.
+    #[cfg_attr(not(feature = "fs"), allow(unused_variables))]
+    fn analyze(
+        file_name: &str,
+        file_type: String,
+        format: Option<&str>,
+        bytes: &[u8],
+        path: Option<&Path>,
+        sidecar: Option<(&Path, &[u8])>,
+        options: &AnalysisOptions
+    ) -> Report {
+        let start = Instant::now();
+        let _span = info_span!("analyze", file = file_name).entered();
+        let mut timings = Timings::default();
+        #[cfg(feature = "pixel")]
+        let image = timings.time("decode", || image::load_from_memory(bytes).ok());
+        #[cfg(feature = "pixel")]
+        let perceptual_hash = image.as_ref().map(|image| timings.time("perceptual_hash", || PerceptualHash::from_image(image)));
+        let mut evidence = Vec::new();
+        let mut analyzers = Vec::new();
+        let mut errors = Vec::new();
+        let mut c2pa = None;
+        let mut file_data = FileData::default();
+        for analyzer in options.analyzers.iter() {
+            let input = AnalysisInput {
+                file_name,
+                format,
+                bytes,
+                #[cfg(feature = "fs")]
+                path,
+                sidecar: sidecar.map(|(_, bytes)| bytes),
+                #[cfg(feature = "pixel")]
+                image: image.as_ref(),
+                signature: c2pa.as_ref().and_then(|c: &C2paFindings| c.data.validation.signature.as_ref()),
+                exif: file_data.exif.as_ref(),
+                options
+            };
+            let mut output = timings.time(analyzer.name(), || analyzer.analyze(&input));
+            timings.extend_prefixed(analyzer.name(), std::mem::take(&mut output.timings));
+            analyzers.push(AnalyzerResult::new(analyzer.name(), &output));
+            evidence.extend(output.evidence.into_iter().map(|e| e.localized(options.locale)));
+            errors.extend(output.errors);
+            c2pa = c2pa.or(output.c2pa);
+            file_data.merge(output.file_data);
+        }
+        let C2paFindings { data, generator_matches, actions, thumbnails, provenance_stripped } = match c2pa {
+            Some(c) => c,
+            None => C2paFindings {
+                data: ManifestData::empty(),
+                generator_matches: Vec::new(),
+                actions: ActionSummary::default(),
+                thumbnails: Vec::new(),
+                provenance_stripped: None
             }
+        };
+        let ManifestData { claims, identities, validation, active_label, ingredients, extracted_assertions, raw, remote, .. } = data;
+        let FileData {
+            exif,
+            xmp,
+            source_type,
+            png_text,
+            jpeg,
+            quant_tables,
+            mut media,
+            audio,
+            gif,
+            bmp,
+            svg,
+            consistency,
+            #[cfg(feature = "pdf")]
+            pdf,
+            #[cfg(feature = "pixel")]
+            ela,
+            #[cfg(feature = "pixel")]
+            noise,
+            #[cfg(feature = "pixel")]
+            spectrum,
+            #[cfg(feature = "pixel")]
+            watermarks
+        } = file_data;
+        if let Some(media) = &mut media {
+            media.set_claim(active_label.as_ref());
+        }
+        let training_mining = TrainingMining::from_claims(&claims, active_label.as_ref());
+        let verify_url = match (claims.is_empty(), validation.state) {
+            (false, ValidationState::Valid | ValidationState::Trusted) => {
+                Some(remote::verify_url(options.verify_base_url.as_deref().unwrap_or(remote::VERIFY_URL), file_name))
+            },
+            _ => None
+        };
+        let (score, score_confidence, verdict, probabilities) = timings.time("scoring", || {
+            let (score, score_confidence) = ensemble::combine(&mut analyzers, &options.scoring);
+            let verdict = Verdict::from_score_with(score, score_confidence, &options.scoring);
+            (score, score_confidence, verdict, VerdictProbabilities::from_evidence(&evidence))
+        });
+        timings.finish(start);
+        Report {
+            schema_version: SCHEMA_VERSION,
+            file_name: file_name.to_string(),
+            file_type,
+            verdict,
+            score,
+            score_confidence,
+            probabilities,
+            evidence,
+            analyzers,
+            errors,
+            claims_found: !claims.is_empty(),
+            claims_count: claims.len(),
+            remote_manifest: remote,
+            sidecar_manifest: sidecar.map(|(path, _)| path.display().to_string()),
+            verify_url,
+            provenance_stripped,
+            claims,
+            identities,
+            generator_matches,
+            ingredients,
+            actions,
+            training_mining,
+            thumbnails,
+            exif,
+            xmp_findings: xmp,
+            digital_source_type: source_type,
+            png_text,
+            jpeg_segments: jpeg,
+            quant_tables,
+            media,
+            audio,
+            gif,
+            bmp,
+            svg,
+            consistency,
+            #[cfg(feature = "pdf")]
+            pdf,
+            #[cfg(feature = "pixel")]
+            ela,
+            #[cfg(feature = "pixel")]
+            pixel_noise: noise,
+            #[cfg(feature = "pixel")]
+            spectrum,
+            #[cfg(feature = "pixel")]
+            watermarks,
+            #[cfg(feature = "pixel")]
+            perceptual_hash,
+            validation,
+            timings: options.timings.then_some(timings),
+            extracted_assertions,
+            raw_manifest: raw
         }
-        if score > 100 { score = 100 };
-        if score_confidence > 100 { score_confidence = 100 };
-        let verdict = Verdict::from_score(score, score_confidence);
-        Report::new(file_name, file_type, verdict, score, score_confidence, claims_found, claims_count, claims, validation_data)
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum Verdict {
     Generated,
     Modified,
@@ -110,52 +455,152 @@ pub enum Verdict {
 
 impl Verdict {
     pub fn from_score(score: u8, score_confidence: u8) -> Verdict {
+        Verdict::from_score_with(score, score_confidence, &ScoringConfig::default())
+    }
+
+    pub fn from_score_with(score: u8, score_confidence: u8, scoring: &ScoringConfig) -> Verdict {
         if score == 0 && score_confidence == 0 {
-            return Verdict::Unknown
-        } else if score < 21 {
-            if score_confidence > 40 { return Verdict::Genuine } else { return Verdict::Modified }
-        } else if score < 81 {
-            return Verdict::Modified
+            Verdict::Unknown
+        } else if score < scoring.genuine_below {
+            if score_confidence >= scoring.genuine_confidence { Verdict::Genuine } else { Verdict::Modified }
+        } else if score < scoring.generated_from {
+            Verdict::Modified
         } else {
-            return Verdict::Generated
+            Verdict::Generated
         }
     }
 }
 
-fn read_c2pa(file: File, path: PathBuf) -> Result<(Vec<ClaimData>, ValidationData), Error> {
-    let format = format_from_path(&path).unwrap();
-    match Reader::from_stream(&format, &file) {
-        Ok(reader) => {
-            //println!("c2pa block found");
-            let data = ClaimData::vec_from_manifest(reader.manifests());
-            let validation_data = match reader.validation_results() {
+fn file_type_from_name(file_name: &str) -> String {
+    file_name.split(".")
+        .last()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// What was read from an asset's manifest store, before scoring.
+pub(crate) struct ManifestData {
+    pub claims: Vec<ClaimData>,
+    pub identities: Vec<IdentityAssertion>,
+    pub validation: ValidationData,
+    pub active_label: Option<String>,
+    pub ingredients: IngredientSummary,
+    /// Only collected when thumbnails are extracted.
+    pub thumbnails: Vec<Thumbnail>,
+    pub extracted_assertions: Vec<ExtractedAssertion>,
+    /// The reader's JSON, only kept with `include_raw_manifest`.
+    pub raw: Option<serde_json::Value>,
+    pub remote: Option<RemoteManifest>
+}
+
+impl ManifestData {
+    pub fn empty() -> ManifestData {
+        ManifestData {
+            claims: Vec::new(),
+            identities: Vec::new(),
+            validation: ValidationData::new(ValidationState::Invalid, 0, 0, Vec::new()),
+            active_label: None,
+            ingredients: IngredientSummary::default(),
+            thumbnails: Vec::new(),
+            extracted_assertions: Vec::new(),
+            raw: None,
+            remote: None
+        }
+    }
+
+    /// Times the `validation`, `revocation`, `claims` and `ingredients`
+    /// stages.
+    pub fn from_reader(reader: &Reader, options: &AnalysisOptions, timings: &mut Timings) -> ManifestData {
+        let mut validation = timings.time("validation", || {
+            let mut validation = match reader.validation_results() {
                 Some(res) => ValidationData::from_result(res),
                 None => ValidationData::new(ValidationState::Invalid, 0, 0, Vec::new())
             };
-            return Ok((data, validation_data));
+            if let Some(info) = reader.active_manifest().and_then(|m| m.signature_info()) {
+                validation.signature = Some(SignatureData::from_info(info));
+                validation.cert_chain = ChainCertificate::vec_from_pem(info.cert_chain());
+            }
+            validation
+        });
+        if let Some(info) = reader.active_manifest().and_then(|m| m.signature_info()) {
+            validation.revocation = Some(timings.time("revocation", || RevocationData::check(info.cert_chain(), &validation.certs, options.revocation.as_ref())));
         }
-        Err(c2pa::Error::JumbfNotFound) => {
-           //println!("no data");
-           return Err(Error::new(std::io::ErrorKind::NotFound, "No data found"));
-        },
-        Err(_) => {
-            //println!("other error");
-            return Err(Error::new(std::io::ErrorKind::Other, "Other error"));
+        let (claims, identities) = timings.time("claims", || (ClaimData::vec_from_manifest(reader.manifests(), &options.assertion_filter), IdentityAssertion::vec_from_manifests(reader.manifests())));
+        ManifestData {
+            claims,
+            identities,
+            validation,
+            active_label: reader.active_label().map(|l| l.to_string()),
+            ingredients: timings.time("ingredients", || IngredientSummary::from_reader(reader, options)),
+            thumbnails: match options.thumbnail_dir {
+                Some(_) => Thumbnail::vec_from_reader(reader),
+                // Compared to the pixels
+                None if cfg!(feature = "pixel") => Thumbnail::from_active_manifest(reader).into_iter().collect(),
+                None => Vec::new()
+            },
+            extracted_assertions: ExtractedAssertion::vec_from_manifests(reader.manifests(), &options.extract_assertions),
+            raw: match options.include_raw_manifest {
+                true => serde_json::from_str(&reader.json()).ok(),
+                false => None
+            },
+            remote: None
         }
-    };
+    }
 }
 
-fn handle_file(path: std::path::PathBuf) -> (Vec<ClaimData>, ValidationData) {
-    match File::open(&path) {
-        Ok(f) => {
-            match read_c2pa(f, path) {
-                Ok(data) => data,
-                Err(_) => (Vec::new(), ValidationData::new(ValidationState::Invalid, 0, 0, Vec::new()))
-            }
-        },
-        Err(_) => {
-            //println!("foiled");
-            (Vec::new(), ValidationData::new(ValidationState::Invalid, 0, 0, Vec::new()))
+/// The report sections the analyzers besides C2PA fill, each set by one
+/// of them.
+#[derive(Default)]
+pub(crate) struct FileData {
+    pub exif: Option<ExifData>,
+    pub xmp: Option<XmpFindings>,
+    pub source_type: Option<SourceTypeFindings>,
+    pub png_text: Option<PngTextFindings>,
+    pub jpeg: Option<JpegFindings>,
+    pub quant_tables: Option<QuantTableFindings>,
+    pub media: Option<MediaData>,
+    pub audio: Option<AudioData>,
+    pub gif: Option<GifData>,
+    pub bmp: Option<BmpData>,
+    pub svg: Option<SvgData>,
+    pub consistency: Option<ConsistencyFindings>,
+    #[cfg(feature = "pdf")]
+    pub pdf: Option<PdfData>,
+    #[cfg(feature = "pixel")]
+    pub ela: Option<ElaFindings>,
+    #[cfg(feature = "pixel")]
+    pub noise: Option<NoiseFindings>,
+    #[cfg(feature = "pixel")]
+    pub spectrum: Option<SpectrumFindings>,
+    #[cfg(feature = "pixel")]
+    pub watermarks: Option<WatermarkFindings>
+}
+
+impl FileData {
+    /// Keeps the sections already set, the first analyzer filling one wins.
+    fn merge(&mut self, other: FileData) {
+        self.exif = self.exif.take().or(other.exif);
+        self.xmp = self.xmp.take().or(other.xmp);
+        self.source_type = self.source_type.take().or(other.source_type);
+        self.png_text = self.png_text.take().or(other.png_text);
+        self.jpeg = self.jpeg.take().or(other.jpeg);
+        self.quant_tables = self.quant_tables.take().or(other.quant_tables);
+        self.media = self.media.take().or(other.media);
+        self.audio = self.audio.take().or(other.audio);
+        self.gif = self.gif.take().or(other.gif);
+        self.bmp = self.bmp.take().or(other.bmp);
+        self.svg = self.svg.take().or(other.svg);
+        self.consistency = self.consistency.take().or(other.consistency);
+        #[cfg(feature = "pdf")]
+        {
+            self.pdf = self.pdf.take().or(other.pdf);
+        }
+        #[cfg(feature = "pixel")]
+        {
+            self.ela = self.ela.take().or(other.ela);
+            self.noise = self.noise.take().or(other.noise);
+            self.spectrum = self.spectrum.take().or(other.spectrum);
+            self.watermarks = self.watermarks.take().or(other.watermarks);
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,372 @@
+use std::{cell::Cell, fs, path::PathBuf, time::Duration};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use x509_parser::{certificate::X509Certificate, extensions::{DistributionPointName, GeneralName, ParsedExtension}, parse_x509_crl, pem::Pem};
+
+use crate::{remote, validation::Certificate};
+
+thread_local!(
+    // Whether this thread last told c2pa to fetch OCSP responses.
+    static OCSP_FETCH: Cell<bool> = const { Cell::new(false) }
+);
+
+/// Online revocation checking of the signing chain: c2pa fetches an OCSP
+/// response for the signing certificate and the CRLs of every certificate
+/// are fetched, kept in `cache_dir` until their next update. Without it
+/// only the OCSP response stapled to the signature is checked.
+///
+/// A CRL counts when its signature verifies with the key of the
+/// certificate's issuer in the chain and it is current, certificates
+/// whose issuer isn't in the chain aren't checked.
+#[derive(Serialize, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RevocationCheck {
+    /// Where fetched CRLs are kept, None to fetch them for every file.
+    pub cache_dir: Option<PathBuf>,
+    pub timeout: Duration
+}
+
+impl Default for RevocationCheck {
+    fn default() -> RevocationCheck {
+        RevocationCheck { cache_dir: None, timeout: Duration::from_secs(10) }
+    }
+}
+
+impl RevocationCheck {
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> RevocationCheck {
+        self.cache_dir = Some(dir);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> RevocationCheck {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Turns c2pa's OCSP fetching on or off for this thread, to match
+    /// `check`.
+    pub(crate) fn apply(check: Option<&RevocationCheck>) -> Result<(), String> {
+        let fetch = check.is_some();
+        // A trust reset puts c2pa back to not fetching, so turning it on is
+        // never skipped.
+        if !fetch && !OCSP_FETCH.get() {
+            return Ok(());
+        }
+        let settings = serde_json::json!({"verify": {"ocsp_fetch": fetch}});
+        match c2pa::settings::load_settings_from_str(&settings.to_string(), "json") {
+            Ok(_) => {
+                OCSP_FETCH.set(fetch);
+                Ok(())
+            },
+            Err(e) => Err(format!("Error loading revocation settings: {}", e))
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationStatus {
+    Good,
+    Revoked,
+    /// Nothing tells, ex. no OCSP response and no reachable CRL.
+    Unknown
+}
+
+/// Whether the certificates that signed the active manifest were revoked.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct RevocationData {
+    /// Revoked if any check says so, Good if the OCSP response or every
+    /// CRL does.
+    pub status: RevocationStatus,
+    /// From the OCSP response c2pa checked, stapled or fetched, None
+    /// without one.
+    pub ocsp: Option<RevocationStatus>,
+    /// Only checked with a `RevocationCheck`.
+    pub crls: Vec<CrlCheck>
+}
+
+/// One certificate looked up in one CRL.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct CrlCheck {
+    pub subject: String,
+    pub url: String,
+    pub status: RevocationStatus,
+    /// RFC 3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+    /// The CRL is past its next update, ex. it couldn't be fetched and the
+    /// cached one is old. It still tells revoked certificates, not good
+    /// ones.
+    pub stale: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>
+}
+
+impl RevocationData {
+    /// Checks the PEM `chain` of the active manifest, with `certs` its
+    /// validation status entries.
+    pub fn check(chain: &str, certs: &[Certificate], check: Option<&RevocationCheck>) -> RevocationData {
+        let ocsp = certs.iter().find_map(|c| match c.cert_code.as_str() {
+            "signingCredential.revoked" => Some(RevocationStatus::Revoked),
+            "signingCredential.ocsp.notRevoked" => Some(RevocationStatus::Good),
+            code if code.starts_with("signingCredential.ocsp.") => Some(RevocationStatus::Unknown),
+            _ => None
+        });
+        let crls = match check {
+            Some(check) => crl_checks(chain, check),
+            None => Vec::new()
+        };
+        let status = if ocsp == Some(RevocationStatus::Revoked) || crls.iter().any(|c| c.status == RevocationStatus::Revoked) {
+            RevocationStatus::Revoked
+        } else if ocsp == Some(RevocationStatus::Good) || (!crls.is_empty() && crls.iter().all(|c| c.status == RevocationStatus::Good)) {
+            RevocationStatus::Good
+        } else {
+            RevocationStatus::Unknown
+        };
+        RevocationData { status, ocsp, crls }
+    }
+}
+
+fn crl_checks(chain: &str, check: &RevocationCheck) -> Vec<CrlCheck> {
+    let pems: Vec<Pem> = Pem::iter_from_buffer(chain.as_bytes()).filter_map(|pem| pem.ok()).collect();
+    let certs: Vec<X509Certificate> = pems.iter().filter_map(|pem| pem.parse_x509().ok()).collect();
+    let mut checks = Vec::new();
+    for cert in &certs {
+        let issuer = certs.iter().find(|c| c.subject() == cert.issuer());
+        for url in crl_urls(cert) {
+            checks.push(check_crl(cert, issuer, url, check));
+        }
+    }
+    checks
+}
+
+/// The http distribution points of the certificate's CRLs.
+fn crl_urls(cert: &X509Certificate) -> Vec<String> {
+    let mut urls = Vec::new();
+    for ext in cert.extensions() {
+        if let ParsedExtension::CRLDistributionPoints(points) = ext.parsed_extension() {
+            for point in points.iter() {
+                if let Some(DistributionPointName::FullName(names)) = &point.distribution_point {
+                    urls.extend(names.iter().filter_map(|n| match n {
+                        GeneralName::URI(uri) if uri.starts_with("http://") || uri.starts_with("https://") => Some(uri.to_string()),
+                        _ => None
+                    }));
+                }
+            }
+        }
+    }
+    urls
+}
+
+fn check_crl(cert: &X509Certificate, issuer: Option<&X509Certificate>, url: String, check: &RevocationCheck) -> CrlCheck {
+    let mut result = CrlCheck {
+        subject: cert.subject().to_string(),
+        url,
+        status: RevocationStatus::Unknown,
+        revoked_at: None,
+        stale: false,
+        error: None
+    };
+    let issuer = match issuer {
+        Some(i) => i,
+        None => {
+            result.error = Some(format!("The issuer {} isn't in the chain, its CRL can't be verified", cert.issuer()));
+            return result;
+        }
+    };
+    match fetch_crl(&result.url, check) {
+        Ok((der, _)) => read_crl(&mut result, cert, issuer, &der, Utc::now().timestamp()),
+        Err(e) => result.error = Some(e)
+    }
+    result
+}
+
+/// Looks `cert` up in the CRL `der`, which must be signed by `issuer` and
+/// issued before `now`, a Unix timestamp.
+fn read_crl(result: &mut CrlCheck, cert: &X509Certificate, issuer: &X509Certificate, der: &[u8], now: i64) {
+    let crl = match parse_x509_crl(der) {
+        Ok((_, crl)) => crl,
+        Err(e) => {
+            result.error = Some(format!("Error parsing the CRL at {}: {}", result.url, e));
+            return;
+        }
+    };
+    if crl.issuer() != cert.issuer() {
+        result.error = Some(format!("The CRL at {} is issued by {}", result.url, crl.issuer()));
+        return;
+    }
+    if crl.verify_signature(issuer.public_key()).is_err() {
+        result.error = Some(format!("The signature of the CRL at {} doesn't verify with the key of {}", result.url, issuer.subject()));
+        return;
+    }
+    if crl.last_update().timestamp() > now {
+        result.error = Some(format!("The CRL at {} is only valid from {}", result.url, crl.last_update()));
+        return;
+    }
+    result.stale = crl.next_update().is_none_or(|next| next.timestamp() <= now);
+    let revoked_at = crl.iter_revoked_certificates()
+        .find(|r| r.raw_serial() == cert.raw_serial())
+        .map(|r| r.revocation_date.timestamp());
+    match revoked_at {
+        Some(revoked_at) => {
+            result.status = RevocationStatus::Revoked;
+            result.revoked_at = DateTime::from_timestamp(revoked_at, 0).map(|t| t.to_rfc3339());
+        },
+        None if !result.stale => result.status = RevocationStatus::Good,
+        None => {}
+    }
+}
+
+/// The DER of the CRL at `url` and whether it is stale, from the cache
+/// while it is current, else fetched, else from the cache past its next
+/// update.
+fn fetch_crl(url: &str, check: &RevocationCheck) -> Result<(Vec<u8>, bool), String> {
+    let entry = check.cache_dir.as_ref().map(|dir| {
+        let key: String = Sha256::digest(url.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+        dir.join(format!("{}.crl", key))
+    });
+    let cached = entry.as_ref().and_then(|e| fs::read(e).ok());
+    if let Some(der) = &cached {
+        if is_current(der) {
+            return Ok((der.clone(), false));
+        }
+    }
+    match remote::fetch(url, check.timeout) {
+        Ok(fetched) => {
+            let der = der_from_crl(fetched);
+            if let (Some(entry), Some(dir)) = (&entry, &check.cache_dir) {
+                // Failing to write only costs a later fetch
+                let _ = fs::create_dir_all(dir).and_then(|_| fs::write(entry, &der));
+            }
+            Ok((der, false))
+        },
+        Err(e) => match cached {
+            Some(der) => Ok((der, true)),
+            None => Err(e)
+        }
+    }
+}
+
+fn is_current(der: &[u8]) -> bool {
+    match parse_x509_crl(der) {
+        Ok((_, crl)) => crl.next_update().is_some_and(|next| next.timestamp() > Utc::now().timestamp()),
+        Err(_) => false
+    }
+}
+
+/// CRLs are served as DER or PEM.
+fn der_from_crl(bytes: Vec<u8>) -> Vec<u8> {
+    match Pem::iter_from_buffer(&bytes).next() {
+        Some(Ok(pem)) if pem.label == "X509 CRL" => pem.contents,
+        _ => bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A P-256 CA, a signer it issued with serial 1234 and CRLs of the CA
+    // from 2026-10-15 to 2126-09-21, the forged one signed by another key
+    // under the same name.
+    const CA: &str = "-----BEGIN CERTIFICATE-----
+MIIBVjCB/aADAgECAgEBMAoGCCqGSM49BAMCMBIxEDAOBgNVBAMMB1Rlc3QgQ0Ew
+IBcNMjYxMDE1MTQyOTU3WhgPMjEyNjA5MjExNDI5NTdaMBIxEDAOBgNVBAMMB1Rl
+c3QgQ0EwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASSlIK7iW1fgfMMBH3oug9J
+uqFJjHSR12MFdYTAlS4Xz/vZM0kZnhEoQfhjBvtdToTWdVmRvvHEPcEvqQR9308I
+o0IwQDAPBgNVHRMBAf8EBTADAQH/MA4GA1UdDwEB/wQEAwIBBjAdBgNVHQ4EFgQU
+0plJwiHLNHtQVFV5qQImH8W74lkwCgYIKoZIzj0EAwIDSAAwRQIhAMbJ/ypVA2Oy
++eKPCOmAtRkWDPzBv1e6+OPGO5qrse8jAiADRLm9dr0G4PyObUTc3QrLgokqSkfF
+t8pyKbXeyMS+vw==
+-----END CERTIFICATE-----";
+    const SIGNER: &str = "-----BEGIN CERTIFICATE-----
+MIIBkjCCATmgAwIBAgICEjQwCgYIKoZIzj0EAwIwEjEQMA4GA1UEAwwHVGVzdCBD
+QTAgFw0yNjEwMTUxNDI5NTdaGA8yMTI2MDkyMTE0Mjk1N1owFjEUMBIGA1UEAwwL
+VGVzdCBTaWduZXIwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARdAny51ZfGfTTY
+/63FXWiY62JpgMWKBAOVrtb96O1j7kDHbosNHLpHdSW/Idl7ViHPkF7cgYgasJIb
+JHjl1rgHo3kwdzAJBgNVHRMEAjAAMCoGA1UdHwQjMCEwH6AdoBuGGWh0dHA6Ly9j
+cmwuZXhhbXBsZS9jYS5jcmwwHQYDVR0OBBYEFFO+vUIiZh0NPGjEq2in6wjA+7KA
+MB8GA1UdIwQYMBaAFNKZScIhyzR7UFRVeakCJh/Fu+JZMAoGCCqGSM49BAMCA0cA
+MEQCIDNXGJvxoUNZK+5cqLaUPMzfKDhaUBnCpZdstHf4kkFcAiBXkU8FnfIy7CsN
+uv9furj7SSMqanTQvdtPEHbBrjcvfw==
+-----END CERTIFICATE-----";
+    const EMPTY_CRL: &str = "-----BEGIN X509 CRL-----
+MIGrMFMCAQEwCgYIKoZIzj0EAwIwEjEQMA4GA1UEAwwHVGVzdCBDQRcNMjYxMDE1
+MTQyOTU3WhgPMjEyNjA5MjExNDI5NTdaoA4wDDAKBgNVHRQEAwIBATAKBggqhkjO
+PQQDAgNIADBFAiA31U5FaypXUXCkzH1RBatWeKidGy9liXCphD/LI7Cb5QIhALOb
+/bYVMvJbuiIW2KVARLT+d1aGm1BqZnkoMhLbDg+d
+-----END X509 CRL-----";
+    const REVOKED_CRL: &str = "-----BEGIN X509 CRL-----
+MIHBMGoCAQEwCgYIKoZIzj0EAwIwEjEQMA4GA1UEAwwHVGVzdCBDQRcNMjYxMDE1
+MTQyOTU3WhgPMjEyNjA5MjExNDI5NTdaMBUwEwICEjQXDTI2MTAxNTE0Mjk1N1qg
+DjAMMAoGA1UdFAQDAgECMAoGCCqGSM49BAMCA0cAMEQCIGXJBRVFo1+6wXugElYY
+CxhROIYTmCjVhzdW1wARBRqAAiA7Q1M7+3U44CvF7Z2azj2+nAgLQpAEzomXHZlu
+N0huXA==
+-----END X509 CRL-----";
+    const FORGED_CRL: &str = "-----BEGIN X509 CRL-----
+MIGqMFMCAQEwCgYIKoZIzj0EAwIwEjEQMA4GA1UEAwwHVGVzdCBDQRcNMjYxMDE1
+MTQyOTU3WhgPMjEyNjA5MjExNDI5NTdaoA4wDDAKBgNVHRQEAwIBAzAKBggqhkjO
+PQQDAgNHADBEAiBPCmibKd/CLcna/MZKjbcg/aJ9craALk8LdWhM54StiAIgXWSn
+JUQN41wMlLKJJSd/YD8hrtwgqxoX2nJnotlWG5k=
+-----END X509 CRL-----";
+    const ISSUED: i64 = 1792074597;
+
+    fn read(crl: &str, now: i64) -> CrlCheck {
+        let pem = |pem: &str| Pem::iter_from_buffer(pem.as_bytes()).next().unwrap().unwrap();
+        let (ca, signer) = (pem(CA), pem(SIGNER));
+        let (ca, signer) = (ca.parse_x509().unwrap(), signer.parse_x509().unwrap());
+        let mut result = CrlCheck {
+            subject: signer.subject().to_string(),
+            url: String::from("http://crl.example/ca.crl"),
+            status: RevocationStatus::Unknown,
+            revoked_at: None,
+            stale: false,
+            error: None
+        };
+        read_crl(&mut result, &signer, &ca, &der_from_crl(crl.as_bytes().to_vec()), now);
+        result
+    }
+
+    #[test]
+    fn crls_are_verified() {
+        let now = ISSUED + 60;
+        let good = read(EMPTY_CRL, now);
+        assert_eq!((good.status, good.error), (RevocationStatus::Good, None));
+        let revoked = read(REVOKED_CRL, now);
+        assert_eq!(revoked.status, RevocationStatus::Revoked);
+        assert_eq!(revoked.revoked_at.as_deref(), Some("2026-10-15T14:29:57+00:00"));
+
+        let forged = read(FORGED_CRL, now);
+        assert_eq!(forged.status, RevocationStatus::Unknown);
+        assert!(forged.error.unwrap().contains("doesn't verify"));
+        let mut garbled = EMPTY_CRL.replace("PQQDAgNIADBFAiA31U5", "PQQDAgNIADBFAiA31U6");
+        assert_eq!(read(&garbled, now).status, RevocationStatus::Unknown);
+        garbled.truncate(60);
+        assert!(read(&garbled, now).error.is_some());
+    }
+
+    #[test]
+    fn crls_are_checked_for_currency() {
+        let early = read(EMPTY_CRL, ISSUED - 60);
+        assert_eq!(early.status, RevocationStatus::Unknown);
+        assert!(early.error.unwrap().contains("only valid from"));
+
+        // past the next update a CRL still tells what was revoked
+        let late = ISSUED + 101 * 365 * 24 * 60 * 60;
+        let stale = read(EMPTY_CRL, late);
+        assert_eq!((stale.status, stale.stale), (RevocationStatus::Unknown, true));
+        assert_eq!(read(REVOKED_CRL, late).status, RevocationStatus::Revoked);
+    }
+
+    #[test]
+    fn issuers_outside_the_chain_are_not_checked() {
+        let check = RevocationCheck::default().with_timeout(Duration::from_millis(1));
+        let data = RevocationData::check(SIGNER, &[], Some(&check));
+        assert_eq!(data.crls.len(), 1);
+        assert_eq!(data.status, RevocationStatus::Unknown);
+        assert!(data.crls[0].error.as_ref().unwrap().contains("isn't in the chain"));
+    }
+}
@@ -0,0 +1,283 @@
+use std::path::Path;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{claimdata::ClaimData, knowledgebase::{GeneratorCategory, KnowledgeBase, SignerEntry}};
+
+/// A claim generator that raises the score when it appears in a manifest.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GeneratorRule {
+    /// Generator name, matched as whole words anywhere in the claim
+    /// generator after normalizing both, so `adobe firefly` matches
+    /// `Adobe Firefly 3.0` and `dall-e` matches `DALL·E 3`.
+    pub name: String,
+    /// Case-insensitive regex used instead of `name` when set, ex.
+    /// `^gpt-?4`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    pub score: u8,
+    pub confidence: u8,
+    /// Who makes the tool, from the knowledge base.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<GeneratorCategory>,
+    /// The tool signs what it writes, from the knowledge base.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub content_credentials: bool,
+    #[serde(skip)]
+    regex: Option<Regex>
+}
+
+impl GeneratorRule {
+    pub fn new(name: &str, score: u8, confidence: u8) -> GeneratorRule {
+        GeneratorRule { name: name.to_string(), pattern: None, score, confidence, vendor: None, category: None, content_credentials: false, regex: None }
+    }
+
+    pub fn with_pattern(name: &str, pattern: &str, score: u8, confidence: u8) -> Result<GeneratorRule, String> {
+        let mut rule = GeneratorRule::new(name, score, confidence);
+        rule.pattern = Some(pattern.to_string());
+        rule.compile()?;
+        Ok(rule)
+    }
+
+    fn compile(&mut self) -> Result<(), String> {
+        self.regex = match &self.pattern {
+            Some(p) => match RegexBuilder::new(p).case_insensitive(true).build() {
+                Ok(r) => Some(r),
+                Err(e) => return Err(format!("Invalid pattern for generator rule '{}': {}", self.name, e))
+            },
+            None => None
+        };
+        Ok(())
+    }
+
+    /// Whether the rule matches a claim generator, `normalized` being the
+    /// generator passed through [`normalize`].
+    fn matches(&self, generator: &str, normalized: &str) -> bool {
+        match &self.regex {
+            Some(r) => r.is_match(generator),
+            None => {
+                let name = normalize(&self.name);
+                !name.is_empty() && format!(" {} ", normalized).contains(&format!(" {} ", name))
+            }
+        }
+    }
+}
+
+/// Lowercases and turns every run of non-alphanumeric characters into a
+/// single space, ex. `ChatGPT-4o` becomes `chatgpt 4o`.
+pub fn normalize(generator: &str) -> String {
+    generator.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Lowercase hex without separators, ex. `AB:01` becomes `ab01`.
+pub fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint.chars().filter(|c| !matches!(c, ':' | ' ')).collect::<String>().to_ascii_lowercase()
+}
+
+/// The `O=` of a subject as x509-parser writes it, ex.
+/// `C=US, O=OpenAI, CN=...`.
+fn subject_organization(subject: &str) -> Option<&str> {
+    subject.split(", ").find_map(|part| part.strip_prefix("O="))
+}
+
+/// Which kind of rule list a generator matched.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    Generated,
+    Modified
+}
+
+/// A claim generator that matched a rule, recorded in the report.
+#[derive(Serialize, Clone)]
+#[non_exhaustive]
+pub struct RuleMatch {
+    pub claim_id: String,
+    pub generator: String,
+    pub kind: RuleKind,
+    /// `name` of the matched rule.
+    pub rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<GeneratorCategory>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+/// Generator lists used for scoring. Loaded from a JSON or TOML file so new
+/// tools can be added without a rebuild, ex.
+///
+/// ```toml
+/// [[generated]]
+/// name = "midjourney"
+/// score = 100
+/// confidence = 50
+///
+/// [[generated]]
+/// name = "gpt-4 family"
+/// pattern = "^(chat)?gpt-?4"
+/// score = 100
+/// confidence = 50
+///
+/// [[modified]]
+/// name = "photoshop"
+/// score = 50
+/// confidence = 50
+/// ```
+///
+/// A generator is checked against `generated` first and only falls back to
+/// `modified` when no rule there matched. The defaults and rules files may
+/// also be a `KnowledgeBase`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GeneratorRules {
+    #[serde(default)]
+    pub generated: Vec<GeneratorRule>,
+    #[serde(default)]
+    pub modified: Vec<GeneratorRule>,
+    /// Certificates of AI services, checked against the signing
+    /// certificate of the active manifest.
+    #[serde(default)]
+    pub signers: Vec<SignerEntry>
+}
+
+impl GeneratorRules {
+    /// Reads a rules file, TOML if the extension is `.toml` and JSON
+    /// otherwise, in the layout above or of a `KnowledgeBase`.
+    pub fn from_file(path: &Path) -> Result<GeneratorRules, String> {
+        match KnowledgeBase::from_file(path)?.rules() {
+            Ok(rules) => Ok(rules),
+            Err(e) => Err(format!("Error in rules file {}: {}", path.display(), e))
+        }
+    }
+
+    /// The first rule a claim generator falls under, if any.
+    pub fn find(&self, generator: &str) -> Option<(RuleKind, &GeneratorRule)> {
+        let normalized = normalize(generator);
+        let generated = self.generated.iter().map(|rule| (RuleKind::Generated, rule));
+        let modified = self.modified.iter().map(|rule| (RuleKind::Modified, rule));
+        generated.chain(modified).find(|(_, rule)| rule.matches(generator, &normalized))
+    }
+
+    /// A rule of a tool that signs what it writes matching `tool`, even
+    /// when another rule is found first.
+    pub fn find_content_credentials(&self, tool: &str) -> Option<&GeneratorRule> {
+        let normalized = normalize(tool);
+        self.generated.iter().chain(self.modified.iter()).find(|rule| rule.content_credentials && rule.matches(tool, &normalized))
+    }
+
+    /// The signer entry of a certificate by its SHA-256 fingerprint and
+    /// subject. Only pass certificates of valid signatures.
+    pub fn find_signer(&self, sha256: &str, subject: &str) -> Option<&SignerEntry> {
+        let fingerprint = normalize_fingerprint(sha256);
+        let organization = subject_organization(subject);
+        self.signers.iter().find(|s| {
+            (s.sha256.is_some() || s.organization.is_some())
+                && s.sha256.as_deref().is_none_or(|f| normalize_fingerprint(f) == fingerprint)
+                && s.organization.as_deref().is_none_or(|o| organization.is_some_and(|org| org.eq_ignore_ascii_case(o.trim())))
+        })
+    }
+
+    /// Matches every generator of every claim, at most one rule each.
+    pub fn match_claims(&self, claims: &[ClaimData]) -> Vec<RuleMatch> {
+        let mut matches = Vec::new();
+        for claim in claims {
+            for generator in &claim.claim_generator {
+                if let Some((kind, rule)) = self.find(generator) {
+                    matches.push(RuleMatch {
+                        claim_id: claim.claim_id.clone(),
+                        generator: generator.clone(),
+                        kind,
+                        rule: rule.name.clone(),
+                        pattern: rule.pattern.clone(),
+                        vendor: rule.vendor.clone(),
+                        category: rule.category,
+                        score: rule.score,
+                        confidence: rule.confidence
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+impl Default for GeneratorRules {
+    /// The rules of `KnowledgeBase::builtin`.
+    fn default() -> GeneratorRules {
+        KnowledgeBase::builtin().rules().unwrap_or(GeneratorRules { generated: Vec::new(), modified: Vec::new(), signers: Vec::new() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(generated: Vec<GeneratorRule>, modified: Vec<GeneratorRule>) -> GeneratorRules {
+        GeneratorRules { generated, modified, signers: Vec::new() }
+    }
+
+    #[test]
+    fn generators_are_normalized() {
+        assert_eq!(normalize("Adobe Firefly 3.0"), "adobe firefly 3 0");
+        assert_eq!(normalize("ChatGPT-4o"), "chatgpt 4o");
+        assert_eq!(normalize("DALL·E 3"), "dall e 3");
+        assert_eq!(normalize("  --  "), "");
+    }
+
+    #[test]
+    fn builtin_rules_match_whole_words() {
+        let rules = GeneratorRules::default();
+        for (generator, rule) in [
+            ("Adobe Firefly 3.0", Some("adobe firefly")),
+            ("ChatGPT-4o", Some("chatgpt")),
+            ("DALL·E 3", Some("dall-e")),
+            ("Midjourney v6", Some("midjourney")),
+            ("midjourneyish", None),
+            ("Fireflyer", None)
+        ] {
+            assert_eq!(rules.find(generator).map(|(_, r)| r.name.as_str()), rule, "{}", generator);
+        }
+    }
+
+    #[test]
+    fn patterns_are_case_insensitive() {
+        let rules = rules(vec![GeneratorRule::with_pattern("gpt-4 family", "^(chat)?gpt-?4", 100, 50).unwrap()], Vec::new());
+        assert!(rules.find("ChatGPT-4o").is_some());
+        assert!(rules.find("GPT4 image").is_some());
+        assert!(rules.find("Not GPT-4").is_none());
+        assert!(GeneratorRule::with_pattern("broken", "(", 100, 50).is_err());
+    }
+
+    #[test]
+    fn generated_wins_over_modified() {
+        let rules = rules(vec![GeneratorRule::new("firefly", 100, 50)], vec![GeneratorRule::new("adobe", 50, 50)]);
+        assert_eq!(rules.find("Adobe Firefly").map(|(kind, r)| (kind, r.name.as_str())), Some((RuleKind::Generated, "firefly")));
+        assert_eq!(rules.find("Adobe Photoshop").map(|(kind, r)| (kind, r.name.as_str())), Some((RuleKind::Modified, "adobe")));
+        assert!(rules.find("GIMP").is_none());
+    }
+
+    #[test]
+    fn claims_record_their_matches() {
+        let mut rule = GeneratorRule::new("firefly", 100, 60);
+        rule.vendor = Some(String::from("Adobe"));
+        let rules = rules(vec![rule], vec![GeneratorRule::new("photoshop", 50, 40)]);
+        let claims = [
+            ClaimData::new(String::from("first"), String::from("Adobe"), vec![String::from("Adobe Firefly 2"), String::from("GIMP")]),
+            ClaimData::new(String::from("second"), String::from("Adobe"), vec![String::from("Photoshop 25.0")])
+        ];
+        let matches = rules.match_claims(&claims);
+        assert_eq!(matches.len(), 2);
+        assert_eq!((matches[0].claim_id.as_str(), matches[0].generator.as_str(), matches[0].kind), ("first", "Adobe Firefly 2", RuleKind::Generated));
+        assert_eq!((matches[0].rule.as_str(), matches[0].vendor.as_deref(), matches[0].score, matches[0].confidence), ("firefly", Some("Adobe"), 100, 60));
+        assert_eq!((matches[1].claim_id.as_str(), matches[1].rule.as_str(), matches[1].kind, matches[1].score), ("second", "photoshop", RuleKind::Modified, 50));
+    }
+}
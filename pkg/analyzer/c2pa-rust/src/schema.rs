@@ -0,0 +1,9 @@
+/// Version of the report JSON, as `major.minor`. Within a major version
+/// fields and enum values are only added, never removed, renamed or
+/// retyped, so a consumer checks the major and ignores what it doesn't
+/// know. A minor bump marks additions.
+pub const SCHEMA_VERSION: &str = "1.19";
+
+/// JSON Schema (draft 2020-12) of `Report` at `SCHEMA_VERSION`, printed by
+/// `c2pa-rust schema`.
+pub const REPORT_SCHEMA: &str = include_str!("../data/report.schema.json");
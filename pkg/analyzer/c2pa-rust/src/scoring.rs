@@ -0,0 +1,265 @@
+use std::{collections::BTreeMap, fs, path::Path};
+use serde::{Deserialize, Serialize};
+
+/// What a finding adds to the report's score and confidence.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Weight {
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl Weight {
+    pub const fn new(score: u8, confidence: u8) -> Weight {
+        Weight { score, confidence }
+    }
+}
+
+/// Weights of the manifest findings and the analyzers, and the verdict
+/// cutoffs. Loaded from a JSON or TOML file, missing keys keep their
+/// defaults, ex.
+///
+/// ```toml
+/// generated_from = 75
+///
+/// [declared_generated]
+/// score = 100
+/// confidence = 60
+///
+/// [analyzer_weights]
+/// pixel = 50
+/// ```
+///
+/// Generator rules carry their own weights, see `GeneratorRules`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ScoringConfig {
+    /// The file has at least one claim.
+    pub claims_found: Weight,
+    /// An action declares generated content, ex. `trainedAlgorithmicMedia`.
+    pub declared_generated: Weight,
+    /// An action declares a composite with generated content.
+    pub declared_composite: Weight,
+    /// Actions edit the content, without generated sources.
+    pub edited: Weight,
+    /// An ingredient's own analysis is Generated, with
+    /// `analyze_ingredients`.
+    pub ingredient_generated: Weight,
+    /// A claim redacts the actions of a manifest it builds on.
+    pub actions_redacted: Weight,
+    /// The manifest is signed, on top of one of the three below.
+    pub signed: Weight,
+    pub signature_valid: Weight,
+    pub signature_trusted: Weight,
+    pub signature_invalid: Weight,
+    /// A certificate of the signing chain is revoked, the signature then
+    /// also counts as invalid.
+    pub signature_revoked: Weight,
+    /// A claim is signed with a weak key or a chain using a deprecated
+    /// digest, ex. SHA-1.
+    pub weak_signature: Weight,
+    /// The asset doesn't match a hard binding, it changed after signing.
+    pub content_altered: Weight,
+    /// The claim thumbnail doesn't look like the delivered image, the
+    /// manifest may come from another one. Scored like `content_altered`,
+    /// the manifest doesn't describe these pixels, with less confidence as
+    /// a perceptual hash is a looser check than a hard binding.
+    pub thumbnail_mismatch: Weight,
+    /// A file without a manifest store holds traces of one, the
+    /// confidence scaled by theirs.
+    pub provenance_stripped: Weight,
+    /// The capture, signing and file times or the camera settings
+    /// contradict each other, see `ConsistencyFindings`.
+    pub metadata_inconsistent: Weight,
+    /// Probability of an `MlAnalyzer` classifier, the score scaled by the
+    /// probability and the confidence by its distance from 0.5.
+    pub ml_classifier: Weight,
+    /// Weight in percent of an analyzer's vote by its name, ex. `pixel`,
+    /// 100 for analyzers not listed and 0 to ignore one.
+    pub analyzer_weights: BTreeMap<String, u8>,
+    /// Analyzers with less confidence abstain from the vote.
+    pub abstain_below: u8,
+    /// Votes from this on are strong evidence, the report scores at least
+    /// as high as the highest of them, see `ensemble`.
+    pub strong_from: u8,
+    /// Scores below are Genuine, or Modified without enough confidence.
+    pub genuine_below: u8,
+    /// Confidence a score below `genuine_below` needs to be Genuine.
+    pub genuine_confidence: u8,
+    /// Scores from here on are Generated, the rest Modified.
+    pub generated_from: u8
+}
+
+impl Default for ScoringConfig {
+    fn default() -> ScoringConfig {
+        ScoringConfig {
+            claims_found: Weight::new(1, 1),
+            declared_generated: Weight::new(100, 80),
+            declared_composite: Weight::new(60, 40),
+            edited: Weight::new(30, 20),
+            ingredient_generated: Weight::new(60, 40),
+            actions_redacted: Weight::new(60, 40),
+            signed: Weight::new(20, 20),
+            signature_valid: Weight::new(0, 40),
+            signature_trusted: Weight::new(0, 60),
+            signature_invalid: Weight::new(60, 20),
+            signature_revoked: Weight::new(40, 40),
+            weak_signature: Weight::new(20, 10),
+            content_altered: Weight::new(40, 40),
+            thumbnail_mismatch: Weight::new(40, 30),
+            provenance_stripped: Weight::new(40, 40),
+            metadata_inconsistent: Weight::new(40, 30),
+            ml_classifier: Weight::new(100, 60),
+            analyzer_weights: BTreeMap::new(),
+            abstain_below: 1,
+            strong_from: 80,
+            genuine_below: 21,
+            genuine_confidence: 41,
+            generated_from: 81
+        }
+    }
+}
+
+impl ScoringConfig {
+    /// Reads a scoring file, TOML if the extension is `.toml` and JSON
+    /// otherwise.
+    pub fn from_file(path: &Path) -> Result<ScoringConfig, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Error reading scoring file {}: {}", path.display(), e))
+        };
+        let is_toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        let config: Result<ScoringConfig, String> = if is_toml {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        };
+        match config {
+            Ok(c) => c.checked().map_err(|e| format!("Error in scoring file {}: {}", path.display(), e)),
+            Err(e) => Err(format!("Error parsing scoring file {}: {}", path.display(), e))
+        }
+    }
+
+    /// Overrides one value, `key` is a field name, `<field>.score` or
+    /// `<field>.confidence`, or `analyzer_weights.<analyzer>`, ex.
+    /// `declared_generated.score`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let number: u8 = match value.trim().parse() {
+            Ok(n) => n,
+            Err(_) => return Err(format!("Scoring value for {} must be 0-100, got '{}'", key, value))
+        };
+        if let Some(analyzer) = key.strip_prefix("analyzer_weights.") {
+            let mut config = self.clone();
+            config.analyzer_weights.insert(analyzer.to_string(), number);
+            *self = config.checked()?;
+            return Ok(());
+        }
+        let mut json = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        let slot = key.split('.').try_fold(&mut json, |node, part| node.get_mut(part));
+        match slot {
+            Some(slot) if slot.is_number() => *slot = number.into(),
+            _ => return Err(format!("Unknown scoring key '{}'", key))
+        }
+        *self = serde_json::from_value::<ScoringConfig>(json).map_err(|e| e.to_string())?.checked()?;
+        Ok(())
+    }
+
+    pub fn analyzer_weight(&self, name: &str) -> u8 {
+        self.analyzer_weights.get(name).copied().unwrap_or(100)
+    }
+
+    /// Every weight, weight in percent and cutoff is 0-100.
+    fn checked(self) -> Result<ScoringConfig, String> {
+        let json = serde_json::to_value(&self).map_err(|e| e.to_string())?;
+        if let Some((key, value)) = above_100(&json, "") {
+            return Err(format!("{} ({}) must be 0-100", key, value));
+        }
+        if self.genuine_below > self.generated_from {
+            return Err(format!(
+                "genuine_below ({}) can't be above generated_from ({})",
+                self.genuine_below, self.generated_from
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// Key and value of the first number over 100, ex. `signed.score`.
+fn above_100(value: &serde_json::Value, key: &str) -> Option<(String, u64)> {
+    match value {
+        serde_json::Value::Object(fields) => fields.iter().find_map(|(name, value)| match key.is_empty() {
+            true => above_100(value, name),
+            false => above_100(value, &format!("{}.{}", key, name))
+        }),
+        serde_json::Value::Number(n) => n.as_u64().filter(|&n| n > 100).map(|n| (key.to_string(), n)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_set_by_key() {
+        let mut config = ScoringConfig::default();
+        config.set("declared_generated.score", "90").unwrap();
+        config.set("declared_generated.confidence", " 70 ").unwrap();
+        config.set("generated_from", "75").unwrap();
+        config.set("analyzer_weights.pixel", "50").unwrap();
+        assert_eq!(config.declared_generated, Weight::new(90, 70));
+        assert_eq!(config.generated_from, 75);
+        assert_eq!((config.analyzer_weight("pixel"), config.analyzer_weight("c2pa")), (50, 100));
+
+        let before = config.clone();
+        assert!(config.set("declared_generated.weight", "10").unwrap_err().contains("Unknown scoring key"));
+        assert!(config.set("no_such_field", "10").is_err());
+        // A weight, not a number
+        assert!(config.set("declared_generated", "10").is_err());
+        assert!(config.set("generated_from", "many").is_err());
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn values_over_100_are_rejected() {
+        let mut config = ScoringConfig::default();
+        for key in ["signed.score", "signed.confidence", "genuine_confidence", "strong_from", "analyzer_weights.pixel"] {
+            let error = config.set(key, "255").unwrap_err();
+            assert!(error.contains(key) && error.contains("0-100"), "{}", error);
+        }
+        assert_eq!(config, ScoringConfig::default());
+        assert!(config.set("genuine_below", "90").unwrap_err().contains("generated_from"));
+        config.set("signed.score", "100").unwrap();
+    }
+
+    #[test]
+    fn files_round_trip_and_are_checked() {
+        let dir = std::env::temp_dir().join(format!("c2pa-scoring-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let defaults = ScoringConfig::default();
+        let toml_path = dir.join("scoring.toml");
+        fs::write(&toml_path, toml::to_string(&defaults).unwrap()).unwrap();
+        assert_eq!(ScoringConfig::from_file(&toml_path).unwrap(), defaults);
+        let json_path = dir.join("scoring.json");
+        fs::write(&json_path, serde_json::to_string(&defaults).unwrap()).unwrap();
+        assert_eq!(ScoringConfig::from_file(&json_path).unwrap(), defaults);
+
+        fs::write(&toml_path, "generated_from = 75\n\n[analyzer_weights]\npixel = 50\n").unwrap();
+        let partial = ScoringConfig::from_file(&toml_path).unwrap();
+        assert_eq!((partial.generated_from, partial.analyzer_weight("pixel"), partial.signed), (75, 50, defaults.signed));
+
+        let rejected = [
+            ("scoring.toml", "[declared_generated]\nscore = 255\nconfidence = 80\n"),
+            ("scoring.toml", "genuine_below = 90\ngenerated_from = 50\n"),
+            ("scoring.toml", "unknown = 1\n"),
+            ("scoring.json", r#"{"strong_from": 101}"#),
+            ("scoring.json", r#"{"genuine_confidence": 300}"#)
+        ];
+        for (name, content) in rejected {
+            fs::write(dir.join(name), content).unwrap();
+            assert!(ScoringConfig::from_file(&dir.join(name)).is_err(), "{}", content);
+        }
+        assert!(ScoringConfig::from_file(&dir.join("missing.toml")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,289 @@
+use std::{io::{Cursor, Read}, sync::Arc, thread};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{input, options::AnalysisOptions, report::Report};
+
+/// Uploads above, or above the options' `max_input_size`, are refused
+/// with 413.
+pub const MAX_BODY_SIZE: usize = 256 * 1024 * 1024;
+
+/// Serves `POST /analyze` and `GET /health` on `listen`, ex.
+/// `0.0.0.0:9090`, until the process ends. Requests are handled by one
+/// thread per core.
+///
+/// `/analyze` takes the file as the first file part of a
+/// `multipart/form-data` body, or as the raw body. The format is told by
+/// the part's file name, the `name` query parameter, ex.
+/// `/analyze?name=upload.jpg`, or else the `Content-Type`, ex. `image/png`.
+pub fn serve(listen: &str, options: AnalysisOptions) -> Result<(), String> {
+    let server = match Server::http(listen) {
+        Ok(s) => Arc::new(s),
+        Err(e) => return Err(format!("Error listening on {}: {}", listen, e))
+    };
+    eprintln!("Listening on {}", listen);
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let server = server.clone();
+            let options = options.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(request, &options);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(())
+}
+
+fn handle(mut request: Request, options: &AnalysisOptions) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let response = match (request.method(), path) {
+        (Method::Get, "/health") => json_response(200, json!({"status": "ok"}).to_string()),
+        (Method::Post, "/analyze") => match analyze(&mut request, query, options) {
+            Ok(report) => {
+                let json = match &options.report_signer {
+                    Some(signer) => signer.sign_report(&report).map(|r| r.to_string()),
+                    None => serde_json::to_string(&report).map_err(|e| e.to_string())
+                };
+                match json {
+                    Ok(j) => json_response(200, j),
+                    Err(e) => error_response(500, &e)
+                }
+            },
+            Err((status, e)) => error_response(status, &e)
+        },
+        (_, "/analyze") | (_, "/health") => error_response(405, "Method not allowed"),
+        _ => error_response(404, "Not found")
+    };
+    let _ = request.respond(response);
+}
+
+fn analyze(request: &mut Request, query: &str, options: &AnalysisOptions) -> Result<Report, (u16, String)> {
+    // A smaller limit of the options applies to the body too
+    let limit = options.max_input_size.map_or(MAX_BODY_SIZE, |l| l.min(MAX_BODY_SIZE as u64) as usize);
+    if request.body_length().is_some_and(|l| l > limit) {
+        return Err((413, format!("Upload over {} bytes", limit)));
+    }
+    let mut body = Vec::new();
+    let read = request.as_reader().take(limit as u64 + 1).read_to_end(&mut body);
+    match read {
+        Ok(_) if body.len() > limit => return Err((413, format!("Upload over {} bytes", limit))),
+        Ok(_) => {},
+        Err(e) => return Err((400, format!("Error reading upload: {}", e)))
+    }
+    let content_type = header(request, "Content-Type").unwrap_or_default();
+
+    let (name, bytes) = match boundary(&content_type) {
+        Some(boundary) => match file_part(&body, &boundary) {
+            Some((name, bytes)) => (name, bytes.to_vec()),
+            None => return Err((400, String::from("No file part in the multipart body")))
+        },
+        None => (query_param(query, "name"), body)
+    };
+    if bytes.is_empty() {
+        return Err((400, String::from("Empty upload")));
+    }
+    // Parameters such as the charset aren't part of the format
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    match name {
+        Some(name) => match input::bare_name(&name) {
+            Some(name) => Ok(Report::from_bytes_lenient(name, &bytes, options)),
+            None => Err((400, format!("Invalid file name {}", name)))
+        },
+        None if !mime.is_empty() && mime != "application/octet-stream" => {
+            let mut report = Report::from_stream_lenient(mime, Cursor::new(bytes), options);
+            report.file_name = String::from("upload");
+            Ok(report)
+        },
+        None => Err((400, String::from("Name the file with ?name= or set a Content-Type")))
+    }
+}
+
+fn header(request: &Request, name: &str) -> Option<String> {
+    request.headers().iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn boundary(content_type: &str) -> Option<String> {
+    if !content_type.starts_with("multipart/form-data") {
+        return None;
+    }
+    content_type.split(';')
+        .filter_map(|p| p.trim().strip_prefix("boundary="))
+        .map(|b| b.trim_matches('"').to_string())
+        .next()
+}
+
+/// File name and content of the first part with a file name, or else of
+/// the part named `file`.
+fn file_part<'a>(body: &'a [u8], boundary: &str) -> Option<(Option<String>, &'a [u8])> {
+    let delimiter = format!("\r\n--{}", boundary);
+    // The first delimiter may start the body without the line break, or
+    // follow a preamble
+    let mut rest = match body.strip_prefix(&delimiter.as_bytes()[2..]) {
+        Some(rest) => rest,
+        None => &body[find(body, delimiter.as_bytes())? + delimiter.len()..]
+    };
+    let mut named_file = None;
+    while let Some(part_end) = find(rest, delimiter.as_bytes()) {
+        let part = rest[..part_end].strip_prefix(b"\r\n")?;
+        rest = &rest[part_end + delimiter.len()..];
+        let header_end = find(part, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&part[..header_end]).to_string();
+        let content = &part[header_end + 4..];
+        let disposition = headers.lines()
+            .find(|l| l.to_ascii_lowercase().starts_with("content-disposition:"))
+            .unwrap_or_default();
+        let filename = disposition_param(disposition, "filename");
+        if filename.is_some() {
+            return Some((filename, content));
+        }
+        if named_file.is_none() && disposition_param(disposition, "name").as_deref() == Some("file") {
+            named_file = Some((None, content));
+        }
+    }
+    named_file
+}
+
+fn disposition_param(disposition: &str, key: &str) -> Option<String> {
+    disposition.split(';')
+        .filter_map(|p| p.trim().strip_prefix(key)?.strip_prefix('='))
+        .map(|v| v.trim_matches('"').to_string())
+        .find(|v| !v.is_empty())
+}
+
+/// The percent-decoded value of `key`, ex. `my photo.jpg` of
+/// `name=my%20photo.jpg`.
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&')
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| percent_decode(v))
+        .filter(|v| !v.is_empty())
+}
+
+/// Decodes `%XX` escapes and `+` as a space, leaving invalid escapes
+/// as they are.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            },
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte)
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn json_response(status: u16, body: String) -> Response<Cursor<Vec<u8>>> {
+    let response = Response::from_data(body.into_bytes()).with_status_code(status);
+    match Header::from_bytes("Content-Type", "application/json") {
+        Ok(content_type) => response.with_header(content_type),
+        Err(_) => response
+    }
+}
+
+fn error_response(status: u16, error: &str) -> Response<Cursor<Vec<u8>>> {
+    json_response(status, json!({"error": error}).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, net::TcpStream};
+    use super::*;
+
+    const PART: &str = "Content-Disposition: form-data; name=\"upload\"; filename=\"a.jpg\"\r\n\r\njpeg";
+
+    #[test]
+    fn query_params_are_percent_decoded() {
+        assert_eq!(query_param("name=my%20photo.jpg", "name").as_deref(), Some("my photo.jpg"));
+        assert_eq!(query_param("x=1&name=caf%C3%A9+1.png", "name").as_deref(), Some("café 1.png"));
+        assert_eq!(query_param("name=100%.jpg", "name").as_deref(), Some("100%.jpg"));
+        assert_eq!(query_param("name=", "name"), None);
+        assert_eq!(query_param("other=a.jpg", "name"), None);
+    }
+
+    #[test]
+    fn boundaries_may_be_quoted() {
+        assert_eq!(boundary("multipart/form-data; boundary=abc").as_deref(), Some("abc"));
+        assert_eq!(boundary("multipart/form-data; charset=utf-8; boundary=\"a b\"").as_deref(), Some("a b"));
+        assert_eq!(boundary("image/png"), None);
+        assert_eq!(boundary("multipart/form-data"), None);
+    }
+
+    #[test]
+    fn file_parts_are_found() {
+        // No preamble, the body starting with the first delimiter
+        let body = format!("--b\r\n{}\r\n--b--\r\n", PART);
+        assert_eq!(file_part(body.as_bytes(), "b"), Some((Some(String::from("a.jpg")), &b"jpeg"[..])));
+        // A preamble and a field before the file
+        let body = format!("preamble\r\n--b\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhi\r\n--b\r\n{}\r\n--b--", PART);
+        assert_eq!(file_part(body.as_bytes(), "b"), Some((Some(String::from("a.jpg")), &b"jpeg"[..])));
+        // The part named file when no part has a file name
+        let body = "--b\r\ncontent-disposition: form-data; name=\"note\"\r\n\r\nhi\r\n--b\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\npng\r\n--b--";
+        assert_eq!(file_part(body.as_bytes(), "b"), Some((None, &b"png"[..])));
+        // filename*= isn't read as filename
+        let body = "--b\r\nContent-Disposition: form-data; name=\"file\"; filename*=UTF-8''a.jpg\r\n\r\njpeg\r\n--b--";
+        assert_eq!(file_part(body.as_bytes(), "b"), Some((None, &b"jpeg"[..])));
+        assert_eq!(disposition_param("form-data; filename*=UTF-8''a.jpg", "filename"), None);
+        assert_eq!(disposition_param("form-data; filename=\"a.jpg\"", "name"), None);
+    }
+
+    #[test]
+    fn truncated_bodies_have_no_file_part() {
+        let body = format!("--b\r\n{}", PART);
+        assert_eq!(file_part(body.as_bytes(), "b"), None);
+        let body = format!("--other\r\n{}\r\n--other--", PART);
+        assert_eq!(file_part(body.as_bytes(), "b"), None);
+    }
+
+    /// The status of a request to a server handling one request.
+    fn status(options: AnalysisOptions, request: &[u8]) -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        let handler = thread::spawn(move || handle(server.recv().unwrap(), &options));
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        handler.join().unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn oversize_bodies_are_refused() {
+        let options = AnalysisOptions::default().with_max_input_size(16);
+        let body = [0u8; 32];
+        let mut sized = b"POST /analyze?name=a.jpg HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 32\r\n\r\n".to_vec();
+        sized.extend_from_slice(&body);
+        assert_eq!(status(options.clone(), &sized), "HTTP/1.1 413 Payload Too Large");
+        // Without a length the body is cut off while read
+        let mut chunked = b"POST /analyze?name=a.jpg HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nTransfer-Encoding: chunked\r\n\r\n20\r\n".to_vec();
+        chunked.extend_from_slice(&body);
+        chunked.extend_from_slice(b"\r\n0\r\n\r\n");
+        assert_eq!(status(options, &chunked), "HTTP/1.1 413 Payload Too Large");
+        assert_eq!(status(AnalysisOptions::default(), b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"), "HTTP/1.1 200 OK");
+    }
+}
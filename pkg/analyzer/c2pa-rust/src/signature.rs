@@ -0,0 +1,150 @@
+use c2pa::SignatureInfo;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use x509_parser::pem::Pem;
+
+use crate::certchain::ChainCertificate;
+
+/// Timestamps before the first C2PA release can't come from a real
+/// signature.
+const EARLIEST_PLAUSIBLE: &str = "2021-01-01T00:00:00Z";
+/// Clock skew allowed before a timestamp counts as being in the future.
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+
+/// Something off about when the active manifest was signed.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureFlag {
+    /// The timestamp is outside the signing certificate's validity window.
+    OutsideCertValidity,
+    /// The timestamp is in the future.
+    FutureTimestamp,
+    /// The timestamp predates C2PA.
+    ImplausibleTimestamp,
+    /// No timestamp and the certificate has expired, so nothing shows the
+    /// signature was made while it was valid.
+    ExpiredWithoutTimestamp,
+    /// The certificate isn't valid yet.
+    CertNotYetValid
+}
+
+/// EC curves C2PA signatures may use.
+const ALLOWED_CURVES: [&str; 3] = ["prime256v1", "secp384r1", "secp521r1"];
+/// Digests certificates mustn't be signed with any more.
+const DEPRECATED_DIGESTS: [&str; 3] = ["md2", "md5", "sha1"];
+
+/// The algorithm and key a claim was signed with.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ClaimSignature {
+    /// ex. `es256` or `ed25519`
+    pub alg: Option<String>,
+    /// `RSA`, `EC` or `Ed25519`, of the signing certificate.
+    pub key_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_curve: Option<String>,
+    pub key_bits: usize,
+    /// Why the key or the chain is weak or deprecated, ex. `RSA key of 1024
+    /// bits`, empty when neither is.
+    pub weaknesses: Vec<String>
+}
+
+impl ClaimSignature {
+    pub fn from_info(info: &SignatureInfo) -> ClaimSignature {
+        let chain = ChainCertificate::vec_from_pem(info.cert_chain());
+        let signing = chain.first();
+        let mut weaknesses = Vec::new();
+        if let Some(cert) = signing {
+            match (cert.key_type.as_str(), &cert.key_curve) {
+                ("RSA", _) if cert.key_bits < 2048 => weaknesses.push(format!("RSA key of {} bits", cert.key_bits)),
+                ("EC", Some(curve)) if !ALLOWED_CURVES.contains(&curve.as_str()) => weaknesses.push(format!("EC key on curve {}", curve)),
+                ("EC", _) if cert.key_bits < 256 => weaknesses.push(format!("EC key of {} bits", cert.key_bits)),
+                _ => ()
+            }
+        }
+        for cert in &chain {
+            let alg = cert.signature_algorithm.to_ascii_lowercase();
+            if DEPRECATED_DIGESTS.iter().any(|d| alg.contains(d)) {
+                weaknesses.push(format!("certificate {} signed with {}", cert.subject, cert.signature_algorithm));
+            }
+        }
+        ClaimSignature {
+            alg: info.alg.map(|a| a.to_string()),
+            key_type: signing.map(|c| c.key_type.clone()),
+            key_curve: signing.and_then(|c| c.key_curve.clone()),
+            key_bits: signing.map(|c| c.key_bits).unwrap_or_default(),
+            weaknesses
+        }
+    }
+}
+
+/// When and with which certificate the active manifest was signed.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct SignatureData {
+    pub issuer: Option<String>,
+    pub cert_serial_number: Option<String>,
+    /// RFC 3339, from the time-stamp authority token. C2PA claims carry no
+    /// other signing time, so this is None when the signature wasn't
+    /// timestamped.
+    pub signing_time: Option<String>,
+    pub tsa_timestamp: bool,
+    /// Validity window of the signing certificate, RFC 3339.
+    pub cert_not_before: Option<String>,
+    pub cert_not_after: Option<String>,
+    pub flags: Vec<SignatureFlag>
+}
+
+impl SignatureData {
+    pub fn from_info(info: &SignatureInfo) -> SignatureData {
+        let now = Utc::now();
+        let signing_time = info.time.as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc));
+        let validity = signing_cert_validity(info.cert_chain());
+
+        let mut flags = Vec::new();
+        if let Some(time) = signing_time {
+            if let Some((not_before, not_after)) = validity {
+                if time < not_before || time > not_after {
+                    flags.push(SignatureFlag::OutsideCertValidity);
+                }
+            }
+            if (time - now).num_seconds() > MAX_CLOCK_SKEW_SECS {
+                flags.push(SignatureFlag::FutureTimestamp);
+            }
+            if EARLIEST_PLAUSIBLE.parse::<DateTime<Utc>>().is_ok_and(|earliest| time < earliest) {
+                flags.push(SignatureFlag::ImplausibleTimestamp);
+            }
+        }
+        if let Some((not_before, not_after)) = validity {
+            if signing_time.is_none() && now > not_after {
+                flags.push(SignatureFlag::ExpiredWithoutTimestamp);
+            }
+            if now < not_before {
+                flags.push(SignatureFlag::CertNotYetValid);
+            }
+        }
+
+        SignatureData {
+            issuer: info.issuer.clone(),
+            cert_serial_number: info.cert_serial_number.clone(),
+            signing_time: signing_time.map(|t| t.to_rfc3339()),
+            tsa_timestamp: signing_time.is_some(),
+            cert_not_before: validity.map(|(not_before, _)| not_before.to_rfc3339()),
+            cert_not_after: validity.map(|(_, not_after)| not_after.to_rfc3339()),
+            flags
+        }
+    }
+}
+
+/// notBefore and notAfter of the first certificate of a PEM chain, the
+/// signing one.
+fn signing_cert_validity(chain: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let pem = Pem::iter_from_buffer(chain.as_bytes()).next()?.ok()?;
+    let cert = pem.parse_x509().ok()?;
+    let validity = cert.validity();
+    let not_before = DateTime::from_timestamp(validity.not_before.timestamp(), 0)?;
+    let not_after = DateTime::from_timestamp(validity.not_after.timestamp(), 0)?;
+    Some((not_before, not_after))
+}
@@ -0,0 +1,162 @@
+use serde::Serialize;
+
+use crate::{actions::{COMPOSITE_SOURCE_TYPES, GENERATED_SOURCE_TYPES, SOURCE_TYPE_PREFIX}, jpeg::jpeg_segments, xmp::XmpFindings};
+
+/// A declared synthetic source type is as strong as a C2PA action saying
+/// the same, it only ever gets written by the generating tool.
+const SYNTHETIC: (u8, u8) = (100, 80);
+const COMPOSITE: (u8, u8) = (60, 50);
+
+/// An IPTC digital source type found outside the manifest store.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct SourceTypeHit {
+    /// Code without the URI prefix, ex. `trainedAlgorithmicMedia`.
+    pub source_type: String,
+    /// `xmp` or `iptc`, the IIM block of a JPEG's APP13 segment.
+    pub found_in: String
+}
+
+/// IPTC digital source types of the file. Several generators write them
+/// even when no C2PA manifest survives recompression.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct SourceTypeFindings {
+    pub hits: Vec<SourceTypeHit>,
+    /// Some hit declares the whole image generated.
+    pub synthetic: bool,
+    /// Some hit declares generated content composited in.
+    pub composite: bool,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl SourceTypeFindings {
+    /// None when neither XMP nor IPTC declare a source type.
+    pub fn from_bytes(bytes: &[u8], xmp: Option<&XmpFindings>) -> Option<SourceTypeFindings> {
+        let mut hits = Vec::new();
+        if let Some(source_type) = xmp.and_then(|x| x.digital_source_type.as_ref()) {
+            hits.push(SourceTypeHit { source_type: source_type.clone(), found_in: String::from("xmp") });
+        }
+        for value in iptc_values(bytes) {
+            if let Some(source_type) = value.strip_prefix(SOURCE_TYPE_PREFIX) {
+                hits.push(SourceTypeHit { source_type: source_type.to_string(), found_in: String::from("iptc") });
+            }
+        }
+        if hits.is_empty() {
+            return None;
+        }
+
+        let synthetic = hits.iter().any(|h| GENERATED_SOURCE_TYPES.contains(&h.source_type.as_str()));
+        let composite = hits.iter().any(|h| COMPOSITE_SOURCE_TYPES.contains(&h.source_type.as_str()));
+        let (score, confidence) = if synthetic {
+            SYNTHETIC
+        } else if composite {
+            COMPOSITE
+        } else {
+            (0, 0)
+        };
+        Some(SourceTypeFindings { hits, synthetic, composite, score, confidence })
+    }
+}
+
+/// Text values of the IPTC-IIM datasets in a JPEG's Photoshop APP13
+/// segment.
+fn iptc_values(bytes: &[u8]) -> Vec<String> {
+    let mut values = Vec::new();
+    for segment in jpeg_segments(bytes, 0xED) {
+        let resources = match segment.strip_prefix(b"Photoshop 3.0\0") {
+            Some(r) => r,
+            None => continue
+        };
+        for (id, data) in photoshop_resources(resources) {
+            // 0x0404 is the IPTC-NAA record
+            if id == 0x0404 {
+                values.extend(iim_values(data));
+            }
+        }
+    }
+    values
+}
+
+/// (id, data) of the `8BIM` image resource blocks.
+fn photoshop_resources(mut bytes: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut resources = Vec::new();
+    while bytes.len() >= 12 && bytes.starts_with(b"8BIM") {
+        let id = u16::from_be_bytes([bytes[4], bytes[5]]);
+        // Pascal string name, padded to an even length with its length byte
+        let name_len = bytes[6] as usize;
+        let name_end = 6 + ((name_len + 2) & !1);
+        if name_end + 4 > bytes.len() {
+            break;
+        }
+        let size = u32::from_be_bytes([bytes[name_end], bytes[name_end + 1], bytes[name_end + 2], bytes[name_end + 3]]) as usize;
+        let data_start = name_end + 4;
+        if data_start + size > bytes.len() {
+            break;
+        }
+        resources.push((id, &bytes[data_start..data_start + size]));
+        bytes = &bytes[(data_start + size + (size & 1)).min(bytes.len())..];
+    }
+    resources
+}
+
+/// Values of the IIM datasets, tag byte 0x1C, record, dataset, length.
+fn iim_values(mut bytes: &[u8]) -> Vec<String> {
+    let mut values = Vec::new();
+    while bytes.len() >= 5 && bytes[0] == 0x1C {
+        let len = u16::from_be_bytes([bytes[3], bytes[4]]) as usize;
+        // extended lengths aren't used for text datasets
+        if len & 0x8000 != 0 || 5 + len > bytes.len() {
+            break;
+        }
+        values.push(String::from_utf8_lossy(&bytes[5..5 + len]).trim().to_string());
+        bytes = &bytes[5 + len..];
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A JPEG whose APP13 carries one IIM dataset with `value`.
+    fn jpeg_with_iptc(value: &str) -> Vec<u8> {
+        let mut iim = vec![0x1C, 2, 228];
+        iim.extend((value.len() as u16).to_be_bytes());
+        iim.extend(value.as_bytes());
+        let mut resources = b"Photoshop 3.0\08BIM\x04\x04\0\0".to_vec();
+        resources.extend((iim.len() as u32).to_be_bytes());
+        resources.extend(&iim);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xED];
+        jpeg.extend(((resources.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(resources);
+        jpeg.extend([0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn reads_iptc_source_type() {
+        let jpeg = jpeg_with_iptc("http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia");
+        let findings = SourceTypeFindings::from_bytes(&jpeg, None).unwrap();
+        assert_eq!(findings.hits[0].source_type, "trainedAlgorithmicMedia");
+        assert_eq!(findings.hits[0].found_in, "iptc");
+        assert!(findings.synthetic);
+        assert_eq!((findings.score, findings.confidence), SYNTHETIC);
+        assert!(SourceTypeFindings::from_bytes(&jpeg_with_iptc("Jane Doe"), None).is_none());
+    }
+
+    #[test]
+    fn truncated_segments_are_skipped() {
+        let value = "http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia";
+        let jpeg = jpeg_with_iptc(value);
+        for end in 0..jpeg.len() {
+            let _ = SourceTypeFindings::from_bytes(&jpeg[..end], None);
+        }
+        // An IIM length past the resource
+        let mut lying = jpeg.clone();
+        let length = lying.len() - 2 - value.len() - 2;
+        lying[length] = 0x7F;
+        assert!(SourceTypeFindings::from_bytes(&lying, None).is_none());
+    }
+}
@@ -0,0 +1,216 @@
+use image::{imageops, DynamicImage};
+use serde::Serialize;
+
+use crate::ela::round;
+
+/// Side of the center crop transformed, smaller images use the largest
+/// power of two that fits.
+const SIZE: u32 = 256;
+const MIN_SIZE: u32 = 64;
+/// Pooled standard deviations over its ring's mean a frequency needs to
+/// count as a peak.
+const PEAK_SIGMA: f64 = 6.0;
+/// Rings closer to the center hold the image content, not upsampling
+/// artifacts.
+const MIN_PEAK_RADIUS: f64 = 1.0 / 8.0;
+/// Confidence of a spectral score above zero, pixel statistics alone are a
+/// weak signal.
+const CONFIDENCE: u8 = 15;
+
+/// Power spectrum of the luma, where upsampling in generative models
+/// leaves periodic peaks away from the center.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct SpectrumFindings {
+    /// Side of the crop transformed.
+    pub size: u32,
+    /// Mean log10 power of each ring, from the center out to the corners
+    /// of the spectrum.
+    pub radial_profile: Vec<f64>,
+    /// Frequencies standing out of their ring by `PEAK_SIGMA`.
+    pub peak_count: usize,
+    /// Largest excess of a frequency over its ring, in standard
+    /// deviations.
+    pub max_peak_sigma: f64,
+    /// Frequencies on the 8 pixel grid of JPEG blocks were left out.
+    pub jpeg_grid_excluded: bool,
+    /// 0-100, how pronounced the peaks are.
+    pub spectral_score: u8,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl SpectrumFindings {
+    /// None when the image is smaller than `MIN_SIZE`. `jpeg` leaves out
+    /// the frequencies of JPEG's block grid.
+    pub fn from_image(image: &DynamicImage, jpeg: bool) -> Option<SpectrumFindings> {
+        let side = image.width().min(image.height()).min(SIZE);
+        if side < MIN_SIZE {
+            return None;
+        }
+        let n = 1_u32 << (31 - side.leading_zeros());
+        let luma = image.to_luma8();
+        let crop = imageops::crop_imm(&luma, (luma.width() - n) / 2, (luma.height() - n) / 2, n, n).to_image();
+
+        let n = n as usize;
+        let window: Vec<f64> = (0..n)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (n - 1) as f64).cos())
+            .collect();
+        let mut grid: Vec<Vec<(f64, f64)>> = (0..n)
+            .map(|y| (0..n).map(|x| (crop.get_pixel(x as u32, y as u32).0[0] as f64 * window[x] * window[y], 0.0)).collect())
+            .collect();
+        fft_2d(&mut grid);
+
+        // log power, shifted so the zero frequency is at the center
+        let half = n / 2;
+        let power = |u: usize, v: usize| {
+            let (re, im) = grid[(v + half) % n][(u + half) % n];
+            (re * re + im * im + 1e-12).log10()
+        };
+        // out to the corners, where the checkerboard of transposed
+        // convolutions peaks
+        let max_radius = (half as f64 * std::f64::consts::SQRT_2).ceil() as usize;
+        let mut rings: Vec<Vec<(usize, usize, f64)>> = vec![Vec::new(); max_radius + 1];
+        for v in 0..n {
+            for u in 0..n {
+                let (du, dv) = (u as f64 - half as f64, v as f64 - half as f64);
+                let r = (du * du + dv * dv).sqrt().round() as usize;
+                rings[r].push((u, v, power(u, v)));
+            }
+        }
+        // Rings past the Nyquist frequency only hold the corners, they are
+        // measured against the last full ring. The spread is pooled over
+        // all rings so a sparse ring can't hide its own peak.
+        let ring_means: Vec<f64> = rings.iter()
+            .map(|ring| ring.iter().map(|p| p.2).sum::<f64>() / ring.len().max(1) as f64)
+            .collect();
+        let grid_step = n / 8;
+        let on_jpeg_grid = |u: usize, v: usize| u.is_multiple_of(grid_step) && v.is_multiple_of(grid_step);
+        let excess: Vec<f64> = rings.iter()
+            .enumerate()
+            .filter(|(r, _)| *r as f64 >= MIN_PEAK_RADIUS * n as f64)
+            .flat_map(|(r, ring)| ring.iter().map(move |p| (r, p)))
+            // the axes carry the structure of the image's horizontals and
+            // verticals
+            .filter(|(_, (u, v, _))| *u != half && *v != half && !(jpeg && on_jpeg_grid(*u, *v)))
+            .map(|(r, (_, _, p))| p - ring_means[r.min(half)])
+            .collect();
+        let stddev = (excess.iter().map(|e| e * e).sum::<f64>() / excess.len().max(1) as f64).sqrt();
+        let sigmas: Vec<f64> = match stddev > 0.0 {
+            true => excess.iter().map(|e| e / stddev).collect(),
+            false => Vec::new()
+        };
+        let peak_count = sigmas.iter().filter(|s| **s > PEAK_SIGMA).count();
+        let max_peak_sigma = sigmas.iter().cloned().fold(0.0_f64, f64::max);
+
+        let spectral_score = ((max_peak_sigma - PEAK_SIGMA).max(0.0) * 15.0).min(100.0) as u8;
+        let (score, confidence) = if spectral_score > 0 { (spectral_score / 3, CONFIDENCE) } else { (0, 0) };
+        Some(SpectrumFindings {
+            size: n as u32,
+            radial_profile: ring_means.into_iter().map(round).collect(),
+            peak_count,
+            max_peak_sigma: round(max_peak_sigma),
+            jpeg_grid_excluded: jpeg,
+            spectral_score,
+            score,
+            confidence
+        })
+    }
+}
+
+/// In place, rows then columns. The side must be a power of two.
+fn fft_2d(grid: &mut [Vec<(f64, f64)>]) {
+    for row in grid.iter_mut() {
+        fft(row);
+    }
+    for x in 0..grid.len() {
+        let mut column: Vec<(f64, f64)> = grid.iter().map(|row| row[x]).collect();
+        fft(&mut column);
+        for (row, value) in grid.iter_mut().zip(column) {
+            row[x] = value;
+        }
+    }
+}
+
+/// Iterative radix-2 Cooley-Tukey.
+fn fft(data: &mut [(f64, f64)]) {
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (wr, wi) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (ar, ai) = data[start + k];
+                let (br, bi) = data[start + k + len / 2];
+                let (tr, ti) = (br * wr - bi * wi, br * wi + bi * wr);
+                data[start + k] = (ar + tr, ai + ti);
+                data[start + k + len / 2] = (ar - tr, ai - ti);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+    use super::*;
+
+    /// Pseudo-random luma, a deterministic stand-in for camera noise.
+    fn noise(seed: u32) -> impl FnMut(u32, u32) -> u8 {
+        let mut state = seed;
+        move |_, _| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 24) as u8
+        }
+    }
+
+    #[test]
+    fn fft_of_impulse_and_constant() {
+        let mut impulse = vec![(0.0, 0.0); 8];
+        impulse[0] = (1.0, 0.0);
+        fft(&mut impulse);
+        assert!(impulse.iter().all(|(re, im)| (re - 1.0).abs() < 1e-9 && im.abs() < 1e-9));
+        let mut constant = vec![(1.0, 0.0); 8];
+        fft(&mut constant);
+        assert!((constant[0].0 - 8.0).abs() < 1e-9);
+        assert!(constant[1..].iter().all(|(re, im)| re.abs() < 1e-9 && im.abs() < 1e-9));
+    }
+
+    #[test]
+    fn checkerboard_peaks() {
+        let mut random = noise(7);
+        let plain = GrayImage::from_fn(128, 128, |x, y| Luma([random(x, y) / 4 + 96]));
+        let findings = SpectrumFindings::from_image(&DynamicImage::ImageLuma8(plain.clone()), false).unwrap();
+        assert_eq!(findings.size, 128);
+        assert_eq!(findings.peak_count, 0);
+        assert_eq!(findings.score, 0);
+        // A 2x2 checkerboard of transposed convolutions
+        let upsampled = GrayImage::from_fn(128, 128, |x, y| {
+            let Luma([v]) = *plain.get_pixel(x, y);
+            Luma([if (x + y) % 2 == 0 { v + 40 } else { v }])
+        });
+        let findings = SpectrumFindings::from_image(&DynamicImage::ImageLuma8(upsampled), false).unwrap();
+        assert!(findings.peak_count > 0);
+        assert_eq!(findings.confidence, CONFIDENCE);
+    }
+
+    #[test]
+    fn small_images_are_skipped() {
+        let image = DynamicImage::ImageLuma8(GrayImage::new(MIN_SIZE - 1, 512));
+        assert!(SpectrumFindings::from_image(&image, false).is_none());
+    }
+}
@@ -0,0 +1,201 @@
+use std::io::Cursor;
+use serde::Serialize;
+
+use crate::{exif::ExifData, rules::GeneratorRules, xmp};
+
+/// Confidence of each signal on its own. Several combine like independent
+/// chances, two signals of 60 give 84.
+const JUMBF_FRAGMENT: u8 = 70;
+const XMP_MANIFEST_REFERENCE: u8 = 60;
+const CREDENTIALS_TOOL: u8 = 30;
+/// First 4 bytes of the UUIDs of the C2PA superboxes: manifest store,
+/// manifest, claim, signature and assertion store.
+const C2PA_BOXES: [&[u8; 4]; 5] = [b"c2pa", b"c2ma", b"c2cl", b"c2cs", b"c2as"];
+/// Labels listed per file.
+const MAX_LABELS: usize = 8;
+
+/// What was found of a manifest store that isn't there.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum StrippedSignalKind {
+    /// C2PA JUMBF boxes the manifest store couldn't be read from, ex. the
+    /// rest of a truncated APP11 segment.
+    JumbfFragment,
+    /// The XMP `dcterms:provenance` names a manifest store.
+    XmpManifestReference,
+    /// EXIF Software names a tool that signs what it writes.
+    CredentialsTool
+}
+
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct StrippedSignal {
+    pub kind: StrippedSignalKind,
+    /// What was found, ex. `dcterms:provenance self#jumbf=/c2pa/...`.
+    pub detail: String,
+    pub confidence: u8
+}
+
+/// Traces of Content Credentials removed from a file without a manifest
+/// store, which is suspicious in itself: re-encoding, screenshots and
+/// metadata scrubbers all drop them.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct StrippedProvenance {
+    pub signals: Vec<StrippedSignal>,
+    /// How likely a manifest was removed, the signals combined, 0-100.
+    pub confidence: u8
+}
+
+impl StrippedProvenance {
+    /// None without any trace. Only meaningful when no manifest store was
+    /// read, embedded, from a sidecar or remote.
+    pub fn from_bytes(bytes: &[u8], rules: &GeneratorRules) -> Option<StrippedProvenance> {
+        let mut signals = Vec::new();
+        let labels = jumbf_labels(bytes);
+        if !labels.is_empty() {
+            let detail = format!("C2PA JUMBF boxes {}", labels.join(", "));
+            signals.push(StrippedSignal { kind: StrippedSignalKind::JumbfFragment, detail, confidence: JUMBF_FRAGMENT });
+        }
+        let reference = xmp::find_packets(bytes).iter()
+            .flat_map(|p| xmp::parse_properties(p))
+            .find(|(name, value)| name == "dcterms:provenance" && !value.is_empty());
+        if let Some((name, value)) = reference {
+            let detail = format!("{} {}", name, value);
+            signals.push(StrippedSignal { kind: StrippedSignalKind::XmpManifestReference, detail, confidence: XMP_MANIFEST_REFERENCE });
+        }
+        let software = ExifData::from_stream(Cursor::new(bytes), rules).and_then(|exif| exif.software);
+        if let Some(rule) = software.as_deref().and_then(|s| rules.find_content_credentials(s)) {
+            let detail = format!("EXIF Software '{}' matched '{}'", software.unwrap_or_default(), rule.name);
+            signals.push(StrippedSignal { kind: StrippedSignalKind::CredentialsTool, detail, confidence: CREDENTIALS_TOOL });
+        }
+        if signals.is_empty() {
+            return None;
+        }
+        let doubt = signals.iter().fold(1.0, |doubt, s| doubt * (1.0 - s.confidence as f64 / 100.0));
+        let confidence = ((1.0 - doubt) * 100.0).round() as u8;
+        Some(StrippedProvenance { signals, confidence })
+    }
+}
+
+/// Labels of the C2PA JUMBF description boxes in the bytes, or their
+/// UUID type when unlabeled, ex. `c2pa` or `c2pa.signature`.
+fn jumbf_labels(bytes: &[u8]) -> Vec<String> {
+    let mut labels = Vec::new();
+    let mut at = 0;
+    while let Some(found) = bytes[at..].windows(4).position(|w| w == b"jumd") {
+        let start = at + found + 4;
+        at = start;
+        let uuid = match bytes.get(start..start + 16) {
+            Some(u) => u,
+            None => break
+        };
+        if !C2PA_BOXES.iter().any(|b| uuid.starts_with(*b)) {
+            continue;
+        }
+        // Toggles, then the label when bit 1 is set
+        let label = match bytes.get(start + 16) {
+            Some(toggles) if toggles & 0x02 != 0 => {
+                let rest = bytes.get(start + 17..).unwrap_or_default();
+                let rest = &rest[..rest.len().min(256)];
+                rest.iter().position(|b| *b == 0).map(|end| String::from_utf8_lossy(&rest[..end]).to_string())
+            },
+            _ => None
+        };
+        if labels.len() == MAX_LABELS {
+            break;
+        }
+        labels.push(label.unwrap_or_else(|| String::from_utf8_lossy(&uuid[..4]).to_string()));
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A JUMBF description box of a C2PA superbox, labeled when `label`
+    /// isn't empty.
+    fn jumd(uuid: &[u8; 4], label: &str) -> Vec<u8> {
+        let mut bytes = b"\x00\x00\x00\x30jumd".to_vec();
+        bytes.extend_from_slice(uuid);
+        bytes.extend_from_slice(b"\x00\x11\x00\x10\x80\x00\x00\xAA\x00\x38\x9B\x71");
+        match label {
+            "" => bytes.push(0x01),
+            label => {
+                bytes.push(0x03);
+                bytes.extend_from_slice(label.as_bytes());
+                bytes.push(0);
+            }
+        }
+        bytes
+    }
+
+    /// A little-endian TIFF with only an EXIF Software tag.
+    fn tiff(software: &str) -> Vec<u8> {
+        let value = format!("{}\0", software);
+        let mut bytes = b"II*\x00\x08\x00\x00\x00\x01\x00\x31\x01\x02\x00".to_vec();
+        bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&26u32.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    const XMP: &[u8] = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:dcterms="http://purl.org/dc/terms/" dcterms:provenance="self#jumbf=/c2pa/urn:uuid:1234/c2pa.assertions"/></rdf:RDF></x:xmpmeta>"#;
+
+    fn kinds(found: &StrippedProvenance) -> Vec<StrippedSignalKind> {
+        found.signals.iter().map(|s| s.kind).collect()
+    }
+
+    #[test]
+    fn jumbf_boxes_are_labeled() {
+        let mut bytes = b"\xFF\xD8\xFF\xEB\x00\x40JP".to_vec();
+        bytes.extend(jumd(b"c2pa", "c2pa"));
+        bytes.extend(jumd(b"c2ma", "urn:uuid:1234"));
+        bytes.extend(jumd(b"c2cs", ""));
+        assert_eq!(jumbf_labels(&bytes), ["c2pa", "urn:uuid:1234", "c2cs"]);
+        // cut inside the UUID
+        assert!(jumbf_labels(&bytes[..12]).is_empty());
+        let boxes: Vec<u8> = (0..12).flat_map(|_| jumd(b"c2as", "c2pa.assertions")).collect();
+        assert_eq!(jumbf_labels(&boxes).len(), MAX_LABELS);
+
+        let found = StrippedProvenance::from_bytes(&bytes, &GeneratorRules::default()).unwrap();
+        assert_eq!(kinds(&found), [StrippedSignalKind::JumbfFragment]);
+        assert_eq!(found.confidence, JUMBF_FRAGMENT);
+    }
+
+    #[test]
+    fn other_jumbf_and_text_are_no_trace() {
+        let rules = GeneratorRules::default();
+        // a JPEG 360 box and prose that happens to say jumd
+        let mut bytes = jumd(b"xyz3", "jpeg360");
+        bytes.extend_from_slice(b"notes: the jumd box describes the superbox; c2pa is described elsewhere");
+        assert!(jumbf_labels(&bytes).is_empty());
+        assert!(StrippedProvenance::from_bytes(&bytes, &rules).is_none());
+        assert!(StrippedProvenance::from_bytes(b"jumd", &rules).is_none());
+        assert!(StrippedProvenance::from_bytes(&tiff("GIMP 2.10"), &rules).is_none());
+    }
+
+    #[test]
+    fn signals_combine_like_independent_chances() {
+        let rules = GeneratorRules::default();
+        let xmp = StrippedProvenance::from_bytes(XMP, &rules).unwrap();
+        assert_eq!(kinds(&xmp), [StrippedSignalKind::XmpManifestReference]);
+        assert_eq!(xmp.confidence, XMP_MANIFEST_REFERENCE);
+        assert!(xmp.signals[0].detail.contains("self#jumbf=/c2pa/urn:uuid:1234"));
+
+        let tool = StrippedProvenance::from_bytes(&tiff("ChatGPT"), &rules).unwrap();
+        assert_eq!(kinds(&tool), [StrippedSignalKind::CredentialsTool]);
+        assert_eq!(tool.confidence, CREDENTIALS_TOOL);
+
+        let mut both = tiff("ChatGPT");
+        both.extend_from_slice(XMP);
+        assert_eq!(StrippedProvenance::from_bytes(&both, &rules).unwrap().confidence, 72);
+        both.extend(jumd(b"c2pa", "c2pa"));
+        let all = StrippedProvenance::from_bytes(&both, &rules).unwrap();
+        assert_eq!(kinds(&all), [StrippedSignalKind::JumbfFragment, StrippedSignalKind::XmpManifestReference, StrippedSignalKind::CredentialsTool]);
+        assert_eq!(all.confidence, 92);
+    }
+}
@@ -0,0 +1,237 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use quick_xml::{events::{BytesStart, Event}, Reader};
+use serde::Serialize;
+
+use crate::{options::AnalysisOptions, report::Report, rules::RuleKind};
+
+/// Embedded images analyzed at most, like PDFs.
+const MAX_IMAGES: usize = 32;
+const MAX_COMMENTS: usize = 32;
+const MAX_COMMENT_LEN: usize = 4096;
+const EDITING_TOOL: (u8, u8) = (20, 10);
+
+/// What an SVG says about the tool that exported it, and the reports of
+/// the raster images it embeds as `data:` URLs. The manifest store and
+/// XMP in `<metadata>` are read by the C2PA and XMP analyses.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct SvgData {
+    /// Attributes of the root element as written, ex. `100mm`.
+    pub width: Option<String>,
+    pub height: Option<String>,
+    pub view_box: Option<String>,
+    pub comments: Vec<String>,
+    /// Tools the root element's attributes name, ex. `Inkscape 1.3`, and
+    /// `Generator:` comments, ex. `Adobe Illustrator 28.0.0`.
+    pub generators: Vec<String>,
+    /// Comment or generator that matched a generator rule, and the rule's
+    /// kind.
+    pub tool: Option<String>,
+    pub tool_rule: Option<RuleKind>,
+    /// Embedded raster images analyzed like standalone files, named
+    /// `<index>.<extension>` in document order.
+    pub images: Vec<Report>,
+    /// Images over `MAX_IMAGES` that were not analyzed.
+    pub images_skipped: usize,
+    /// Images linked by URL, which aren't fetched.
+    pub linked_images: usize,
+    /// The higher of the tool rule's and the highest image's, several
+    /// weak images don't add up to a strong one.
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl SvgData {
+    /// None unless the bytes are XML with an `svg` root element.
+    pub fn from_bytes(bytes: &[u8], options: &AnalysisOptions) -> Option<SvgData> {
+        let start = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        if start.iter().find(|b| !b.is_ascii_whitespace()) != Some(&b'<') {
+            return None;
+        }
+        let text = String::from_utf8_lossy(bytes);
+        let mut reader = Reader::from_str(&text);
+        let mut svg: Option<SvgData> = None;
+        let mut comments = Vec::new();
+        let mut images = Vec::new();
+        let mut images_skipped = 0;
+        let mut linked_images = 0;
+        loop {
+            let element = match reader.read_event() {
+                Ok(Event::Start(e)) | Ok(Event::Empty(e)) => e,
+                Ok(Event::Comment(c)) => {
+                    if comments.len() < MAX_COMMENTS {
+                        let comment = String::from_utf8_lossy(&c[..c.len().min(MAX_COMMENT_LEN)]).trim().to_string();
+                        comments.push(comment);
+                    }
+                    continue;
+                },
+                Ok(Event::Eof) | Err(_) => break,
+                _ => continue
+            };
+            match (&svg, element.local_name().as_ref()) {
+                (None, b"svg") => svg = Some(SvgData::from_root(&element)),
+                // Some other XML document
+                (None, _) => return None,
+                (Some(_), b"image") => match embedded_image(&element) {
+                    Some(_) if images.len() == MAX_IMAGES => images_skipped += 1,
+                    Some((extension, image)) => {
+                        let name = format!("{}.{}", images.len(), extension);
+                        images.push(Report::from_bytes_lenient(&name, &image, options));
+                    },
+                    None if href(&element).is_some_and(|h| !h.starts_with("data:")) => linked_images += 1,
+                    None => {}
+                },
+                (Some(_), _) => {}
+            }
+        }
+        let mut svg = svg?;
+        svg.generators.extend(comments.iter().filter_map(|c| generator_comment(c)));
+        let tool = svg.generators.iter().chain(comments.iter())
+            .filter_map(|t| options.rules.find(t).map(|(kind, rule)| (kind, rule, t)))
+            .min_by_key(|(kind, _, _)| *kind != RuleKind::Generated);
+        let (mut score, mut confidence) = match tool {
+            Some((RuleKind::Generated, rule, _)) => (rule.score, rule.confidence),
+            Some((RuleKind::Modified, _, _)) => EDITING_TOOL,
+            None => (0, 0)
+        };
+        if let Some(image) = images.iter().max_by_key(|r| (r.score, r.score_confidence)).filter(|r| (r.score, r.score_confidence) > (score, confidence)) {
+            (score, confidence) = (image.score, image.score_confidence);
+        }
+        svg.tool = tool.map(|(_, _, t)| t.clone());
+        svg.tool_rule = tool.map(|(kind, _, _)| kind);
+        svg.comments = comments;
+        svg.images = images;
+        svg.images_skipped = images_skipped;
+        svg.linked_images = linked_images;
+        svg.score = score.min(100);
+        svg.confidence = confidence.min(100);
+        Some(svg)
+    }
+
+    fn from_root(root: &BytesStart) -> SvgData {
+        let mut svg = SvgData {
+            width: None,
+            height: None,
+            view_box: None,
+            comments: Vec::new(),
+            generators: Vec::new(),
+            tool: None,
+            tool_rule: None,
+            images: Vec::new(),
+            images_skipped: 0,
+            linked_images: 0,
+            score: 0,
+            confidence: 0
+        };
+        for attribute in root.attributes().filter_map(|a| a.ok()) {
+            let value = match attribute.unescape_value() {
+                Ok(v) => v.trim().to_string(),
+                Err(_) => continue
+            };
+            match attribute.key.as_ref() {
+                b"width" => svg.width = Some(value),
+                b"height" => svg.height = Some(value),
+                b"viewBox" => svg.view_box = Some(value),
+                b"inkscape:version" => svg.generators.push(format!("Inkscape {}", value)),
+                b"xmlns:sketch" => svg.generators.push(String::from("Sketch")),
+                b"xmlns:serif" => svg.generators.push(String::from("Affinity Designer")),
+                _ => {}
+            }
+        }
+        svg
+    }
+}
+
+/// The tool of comments such as `Generator: Adobe Illustrator 28.0.0, SVG
+/// Export Plug-In` or `Created with Inkscape (http://www.inkscape.org/)`.
+fn generator_comment(comment: &str) -> Option<String> {
+    let tool = comment.strip_prefix("Generator:")
+        .or_else(|| comment.strip_prefix("Created with"))
+        .or_else(|| comment.strip_prefix("Created by"))?;
+    let tool = tool.split([',', '(']).next().unwrap_or_default().trim();
+    if tool.is_empty() { None } else { Some(tool.to_string()) }
+}
+
+fn href(element: &BytesStart) -> Option<String> {
+    element.attributes()
+        .filter_map(|a| a.ok())
+        .find(|a| matches!(a.key.as_ref(), b"href" | b"xlink:href"))
+        .and_then(|a| a.unescape_value().ok().map(|v| v.trim().to_string()))
+}
+
+/// The extension and bytes of a base64 `data:` URL of a raster image.
+/// Embedded SVGs aren't followed, an SVG can't nest itself that way.
+fn embedded_image(element: &BytesStart) -> Option<(&'static str, Vec<u8>)> {
+    let href = href(element)?;
+    let (header, data) = href.strip_prefix("data:")?.split_once(',')?;
+    let mime = header.strip_suffix(";base64")?;
+    let extension = match mime.to_lowercase().as_str() {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "image/avif" => "avif",
+        "image/heic" => "heic",
+        "image/tiff" => "tif",
+        _ => return None
+    };
+    // Line breaks in the URL are common in exported files
+    let data: String = data.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    Some((extension, STANDARD.decode(data).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 GIF with `comment`.
+    fn gif(comment: &str) -> Vec<u8> {
+        let mut gif = b"GIF89a\x01\x00\x01\x00\x00\x00\x00".to_vec();
+        gif.extend_from_slice(&[0x21, 0xFE, comment.len() as u8]);
+        gif.extend_from_slice(comment.as_bytes());
+        gif.extend_from_slice(b"\x00\x2C\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00\x3B");
+        gif
+    }
+
+    fn gif_image(comment: &str) -> String {
+        format!(r#"<image href="data:image/gif;base64,{}"/>"#, STANDARD.encode(gif(comment)))
+    }
+
+    #[test]
+    fn reads_the_root_and_comments() {
+        let options = AnalysisOptions::default();
+        let svg = SvgData::from_bytes(br#"<?xml version="1.0"?>
+            <!-- Generator: Adobe Illustrator 28.0.0, SVG Export Plug-In -->
+            <svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+                width="100mm" height="50mm" viewBox="0 0 100 50" inkscape:version="1.3">
+              <image href="https://example.com/a.png"/>
+            </svg>"#, &options).unwrap();
+        assert_eq!((svg.width.as_deref(), svg.height.as_deref(), svg.view_box.as_deref()), (Some("100mm"), Some("50mm"), Some("0 0 100 50")));
+        assert_eq!(svg.generators, ["Inkscape 1.3", "Adobe Illustrator 28.0.0"]);
+        assert_eq!(svg.linked_images, 1);
+        assert!(svg.images.is_empty());
+        assert!(SvgData::from_bytes(b"<html><svg/></html>", &options).is_none());
+        assert!(SvgData::from_bytes(b"GIF89a", &options).is_none());
+    }
+
+    #[test]
+    fn weak_images_are_not_summed() {
+        let options = AnalysisOptions::default();
+        let image = Report::from_bytes_lenient("0.gif", &gif("GIMP 2.10"), &options);
+        assert!(image.score > 0);
+        let document = format!("<svg>{}{}{}</svg>", gif_image("GIMP 2.10"), gif_image("GIMP 2.10"), gif_image("GIMP 2.10"));
+        let svg = SvgData::from_bytes(document.as_bytes(), &options).unwrap();
+        assert_eq!(svg.images.len(), 3);
+        assert_eq!((svg.score, svg.confidence), (image.score, image.score_confidence));
+
+        let with_tool = format!("<!-- Created with GIMP --><svg>{}</svg>", gif_image("GIMP 2.10"));
+        let svg = SvgData::from_bytes(with_tool.as_bytes(), &options).unwrap();
+        assert!(svg.tool_rule == Some(RuleKind::Modified));
+        assert_eq!(svg.score, image.score.max(EDITING_TOOL.0));
+
+        let generated = format!("<!-- Created with GIMP --><svg>{}{}</svg>", gif_image("GIMP 2.10"), gif_image("Made with Midjourney"));
+        let svg = SvgData::from_bytes(generated.as_bytes(), &options).unwrap();
+        assert_eq!(svg.score, svg.images.iter().map(|r| r.score).max().unwrap());
+    }
+}
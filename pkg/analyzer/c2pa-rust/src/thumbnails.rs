@@ -0,0 +1,206 @@
+use std::{fs, path::Path};
+use c2pa::Reader;
+use serde::Serialize;
+
+use crate::input;
+
+/// A claim or ingredient thumbnail read from the manifest store.
+pub struct Thumbnail {
+    pub claim_id: String,
+    /// Title of the ingredient, None for the claim's own thumbnail.
+    pub ingredient: Option<String>,
+    /// MIME type, ex. `image/jpeg`.
+    pub format: String,
+    pub bytes: Vec<u8>
+}
+
+impl Thumbnail {
+    /// Every thumbnail of every manifest, sorted by manifest label.
+    pub fn vec_from_reader(reader: &Reader) -> Vec<Thumbnail> {
+        let mut labels: Vec<&String> = reader.manifests().keys().collect();
+        labels.sort();
+        let mut thumbnails = Vec::new();
+        for label in labels {
+            let manifest = &reader.manifests()[label];
+            if let Some((format, bytes)) = manifest.thumbnail() {
+                thumbnails.push(Thumbnail {
+                    claim_id: label.clone(),
+                    ingredient: None,
+                    format: format.to_string(),
+                    bytes: bytes.into_owned()
+                });
+            }
+            for ingredient in manifest.ingredients() {
+                if let Some((format, bytes)) = ingredient.thumbnail() {
+                    thumbnails.push(Thumbnail {
+                        claim_id: label.clone(),
+                        ingredient: Some(ingredient.title().unwrap_or("n/a").to_string()),
+                        format: format.to_string(),
+                        bytes: bytes.into_owned()
+                    });
+                }
+            }
+        }
+        thumbnails
+    }
+
+    /// The active manifest's own thumbnail.
+    pub fn from_active_manifest(reader: &Reader) -> Option<Thumbnail> {
+        let (format, bytes) = reader.active_manifest()?.thumbnail()?;
+        Some(Thumbnail {
+            claim_id: reader.active_label()?.to_string(),
+            ingredient: None,
+            format: format.to_string(),
+            bytes: bytes.into_owned()
+        })
+    }
+}
+
+/// Bits of the perceptual hashes over which the claim thumbnail doesn't
+/// show the delivered image. Re-encoded and resized copies stay within
+/// about 10. The bits of unrelated images agree by chance, their distance
+/// is binomial with a mean of 32 and a deviation of 4, so 22 is 2.5
+/// deviations below it: under 1% of unrelated pairs pass as a match, and a
+/// real thumbnail would need twice the drift of a re-encode to be flagged.
+#[cfg(feature = "pixel")]
+pub const MISMATCH_DISTANCE: u32 = 22;
+
+/// How the active claim's thumbnail compares to the delivered pixels. A
+/// mismatch hints the manifest was transplanted from another image.
+#[cfg(feature = "pixel")]
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ThumbnailMatch {
+    pub claim_id: String,
+    pub format: String,
+    /// Perceptual hashes, 16 hex digits.
+    pub thumbnail_hash: String,
+    pub image_hash: String,
+    /// Bits the hashes differ in, 0-64, the least of the thumbnail's four
+    /// rotations as the pixels are decoded without EXIF orientation.
+    pub distance: u32,
+    pub mismatch: bool
+}
+
+#[cfg(feature = "pixel")]
+impl ThumbnailMatch {
+    /// None when the thumbnail can't be decoded, ex. an SVG.
+    pub fn new(thumbnail: &Thumbnail, image: &image::DynamicImage) -> Option<ThumbnailMatch> {
+        use crate::phash::{distance, phash, rotated_phashes};
+
+        let decoded = image::load_from_memory(&thumbnail.bytes).ok()?;
+        let image_hash = phash(image);
+        let rotations = rotated_phashes(&decoded);
+        let thumbnail_hash = rotations[0];
+        let distance = rotations.iter().map(|hash| distance(*hash, image_hash)).min().unwrap_or(64);
+        Some(ThumbnailMatch {
+            claim_id: thumbnail.claim_id.clone(),
+            format: thumbnail.format.clone(),
+            thumbnail_hash: format!("{:016x}", thumbnail_hash),
+            image_hash: format!("{:016x}", image_hash),
+            distance,
+            mismatch: distance > MISMATCH_DISTANCE
+        })
+    }
+}
+
+/// A thumbnail written out by `--extract-thumbnails`.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct ThumbnailFile {
+    pub claim_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingredient: Option<String>,
+    pub format: String,
+    pub path: String
+}
+
+/// Writes the thumbnails to `dir` as `<file_name>.<n>.claim.<ext>` or
+/// `<file_name>.<n>.ingredient.<ext>`, so reviewers can put them next to
+/// the delivered image. Thumbnails that can't be written are skipped.
+pub fn write_thumbnails(file_name: &str, thumbnails: &[Thumbnail], dir: &Path) -> Vec<ThumbnailFile> {
+    // a path or an uploaded name mustn't lead out of the directory
+    let file_name = match input::bare_name(file_name) {
+        Some(name) if !thumbnails.is_empty() => name,
+        _ => return Vec::new()
+    };
+    if let Err(e) = fs::create_dir_all(dir) {
+        eprintln!("Error creating thumbnail directory {}: {}", dir.display(), e);
+        return Vec::new();
+    }
+    let mut files = Vec::new();
+    for (i, thumbnail) in thumbnails.iter().enumerate() {
+        let kind = match thumbnail.ingredient {
+            Some(_) => "ingredient",
+            None => "claim"
+        };
+        let path = dir.join(format!("{}.{}.{}.{}", file_name, i, kind, extension(&thumbnail.format)));
+        match fs::write(&path, &thumbnail.bytes) {
+            Ok(_) => files.push(ThumbnailFile {
+                claim_id: thumbnail.claim_id.clone(),
+                ingredient: thumbnail.ingredient.clone(),
+                format: thumbnail.format.clone(),
+                path: path.to_string_lossy().to_string()
+            }),
+            Err(e) => eprintln!("Error writing thumbnail {}: {}", path.display(), e)
+        }
+    }
+    files
+}
+
+fn extension(format: &str) -> &str {
+    match format {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        other => match other.rsplit_once('/') {
+            Some((_, subtype)) => subtype,
+            None => other
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnails_are_written_in_the_directory() {
+        let dir = std::env::temp_dir().join(format!("c2pa-thumbnails-{}", std::process::id()));
+        let thumbnail = Thumbnail {
+            claim_id: String::from("claim"),
+            ingredient: None,
+            format: String::from("image/jpeg"),
+            bytes: vec![0xFF, 0xD8]
+        };
+        let files = write_thumbnails("../escaped.jpg", std::slice::from_ref(&thumbnail), &dir.join("inner"));
+        assert_eq!(files.len(), 1);
+        assert!(Path::new(&files[0].path).starts_with(dir.join("inner")));
+        assert!(write_thumbnails("..", &[thumbnail], &dir).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "pixel")]
+    #[test]
+    fn thumbnails_of_other_images_mismatch() {
+        use image::{DynamicImage, ImageBuffer, ImageFormat, Luma};
+
+        // a bright square on a diagonal gradient
+        let image = DynamicImage::ImageLuma8(ImageBuffer::from_fn(240, 160, |x, y| match (60..120).contains(&x) && (40..100).contains(&y) {
+            true => Luma([230]),
+            false => Luma([((x + y) / 2) as u8])
+        }));
+        let jpeg = |image: &DynamicImage| {
+            let mut bytes = std::io::Cursor::new(Vec::new());
+            image.thumbnail(96, 64).to_rgb8().write_to(&mut bytes, ImageFormat::Jpeg).unwrap();
+            Thumbnail { claim_id: String::from("claim"), ingredient: None, format: String::from("image/jpeg"), bytes: bytes.into_inner() }
+        };
+        let own = ThumbnailMatch::new(&jpeg(&image), &image).unwrap();
+        assert!(own.distance <= 10 && !own.mismatch, "{}", own.distance);
+        let turned = ThumbnailMatch::new(&jpeg(&image.rotate270()), &image).unwrap();
+        assert!(!turned.mismatch, "{}", turned.distance);
+        let bands = DynamicImage::ImageLuma8(ImageBuffer::from_fn(240, 160, |x, y| Luma([if (y / 20 + x / 80) % 2 == 0 { 40 } else { 200 }])));
+        let other = ThumbnailMatch::new(&jpeg(&bands), &image).unwrap();
+        assert!(other.distance > MISMATCH_DISTANCE && other.mismatch, "{}", other.distance);
+        assert!(ThumbnailMatch::new(&Thumbnail { bytes: b"<svg/>".to_vec(), ..jpeg(&image) }, &image).is_none());
+    }
+}
@@ -0,0 +1,53 @@
+use serde::Serialize;
+use tracing::info_span;
+use web_time::{Duration, Instant};
+
+/// How long the stages of an analysis took, to find what makes one slow.
+/// Each stage also runs in a `tracing` span of the same name.
+///
+/// Stages are named `decode` for the pixels, then by analyzer, ex. `c2pa`,
+/// each followed by its own stages, ex. `c2pa.read_manifest`, then
+/// `scoring`. Reports only have them with `AnalysisOptions::with_timings`,
+/// and the cache doesn't keep them.
+#[derive(Serialize, Clone, Default)]
+#[non_exhaustive]
+pub struct Timings {
+    pub total_ms: f64,
+    pub stages: Vec<StageTiming>
+}
+
+#[derive(Serialize, Clone)]
+#[non_exhaustive]
+pub struct StageTiming {
+    pub stage: String,
+    pub ms: f64
+}
+
+impl Timings {
+    /// Runs `f` in the span of `stage` and records how long it took.
+    pub fn time<T>(&mut self, stage: &str, f: impl FnOnce() -> T) -> T {
+        let span = info_span!("stage", name = stage);
+        let start = Instant::now();
+        let result = span.in_scope(f);
+        self.add(stage, start.elapsed());
+        result
+    }
+
+    pub fn add(&mut self, stage: &str, elapsed: Duration) {
+        self.stages.push(StageTiming { stage: stage.to_string(), ms: millis(elapsed) });
+    }
+
+    /// Adds the stages of an analyzer after its own, named `<prefix>.<stage>`.
+    pub(crate) fn extend_prefixed(&mut self, prefix: &str, timings: Timings) {
+        self.stages.extend(timings.stages.into_iter().map(|s| StageTiming { stage: format!("{}.{}", prefix, s.stage), ms: s.ms }));
+    }
+
+    pub(crate) fn finish(&mut self, start: Instant) {
+        self.total_ms = millis(start.elapsed());
+    }
+}
+
+/// Rounded to microseconds, finer is noise.
+fn millis(elapsed: Duration) -> f64 {
+    (elapsed.as_secs_f64() * 1_000_000.0).round() / 1000.0
+}
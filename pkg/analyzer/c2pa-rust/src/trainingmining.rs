@@ -0,0 +1,91 @@
+use c2pa::Manifest;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::claimdata::ClaimData;
+
+/// Whether a use of the asset is permitted, an entry of a
+/// `c2pa.training-mining` or `cawg.training-mining` assertion.
+#[derive(Serialize, Clone)]
+#[non_exhaustive]
+pub struct TrainingPermission {
+    /// ex. `c2pa.ai_training`, `c2pa.ai_generative_training`,
+    /// `c2pa.ai_inference` or `c2pa.data_mining`.
+    pub purpose: String,
+    /// `allowed`, `notAllowed` or `constrained`.
+    #[serde(rename = "use")]
+    pub usage: String,
+    /// What a `constrained` use is subject to, ex. a licensing contact.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constraint_info: Option<String>
+}
+
+impl TrainingPermission {
+    /// Entries of every training and mining assertion of a manifest, sorted
+    /// by purpose.
+    pub fn vec_from_manifest(manifest: &Manifest) -> Vec<TrainingPermission> {
+        let mut permissions = Vec::new();
+        for assertion in manifest.assertions() {
+            let label = assertion.label();
+            if !label.starts_with("c2pa.training-mining") && !label.starts_with("cawg.training-mining") {
+                continue;
+            }
+            let value = match assertion.value() {
+                Ok(v) => v,
+                Err(_) => continue
+            };
+            if let Some(entries) = value.get("entries").and_then(|e| e.as_object()) {
+                permissions.extend(entries.iter().filter_map(|(purpose, entry)| TrainingPermission::from_value(purpose, entry)));
+            }
+        }
+        permissions.sort_by(|a, b| a.purpose.cmp(&b.purpose));
+        permissions
+    }
+
+    fn from_value(purpose: &str, entry: &Value) -> Option<TrainingPermission> {
+        Some(TrainingPermission {
+            purpose: purpose.to_string(),
+            usage: entry.get("use")?.as_str()?.to_string(),
+            constraint_info: entry.get("constraint_info").and_then(|c| c.as_str()).map(|c| c.to_string())
+        })
+    }
+
+    fn is_training(&self) -> bool {
+        self.purpose.ends_with("_training")
+    }
+}
+
+/// The training and mining permissions of the active manifest, which the
+/// asset's creator or publisher declared. Those of the manifests it builds
+/// on are in their claims.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct TrainingMining {
+    pub claim_id: String,
+    pub permissions: Vec<TrainingPermission>,
+    /// Some use for AI training is `notAllowed`.
+    pub do_not_train: bool,
+    /// Data mining is `notAllowed`.
+    pub do_not_mine: bool,
+    /// Some use is `constrained`, see its `constraint_info`.
+    pub constrained: bool
+}
+
+impl TrainingMining {
+    /// None when the active manifest has no training and mining assertion.
+    pub fn from_claims(claims: &[ClaimData], active_label: Option<&String>) -> Option<TrainingMining> {
+        let claim = claims.iter().find(|c| Some(&c.claim_id) == active_label)?;
+        if claim.training_mining.is_empty() {
+            return None;
+        }
+        let permissions = claim.training_mining.clone();
+        let not_allowed = |p: &&TrainingPermission| p.usage == "notAllowed";
+        Some(TrainingMining {
+            claim_id: claim.claim_id.clone(),
+            do_not_train: permissions.iter().filter(not_allowed).any(|p| p.is_training()),
+            do_not_mine: permissions.iter().filter(not_allowed).any(|p| p.purpose.ends_with("data_mining")),
+            constrained: permissions.iter().any(|p| p.usage == "constrained"),
+            permissions
+        })
+    }
+}
@@ -0,0 +1,111 @@
+use std::{cell::RefCell, fs, path::Path};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+thread_local!(
+    // c2pa keeps its settings per thread, so each thread remembers which
+    // policy it last loaded instead of reloading it for every file.
+    static APPLIED: RefCell<Option<TrustConfig>> = const { RefCell::new(None) }
+);
+
+/// Trust policy deciding when validation reports
+/// [`ValidationState::Trusted`](c2pa::ValidationState::Trusted) instead of
+/// the library default, which trusts nothing.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct TrustConfig {
+    /// PEM bundle of root and intermediate certificates signers must chain
+    /// to.
+    #[serde(default)]
+    pub trust_anchors: Option<String>,
+    /// End-entity certificates trusted directly, as PEM or base64 SHA-256
+    /// hashes of their DER encoding, one per line.
+    #[serde(default)]
+    pub allowed_list: Option<String>,
+    /// Extended key usage OIDs a signing certificate may have, one per
+    /// line. Defaults to the C2PA list.
+    #[serde(default)]
+    pub trust_config: Option<String>
+}
+
+impl TrustConfig {
+    pub fn from_anchors(trust_anchors: String) -> TrustConfig {
+        TrustConfig { trust_anchors: Some(trust_anchors), ..Default::default() }
+    }
+
+    /// Reads a JSON or TOML file naming the PEM and config files, relative
+    /// to the file itself, ex.
+    /// `{"trust_anchors": "anchors.pem", "allowed_list": "allowed.pem"}`.
+    pub fn from_file(path: &Path) -> Result<TrustConfig, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Error reading trust config {}: {}", path.display(), e))
+        };
+        let is_toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        let paths: Result<TrustConfig, String> = if is_toml {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        };
+        let paths = match paths {
+            Ok(p) => p,
+            Err(e) => return Err(format!("Error parsing trust config {}: {}", path.display(), e))
+        };
+
+        let base = path.parent().unwrap_or(Path::new("."));
+        let read = |file: Option<String>| -> Result<Option<String>, String> {
+            match file {
+                Some(f) => match fs::read_to_string(base.join(&f)) {
+                    Ok(c) => Ok(Some(c)),
+                    Err(e) => Err(format!("Error reading {} from trust config: {}", f, e))
+                },
+                None => Ok(None)
+            }
+        };
+        let config = TrustConfig {
+            trust_anchors: read(paths.trust_anchors)?,
+            allowed_list: read(paths.allowed_list)?,
+            trust_config: read(paths.trust_config)?
+        };
+        // Loading it once here reports a bad bundle at startup instead of
+        // on every file.
+        config.apply()?;
+        Ok(config)
+    }
+
+    /// Loads the policy into the c2pa settings of the current thread.
+    pub(crate) fn apply(&self) -> Result<(), String> {
+        if APPLIED.with_borrow(|applied| applied.as_ref() == Some(self)) {
+            return Ok(());
+        }
+        // c2pa merges new settings into the current ones, so a previous
+        // policy has to go first or its lists would stay.
+        TrustConfig::reset();
+        let mut trust = Map::new();
+        for (key, value) in [
+            ("trust_anchors", &self.trust_anchors),
+            ("allowed_list", &self.allowed_list),
+            ("trust_config", &self.trust_config)
+        ] {
+            if let Some(v) = value {
+                trust.insert(key.to_string(), Value::String(v.clone()));
+            }
+        }
+        let settings = json!({"trust": trust, "verify": {"verify_trust": true}});
+        match c2pa::settings::load_settings_from_str(&settings.to_string(), "json") {
+            Ok(_) => {
+                APPLIED.set(Some(self.clone()));
+                Ok(())
+            },
+            Err(e) => Err(format!("Error loading trust settings: {}", e))
+        }
+    }
+
+    /// Puts the c2pa defaults back if the current thread loaded a policy.
+    pub(crate) fn reset() {
+        if APPLIED.with_borrow(|applied| applied.is_some()) {
+            let _ = c2pa::settings::reset_default_settings();
+            APPLIED.set(None);
+        }
+    }
+}
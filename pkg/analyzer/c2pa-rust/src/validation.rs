@@ -2,12 +2,29 @@ use c2pa::{validation_results::StatusCodes, validation_status::ValidationStatus,
 use c2pa_status_tracker::LogKind;
 use serde::Serialize;
 
+#[cfg(feature = "pixel")]
+use crate::thumbnails::ThumbnailMatch;
+use crate::{certchain::ChainCertificate, hashbinding::HashMismatch, knowledgebase::SignerEntry, revocation::{RevocationData, RevocationStatus}, rules::GeneratorRules, signature::SignatureData};
+
 #[derive(Serialize)]
+#[non_exhaustive]
 pub struct ValidationData {
     pub state: ValidationState,
     pub certs_count: usize,
     pub certs_valid: usize,
-    pub certs: Vec<Certificate>
+    pub certs: Vec<Certificate>,
+    /// Signing time and certificate validity of the active manifest.
+    pub signature: Option<SignatureData>,
+    /// Certificates that signed the active manifest, signer first.
+    pub cert_chain: Vec<ChainCertificate>,
+    /// Hard bindings of the active manifest the asset no longer matches.
+    pub hash_mismatches: Vec<HashMismatch>,
+    /// Revocation of the certificates that signed the active manifest.
+    pub revocation: Option<RevocationData>,
+    /// The active claim's thumbnail against the pixels, None without one
+    /// or when either can't be decoded.
+    #[cfg(feature = "pixel")]
+    pub thumbnail_match: Option<ThumbnailMatch>
 }
 
 impl ValidationData {
@@ -17,7 +34,18 @@ impl ValidationData {
         certs_valid: usize,
         certs: Vec<Certificate>
     ) -> ValidationData {
-        ValidationData { state, certs_count, certs_valid, certs }
+        ValidationData {
+            state,
+            certs_count,
+            certs_valid,
+            certs,
+            signature: None,
+            cert_chain: Vec::new(),
+            hash_mismatches: Vec::new(),
+            revocation: None,
+            #[cfg(feature = "pixel")]
+            thumbnail_match: None
+        }
     }
     
     pub fn from_result(result: &ValidationResults) -> ValidationData {
@@ -28,9 +56,22 @@ impl ValidationData {
         };
         ValidationData::new(state, certs_count, certs_valid, certs)
     }
+
+    /// The signer entry of the signing certificate, None unless the
+    /// signature is valid, the content unaltered and the certificate not
+    /// revoked, as anyone can put a known certificate in a manifest.
+    pub fn signer<'a>(&self, rules: &'a GeneratorRules) -> Option<&'a SignerEntry> {
+        let revoked = self.revocation.as_ref().is_some_and(|r| r.status == RevocationStatus::Revoked);
+        if revoked || !self.hash_mismatches.is_empty() || !matches!(self.state, ValidationState::Valid | ValidationState::Trusted) {
+            return None;
+        }
+        self.cert_chain.first().and_then(|cert| rules.find_signer(&cert.sha256, &cert.subject))
+    }
 }
 
+/// A validation status entry of the active manifest.
 #[derive(Serialize)]
+#[non_exhaustive]
 pub struct Certificate {
     pub cert_id: String,
     pub cert_code: String,
@@ -52,10 +93,7 @@ impl Certificate {
             Some(expl) => expl.to_string(),
             None => String::from("n/a")
         };
-        let is_valid = match status.kind() {
-            LogKind::Success => true,
-            _ => false
-        };
+        let is_valid = matches!(status.kind(), LogKind::Success);
         Certificate::new(id, status.code().to_string(), explanation, is_valid)
     }
     
@@ -72,7 +110,69 @@ impl Certificate {
             vector.push(Certificate::from_status(code));
         });
         
-        let len = &vector.iter().count();
-        (vector, *len, codes.success().iter().count())
+        // By code, so reports of the same file compare equal
+        vector.sort_by(|a, b| (&a.cert_code, &a.cert_id).cmp(&(&b.cert_code, &b.cert_id)));
+        let len = vector.len();
+        (vector, len, codes.success().len())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certificates_are_sorted_by_code() {
+        let codes: StatusCodes = serde_json::from_value(serde_json::json!({
+            "success": [
+                {"code": "timeStamp.validated", "url": "b"},
+                {"code": "claimSignature.validated", "url": "a"},
+                {"code": "assertion.hashedURI.match", "url": "c"},
+                {"code": "assertion.hashedURI.match", "url": "a"}
+            ],
+            "informational": [{"code": "signingCredential.ocsp.skipped"}],
+            "failure": [{"code": "assertion.dataHash.mismatch", "url": "d"}]
+        })).unwrap();
+        let (certs, count, valid) = Certificate::vec_from_codes(codes);
+        let order: Vec<(&str, &str)> = certs.iter().map(|c| (c.cert_code.as_str(), c.cert_id.as_str())).collect();
+        assert_eq!(order, vec![
+            ("assertion.dataHash.mismatch", "d"),
+            ("assertion.hashedURI.match", "a"),
+            ("assertion.hashedURI.match", "c"),
+            ("claimSignature.validated", "a"),
+            ("signingCredential.ocsp.skipped", "n/a"),
+            ("timeStamp.validated", "b")
+        ]);
+        assert_eq!((count, valid), (6, 4));
     }
-}
\ No newline at end of file
+
+    fn signed_by(subject: &str, state: ValidationState) -> ValidationData {
+        let mut validation = ValidationData::new(state, 1, 1, Vec::new());
+        validation.cert_chain.push(ChainCertificate {
+            subject: subject.to_string(),
+            issuer: String::from("C=US, O=Truepic, CN=Truepic Lens CA"),
+            serial: String::from("01"),
+            sha256: String::from("00:11"),
+            signature_algorithm: String::from("ecdsa-with-SHA256"),
+            key_type: String::from("EC"),
+            key_curve: Some(String::from("prime256v1")),
+            key_bits: 256,
+            not_before: String::from("2025-01-01T00:00:00Z"),
+            not_after: String::from("2027-01-01T00:00:00Z"),
+            expired: false
+        });
+        validation
+    }
+
+    #[test]
+    fn signers_match_valid_signatures_only() {
+        let rules = GeneratorRules::default();
+        let openai = "C=US, O=OpenAI, CN=Truepic Lens CLI in DALL-E";
+        assert_eq!(signed_by(openai, ValidationState::Valid).signer(&rules).map(|s| s.name.as_str()), Some("OpenAI image generation"));
+        assert_eq!(signed_by(openai, ValidationState::Trusted).signer(&rules).map(|s| s.name.as_str()), Some("OpenAI image generation"));
+        assert!(signed_by(openai, ValidationState::Invalid).signer(&rules).is_none());
+        assert!(signed_by("C=US, O=Example, CN=OpenAI", ValidationState::Valid).signer(&rules).is_none());
+        let mut revoked = signed_by(openai, ValidationState::Valid);
+        revoked.revocation = Some(RevocationData { status: RevocationStatus::Revoked, ocsp: Some(RevocationStatus::Revoked), crls: Vec::new() });
+        assert!(revoked.signer(&rules).is_none());
+    }
+}
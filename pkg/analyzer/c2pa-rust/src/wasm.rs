@@ -0,0 +1,24 @@
+//! Bindings for running the analyzer in the browser. Build with
+//!
+//! ```sh
+//! cargo rustc --release --lib --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web target/wasm32-unknown-unknown/release/c2pa_rust.wasm
+//! ```
+//!
+//! Without the default features the pixel and PDF analyses are left out,
+//! which keeps the module small enough for a pre-check before upload.
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{options::AnalysisOptions, report::Report};
+
+/// Report JSON of a file read in the browser, ex. from a `File` input.
+/// `file_name` tells the format by its extension, ex. `upload.jpg`.
+#[wasm_bindgen]
+pub fn analyze(file_name: &str, bytes: &[u8]) -> String {
+    match serde_json::to_string(&Report::from_bytes_lenient(file_name, bytes, &AnalysisOptions::default())) {
+        Ok(j) => j,
+        Err(_) => String::from("{}")
+    }
+}
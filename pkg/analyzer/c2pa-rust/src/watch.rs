@@ -0,0 +1,202 @@
+use std::{collections::{HashMap, HashSet}, fs::{self, OpenOptions}, io::Write, path::{Path, PathBuf}, thread, time::{Duration, SystemTime}};
+use serde_json::Value;
+
+use crate::batch::{analyze_parallel, collect_paths};
+
+/// Name of the stream every report is also appended to, in the output
+/// directory.
+pub const NDJSON_FILE: &str = "reports.ndjson";
+
+/// A drop folder whose new files are analyzed, each report written to
+/// `<out_dir>/<relative path>.json` and appended to `reports.ndjson`.
+///
+/// The folder is polled, which also works on network shares. A file is
+/// analyzed once its size and modification time didn't change for a poll,
+/// so files still being copied in are left for later, and again when it
+/// is replaced or re-created. Files with a report newer than them when
+/// the watch starts are skipped, a restarted watch only analyzes what came
+/// in meanwhile.
+#[non_exhaustive]
+pub struct WatchFolder {
+    pub dir: PathBuf,
+    pub out_dir: PathBuf,
+    pub interval: Duration,
+    /// Size and modification time of each file at the last poll.
+    pending: HashMap<PathBuf, (u64, SystemTime)>,
+    /// Files analyzed by modification time, those no longer there
+    /// dropped at each poll.
+    done: HashSet<(PathBuf, SystemTime)>,
+    started: bool
+}
+
+impl WatchFolder {
+    pub fn new(dir: PathBuf, out_dir: PathBuf) -> WatchFolder {
+        WatchFolder { dir, out_dir, interval: Duration::from_secs(2), pending: HashMap::new(), done: HashSet::new(), started: false }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> WatchFolder {
+        self.interval = interval;
+        self
+    }
+
+    /// Polls until the process ends, see `poll`. Fails only when the
+    /// directories can't be used.
+    pub fn run(
+        mut self,
+        jobs: usize,
+        analyze: impl Fn(PathBuf) -> Value + Sync,
+        mut emit: impl FnMut(&Path, Result<&Value, String>)
+    ) -> Result<(), String> {
+        self.check_dirs()?;
+        loop {
+            self.poll(jobs, &analyze, &mut emit, false);
+            thread::sleep(self.interval);
+        }
+    }
+
+    /// Analyzes the files that settled since the last poll on `jobs`
+    /// threads, writes their reports and hands them to `emit`, or the
+    /// error writing them, which leaves the file for the next poll. With
+    /// `all` every new file is taken as complete, ex. for a single pass
+    /// over a folder.
+    pub fn poll(
+        &mut self,
+        jobs: usize,
+        analyze: impl Fn(PathBuf) -> Value + Sync,
+        mut emit: impl FnMut(&Path, Result<&Value, String>),
+        all: bool
+    ) {
+        let mut ready = Vec::new();
+        let mut seen = HashMap::new();
+        let mut done = HashSet::new();
+        for path in collect_paths(std::slice::from_ref(&self.dir)) {
+            let state = match fs::metadata(&path).and_then(|m| Ok((m.len(), m.modified()?))) {
+                Ok(s) => s,
+                Err(_) => continue
+            };
+            let key = (path, state.1);
+            if self.done.contains(&key) || (!self.started && self.has_newer_report(&key.0, state.1)) {
+                done.insert(key);
+                continue;
+            }
+            let path = key.0;
+            if all || self.pending.get(&path) == Some(&state) {
+                ready.push(path.clone());
+            }
+            seen.insert(path, state);
+        }
+        self.done = done;
+        self.started = true;
+        let _ = analyze_parallel(ready, jobs, |path| {
+            let report = analyze(path.clone());
+            (path, report)
+        }, |(path, report)| {
+            match self.write(&path, &report) {
+                Ok(()) => {
+                    if let Some((_, modified)) = seen.remove(&path) {
+                        self.done.insert((path.clone(), modified));
+                    }
+                    emit(&path, Ok(&report));
+                },
+                Err(e) => emit(&path, Err(e))
+            }
+            Ok::<(), ()>(())
+        });
+        self.pending = seen;
+    }
+
+    pub fn check_dirs(&self) -> Result<(), String> {
+        if !self.dir.is_dir() {
+            return Err(format!("{} isn't a directory", self.dir.display()));
+        }
+        fs::create_dir_all(&self.out_dir).map_err(|e| format!("Error creating {}: {}", self.out_dir.display(), e))
+    }
+
+    /// `<out_dir>/<path relative to dir>.json`.
+    pub fn report_path(&self, path: &Path) -> PathBuf {
+        let relative = path.strip_prefix(&self.dir).unwrap_or(path);
+        let mut name = self.out_dir.join(relative).into_os_string();
+        name.push(".json");
+        PathBuf::from(name)
+    }
+
+    fn has_newer_report(&self, path: &Path, modified: SystemTime) -> bool {
+        fs::metadata(self.report_path(path)).and_then(|m| m.modified()).is_ok_and(|written| written >= modified)
+    }
+
+    /// Writes the report file through a temporary file, so readers of the
+    /// output directory never see half a report, then appends it to the
+    /// stream.
+    fn write(&self, path: &Path, report: &Value) -> Result<(), String> {
+        let report_path = self.report_path(path);
+        let with_path = |p: &Path, e: std::io::Error| format!("Error writing {}: {}", p.display(), e);
+        if let Some(parent) = report_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| with_path(parent, e))?;
+        }
+        let json = report.to_string();
+        let temporary = report_path.with_extension("json.tmp");
+        fs::write(&temporary, format!("{}\n", json))
+            .and_then(|_| fs::rename(&temporary, &report_path))
+            .map_err(|e| with_path(&report_path, e))?;
+        let stream = self.out_dir.join(NDJSON_FILE);
+        OpenOptions::new().create(true).append(true).open(&stream)
+            .and_then(|mut f| writeln!(f, "{}", json))
+            .map_err(|e| with_path(&stream, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, fs::File, sync::Mutex};
+
+    #[test]
+    fn files_are_analyzed_again_when_replaced_or_re_created() {
+        let dir = std::env::temp_dir().join(format!("c2pa-watch-{}", std::process::id()));
+        let (input, out) = (dir.join("in"), dir.join("out"));
+        fs::create_dir_all(&input).unwrap();
+        let file = input.join("a.jpg");
+        let set_modified = |secs: u64| File::options().write(true).open(&file).unwrap().set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+        fs::write(&file, b"first").unwrap();
+        set_modified(1_700_000_000);
+
+        let mut watch = WatchFolder::new(input.clone(), out.clone());
+        watch.check_dirs().unwrap();
+        let analyzed = Mutex::new(Vec::new());
+        let emitted = RefCell::new(0);
+        let poll = |watch: &mut WatchFolder, all: bool| {
+            watch.poll(1, |path| {
+                analyzed.lock().unwrap().push(path);
+                serde_json::json!({"verdict": "Unknown"})
+            }, |_, report| {
+                assert!(report.is_ok());
+                *emitted.borrow_mut() += 1;
+            }, all);
+            analyzed.lock().unwrap().len()
+        };
+        // settled after a poll without changes
+        assert_eq!(poll(&mut watch, false), 0);
+        assert_eq!(poll(&mut watch, false), 1);
+        assert_eq!(poll(&mut watch, false), 1);
+        assert!(watch.report_path(&file).is_file());
+
+        fs::write(&file, b"replaced").unwrap();
+        set_modified(1_700_000_100);
+        assert_eq!(poll(&mut watch, true), 2);
+        assert_eq!(watch.done.len(), 1);
+
+        fs::remove_file(&file).unwrap();
+        assert_eq!(poll(&mut watch, true), 2);
+        assert!(watch.done.is_empty() && watch.pending.is_empty());
+        // copied back with its old time, older than its report
+        fs::write(&file, b"first").unwrap();
+        set_modified(1_700_000_000);
+        assert_eq!(poll(&mut watch, true), 3);
+        assert_eq!(*emitted.borrow(), 3);
+
+        // a restarted watch skips what it reported before
+        let mut restarted = WatchFolder::new(input.clone(), out.clone());
+        assert_eq!(poll(&mut restarted, true), 3);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+use image::{DynamicImage, RgbImage};
+use serde::Serialize;
+
+use crate::ela::round;
+
+/// A decoded payload matching this share of an expected message's bits is
+/// a detection, chance alone gets near half.
+const DETECTION_ACCURACY: f64 = 0.9;
+const DETECTED: (u8, u8) = (100, 90);
+/// Message the Stable Diffusion XL pipelines embed, 48 bits.
+const SDXL_MESSAGE: u64 = 0b101100111110110010010000011110111011000110011110;
+/// Message the Stable Diffusion 1.x and 2.x scripts embed.
+const SD_MESSAGE: &[u8] = b"StableDiffusionV1";
+
+/// A watermark scheme that can be looked for in decoded pixels.
+/// Implementations are added to [`WatermarkDecoders`].
+pub trait WatermarkDecoder: Send + Sync {
+    /// Name reported with detections, ex. `invisible-watermark dwtDct`.
+    fn name(&self) -> &str;
+    /// None when the image carries no watermark of this scheme.
+    fn decode(&self, image: &DynamicImage) -> Option<WatermarkDetection>;
+}
+
+/// A watermark found in the pixels.
+#[derive(Serialize, Clone)]
+#[non_exhaustive]
+pub struct WatermarkDetection {
+    pub decoder: String,
+    /// Who embeds the payload, ex. `Stable Diffusion XL`.
+    pub generator: Option<String>,
+    /// Decoded bits as hex, or the text when the payload is text.
+    pub payload: String,
+    /// Share of bits agreeing with the expected message, 1.0 for schemes
+    /// without one.
+    pub bit_accuracy: f64
+}
+
+impl WatermarkDetection {
+    pub fn new(decoder: &str, generator: Option<&str>, payload: &str, bit_accuracy: f64) -> WatermarkDetection {
+        WatermarkDetection {
+            decoder: decoder.to_string(),
+            generator: generator.map(|g| g.to_string()),
+            payload: payload.to_string(),
+            bit_accuracy
+        }
+    }
+}
+
+/// The decoders run on every image, the built-in ones by default.
+#[derive(Clone)]
+pub struct WatermarkDecoders {
+    decoders: Vec<Arc<dyn WatermarkDecoder>>
+}
+
+impl WatermarkDecoders {
+    pub fn empty() -> WatermarkDecoders {
+        WatermarkDecoders { decoders: Vec::new() }
+    }
+
+    pub fn with_decoder(mut self, decoder: Arc<dyn WatermarkDecoder>) -> WatermarkDecoders {
+        self.decoders.push(decoder);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decoders.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.decoders.iter().map(|d| d.name()).collect()
+    }
+}
+
+impl Default for WatermarkDecoders {
+    fn default() -> WatermarkDecoders {
+        WatermarkDecoders::empty()
+            .with_decoder(Arc::new(DwtDctDecoder::new("Stable Diffusion XL", bits_of_u64(SDXL_MESSAGE, 48))))
+            .with_decoder(Arc::new(DwtDctDecoder::new("Stable Diffusion", bits_of_bytes(SD_MESSAGE))))
+    }
+}
+
+/// Watermarks found by the decoders.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct WatermarkFindings {
+    /// Names of the decoders run.
+    pub decoders: Vec<String>,
+    pub detections: Vec<WatermarkDetection>,
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl WatermarkFindings {
+    /// None when there are no decoders.
+    pub fn from_image(image: &DynamicImage, decoders: &WatermarkDecoders) -> Option<WatermarkFindings> {
+        if decoders.is_empty() {
+            return None;
+        }
+        let detections: Vec<WatermarkDetection> = decoders.decoders.iter().filter_map(|d| d.decode(image)).collect();
+        let (score, confidence) = if detections.is_empty() { (0, 0) } else { DETECTED };
+        Some(WatermarkFindings {
+            decoders: decoders.decoders.iter().map(|d| d.name().to_string()).collect(),
+            detections,
+            score,
+            confidence
+        })
+    }
+}
+
+/// The `dwtDct` method of the invisible-watermark library: each bit is
+/// repeated over the 4x4 blocks of the Haar LL band of the chroma, in the
+/// remainder of the largest AC coefficient of the block's DCT.
+pub struct DwtDctDecoder {
+    name: String,
+    generator: String,
+    message: Vec<bool>
+}
+
+impl DwtDctDecoder {
+    /// Step the coefficients are quantized with, the library's default.
+    const SCALE: f64 = 36.0;
+    const BLOCK: usize = 4;
+    /// Smallest side the library embeds into.
+    const MIN_SIDE: u32 = 256;
+
+    /// Looks for `message`, bits in embedding order, attributed to
+    /// `generator`.
+    pub fn new(generator: &str, message: Vec<bool>) -> DwtDctDecoder {
+        DwtDctDecoder {
+            name: format!("invisible-watermark dwtDct ({})", generator),
+            generator: generator.to_string(),
+            message
+        }
+    }
+
+    /// Vote of each block for its bit, over the LL band of `plane`.
+    fn votes(&self, plane: &[Vec<f64>], scores: &mut [Vec<f64>]) {
+        let ll = haar_ll(plane);
+        let (rows, cols) = (ll.len() / Self::BLOCK, ll.first().map_or(0, |r| r.len()) / Self::BLOCK);
+        let mut num = 0;
+        for i in 0..rows {
+            for j in 0..cols {
+                let mut block = [[0.0; 4]; 4];
+                for (y, row) in block.iter_mut().enumerate() {
+                    for (x, value) in row.iter_mut().enumerate() {
+                        *value = ll[i * Self::BLOCK + y][j * Self::BLOCK + x];
+                    }
+                }
+                let coefficients = dct_4x4(&block);
+                let largest = coefficients.iter()
+                    .flatten()
+                    .skip(1)
+                    .fold(0.0_f64, |max, c| if c.abs() > max.abs() { *c } else { max });
+                let bit = if largest.abs() % Self::SCALE > 0.5 * Self::SCALE { 1.0 } else { 0.0 };
+                scores[num % self.message.len()].push(bit);
+                num += 1;
+            }
+        }
+    }
+
+    fn accuracy(&self, bits: &[bool]) -> f64 {
+        let matching = bits.iter().zip(&self.message).filter(|(a, b)| a == b).count();
+        matching as f64 / self.message.len() as f64
+    }
+}
+
+impl WatermarkDecoder for DwtDctDecoder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn decode(&self, image: &DynamicImage) -> Option<WatermarkDetection> {
+        if image.width() < Self::MIN_SIDE || image.height() < Self::MIN_SIDE || self.message.is_empty() {
+            return None;
+        }
+        let rgb = image.to_rgb8();
+        // Pipelines hand the library RGB as well as BGR, and depending on
+        // the version it reads U alone or U and V.
+        let mut best: Option<Vec<bool>> = None;
+        for swapped in [false, true] {
+            let (u, v) = chroma_planes(&rgb, swapped);
+            for planes in [vec![&u], vec![&u, &v]] {
+                let mut scores = vec![Vec::new(); self.message.len()];
+                for plane in planes {
+                    self.votes(plane, &mut scores);
+                }
+                let bits: Vec<bool> = scores.iter()
+                    .map(|s| s.iter().sum::<f64>() / s.len().max(1) as f64 * 255.0 > 127.0)
+                    .collect();
+                if best.as_ref().is_none_or(|b| self.accuracy(&bits) > self.accuracy(b)) {
+                    best = Some(bits);
+                }
+            }
+        }
+        let bits = best?;
+        let accuracy = self.accuracy(&bits);
+        if accuracy < DETECTION_ACCURACY {
+            return None;
+        }
+        Some(WatermarkDetection::new(&self.name, Some(&self.generator), &hex(&bits), round(accuracy)))
+    }
+}
+
+/// U and V planes as OpenCV's BGR to YUV conversion computes them, cut to
+/// a multiple of 4. `swapped` reads the pixels as BGR.
+fn chroma_planes(rgb: &RgbImage, swapped: bool) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let (width, height) = (rgb.width() / 4 * 4, rgb.height() / 4 * 4);
+    let mut u = Vec::with_capacity(height as usize);
+    let mut v = Vec::with_capacity(height as usize);
+    for y in 0..height {
+        let mut u_row = Vec::with_capacity(width as usize);
+        let mut v_row = Vec::with_capacity(width as usize);
+        for x in 0..width {
+            let [r, g, b] = rgb.get_pixel(x, y).0.map(|c| c as f64);
+            let (r, b) = if swapped { (b, r) } else { (r, b) };
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+            u_row.push(((b - luma) * 0.492 + 128.0).round().clamp(0.0, 255.0));
+            v_row.push(((r - luma) * 0.877 + 128.0).round().clamp(0.0, 255.0));
+        }
+        u.push(u_row);
+        v.push(v_row);
+    }
+    (u, v)
+}
+
+/// Approximation band of a one level Haar DWT, as pywt computes it.
+fn haar_ll(plane: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    plane.chunks_exact(2)
+        .map(|rows| (0..rows[0].len() / 2)
+            .map(|x| (rows[0][2 * x] + rows[0][2 * x + 1] + rows[1][2 * x] + rows[1][2 * x + 1]) / 2.0)
+            .collect())
+        .collect()
+}
+
+/// Orthonormal 2D DCT-II, as OpenCV's `dct`.
+fn dct_4x4(block: &[[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let basis = |k: usize, n: usize| {
+        let norm = if k == 0 { 0.5 } else { std::f64::consts::FRAC_1_SQRT_2 };
+        norm * (std::f64::consts::PI * (2 * n + 1) as f64 * k as f64 / 8.0).cos()
+    };
+    let mut out = [[0.0; 4]; 4];
+    for (k, row) in out.iter_mut().enumerate() {
+        for (l, value) in row.iter_mut().enumerate() {
+            for (y, block_row) in block.iter().enumerate() {
+                for (x, pixel) in block_row.iter().enumerate() {
+                    *value += basis(k, y) * basis(l, x) * pixel;
+                }
+            }
+        }
+    }
+    out
+}
+
+fn bits_of_u64(value: u64, len: u32) -> Vec<bool> {
+    (0..len).rev().map(|i| value >> i & 1 == 1).collect()
+}
+
+fn bits_of_bytes(bytes: &[u8]) -> Vec<bool> {
+    bytes.iter().flat_map(|b| (0..8).rev().map(move |i| b >> i & 1 == 1)).collect()
+}
+
+fn hex(bits: &[bool]) -> String {
+    bits.chunks(8)
+        .map(|byte| byte.iter().fold(0_u8, |acc, bit| acc << 1 | *bit as u8))
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+    use super::*;
+
+    #[test]
+    fn dct_and_bits_known_answers() {
+        let coefficients = dct_4x4(&[[10.0; 4]; 4]);
+        assert!((coefficients[0][0] - 40.0).abs() < 1e-9);
+        assert!(coefficients.iter().flatten().skip(1).all(|c| c.abs() < 1e-9));
+        assert_eq!(hex(&bits_of_u64(SDXL_MESSAGE, 48)), "b3ec907bb19e");
+        assert_eq!(hex(&bits_of_bytes(b"SD")), "5344");
+        assert_eq!(haar_ll(&[vec![1.0, 3.0], vec![5.0, 7.0]]), vec![vec![8.0]]);
+    }
+
+    #[test]
+    fn plain_images_carry_no_watermark() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(256, 256, Rgb([120, 130, 140])));
+        let findings = WatermarkFindings::from_image(&image, &WatermarkDecoders::default()).unwrap();
+        assert_eq!(findings.decoders.len(), 2);
+        assert!(findings.detections.is_empty());
+        assert_eq!((findings.score, findings.confidence), (0, 0));
+        let small = DynamicImage::ImageRgb8(RgbImage::new(255, 512));
+        assert!(DwtDctDecoder::new("test", vec![true]).decode(&small).is_none());
+        assert!(WatermarkFindings::from_image(&image, &WatermarkDecoders::empty()).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use quick_xml::{events::{BytesStart, Event}, Reader};
+use serde::Serialize;
+
+use crate::{actions::SOURCE_TYPE_PREFIX, rules::{GeneratorRules, RuleKind}};
+
+const GENERATIVE_EDIT: (u8, u8) = (60, 40);
+const EDITING_TOOL: (u8, u8) = (20, 10);
+/// History parameters Photoshop writes for generated edits.
+const GENERATIVE_MARKERS: [&str; 2] = ["generative fill", "generative expand"];
+
+/// An XMP property that counted towards the score.
+#[derive(Serialize)]
+#[non_exhaustive]
+pub struct XmpMarker {
+    /// Qualified name as written, ex. `xmp:CreatorTool`.
+    pub property: String,
+    pub value: String,
+    pub reason: String,
+    pub score: u8,
+    pub confidence: u8
+}
+
+/// AI-generation markers in the file's XMP packets.
+#[derive(Serialize, Default)]
+#[non_exhaustive]
+pub struct XmpFindings {
+    pub creator_tool: Option<String>,
+    /// `Iptc4xmpExt:DigitalSourceType` without the IPTC URI prefix, ex.
+    /// `trainedAlgorithmicMedia`. Scored with the IPTC block in the report's
+    /// `digital_source_type`.
+    pub digital_source_type: Option<String>,
+    /// `stEvt:softwareAgent` of the `xmpMM:History` entries.
+    pub history_agents: Vec<String>,
+    pub markers: Vec<XmpMarker>,
+    /// Sum of the markers, at most 100.
+    pub score: u8,
+    pub confidence: u8
+}
+
+impl XmpFindings {
+    /// None when the file has no XMP packet.
+    pub fn from_bytes(bytes: &[u8], rules: &GeneratorRules) -> Option<XmpFindings> {
+        let packets = find_packets(bytes);
+        if packets.is_empty() {
+            return None;
+        }
+        let properties: Vec<(String, String)> = packets.iter().flat_map(|p| parse_properties(p)).collect();
+
+        let mut findings = XmpFindings::default();
+        for (name, value) in &properties {
+            match name.as_str() {
+                "xmp:CreatorTool" => {
+                    findings.creator_tool.get_or_insert_with(|| value.clone());
+                    findings.add_tool_marker(name, value, rules);
+                },
+                "Iptc4xmpExt:DigitalSourceType" => {
+                    let source_type = value.strip_prefix(SOURCE_TYPE_PREFIX).unwrap_or(value).to_string();
+                    findings.digital_source_type.get_or_insert(source_type);
+                },
+                "stEvt:softwareAgent" if !findings.history_agents.contains(value) => {
+                    findings.history_agents.push(value.clone());
+                    findings.add_tool_marker(name, value, rules);
+                },
+                "stEvt:parameters" | "stEvt:changed" if is_generative_edit(value) => {
+                    findings.add_marker(name, value, "history records a generative edit", GENERATIVE_EDIT);
+                },
+                _ => {}
+            }
+        }
+        Some(findings)
+    }
+
+    fn add_tool_marker(&mut self, property: &str, tool: &str, rules: &GeneratorRules) {
+        match rules.find(tool) {
+            Some((RuleKind::Generated, rule)) => {
+                let reason = format!("matched generator rule '{}'", rule.name);
+                self.add_marker(property, tool, &reason, (rule.score, rule.confidence));
+            },
+            Some((RuleKind::Modified, rule)) => {
+                let reason = format!("matched editing tool rule '{}'", rule.name);
+                self.add_marker(property, tool, &reason, EDITING_TOOL);
+            },
+            None => {}
+        }
+    }
+
+    fn add_marker(&mut self, property: &str, value: &str, reason: &str, (score, confidence): (u8, u8)) {
+        self.score = self.score.saturating_add(score).min(100);
+        self.confidence = self.confidence.saturating_add(confidence).min(100);
+        self.markers.push(XmpMarker {
+            property: property.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+            score,
+            confidence
+        });
+    }
+}
+
+fn is_generative_edit(value: &str) -> bool {
+    let lower = value.to_lowercase();
+    GENERATIVE_MARKERS.iter().any(|m| lower.contains(m))
+}
+
+/// Every `<x:xmpmeta>` element in the file, wherever the container put it.
+pub(crate) fn find_packets(bytes: &[u8]) -> Vec<&[u8]> {
+    let (open, close) = (b"<x:xmpmeta".as_slice(), b"</x:xmpmeta>".as_slice());
+    let mut packets = Vec::new();
+    let mut rest = bytes;
+    while let Some(start) = find(rest, open) {
+        match find(&rest[start..], close) {
+            Some(len) => {
+                let end = start + len + close.len();
+                packets.push(&rest[start..end]);
+                rest = &rest[end..];
+            },
+            None => break
+        }
+    }
+    packets
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Flattens a packet to (qualified name, value) pairs, from attributes and
+/// from element text. Text inside `rdf:li` lists belongs to the property
+/// holding the list.
+pub(crate) fn parse_properties(packet: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(packet);
+    let mut reader = Reader::from_str(&text);
+    let mut properties = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                properties.extend(attributes(&e));
+                stack.push(String::from_utf8_lossy(e.name().as_ref()).to_string());
+            },
+            Ok(Event::Empty(e)) => properties.extend(attributes(&e)),
+            Ok(Event::End(_)) => {
+                stack.pop();
+            },
+            Ok(Event::Text(t)) => {
+                let value = match t.unescape() {
+                    Ok(v) => v.trim().to_string(),
+                    Err(_) => continue
+                };
+                let property = stack.iter().rev().find(|name| !name.starts_with("rdf:"));
+                if let (false, Some(property)) = (value.is_empty(), property) {
+                    properties.push((property.clone(), value));
+                }
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    properties
+}
+
+fn attributes(element: &BytesStart) -> Vec<(String, String)> {
+    element.attributes()
+        .filter_map(|a| a.ok())
+        .filter(|a| !a.key.as_ref().starts_with(b"xmlns") && !a.key.as_ref().starts_with(b"rdf:"))
+        .filter_map(|a| {
+            let value = a.unescape_value().ok()?.to_string();
+            Some((String::from_utf8_lossy(a.key.as_ref()).to_string(), value))
+        })
+        .collect()
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::evalresult::EvalResult;
+
+/// Tag in the `--tags` sidecar naming the tool that made a file.
+pub const GENERATOR_TAG: &str = "generator";
+
+/// Whether one of the generators found in the C2PA claims names the
+/// expected tool. Case-insensitive and lenient about versions, so
+/// `midjourney` matches `Midjourney v6.1`.
+pub fn generator_matches(expected: &str, detected: &[String]) -> bool {
+    let expected = expected.trim().to_lowercase();
+    !expected.is_empty() && detected.iter().any(|d| {
+        let d = d.to_lowercase();
+        d.contains(&expected) || (!d.is_empty() && expected.contains(&d))
+    })
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct AttributionCounts {
+    /// Files with an expected generator.
+    pub files: usize,
+    /// Of those, files where the backend reported any generator.
+    pub detected: usize,
+    /// Of those, files where a reported generator matched.
+    pub correct: usize
+}
+
+/// How often the backend named the right tool, not only the right class.
+#[derive(Serialize, Deserialize)]
+pub struct AttributionSummary {
+    #[serde(flatten)]
+    pub total: AttributionCounts,
+    /// correct / files
+    pub accuracy: f32,
+    /// correct / detected, attribution quality when C2PA data is present.
+    pub accuracy_detected: f32,
+    #[serde(default)]
+    pub by_generator: BTreeMap<String, AttributionCounts>
+}
+
+impl AttributionSummary {
+    /// None unless some file has an expected generator.
+    pub fn from(results: &[EvalResult]) -> Option<AttributionSummary> {
+        let mut total = AttributionCounts::default();
+        let mut by_generator: BTreeMap<String, AttributionCounts> = BTreeMap::new();
+        for result in results {
+            let expected = match &result.expected_generator {
+                Some(g) => g,
+                None => continue
+            };
+            let detected = !result.detected_generators.is_empty();
+            let correct = generator_matches(expected, &result.detected_generators);
+            for counts in [&mut total, by_generator.entry(expected.to_lowercase()).or_default()] {
+                counts.files += 1;
+                counts.detected += detected as usize;
+                counts.correct += correct as usize;
+            }
+        }
+        if total.files == 0 {
+            return None;
+        }
+        Some(AttributionSummary {
+            accuracy: total.correct as f32 / total.files as f32,
+            accuracy_detected: match total.detected {
+                0 => 0.0,
+                n => total.correct as f32 / n as f32
+            },
+            total,
+            by_generator
+        })
+    }
+}
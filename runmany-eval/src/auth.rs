@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Login request sent before a run, for backends behind a gateway that
+/// hands out a session cookie. String values may reference environment
+/// variables as `${NAME}` so credentials stay out of the config file, ex.
+/// `{"url": "https://gw/login", "form": {"user": "eval", "password": "${GW_PASSWORD}"}}`.
+#[derive(Deserialize, Clone)]
+pub struct AuthRequest {
+    pub url: String,
+    /// Defaults to POST.
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Sent as `application/x-www-form-urlencoded`.
+    #[serde(default)]
+    pub form: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub json: Option<Value>
+}
+
+impl AuthRequest {
+    /// Sends the request with `client`, whose cookie store keeps whatever
+    /// session the gateway sets.
+    pub fn send(&self, client: &Client) -> Result<(), String> {
+        let method = match reqwest::Method::from_bytes(self.method.as_deref().unwrap_or("POST").to_uppercase().as_bytes()) {
+            Ok(m) => m,
+            Err(_) => return Err(format!("Invalid auth method '{}'", self.method.as_deref().unwrap_or_default()))
+        };
+        let mut request = client.request(method, expand_env(&self.url)?);
+        for (name, value) in &self.headers {
+            request = request.header(name, expand_env(value)?);
+        }
+        if let Some(form) = &self.form {
+            let mut expanded = BTreeMap::new();
+            for (name, value) in form {
+                expanded.insert(name, expand_env(value)?);
+            }
+            request = request.form(&expanded);
+        }
+        if let Some(json) = &self.json {
+            request = request.json(&expand_json(json)?);
+        }
+
+        match request.send() {
+            Ok(resp) if resp.status().is_success() => {
+                println!("Authenticated against {} ({} cookies set)", self.url, resp.cookies().count());
+                Ok(())
+            },
+            Ok(resp) => Err(format!("Authentication request returned {}", resp.status())),
+            Err(e) => Err(format!("Error sending authentication request: {}", e))
+        }
+    }
+}
+
+/// Replaces `${NAME}` with the value of the environment variable NAME.
+fn expand_env(value: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break
+        };
+        let name = &rest[start + 2..end];
+        match std::env::var(name) {
+            Ok(v) => {
+                expanded.push_str(&rest[..start]);
+                expanded.push_str(&v);
+            },
+            Err(_) => return Err(format!("Environment variable {} used in auth config is not set", name))
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn expand_json(value: &Value) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(s) => Value::String(expand_env(s)?),
+        Value::Array(items) => Value::Array(items.iter().map(expand_json).collect::<Result<_, _>>()?),
+        Value::Object(fields) => {
+            let mut expanded = serde_json::Map::new();
+            for (name, field) in fields {
+                expanded.insert(name.clone(), expand_json(field)?);
+            }
+            Value::Object(expanded)
+        },
+        other => other.clone()
+    })
+}
@@ -0,0 +1,148 @@
+use std::{path::PathBuf, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}, thread, time::Instant};
+use reqwest::blocking::Client;
+use serde::Serialize;
+
+use runmany_eval::upload_file;
+
+use crate::{dataset_from_args, options::Options, write_report};
+
+const DEFAULT_ROUNDS: usize = 3;
+const DEFAULT_CONCURRENCY: &str = "1,2,4,8";
+
+/// Latency and throughput at one concurrency level. Latency figures
+/// and throughput cover successful requests only.
+#[derive(Serialize)]
+pub struct LevelStats {
+    pub concurrency: usize,
+    pub requests: usize,
+    pub errors: usize,
+    pub wall_ms: u64,
+    pub throughput_rps: f64,
+    pub mean_ms: f64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64
+}
+
+impl LevelStats {
+    fn from(concurrency: usize, mut latencies: Vec<u64>, errors: usize, wall_ms: u64) -> LevelStats {
+        latencies.sort();
+        let succeeded = latencies.len();
+        let requests = succeeded + errors;
+        let percentile = |p: f64| match succeeded {
+            0 => 0,
+            n => latencies[((n as f64 * p).ceil() as usize).clamp(1, n) - 1]
+        };
+        let mean_ms = match succeeded {
+            0 => 0.0,
+            n => latencies.iter().sum::<u64>() as f64 / n as f64
+        };
+        let throughput_rps = match wall_ms {
+            0 => 0.0,
+            ms => succeeded as f64 / (ms as f64 / 1000.0)
+        };
+        LevelStats {
+            concurrency,
+            requests,
+            errors,
+            wall_ms,
+            throughput_rps,
+            mean_ms,
+            p50_ms: percentile(0.50),
+            p90_ms: percentile(0.90),
+            p99_ms: percentile(0.99),
+            max_ms: latencies.last().copied().unwrap_or(0)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct EndpointBench {
+    pub url: String,
+    pub levels: Vec<LevelStats>
+}
+
+/// `bench <path> <url> [<url>...]`: repeatedly submits the probe images
+/// in `path` to each endpoint at each concurrency level. Verdicts are
+/// ignored, only latency and throughput are measured.
+pub fn bench(options: &Options) -> Result<Vec<EndpointBench>, String> {
+    let argv = &options.positional; // [0:bench, 1:path, 2..:urls]
+    if argv.len() < 3 {
+        return Err(String::from("bench needs a probe image path and at least one url"));
+    }
+    let rounds = options.parse_num::<usize>("rounds")?.unwrap_or(DEFAULT_ROUNDS);
+    let mut levels = Vec::new();
+    for level in options.get_or("concurrency", DEFAULT_CONCURRENCY).split(',') {
+        match level.trim().parse::<usize>() {
+            Ok(l) if l > 0 => levels.push(l),
+            _ => return Err(format!("Invalid concurrency level '{}'", level))
+        }
+    }
+
+    let client = Client::new();
+    let dataset = dataset_from_args(&argv[1], options)?;
+    let mut probes = Vec::new();
+    for entry in dataset.entries(&client).map_err(|e| e.to_string())? {
+        match entry.read(&client) {
+            Ok(buffer) => probes.push((entry.file_name, buffer)),
+            Err(e) => println!("Skipping probe {}: {}", entry.file_name, e)
+        }
+    }
+    if probes.is_empty() {
+        return Err(String::from("No probe images found"));
+    }
+    let probes = Arc::new(probes);
+
+    let mut results = Vec::new();
+    for url in &argv[2..] {
+        let mut endpoint = EndpointBench { url: url.clone(), levels: Vec::new() };
+        for &concurrency in &levels {
+            let stats = bench_level(&client, url, probes.clone(), rounds, concurrency);
+            println!(
+                "{}\tc={}\t{:.1} req/s\tp50 {} ms\tp90 {} ms\tp99 {} ms\terrors {}",
+                url, concurrency, stats.throughput_rps, stats.p50_ms, stats.p90_ms, stats.p99_ms, stats.errors
+            );
+            endpoint.levels.push(stats);
+        }
+        results.push(endpoint);
+    }
+
+    if let Some(output) = options.get("output") {
+        match serde_json::to_string(&results) {
+            Ok(json) => write_report(json, PathBuf::from(output)),
+            Err(e) => println!("Error serializing benchmark: {}", e)
+        }
+    }
+    Ok(results)
+}
+
+fn bench_level(client: &Client, url: &str, probes: Arc<Vec<(String, Vec<u8>)>>, rounds: usize, concurrency: usize) -> LevelStats {
+    let total = probes.len() * rounds;
+    let next = Arc::new(AtomicUsize::new(0));
+    let latencies = Arc::new(Mutex::new(Vec::with_capacity(total)));
+    let errors = Arc::new(AtomicUsize::new(0));
+
+    let start = Instant::now();
+    let workers: Vec<_> = (0..concurrency).map(|_| {
+        let (client, url, probes) = (client.clone(), url.to_string(), probes.clone());
+        let (next, latencies, errors) = (next.clone(), latencies.clone(), errors.clone());
+        thread::spawn(move || loop {
+            let idx = next.fetch_add(1, Ordering::SeqCst);
+            if idx >= total {
+                break;
+            }
+            let (file_name, buffer) = &probes[idx % probes.len()];
+            let request_start = Instant::now();
+            match upload_file(file_name.clone(), buffer.clone(), &client, &url, 0, 0) {
+                Ok(_) => latencies.lock().unwrap().push(request_start.elapsed().as_millis() as u64),
+                Err(_) => { errors.fetch_add(1, Ordering::SeqCst); }
+            }
+        })
+    }).collect();
+    workers.into_iter().for_each(|w| { let _ = w.join(); });
+    let wall_ms = start.elapsed().as_millis() as u64;
+
+    let latencies = std::mem::take(&mut *latencies.lock().unwrap());
+    LevelStats::from(concurrency, latencies, errors.load(Ordering::SeqCst), wall_ms)
+}
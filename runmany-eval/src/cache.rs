@@ -0,0 +1,75 @@
+use std::{path::Path, sync::Mutex};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Server responses keyed by content hash, endpoint and detector model
+/// version, so unchanged files aren't re-uploaded between runs.
+pub struct ResponseCache {
+    conn: Mutex<Connection>,
+    endpoint: String,
+    model_version: String
+}
+
+impl ResponseCache {
+    /// Fails for an empty `model_version`, answers of different models
+    /// would share entries.
+    pub fn open(path: &Path, endpoint: String, model_version: String) -> Result<ResponseCache, String> {
+        if model_version.trim().is_empty() {
+            return Err(String::from("The response cache needs the detector's model version"));
+        }
+        let conn = match Connection::open(path) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Error opening cache: {}", e))
+        };
+        let created = conn.execute(
+            "CREATE TABLE IF NOT EXISTS responses (
+                sha256 TEXT NOT NULL,
+                endpoint TEXT NOT NULL,
+                model_version TEXT NOT NULL,
+                body TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (sha256, endpoint, model_version)
+            )",
+            []
+        );
+        match created {
+            Ok(_) => Ok(ResponseCache { conn: Mutex::new(conn), endpoint, model_version }),
+            Err(e) => Err(format!("Error creating cache table: {}", e))
+        }
+    }
+
+    pub fn get(&self, sha256: &str) -> Option<String> {
+        self.conn.lock().unwrap().query_row(
+            "SELECT body FROM responses WHERE sha256 = ?1 AND endpoint = ?2 AND model_version = ?3",
+            params![sha256, self.endpoint, self.model_version],
+            |row| row.get(0)
+        ).optional().unwrap_or(None)
+    }
+
+    pub fn put(&self, sha256: &str, body: &str) {
+        let stored = self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO responses (sha256, endpoint, model_version, body, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![sha256, self.endpoint, self.model_version, body, chrono::Utc::now().to_rfc3339()]
+        );
+        if let Err(e) = stored {
+            println!("Error writing to cache: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_model_version() {
+        let path = std::env::temp_dir().join(format!("runmany-cache-{}.sqlite", std::process::id()));
+        assert!(ResponseCache::open(&path, String::from("http://localhost/upload"), String::new()).is_err());
+        let cache = ResponseCache::open(&path, String::from("http://localhost/upload"), String::from("v1")).unwrap();
+        cache.put("abc", "{}");
+        assert_eq!(cache.get("abc").as_deref(), Some("{}"));
+        let other = ResponseCache::open(&path, String::from("http://localhost/upload"), String::from("v2")).unwrap();
+        assert_eq!(other.get("abc"), None);
+        let _ = std::fs::remove_file(path);
+    }
+}
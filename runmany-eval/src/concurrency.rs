@@ -0,0 +1,192 @@
+use std::sync::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MAX_CONCURRENCY: usize = 16;
+/// Window error rate above which the adaptive limit is halved.
+const MAX_ERROR_RATE: f64 = 0.05;
+/// Window mean latency, relative to the best window seen, above which
+/// the adaptive limit is halved.
+const MAX_LATENCY_FACTOR: f64 = 2.0;
+/// Completions per adjustment at low limits, so one slow file doesn't
+/// decide the level.
+const MIN_WINDOW: usize = 4;
+
+#[derive(Clone, Copy)]
+pub enum Concurrency {
+    Fixed(usize),
+    /// AIMD: start at 1, add one per healthy window, halve on errors or
+    /// latency blowups, never exceed `max`.
+    Adaptive { max: usize }
+}
+
+impl Concurrency {
+    /// `<n>` or `auto`.
+    pub fn parse(spec: &str, max: usize) -> Result<Concurrency, String> {
+        match spec {
+            "auto" | "adaptive" => Ok(Concurrency::Adaptive { max: max.max(1) }),
+            n => match n.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Concurrency::Fixed(n)),
+                _ => Err(format!("Invalid concurrency '{}', expected a positive number or auto", spec))
+            }
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        match self {
+            Concurrency::Fixed(n) => *n,
+            Concurrency::Adaptive { max } => *max
+        }
+    }
+}
+
+/// Parallelism a run used.
+#[derive(Serialize, Deserialize)]
+pub struct ConcurrencyReport {
+    pub adaptive: bool,
+    /// Level at the end of the run, the sustainable one in adaptive mode.
+    pub level: usize,
+    pub peak: usize,
+    /// Level after each adjustment, adaptive mode only.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<usize>
+}
+
+struct LimiterState {
+    limit: usize,
+    in_flight: usize,
+    peak: usize,
+    /// (latency ms, transport or server error) since the last adjustment.
+    window: Vec<(f64, bool)>,
+    best_latency_ms: Option<f64>,
+    history: Vec<usize>
+}
+
+/// Gates workers to the current concurrency limit.
+pub(crate) struct Limiter {
+    adaptive: bool,
+    max: usize,
+    state: Mutex<LimiterState>,
+    available: Condvar
+}
+
+impl Limiter {
+    pub(crate) fn new(concurrency: Concurrency) -> Limiter {
+        let (adaptive, limit) = match concurrency {
+            Concurrency::Fixed(n) => (false, n),
+            Concurrency::Adaptive { .. } => (true, 1)
+        };
+        Limiter {
+            adaptive,
+            max: concurrency.max(),
+            state: Mutex::new(LimiterState {
+                limit,
+                in_flight: 0,
+                peak: limit,
+                window: Vec::new(),
+                best_latency_ms: None,
+                history: Vec::new()
+            }),
+            available: Condvar::new()
+        }
+    }
+
+    pub(crate) fn acquire(&self) {
+        let mut state = self.state.lock().unwrap();
+        while state.in_flight >= state.limit {
+            state = self.available.wait(state).unwrap();
+        }
+        state.in_flight += 1;
+    }
+
+    /// Gives back a slot without a measurement.
+    pub(crate) fn release_idle(&self) {
+        self.state.lock().unwrap().in_flight -= 1;
+        self.available.notify_all();
+    }
+
+    pub(crate) fn release(&self, latency_ms: f64, error: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+        if self.adaptive {
+            state.window.push((latency_ms, error));
+            if state.window.len() >= state.limit.max(MIN_WINDOW) {
+                self.adjust(&mut state);
+            }
+        }
+        drop(state);
+        self.available.notify_all();
+    }
+
+    fn adjust(&self, state: &mut LimiterState) {
+        let count = state.window.len() as f64;
+        let error_rate = state.window.iter().filter(|(_, e)| *e).count() as f64 / count;
+        let mean_ms = state.window.iter().map(|(l, _)| l).sum::<f64>() / count;
+        state.window.clear();
+
+        let best = *state.best_latency_ms.get_or_insert(mean_ms);
+        if error_rate > MAX_ERROR_RATE || mean_ms > best * MAX_LATENCY_FACTOR {
+            state.limit = (state.limit / 2).max(1);
+        } else {
+            state.best_latency_ms = Some(best.min(mean_ms));
+            state.limit = (state.limit + 1).min(self.max);
+        }
+        state.peak = state.peak.max(state.limit);
+        state.history.push(state.limit);
+        println!("Concurrency {} (window error rate {:.2}, mean {:.0} ms)", state.limit, error_rate, mean_ms);
+    }
+
+    pub(crate) fn report(&self) -> ConcurrencyReport {
+        let state = self.state.lock().unwrap();
+        ConcurrencyReport {
+            adaptive: self.adaptive,
+            level: state.limit,
+            peak: state.peak,
+            history: state.history.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completes one window of files at the current limit, `errors` of
+    /// them failing.
+    fn window(limiter: &Limiter, latency_ms: f64, errors: usize) {
+        let size = limiter.report().level.max(MIN_WINDOW);
+        for i in 0..size {
+            limiter.acquire();
+            limiter.release(latency_ms, i < errors);
+        }
+    }
+
+    #[test]
+    fn adaptive_limits_increase_additively_and_halve() {
+        let limiter = Limiter::new(Concurrency::parse("auto", 3).unwrap());
+        for _ in 0..3 {
+            window(&limiter, 100.0, 0);
+        }
+        // One failure in a window of 4 is over 5%
+        window(&limiter, 100.0, 1);
+        window(&limiter, 100.0, 0);
+        // Over twice the best mean latency of 100 ms
+        window(&limiter, 250.0, 0);
+        window(&limiter, 250.0, 0);
+        window(&limiter, 190.0, 0);
+        let report = limiter.report();
+        assert!(report.adaptive);
+        assert_eq!(report.history, [2, 3, 3, 1, 2, 1, 1, 2]);
+        assert_eq!((report.level, report.peak), (2, 3));
+    }
+
+    #[test]
+    fn fixed_limits_stay() {
+        let limiter = Limiter::new(Concurrency::parse("4", DEFAULT_MAX_CONCURRENCY).unwrap());
+        window(&limiter, 100.0, 4);
+        let report = limiter.report();
+        assert!(!report.adaptive);
+        assert_eq!((report.level, report.peak, report.history.len()), (4, 4, 0));
+        assert!(Concurrency::parse("0", 4).is_err());
+        assert_eq!(Concurrency::parse("auto", 0).unwrap().max(), 1);
+    }
+}
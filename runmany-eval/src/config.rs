@@ -0,0 +1,34 @@
+use std::path::Path;
+use runmany_eval::auth::AuthRequest;
+use serde::Deserialize;
+
+/// Evaluation run by serve mode. `args` are the same arguments the
+/// command line takes, e.g. `["fake", "http://localhost:8080/upload", "/data/fake"]`.
+#[derive(Deserialize)]
+pub struct EvaluationConfig {
+    pub name: String,
+    pub args: Vec<String>,
+    /// Cron expression, falls back to `--schedule`.
+    #[serde(default)]
+    pub schedule: Option<String>
+}
+
+#[derive(Deserialize, Default)]
+pub struct Config {
+    /// Login sent before each evaluation, see [`AuthRequest`].
+    #[serde(default)]
+    pub auth: Option<AuthRequest>,
+    #[serde(default)]
+    pub evaluations: Vec<EvaluationConfig>
+}
+
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(format!("Error reading config file: {}", e))
+    };
+    match serde_json::from_str(&content) {
+        Ok(config) => Ok(config),
+        Err(e) => Err(format!("Error parsing config file: {}", e))
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::evalresult::EvalResult;
+
+/// Verdicts and scores from submitting the same bytes several times.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Consistency {
+    pub submissions: u32,
+    /// Result code of every answered submission, in order.
+    pub verdicts: Vec<u8>,
+    pub failures: u32,
+    /// Submissions the backend answered from its own cache, which hide
+    /// any nondeterminism.
+    pub backend_cache_hits: u32,
+    /// Fraction of answered submissions that disagree with the most
+    /// common verdict.
+    pub flip_rate: f32,
+    pub score_mean: Option<f64>,
+    /// Population variance of the reported probability.
+    pub score_variance: Option<f64>
+}
+
+impl Consistency {
+    pub fn from(verdicts: Vec<u8>, scores: &[f64], failures: u32, backend_cache_hits: u32) -> Consistency {
+        let mut counts: BTreeMap<u8, usize> = BTreeMap::new();
+        verdicts.iter().for_each(|v| *counts.entry(*v).or_default() += 1);
+        let majority = counts.values().max().copied().unwrap_or(0);
+        let flip_rate = match verdicts.len() {
+            0 => 0.0,
+            n => (n - majority) as f32 / n as f32
+        };
+        let (score_mean, score_variance) = match scores.len() {
+            0 => (None, None),
+            n => {
+                let mean = scores.iter().sum::<f64>() / n as f64;
+                let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n as f64;
+                (Some(mean), Some(variance))
+            }
+        };
+        Consistency {
+            submissions: verdicts.len() as u32 + failures,
+            verdicts,
+            failures,
+            backend_cache_hits,
+            flip_rate,
+            score_mean,
+            score_variance
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConsistencySummary {
+    pub files: usize,
+    /// Files that got more than one distinct verdict.
+    pub unstable_files: usize,
+    pub mean_flip_rate: f32,
+    pub mean_score_variance: f64,
+    pub max_score_variance: f64
+}
+
+impl ConsistencySummary {
+    /// None unless the run was made with `--consistency`.
+    pub fn from(results: &[EvalResult]) -> Option<ConsistencySummary> {
+        let checked: Vec<&Consistency> = results.iter().filter_map(|r| r.consistency.as_ref()).collect();
+        if checked.is_empty() {
+            return None;
+        }
+        let files = checked.len();
+        let variances: Vec<f64> = checked.iter().filter_map(|c| c.score_variance).collect();
+        Some(ConsistencySummary {
+            files,
+            unstable_files: checked.iter().filter(|c| c.flip_rate > 0.0).count(),
+            mean_flip_rate: checked.iter().map(|c| c.flip_rate).sum::<f32>() / files as f32,
+            mean_score_variance: match variances.len() {
+                0 => 0.0,
+                n => variances.iter().sum::<f64>() / n as f64
+            },
+            max_score_variance: variances.iter().copied().fold(0.0, f64::max)
+        })
+    }
+}
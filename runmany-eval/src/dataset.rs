@@ -0,0 +1,155 @@
+use std::{fs::File, io::{BufRead, Error, ErrorKind, Read}, path::{Path, PathBuf}, sync::Arc};
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+
+use crate::{huggingface::HfDataset, objectstore::ObjectStore};
+
+/// Where the bytes of a dataset entry are fetched from.
+pub enum EntrySource {
+    Local(PathBuf),
+    Remote(String),
+    Object(Arc<ObjectStore>, String)
+}
+
+/// A single image to evaluate. `expected_result` overrides the run-wide
+/// expectation for datasets that carry their own labels.
+pub struct DatasetEntry {
+    pub file_name: String,
+    pub source: EntrySource,
+    pub expected_result: Option<u8>
+}
+
+impl DatasetEntry {
+    pub fn new(file_name: String, source: EntrySource, expected_result: Option<u8>) -> DatasetEntry {
+        DatasetEntry { file_name, source, expected_result }
+    }
+
+    pub fn read(&self, client: &Client) -> Result<Vec<u8>, Error> {
+        let mut buffer = Vec::new();
+        match &self.source {
+            EntrySource::Local(path) => {
+                File::open(path)?.read_to_end(&mut buffer)?;
+            },
+            EntrySource::Remote(url) => {
+                let mut response = match client.get(url).send().and_then(|r| r.error_for_status()) {
+                    Ok(resp) => resp,
+                    Err(e) => return Err(Error::other(e.to_string()))
+                };
+                response.read_to_end(&mut buffer)?;
+            },
+            EntrySource::Object(store, key) => {
+                buffer = store.read_object(client, key)?;
+            }
+        }
+        Ok(buffer)
+    }
+}
+
+/// One of `count` disjoint slices of a dataset, given as `index/count`
+/// with `0 <= index < count`. Membership depends only on the file name,
+/// so every machine picks the same files regardless of listing order.
+#[derive(Clone, Copy)]
+pub struct Shard {
+    pub index: u64,
+    pub count: u64
+}
+
+impl Shard {
+    pub fn parse(spec: &str) -> Result<Shard, String> {
+        let invalid = || format!("Invalid shard '{}', expected <index>/<count> with index < count", spec);
+        let (index, count) = spec.split_once('/').ok_or_else(invalid)?;
+        match (index.trim().parse::<u64>(), count.trim().parse::<u64>()) {
+            (Ok(index), Ok(count)) if index < count => Ok(Shard { index, count }),
+            _ => Err(invalid())
+        }
+    }
+
+    pub fn contains(&self, file_name: &str) -> bool {
+        let digest = Sha256::digest(file_name.as_bytes());
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(prefix) % self.count == self.index
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Dataset location given on the command line.
+pub enum Dataset {
+    Directory(PathBuf),
+    /// Newline separated file paths read from stdin, given as `-`.
+    Stdin,
+    HuggingFace(HfDataset),
+    ObjectStore(ObjectStore)
+}
+
+impl Dataset {
+    pub fn entries(self, client: &Client) -> Result<Vec<DatasetEntry>, Error> {
+        match self {
+            Dataset::Directory(path) => entries_from_dir(&path),
+            Dataset::Stdin => entries_from_lines(std::io::stdin().lock()),
+            Dataset::HuggingFace(hf) => hf.entries(client),
+            Dataset::ObjectStore(store) => store.entries(client)
+        }
+    }
+}
+
+fn entry_from_path(path: PathBuf) -> Result<DatasetEntry, Error> {
+    let file_name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(n) => n.to_string(),
+        None => return Err(Error::new(ErrorKind::InvalidData, format!("Invalid file path {}", path.display())))
+    };
+    Ok(DatasetEntry::new(file_name, EntrySource::Local(path), None))
+}
+
+fn entries_from_lines(reader: impl BufRead) -> Result<Vec<DatasetEntry>, Error> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        entries.push(entry_from_path(PathBuf::from(line))?);
+    }
+    Ok(entries)
+}
+
+fn entries_from_dir(path: &Path) -> Result<Vec<DatasetEntry>, Error> {
+    let mut entries = Vec::new();
+    for dir_entry in std::fs::read_dir(path)? {
+        entries.push(entry_from_path(dir_entry?.path())?);
+    }
+    // read_dir order is unspecified, --limit and reruns need a stable one
+    entries.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_are_parsed() {
+        let shard = Shard::parse(" 1 / 4 ").unwrap();
+        assert_eq!((shard.index, shard.count, shard.to_string()), (1, 4, String::from("1/4")));
+        for spec in ["4/4", "0/0", "1", "-1/4", "a/4", "1/b", ""] {
+            assert!(Shard::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn every_file_is_in_one_stable_shard() {
+        // The first 8 bytes of SHA-256 of the name, so shards of earlier
+        // runs stay valid
+        for (name, index) in [("a.jpg", 2), ("b.jpg", 2), ("c.png", 3), ("photo-001.jpg", 3)] {
+            let owners: Vec<u64> = (0..4).filter(|&i| Shard { index: i, count: 4 }.contains(name)).collect();
+            assert_eq!(owners, [index], "{}", name);
+        }
+        assert!(Shard::parse("0/1").unwrap().contains("anything.png"));
+    }
+}
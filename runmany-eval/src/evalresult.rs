@@ -1,15 +1,154 @@
-use serde::Serialize;
+use std::{collections::BTreeMap, fmt::Display};
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+use crate::attribution::AttributionSummary;
+use crate::concurrency::ConcurrencyReport;
+use crate::consistency::{Consistency, ConsistencySummary};
+use crate::metadata::RunMetadata;
+use crate::timing::{stage_stats, StageStats, StageTimings};
+use crate::phash::{cluster_misses, MissCluster, DEFAULT_CLUSTER_DISTANCE};
+
+/// Result code for a server that declined to decide (Unknown verdict or
+/// confidence below the threshold). 0 = failed, 1 = genuine, 2 = generated.
+pub const ABSTAINED: u8 = 3;
+
+/// Maps an expectation given on the command line or in dataset labels
+/// to a result code: 1 = genuine, 2 = generated.
+pub fn parse_expect(value: &str) -> Option<u8> {
+    match value.to_lowercase().as_str() {
+        "1" | "real" | "genuine" => Some(1),
+        "2" | "fake" | "generated" => Some(2),
+        _ => None
+    }
+}
+
+/// Why a file produced no verdict.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind")]
+pub enum FailureReason {
+    /// The file could not be read.
+    Io,
+    UnsupportedType,
+    Network,
+    Timeout,
+    /// The server answered with a non-success status.
+    ServerError { status: u16 },
+    /// The response body was not the expected JSON.
+    ParseError,
+    /// The server reported that its analysis pipeline failed.
+    AnalysisFailed,
+    /// Not uploaded because `--max-total-bytes` was reached.
+    BudgetExhausted
+}
+
+impl FailureReason {
+    pub fn from_request_error(error: &reqwest::Error) -> FailureReason {
+        if error.is_timeout() {
+            FailureReason::Timeout
+        } else if let Some(status) = error.status() {
+            FailureReason::ServerError { status: status.as_u16() }
+        } else if error.is_decode() {
+            FailureReason::ParseError
+        } else {
+            FailureReason::Network
+        }
+    }
+
+    /// Grouping key for per-reason counts.
+    pub fn key(&self) -> String {
+        match self {
+            FailureReason::Io => String::from("io"),
+            FailureReason::UnsupportedType => String::from("unsupported_type"),
+            FailureReason::Network => String::from("network"),
+            FailureReason::Timeout => String::from("timeout"),
+            FailureReason::ServerError { status } => format!("server_error_{}", status),
+            FailureReason::ParseError => String::from("parse_error"),
+            FailureReason::AnalysisFailed => String::from("analysis_failed"),
+            FailureReason::BudgetExhausted => String::from("budget_exhausted")
+        }
+    }
+}
+
+impl Display for FailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct EvalResult {
     pub expected_result: u8,
+    /// 0 for failed files, kept for consumers of older reports.
     pub actual_result: u8,
-    pub file_name: String
+    pub file_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureReason>,
+    #[serde(default)]
+    pub file_size: u64,
+    /// Image bytes sent for this file across retries and repeats.
+    #[serde(default)]
+    pub bytes_uploaded: u64,
+    /// Upload and analysis time as seen by the client.
+    #[serde(default)]
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub timing: StageTimings,
+    /// Pixel dimensions read from the image header, 0 if unknown.
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    /// Hex SHA-256 of the file, also sent as the upload's idempotency key.
+    #[serde(default)]
+    pub sha256: String,
+    /// Hex difference hash of the decoded image, see [`crate::phash::dhash`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phash: Option<String>,
+    #[serde(default)]
+    pub attempts: u32,
+    /// Whether the backend answered from its own cache.
+    #[serde(default)]
+    pub backend_cached: bool,
+    /// Whether the response came from the local `--cache` instead of an upload.
+    #[serde(default)]
+    pub cached: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// Tool expected to have made the file, from `--expect-generator` or
+    /// the `generator` tag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_generator: Option<String>,
+    /// Claim generators reported by the backend's C2PA analysis.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detected_generators: Vec<String>,
+    /// Repeat submissions made with `--consistency`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<Consistency>
 }
 
 impl EvalResult {
     pub fn new(expected_result: u8, actual_result: u8, file_name: String) -> EvalResult {
-        EvalResult { expected_result, actual_result, file_name }
+        EvalResult {
+            expected_result,
+            actual_result,
+            file_name,
+            failure: None,
+            file_size: 0,
+            bytes_uploaded: 0,
+            duration_ms: 0,
+            timing: StageTimings::default(),
+            width: 0,
+            height: 0,
+            sha256: String::new(),
+            phash: None,
+            attempts: 0,
+            backend_cached: false,
+            cached: false,
+            tags: BTreeMap::new(),
+            expected_generator: None,
+            detected_generators: Vec::new(),
+            consistency: None
+        }
     }
 }
 
@@ -17,54 +156,419 @@ pub trait Stringify {
     fn to_string(&self) -> String;
 }
 
+impl EvalResult {
+    /// Reports written before FailureReason only have `actual_result: 0`.
+    pub fn is_failure(&self) -> bool {
+        self.failure.is_some() || self.actual_result == 0
+    }
+
+    pub fn extension(&self) -> String {
+        match self.file_name.rsplit_once('.') {
+            Some((_, ext)) => ext.to_lowercase(),
+            None => String::from("none")
+        }
+    }
+
+    /// Bucket by the longer image side.
+    pub fn resolution_bucket(&self) -> &'static str {
+        match self.width.max(self.height) {
+            0 => "unknown",
+            1..512 => "<512px",
+            512..=1024 => "512-1024px",
+            _ => ">1024px"
+        }
+    }
+}
+
 impl Stringify for EvalResult {
     fn to_string(&self) -> String {
         format!("{}\t{}\t{}", self.expected_result, self.actual_result, self.file_name)
     }
 }
 
-#[derive(Serialize)]
+/// Hit/miss/fail counts over a group of results.
+#[derive(Serialize, Deserialize)]
+pub struct Breakdown {
+    pub files: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub fails: usize,
+    #[serde(default)]
+    pub abstentions: usize,
+    pub accuracy: f32
+}
+
+impl Breakdown {
+    pub fn from<'a>(results: impl Iterator<Item = &'a EvalResult>) -> Breakdown {
+        let mut files: usize = 0;
+        let mut hits: usize = 0;
+        let mut misses: usize = 0;
+        let mut fails: usize = 0;
+        let mut abstentions: usize = 0;
+        results.for_each(|result| {
+            files += 1;
+            if result.is_failure() {
+                fails += 1;
+            } else if result.actual_result == ABSTAINED {
+                abstentions += 1;
+            } else if result.actual_result == result.expected_result {
+                hits += 1;
+            } else {
+                misses += 1;
+            }
+        });
+
+        let accuracy: f32 = match files {
+            0 => 0.0,
+            _ => hits as f32 / files as f32
+        };
+        Breakdown { files, hits, misses, fails, abstentions, accuracy }
+    }
+
+    /// Fraction of files the server gave a decision on.
+    pub fn coverage(&self) -> f32 {
+        match self.files {
+            0 => 0.0,
+            _ => (self.hits + self.misses) as f32 / self.files as f32
+        }
+    }
+
+    /// Accuracy over the files the server gave a decision on.
+    pub fn accuracy_answered(&self) -> f32 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            answered => self.hits as f32 / answered as f32
+        }
+    }
+}
+
+impl Stringify for Breakdown {
+    fn to_string(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}\t{}", self.files, self.hits, self.misses, self.fails, self.abstentions, self.accuracy)
+    }
+}
+
+/// Counts over answered files, with generated as the positive class.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Confusion {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize
+}
+
+impl Confusion {
+    pub fn from(results: &[EvalResult]) -> Confusion {
+        let mut confusion = Confusion::default();
+        results.iter().for_each(|result| {
+            match (result.expected_result, result.actual_result) {
+                (2, 2) => confusion.true_positives += 1,
+                (1, 2) => confusion.false_positives += 1,
+                (1, 1) => confusion.true_negatives += 1,
+                (2, 1) => confusion.false_negatives += 1,
+                _ => {}
+            }
+        });
+        confusion
+    }
+}
+
+/// Cost-sensitive view of the confusion counts. A false positive is a
+/// genuine image flagged as generated.
+#[derive(Serialize, Deserialize)]
+pub struct CostSummary {
+    pub cost_fp: f64,
+    pub cost_fn: f64,
+    pub total_cost: f64,
+    /// 1 - total_cost / worst-case cost, so 1.0 is perfect and 0.0 means
+    /// every answered file was wrong.
+    pub weighted_score: f64
+}
+
+impl CostSummary {
+    pub fn from(confusion: &Confusion, cost_fp: f64, cost_fn: f64) -> CostSummary {
+        let total_cost = confusion.false_positives as f64 * cost_fp + confusion.false_negatives as f64 * cost_fn;
+        let genuine = (confusion.true_negatives + confusion.false_positives) as f64;
+        let generated = (confusion.true_positives + confusion.false_negatives) as f64;
+        let max_cost = genuine * cost_fp + generated * cost_fn;
+        let weighted_score = match max_cost > 0.0 {
+            true => 1.0 - total_cost / max_cost,
+            false => 0.0
+        };
+        CostSummary { cost_fp, cost_fn, total_cost, weighted_score }
+    }
+}
+
+pub const DEFAULT_OUTLIERS: usize = 5;
+
+#[derive(Serialize, Deserialize)]
+pub struct Outlier {
+    pub file_name: String,
+    pub file_size: u64,
+    pub duration_ms: u64,
+    pub expected_result: u8,
+    pub actual_result: u8
+}
+
+impl Outlier {
+    pub fn from(result: &EvalResult) -> Outlier {
+        Outlier {
+            file_name: result.file_name.clone(),
+            file_size: result.file_size,
+            duration_ms: result.duration_ms,
+            expected_result: result.expected_result,
+            actual_result: result.actual_result
+        }
+    }
+}
+
+impl Stringify for Outlier {
+    fn to_string(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}", self.duration_ms, self.file_size, self.expected_result, self.actual_result, self.file_name)
+    }
+}
+
+/// The N slowest analyses and N largest files, the usual suspects
+/// behind backend timeouts.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Outliers {
+    pub slowest: Vec<Outlier>,
+    pub largest: Vec<Outlier>
+}
+
+impl Outliers {
+    pub fn from(results: &[EvalResult], count: usize) -> Outliers {
+        let mut by_duration: Vec<&EvalResult> = results.iter().collect();
+        by_duration.sort_by_key(|r| std::cmp::Reverse(r.duration_ms));
+        let mut by_size: Vec<&EvalResult> = results.iter().collect();
+        by_size.sort_by_key(|r| std::cmp::Reverse(r.file_size));
+        Outliers {
+            slowest: by_duration.into_iter().take(count).map(Outlier::from).collect(),
+            largest: by_size.into_iter().take(count).map(Outlier::from).collect()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct EvalReport {
+    #[serde(default)]
+    pub run: Option<RunMetadata>,
     pub files_analyzed: usize,
     pub expected_result: u8,
     pub hits: usize,
     pub misses: usize,
     pub fails: usize,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub failures_by_reason: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub abstentions: usize,
+    #[serde(default)]
+    pub bytes_uploaded: u64,
+    /// Files left out after `--max-total-bytes` was reached.
+    #[serde(default)]
+    pub files_skipped: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<ConcurrencyReport>,
     pub accuracy: f32,
+    /// Fraction of files with a genuine/generated answer.
+    #[serde(default)]
+    pub coverage: f32,
+    /// Accuracy over answered files only, for selective-prediction setups.
+    #[serde(default)]
+    pub accuracy_answered: f32,
+    #[serde(default)]
+    pub confusion: Confusion,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<CostSummary>,
+    /// Keyed by `tag=value`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub accuracy_by_tag: BTreeMap<String, Breakdown>,
+    #[serde(default)]
+    pub accuracy_by_extension: BTreeMap<String, Breakdown>,
+    #[serde(default)]
+    pub accuracy_by_resolution: BTreeMap<String, Breakdown>,
+    #[serde(default)]
+    pub outliers: Outliers,
+    /// Keyed by stage: read, transfer, server, parse.
+    #[serde(default)]
+    pub timing: BTreeMap<String, StageStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencySummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<AttributionSummary>,
+    /// Near-duplicate families among the misclassified files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub miss_clusters: Vec<MissCluster>,
     pub results: Vec<EvalResult>
 }
 
 impl EvalReport {
     pub fn from(results: Vec<EvalResult>) -> EvalReport {
-        let files_analyzed = results.iter().count();
+        let files_analyzed = results.len();
         if files_analyzed == 0 {
             return EvalReport {
+                run: None,
                 files_analyzed,
                 expected_result: 0,
                 hits: 0,
                 misses: 0,
                 fails: 0,
+                failures_by_reason: BTreeMap::new(),
+                abstentions: 0,
+                bytes_uploaded: 0,
+                files_skipped: 0,
+                concurrency: None,
                 accuracy: 0.0,
-                results: results
+                coverage: 0.0,
+                accuracy_answered: 0.0,
+                confusion: Confusion::default(),
+                cost: None,
+                accuracy_by_tag: BTreeMap::new(),
+                accuracy_by_extension: BTreeMap::new(),
+                accuracy_by_resolution: BTreeMap::new(),
+                outliers: Outliers::default(),
+                timing: BTreeMap::new(),
+                consistency: None,
+                attribution: None,
+                miss_clusters: Vec::new(),
+                results
             }
         }
-        
-        let expected_result = results.first().unwrap().expected_result;
-        let mut hits: usize = 0;
-        let mut misses: usize = 0;
-        let mut fails: usize = 0;
-        results.iter().for_each(|result| {
-            if result.actual_result == 0 {
-                fails += 1;
-            } else if result.actual_result == result.expected_result {
-                hits += 1;
-            } else {
-                misses += 1;
-            }
+
+        // 0 marks a dataset with mixed per-file labels
+        let first_expected = results[0].expected_result;
+        let expected_result = match results.iter().all(|r| r.expected_result == first_expected) {
+            true => first_expected,
+            false => 0
+        };
+        let total = Breakdown::from(results.iter());
+        let mut failures_by_reason: BTreeMap<String, usize> = BTreeMap::new();
+        results.iter().filter_map(|r| r.failure.as_ref()).for_each(|reason| {
+            *failures_by_reason.entry(reason.key()).or_default() += 1;
+        });
+        let accuracy_by_tag = group_by(&results, |result| {
+            result.tags.iter().map(|(tag, value)| format!("{}={}", tag, value)).collect()
         });
-        
-        let accuracy: f32 = hits as f32 / files_analyzed as f32;
-        
-        EvalReport { files_analyzed, expected_result, hits, misses, fails, accuracy, results }
+        let accuracy_by_extension = group_by(&results, |result| vec![result.extension()]);
+        let accuracy_by_resolution = group_by(&results, |result| vec![result.resolution_bucket().to_string()]);
+        let outliers = Outliers::from(&results, DEFAULT_OUTLIERS);
+
+        EvalReport {
+            run: None,
+            files_analyzed,
+            expected_result,
+            hits: total.hits,
+            misses: total.misses,
+            fails: total.fails,
+            failures_by_reason,
+            abstentions: total.abstentions,
+            bytes_uploaded: results.iter().map(|r| r.bytes_uploaded).sum(),
+            files_skipped: 0,
+            concurrency: None,
+            accuracy: total.accuracy,
+            coverage: total.coverage(),
+            accuracy_answered: total.accuracy_answered(),
+            confusion: Confusion::from(&results),
+            cost: None,
+            accuracy_by_tag,
+            accuracy_by_extension,
+            accuracy_by_resolution,
+            outliers,
+            timing: stage_stats(&results),
+            consistency: ConsistencySummary::from(&results),
+            attribution: AttributionSummary::from(&results),
+            miss_clusters: cluster_misses(&results, DEFAULT_CLUSTER_DISTANCE),
+            results
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Builds a Breakdown for every key returned by `keys`; a result may
+/// belong to several groups.
+fn group_by(results: &[EvalResult], keys: impl Fn(&EvalResult) -> Vec<String>) -> BTreeMap<String, Breakdown> {
+    let mut groups: BTreeMap<String, Vec<&EvalResult>> = BTreeMap::new();
+    results.iter().for_each(|result| {
+        keys(result).into_iter().for_each(|key| groups.entry(key).or_default().push(result));
+    });
+    groups.into_iter()
+        .map(|(key, members)| (key, Breakdown::from(members.into_iter())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two hits and a miss of each class, an abstention of each class and
+    /// two failures, one of a report written before FailureReason.
+    fn results() -> Vec<EvalResult> {
+        let result = |expected: u8, actual: u8, file_name: &str, width: u32, height: u32| {
+            let mut result = EvalResult::new(expected, actual, file_name.to_string());
+            (result.width, result.height) = (width, height);
+            result
+        };
+        let mut failed = result(2, 0, "h.jpg", 0, 0);
+        failed.failure = Some(FailureReason::Io);
+        vec![
+            result(2, 2, "a.jpg", 2000, 1000),
+            result(2, 2, "b.PNG", 800, 600),
+            result(2, 1, "c.jpg", 300, 200),
+            result(1, 1, "d.jpg", 0, 0),
+            result(1, 2, "e.webp", 1024, 1024),
+            result(1, ABSTAINED, "f.jpg", 0, 0),
+            result(2, ABSTAINED, "g", 0, 0),
+            failed,
+            result(1, 0, "i.jpg", 0, 0)
+        ]
+    }
+
+    fn counts(breakdown: &Breakdown) -> (usize, usize, usize, usize, usize) {
+        (breakdown.files, breakdown.hits, breakdown.misses, breakdown.fails, breakdown.abstentions)
+    }
+
+    #[test]
+    fn confusion_counts_answered_files() {
+        // Abstentions and failures are in no cell
+        let confusion = Confusion::from(&results());
+        let cells = (confusion.true_positives, confusion.false_positives, confusion.false_negatives, confusion.true_negatives);
+        assert_eq!(cells, (2, 1, 1, 1));
+
+        let cost = CostSummary::from(&confusion, 1.0, 5.0);
+        assert_eq!(cost.total_cost, 6.0);
+        // Out of 2 genuine files at 1 and 3 generated at 5
+        assert!((cost.weighted_score - (1.0 - 6.0 / 17.0)).abs() < 1e-12);
+        assert_eq!(CostSummary::from(&Confusion::default(), 1.0, 1.0).weighted_score, 0.0);
+    }
+
+    #[test]
+    fn abstentions_are_counted_apart() {
+        let report = EvalReport::from(results());
+        assert_eq!((report.files_analyzed, report.hits, report.misses, report.fails, report.abstentions), (9, 3, 2, 2, 2));
+        assert_eq!(report.expected_result, 0);
+        assert_eq!(report.failures_by_reason, BTreeMap::from([(String::from("io"), 1)]));
+        assert!((report.accuracy - 3.0 / 9.0).abs() < 1e-6);
+        assert!((report.coverage - 5.0 / 9.0).abs() < 1e-6);
+        assert!((report.accuracy_answered - 3.0 / 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn results_are_sliced_by_extension_and_resolution() {
+        let report = EvalReport::from(results());
+        let extensions: Vec<(&str, _)> = report.accuracy_by_extension.iter().map(|(k, b)| (k.as_str(), counts(b))).collect();
+        assert_eq!(extensions, [("jpg", (6, 2, 1, 2, 1)), ("none", (1, 0, 0, 0, 1)), ("png", (1, 1, 0, 0, 0)), ("webp", (1, 0, 1, 0, 0))]);
+        let resolutions: Vec<(&str, _)> = report.accuracy_by_resolution.iter().map(|(k, b)| (k.as_str(), counts(b))).collect();
+        assert_eq!(resolutions, [
+            ("512-1024px", (2, 1, 1, 0, 0)),
+            ("<512px", (1, 0, 1, 0, 0)),
+            (">1024px", (1, 1, 0, 0, 0)),
+            ("unknown", (5, 1, 0, 2, 2))
+        ]);
+
+        let bucket = |width: u32, height: u32| {
+            let mut result = EvalResult::new(1, 1, String::from("x.jpg"));
+            (result.width, result.height) = (width, height);
+            result.resolution_bucket()
+        };
+        assert_eq!([bucket(511, 100), bucket(100, 512), bucket(1024, 1024), bucket(1025, 10)], ["<512px", "512-1024px", "512-1024px", ">1024px"]);
+    }
+}
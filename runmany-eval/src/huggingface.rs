@@ -0,0 +1,242 @@
+use std::{collections::HashMap, io::{Error, ErrorKind}};
+use reqwest::blocking::Client;
+use serde_json::Value;
+
+use crate::{dataset::{DatasetEntry, EntrySource}, evalresult::parse_expect};
+
+const ROWS_ENDPOINT: &str = "https://datasets-server.huggingface.co/rows";
+const PAGE_SIZE: usize = 100;
+
+/// Image dataset hosted on the Hugging Face Hub, read through the
+/// datasets-server rows API so no local checkout is needed.
+pub struct HfDataset {
+    pub repo_id: String,
+    pub config: String,
+    pub split: String,
+    pub image_column: String,
+    pub label_column: Option<String>,
+    pub label_map: HashMap<String, u8>,
+    /// Rows fetched at most. Only saves requests: `Evaluator::run` applies
+    /// `--limit` itself, after picking the rows of the shard.
+    pub limit: Option<usize>
+}
+
+impl HfDataset {
+    pub fn new(repo_id: String, config: String, split: String, image_column: String) -> HfDataset {
+        HfDataset {
+            repo_id,
+            config,
+            split,
+            image_column,
+            label_column: None,
+            label_map: HashMap::new(),
+            limit: None
+        }
+    }
+
+    pub fn entries(&self, client: &Client) -> Result<Vec<DatasetEntry>, Error> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        loop {
+            let length = match self.limit {
+                Some(limit) => PAGE_SIZE.min(limit - entries.len()),
+                None => PAGE_SIZE
+            };
+            if length == 0 {
+                break;
+            }
+            let page = self.fetch_page(client, offset, length)?;
+            let (page_entries, skipped) = self.entries_from_page(&page)?;
+            skipped.iter().for_each(|reason| println!("Skipping {}", reason));
+            let rows = page_entries.len() + skipped.len();
+            entries.extend(page_entries);
+            offset += rows;
+            let total = page["num_rows_total"].as_u64().unwrap_or(0) as usize;
+            if rows == 0 || offset >= total {
+                break;
+            }
+        }
+        Ok(entries)
+    }
+
+    /// The entries of a page of the rows API, and why rows were left out.
+    fn entries_from_page(&self, page: &Value) -> Result<(Vec<DatasetEntry>, Vec<String>), Error> {
+        let class_names = class_label_names(page, self.label_column.as_deref());
+        let rows = match page["rows"].as_array() {
+            Some(r) => r,
+            None => return Err(Error::new(ErrorKind::InvalidData, "Response has no rows"))
+        };
+        let mut entries = Vec::new();
+        let mut skipped = Vec::new();
+        for row in rows {
+            match self.entry_from_row(row, &class_names)? {
+                Ok(entry) => entries.push(entry),
+                Err(reason) => skipped.push(reason)
+            }
+        }
+        Ok((entries, skipped))
+    }
+
+    fn fetch_page(&self, client: &Client, offset: usize, length: usize) -> Result<Value, Error> {
+        let mut request = client.get(ROWS_ENDPOINT).query(&[
+            ("dataset", self.repo_id.as_str()),
+            ("config", self.config.as_str()),
+            ("split", self.split.as_str()),
+            ("offset", offset.to_string().as_str()),
+            ("length", length.to_string().as_str())
+        ]);
+        if let Ok(token) = std::env::var("HF_TOKEN") {
+            request = request.bearer_auth(token);
+        }
+        let response = match request.send().and_then(|r| r.error_for_status()) {
+            Ok(resp) => resp,
+            Err(e) => return Err(Error::other(e.to_string()))
+        };
+        match response.json::<Value>() {
+            Ok(json) => Ok(json),
+            Err(e) => Err(Error::new(ErrorKind::InvalidData, e.to_string()))
+        }
+    }
+
+    /// The entry of a row, or why it's left out when its label maps to no
+    /// expected result.
+    fn entry_from_row(&self, row: &Value, class_names: &[String]) -> Result<Result<DatasetEntry, String>, Error> {
+        let row_idx = row["row_idx"].as_u64().unwrap_or(0);
+        let src = match row["row"][&self.image_column]["src"].as_str() {
+            Some(s) => s.to_string(),
+            None => return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Row {} has no image in column '{}'", row_idx, self.image_column)
+            ))
+        };
+        let file_ext = src.split('?').next().unwrap_or("").rsplit('.').next().unwrap_or("jpg");
+        let file_name = format!("{}-{}-{}.{}", self.repo_id.replace('/', "_"), self.split, row_idx, file_ext);
+
+        let expected_result = match &self.label_column {
+            Some(column) => match self.map_label(&row["row"][column], class_names) {
+                Ok(expected) => Some(expected),
+                Err(label) => return Ok(Err(format!("row {}: unmapped label '{}'", row_idx, label)))
+            },
+            None => None
+        };
+        Ok(Ok(DatasetEntry::new(file_name, EntrySource::Remote(src), expected_result)))
+    }
+
+    /// Resolves a label cell to an expected result: explicit `--label-map`
+    /// entries win, then ClassLabel names, then the raw value itself. The
+    /// raw value when none of them maps.
+    fn map_label(&self, cell: &Value, class_names: &[String]) -> Result<u8, String> {
+        let raw = match cell {
+            Value::String(s) => s.clone(),
+            other => other.to_string()
+        };
+        if let Some(expected) = self.label_map.get(&raw) {
+            return Ok(*expected);
+        }
+        let name = match cell.as_u64() {
+            Some(idx) => class_names.get(idx as usize).cloned().unwrap_or(raw.clone()),
+            None => raw.clone()
+        };
+        match self.label_map.get(&name).copied().or(parse_expect(&name)) {
+            Some(expected) => Ok(expected),
+            None => Err(raw)
+        }
+    }
+}
+
+fn class_label_names(page: &Value, label_column: Option<&str>) -> Vec<String> {
+    let column = match label_column {
+        Some(c) => c,
+        None => return Vec::new()
+    };
+    let features = match page["features"].as_array() {
+        Some(f) => f,
+        None => return Vec::new()
+    };
+    features.iter()
+        .find(|f| f["name"] == column)
+        .and_then(|f| f["type"]["names"].as_array())
+        .map(|names| names.iter().filter_map(|n| n.as_str().map(String::from)).collect())
+        .unwrap_or_default()
+}
+
+/// Parses `--label-map 0=generated,1=genuine`.
+pub fn parse_label_map(spec: &str) -> Result<HashMap<String, u8>, String> {
+    let mut map = HashMap::new();
+    for pair in spec.split(',').filter(|p| !p.is_empty()) {
+        let (label, expect) = match pair.split_once('=') {
+            Some(p) => p,
+            None => return Err(format!("Invalid label mapping '{}'", pair))
+        };
+        match parse_expect(expect.trim()) {
+            Some(e) => map.insert(label.trim().to_string(), e),
+            None => return Err(format!("Invalid expected result '{}'", expect))
+        };
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    /// A page of the datasets-server rows API, the label a ClassLabel
+    /// of `names`.
+    fn page(names: &[&str], labels: &[Value]) -> Value {
+        let rows: Vec<Value> = labels.iter().enumerate().map(|(i, label)| json!({
+            "row_idx": i,
+            "row": {"image": {"src": format!("https://datasets-server.example/{}/image.jpg?Expires=1", i)}, "label": label},
+            "truncated_cells": []
+        })).collect();
+        json!({
+            "features": [
+                {"feature_idx": 0, "name": "image", "type": {"_type": "Image"}},
+                {"feature_idx": 1, "name": "label", "type": {"_type": "ClassLabel", "names": names}}
+            ],
+            "rows": rows,
+            "num_rows_total": labels.len(),
+            "num_rows_per_page": 100,
+            "partial": false
+        })
+    }
+
+    fn dataset(label_map: &str) -> HfDataset {
+        let mut hf = HfDataset::new(String::from("org/images"), String::from("default"), String::from("train"), String::from("image"));
+        hf.label_column = Some(String::from("label"));
+        hf.label_map = parse_label_map(label_map).unwrap();
+        hf
+    }
+
+    fn expected(hf: &HfDataset, page: &Value) -> (Vec<Option<u8>>, Vec<String>) {
+        let (entries, skipped) = hf.entries_from_page(page).unwrap();
+        (entries.iter().map(|e| e.expected_result).collect(), skipped)
+    }
+
+    #[test]
+    fn labels_map_through_the_fallbacks() {
+        // ClassLabel names, parsed as expectations
+        let labels = page(&["real", "fake"], &[json!(0), json!(1)]);
+        assert_eq!(expected(&dataset(""), &labels), (vec![Some(1), Some(2)], Vec::new()));
+        // --label-map by the raw value wins over the names
+        assert_eq!(expected(&dataset("0=generated"), &labels), (vec![Some(2), Some(2)], Vec::new()));
+        // --label-map by the name
+        let labels = page(&["human", "ai"], &[json!(0), json!(1)]);
+        assert_eq!(expected(&dataset("human=genuine,ai=generated"), &labels), (vec![Some(1), Some(2)], Vec::new()));
+        // String labels without a ClassLabel
+        let labels = page(&[], &[json!("genuine"), json!("2")]);
+        assert_eq!(expected(&dataset(""), &labels), (vec![Some(1), Some(2)], Vec::new()));
+    }
+
+    #[test]
+    fn unmapped_labels_are_skipped() {
+        let labels = page(&["human", "ai"], &[json!(0), json!(1), json!(7)]);
+        let (expected_results, skipped) = expected(&dataset("ai=generated"), &labels);
+        assert_eq!(expected_results, [Some(2)]);
+        assert_eq!(skipped, ["row 0: unmapped label '0'", "row 2: unmapped label '7'"]);
+
+        let (entries, _) = dataset("ai=generated").entries_from_page(&labels).unwrap();
+        assert_eq!(entries[0].file_name, "org_images-train-1.jpg");
+        assert!(dataset("").entries_from_page(&json!({"error": "not found"})).is_err());
+    }
+}
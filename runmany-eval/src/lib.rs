@@ -0,0 +1,465 @@
+use std::{sync::{atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, Mutex}, thread, time::{Duration, Instant}};
+use reqwest::blocking::{multipart, Client};
+use sha2::{Digest, Sha256};
+
+pub mod attribution;
+pub mod auth;
+pub mod cache;
+pub mod concurrency;
+pub mod consistency;
+pub mod dataset;
+pub mod evalresult;
+pub mod huggingface;
+pub mod metadata;
+pub mod notify;
+pub mod objectstore;
+pub mod phash;
+pub mod tags;
+pub mod timing;
+pub mod verdict;
+use crate::attribution::GENERATOR_TAG;
+use crate::auth::AuthRequest;
+use crate::cache::ResponseCache;
+use crate::concurrency::{Concurrency, Limiter};
+use crate::consistency::Consistency;
+use crate::dataset::{Dataset, DatasetEntry, Shard};
+use crate::evalresult::{EvalResult, EvalReport, FailureReason, ABSTAINED};
+use crate::tags::TagMap;
+use crate::verdict::{parse_verdict, Label, Verdict};
+
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Submits dataset entries to an upload endpoint and collects the
+/// verdicts into a report. Optional settings are public fields, ex.
+///
+/// ```no_run
+/// use runmany_eval::{dataset::Dataset, Evaluator};
+///
+/// let mut evaluator = Evaluator::new("http://localhost:8080/upload".to_string(), 2);
+/// evaluator.retries = 2;
+/// let report = evaluator.run(Dataset::Directory("/data/fake".into()));
+/// println!("{}", report.accuracy);
+/// ```
+pub struct Evaluator {
+    client: Client,
+    url: String,
+    /// Used for entries without a label of their own.
+    pub expected_result: u8,
+    pub tags: TagMap,
+    pub retries: u32,
+    pub min_confidence: f64,
+    pub cache: Option<ResponseCache>,
+    /// Times each file is submitted; above 1 the repeats are recorded
+    /// in the result's `consistency`.
+    pub consistency: u32,
+    /// Stops the run once this many image bytes have been uploaded,
+    /// retries included.
+    pub max_total_bytes: Option<u64>,
+    /// Only evaluate the entries belonging to this shard.
+    pub shard: Option<Shard>,
+    /// Evaluate at most this many entries, the first by file name for
+    /// local directories.
+    pub limit: Option<usize>,
+    pub concurrency: Concurrency,
+    /// Tool every file is expected to come from; a `generator` tag wins.
+    pub expected_generator: Option<String>,
+    uploaded: AtomicU64
+}
+
+impl Evaluator {
+    pub fn new(url: String, expected_result: u8) -> Evaluator {
+        // Keeps session cookies from `authenticate` and any the backend sets
+        let client = Client::builder().cookie_store(true).build().unwrap_or_else(|_| Client::new());
+        Evaluator {
+            client,
+            url,
+            expected_result,
+            tags: TagMap::new(),
+            retries: 0,
+            min_confidence: 0.0,
+            cache: None,
+            consistency: 1,
+            max_total_bytes: None,
+            shard: None,
+            limit: None,
+            concurrency: Concurrency::Fixed(1),
+            expected_generator: None,
+            uploaded: AtomicU64::new(0)
+        }
+    }
+
+    /// Client shared by every request of the run, cookies included.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn authenticate(&self, auth: &AuthRequest) -> Result<(), String> {
+        auth.send(&self.client)
+    }
+
+    /// The entries of the shard, then the first `limit` of them, so every
+    /// dataset kind is limited the same way.
+    fn select(&self, mut entries: Vec<DatasetEntry>) -> Vec<DatasetEntry> {
+        if let Some(shard) = &self.shard {
+            let total = entries.len();
+            entries.retain(|e| shard.contains(&e.file_name));
+            println!("Shard {} holds {} of {} files", shard, entries.len(), total);
+        }
+        if let Some(limit) = self.limit {
+            entries.truncate(limit);
+        }
+        entries
+    }
+
+    pub fn run(&self, dataset: Dataset) -> EvalReport {
+        let mut entries: Vec<DatasetEntry> = match dataset.entries(&self.client) {
+            Ok(entries) => entries,
+            Err(e) => {
+                println!("Error loading dataset: {}\n", e);
+                return EvalReport::from(Vec::new());
+            }
+        };
+        entries = self.select(entries);
+
+        let files_count = entries.len();
+        println!("Analyzing {} files", files_count);
+        let limiter = Limiter::new(self.concurrency);
+        let next = AtomicUsize::new(0);
+        let budget_reached = AtomicBool::new(false);
+        let slots: Mutex<Vec<Option<EvalResult>>> = Mutex::new((0..files_count).map(|_| None).collect());
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.max() {
+                scope.spawn(|| loop {
+                    limiter.acquire();
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    if idx >= files_count || budget_reached.load(Ordering::SeqCst) {
+                        limiter.release_idle();
+                        break;
+                    }
+                    let entry = &entries[idx];
+                    println!("({}/{}) Performing analysis on file {}", (idx + 1), files_count, entry.file_name);
+                    let result = self.evaluate(entry);
+                    let transport_error = matches!(result.failure,
+                        Some(FailureReason::Network | FailureReason::Timeout | FailureReason::ServerError { .. }));
+                    // Cache hits and skipped files say nothing about the backend
+                    if result.bytes_uploaded == 0 && !transport_error {
+                        limiter.release_idle();
+                    } else {
+                        limiter.release(result.timing.transfer_ms + result.timing.server_ms.unwrap_or(0.0), transport_error);
+                    }
+                    if result.failure == Some(FailureReason::BudgetExhausted) {
+                        budget_reached.store(true, Ordering::SeqCst);
+                        continue;
+                    }
+                    slots.lock().unwrap()[idx] = Some(result);
+                });
+            }
+        });
+
+        let results: Vec<EvalResult> = slots.into_inner().unwrap().into_iter().flatten().collect();
+        let files_skipped = files_count - results.len();
+        if files_skipped > 0 {
+            println!("Upload budget reached, skipped {} files\n", files_skipped);
+        }
+        let mut report = EvalReport::from(results);
+        report.files_skipped = files_skipped;
+        report.concurrency = Some(limiter.report());
+        report
+    }
+
+    /// Total image bytes uploaded by this evaluator so far.
+    pub fn bytes_uploaded(&self) -> u64 {
+        self.uploaded.load(Ordering::SeqCst)
+    }
+
+    /// Evaluates a single entry. Failures are recorded in the result.
+    pub fn evaluate(&self, entry: &DatasetEntry) -> EvalResult {
+        let expected = entry.expected_result.unwrap_or(self.expected_result);
+        let mut result = EvalResult::new(expected, 0, entry.file_name.clone());
+        let start = Instant::now();
+        match self.analyze_entry(entry, &mut result) {
+            Ok(verdict) => {
+                println!("Analysis of file {} returned {}, expected {}{}\n",
+                    result.file_name, verdict, expected, if result.cached { " (cached)" } else { "" });
+                result.actual_result = verdict;
+            },
+            Err(reason) => {
+                println!("Analysis of file {} failed: {}\n", result.file_name, reason);
+                result.failure = Some(reason);
+            }
+        }
+        result.duration_ms = start.elapsed().as_millis() as u64;
+        if let Some(file_tags) = self.tags.get(&result.file_name) {
+            result.tags = file_tags.clone();
+        }
+        result.expected_generator = result.tags.get(GENERATOR_TAG).cloned().or(self.expected_generator.clone());
+        result
+    }
+
+    /// Reads, uploads (or looks up) and parses one entry, filling in the
+    /// per-file details of `result` along the way.
+    fn analyze_entry(&self, entry: &DatasetEntry, result: &mut EvalResult) -> Result<u8, FailureReason> {
+        let read_start = Instant::now();
+        let buffer = match entry.read(&self.client) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("{}", e);
+                return Err(FailureReason::Io);
+            }
+        };
+        result.timing.read_ms = elapsed_ms(read_start);
+        result.file_size = buffer.len() as u64;
+        if let Ok(size) = imagesize::blob_size(&buffer) {
+            (result.width, result.height) = (size.width as u32, size.height as u32);
+        }
+        result.sha256 = hex::encode(Sha256::digest(&buffer));
+        result.phash = phash::dhash(&buffer).map(|h| format!("{:016x}", h));
+
+        let cache = self.cache.as_ref();
+        let body = match cache.and_then(|c| c.get(&result.sha256)) {
+            Some(body) => {
+                result.cached = true;
+                body
+            },
+            None => {
+                let upload = self.upload(&result.file_name, &buffer, 0, &mut result.bytes_uploaded)?;
+                result.attempts = upload.attempts;
+                result.backend_cached = upload.backend_cached;
+                result.timing.transfer_ms = upload.round_trip_ms;
+                upload.body
+            }
+        };
+        let parse_start = Instant::now();
+        let (code, verdict) = get_verdict(&body, self.min_confidence)?;
+        result.timing.parse_ms = elapsed_ms(parse_start);
+        if !result.cached {
+            result.timing.server_ms = verdict.analysis_ms.map(|ms| ms.min(result.timing.transfer_ms));
+            result.timing.transfer_ms -= result.timing.server_ms.unwrap_or(0.0);
+        }
+        result.backend_cached |= verdict.cache_hit;
+        result.detected_generators = verdict.generators.clone();
+        if let (Some(c), false) = (cache, result.cached) {
+            c.put(&result.sha256, &body);
+        }
+        if self.consistency > 1 {
+            let first = (code, &verdict, result.backend_cached);
+            result.consistency = Some(self.resubmit(&result.file_name, buffer, first, &mut result.bytes_uploaded));
+        }
+        Ok(code)
+    }
+
+    /// Uploads within the byte budget and adds what was sent to `sent`.
+    /// Every attempt, retries included, is reserved before it is sent, so
+    /// parallel workers and retries can't overshoot the budget.
+    fn upload(&self, file_name: &str, buffer: &[u8], submission: u32, sent: &mut u64) -> Result<Upload, FailureReason> {
+        let size = buffer.len() as u64;
+        let mut attempts: u64 = 0;
+        let reserve = || {
+            let reserved = self.uploaded.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |total| {
+                match self.max_total_bytes {
+                    Some(max) if total + size > max => None,
+                    _ => Some(total + size)
+                }
+            }).is_ok();
+            attempts += reserved as u64;
+            reserved
+        };
+        let upload = send_file(file_name.to_string(), buffer.to_vec(), &self.client, &self.url, self.retries, submission, reserve);
+        *sent += attempts * size;
+        upload
+    }
+
+    /// Uploads the same bytes `consistency - 1` more times, bypassing the
+    /// local and backend caches, and compares the answers with the first
+    /// one.
+    fn resubmit(&self, file_name: &str, buffer: Vec<u8>, first: (u8, &Verdict, bool), sent: &mut u64) -> Consistency {
+        let (code, first, first_cached) = first;
+        let mut verdicts = vec![code];
+        let mut scores: Vec<f64> = first.probability.into_iter().collect();
+        let mut failures: u32 = 0;
+        let mut backend_cache_hits = first_cached as u32;
+        for submission in 1..self.consistency {
+            let answer = self.upload(file_name, &buffer, submission, sent)
+                .and_then(|upload| {
+                    let backend_cached = upload.backend_cached;
+                    get_verdict(&upload.body, self.min_confidence).map(|v| (v, backend_cached))
+                });
+            match answer {
+                Ok(((code, verdict), backend_cached)) => {
+                    verdicts.push(code);
+                    scores.extend(verdict.probability);
+                    backend_cache_hits += (backend_cached || verdict.cache_hit) as u32;
+                },
+                Err(FailureReason::BudgetExhausted) => break,
+                Err(_) => failures += 1
+            }
+        }
+        Consistency::from(verdicts, &scores, failures, backend_cache_hits)
+    }
+}
+
+/// Outcome of a successful upload.
+pub struct Upload {
+    pub body: String,
+    pub attempts: u32,
+    /// Cache hit signalled through an `X-Cache` response header.
+    pub backend_cached: bool,
+    /// Send to end of body for the successful attempt.
+    pub round_trip_ms: f64
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Uploads with retries. `submission` counts repeated uploads of the same
+/// bytes, above 0 they get their own idempotency key and ask the backend
+/// not to answer from its cache.
+pub fn upload_file(file_name: String, buffer: Vec<u8>, client: &Client, url: &str, retries: u32, submission: u32) -> Result<Upload, FailureReason>{
+    send_file(file_name, buffer, client, url, retries, submission, || true)
+}
+
+/// `upload_file`, calling `reserve` before every attempt and giving up
+/// with `BudgetExhausted` when it returns false.
+fn send_file(
+    file_name: String,
+    buffer: Vec<u8>,
+    client: &Client,
+    url: &str,
+    retries: u32,
+    submission: u32,
+    mut reserve: impl FnMut() -> bool
+) -> Result<Upload, FailureReason> {
+    let file_ext = file_name.rsplit('.').next().unwrap_or_default();
+    let mime = match file_ext.to_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        _ => return Err(FailureReason::UnsupportedType)
+    };
+
+    // Stable across retries and runs, so backends that honour the key
+    // can hand back the earlier analysis instead of re-running it.
+    let idempotency_key = idempotency_key(&buffer, submission);
+    let mut attempts: u32 = 0;
+    loop {
+        if !reserve() {
+            return Err(FailureReason::BudgetExhausted);
+        }
+        attempts += 1;
+        let part = match multipart::Part::bytes(buffer.clone()).file_name(file_name.clone()).mime_str(mime) {
+            Ok(p) => p,
+            Err(_) => return Err(FailureReason::UnsupportedType)
+        };
+
+        let form = multipart::Form::new().part("image", part);
+
+        let request_start = Instant::now();
+        let mut request = client.post(url)
+            .header("Idempotency-Key", &idempotency_key)
+            .multipart(form);
+        if submission > 0 {
+            request = request.header("Cache-Control", "no-cache");
+        }
+        match request.send() {
+            Ok(resp) if resp.status().is_server_error() && attempts <= retries => {
+                println!("Server returned {}, retrying ({}/{})", resp.status(), attempts, retries);
+            },
+            Ok(resp) if !resp.status().is_success() => {
+                return Err(FailureReason::ServerError { status: resp.status().as_u16() });
+            },
+            Ok(resp) => {
+                let backend_cached = resp.headers().get("X-Cache")
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.to_uppercase().contains("HIT"))
+                    .unwrap_or(false);
+                let body = match resp.text() {
+                    Ok(b) => b,
+                    Err(e) => return Err(FailureReason::from_request_error(&e))
+                };
+                return Ok(Upload { body, attempts, backend_cached, round_trip_ms: elapsed_ms(request_start) });
+            },
+            Err(e) if attempts <= retries => {
+                println!("{}, retrying ({}/{})", e, attempts, retries);
+            },
+            Err(e) => {
+                println!("{}", e);
+                return Err(FailureReason::from_request_error(&e));
+            }
+        }
+        std::thread::sleep(RETRY_BACKOFF * attempts);
+    }
+}
+
+fn idempotency_key(buffer: &[u8], submission: u32) -> String {
+    let hash = hex::encode(Sha256::digest(buffer));
+    match submission {
+        0 => hash,
+        n => format!("{}-{}", hash, n)
+    }
+}
+
+/// Returns the result code along with the parsed response. Abstentions
+/// and answers below `min_confidence` count as [`ABSTAINED`].
+pub fn get_verdict(result_plain: &str, min_confidence: f64) -> Result<(u8, Verdict), FailureReason> {
+    let verdict = match parse_verdict(result_plain) {
+        Ok(v) => v,
+        Err(e) => {
+            println!("Error parsing response: {}", e);
+            return Err(FailureReason::ParseError);
+        }
+    };
+    let code = match verdict.label {
+        Label::Failed => return Err(FailureReason::AnalysisFailed),
+        Label::Abstained => ABSTAINED,
+        _ if verdict.confidence.is_some_and(|c| c < min_confidence) => ABSTAINED,
+        Label::Genuine => 1,
+        Label::Generated => 2
+    };
+    Ok((code, verdict))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::EntrySource;
+    use super::*;
+
+    #[test]
+    fn resubmissions_get_their_own_key() {
+        let first = idempotency_key(b"image", 0);
+        assert_eq!(first, hex::encode(Sha256::digest(b"image")));
+        assert_eq!(idempotency_key(b"image", 1), format!("{}-1", first));
+        assert_ne!(idempotency_key(b"image", 1), idempotency_key(b"image", 2));
+    }
+
+    #[test]
+    fn limits_apply_within_the_shard() {
+        let mut evaluator = Evaluator::new(String::new(), 2);
+        let names = ["a.jpg", "b.jpg", "c.png", "photo-001.jpg"];
+        let entries = || names.iter().map(|n| DatasetEntry::new(n.to_string(), EntrySource::Remote(String::new()), None)).collect();
+        evaluator.limit = Some(1);
+        assert_eq!(evaluator.select(entries()).iter().map(|e| e.file_name.as_str()).collect::<Vec<_>>(), ["a.jpg"]);
+        evaluator.shard = Some(Shard::parse("3/4").unwrap());
+        assert_eq!(evaluator.select(entries()).iter().map(|e| e.file_name.as_str()).collect::<Vec<_>>(), ["c.png"]);
+        evaluator.limit = None;
+        assert_eq!(evaluator.select(entries()).iter().map(|e| e.file_name.as_str()).collect::<Vec<_>>(), ["c.png", "photo-001.jpg"]);
+    }
+
+    #[test]
+    fn retries_stay_within_budget() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/upload", server.server_addr().to_ip().unwrap());
+        let responder = thread::spawn(move || {
+            for request in server.incoming_requests().take(2) {
+                let _ = request.respond(tiny_http::Response::empty(503));
+            }
+        });
+        let mut evaluator = Evaluator::new(url, 2);
+        evaluator.retries = 5;
+        evaluator.max_total_bytes = Some(10);
+        let mut sent = 0;
+        let upload = evaluator.upload("a.png", b"12345", 0, &mut sent);
+        assert!(matches!(upload, Err(FailureReason::BudgetExhausted)));
+        assert_eq!((sent, evaluator.bytes_uploaded()), (10, 10));
+        responder.join().unwrap();
+    }
+}
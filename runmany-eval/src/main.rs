@@ -1,114 +1,308 @@
-use std::{error::Error, fs::File, io::{ErrorKind, Read, Write}, path::PathBuf};
-use reqwest::{blocking::{multipart, Client, Response}};
-use serde_json::Value;
+use std::{fs::File, io::Write, path::PathBuf};
+use reqwest::blocking::Client;
 
-mod evalresult;
-use crate::evalresult::{EvalResult, Stringify, EvalReport};
+mod bench;
+mod config;
+mod merge;
+mod options;
+mod serve;
+use runmany_eval::Evaluator;
+use runmany_eval::auth::AuthRequest;
+use runmany_eval::cache::ResponseCache;
+use runmany_eval::concurrency::{Concurrency, DEFAULT_MAX_CONCURRENCY};
+use runmany_eval::dataset::{Dataset, Shard};
+use runmany_eval::evalresult::{parse_expect, CostSummary, Stringify, EvalReport, Outliers};
+use runmany_eval::huggingface::{parse_label_map, HfDataset};
+use runmany_eval::metadata::{dataset_hash, health_url, DetectorInfo, RunMetadata};
+use runmany_eval::notify::{format_summary, load_baseline, notify, Channel};
+use runmany_eval::objectstore::ObjectStore;
+use runmany_eval::phash::cluster_misses;
+use runmany_eval::tags::{load_tags, TagMap};
+use crate::config::{load_config, Config};
+use crate::options::Options;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let argv: Vec<String> = std::env::args().collect(); // [0:cmd, 1:expect, 2:url, 3:path, 4:output]
-    let argc = std::env::args().count();
-    if argc < 4 {
-        print_usage();
-        return Ok(());
-    }
-
-    let expect: u8 = match &argv[1].as_str() {
-        &"1" | &"real" | &"genuine" => 1,
-        &"2" | &"fake" | &"generated" => 2,
-        _ => {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(o) => o,
+        Err(e) => {
+            println!("{}\n", e);
             print_usage();
             return Ok(());
         }
     };
-    let path = PathBuf::from(&argv[3]);
-    let url: &str = &argv[2];
-    let report = run_multiple(path, expect, url);
 
-    if argc == 4 {
+    let subcommand = match options.positional.first().map(String::as_str) {
+        Some("serve") => Some(serve::serve(&options)),
+        Some("bench") => Some(bench::bench(&options).map(|_| ())),
+        Some("merge") => Some(merge::merge(&options).map(|_| ())),
+        _ => None
+    };
+    if let Some(outcome) = subcommand {
+        if let Err(e) = outcome {
+            println!("{}\n", e);
+            print_usage();
+        }
         return Ok(());
     }
 
-    let write_path = &argv[4];
-    let report_json = match serde_json::to_string(&report) {
-        Ok(j) => j,
-        Err(_) => String::from("{}")
+    let config = match options.get("config").filter(|_| hf_config_alias(&options).is_none()) {
+        Some(config_path) => load_config(&PathBuf::from(config_path)),
+        None => Ok(Config::default())
     };
-    write_report(report_json, PathBuf::from(write_path));
+    if let Err(e) = config.and_then(|config| evaluate(&options, config.auth.as_ref())) {
+        if !e.is_empty() {
+            println!("{}\n", e);
+        }
+        print_usage();
+    }
     Ok(())
 }
 
-fn print_usage() {
-    println!("Usage: runmany-eval [expect] [url] [path] [output]\n");
-    println!("expect: analysis result to expect. values:\n\t(1,genuine,real)\tgenuine image\n\t(2,generated,fake)\tgenerated image\n");
-    println!("url: image upload endpoint, ex. http://localhost:8080/upload\n");
-    println!("path: path containing images for analysis\n");
-    println!("output: path to write results to. optional");
-}
+/// Runs one evaluation described by command line style options, writes
+/// the report and sends notifications. `auth` is sent first when the
+/// backend sits behind a login. An empty error means bad usage.
+fn evaluate(options: &Options, auth: Option<&AuthRequest>) -> Result<EvalReport, String> {
+    let argv = &options.positional; // [0:expect, 1:url, 2:path, 3:output]
+    if argv.len() < 3 {
+        return Err(String::new());
+    }
 
-fn write_report(report: String, write_path: PathBuf) {
-    let mut outfile = match File::create(write_path) {
-        Ok(f) => f,
-        Err(_) => {
-            println!("Error opening file. Report:\n{}", report);
-            return;
+    // 0 takes the expectation from the dataset's own labels
+    let expect: u8 = match argv[0].as_str() {
+        "0" | "labels" => 0,
+        other => match parse_expect(other) {
+            Some(e) => e,
+            None => return Err(String::new())
         }
     };
-    match outfile.write_all(report.as_bytes()) {
-        Ok(_) => {},
-        Err(_) => {
-            println!("Error writing to file. Report:\n{}", report);
-            return;
-        }
+    let url: &str = &argv[1];
+    let dataset = dataset_from_args(&argv[2], options)?;
+    let tags = match options.get("tags") {
+        Some(tags_path) => load_tags(&PathBuf::from(tags_path))?,
+        None => TagMap::new()
+    };
+    let baseline = match options.get("baseline") {
+        Some(baseline_path) => Some(load_baseline(&PathBuf::from(baseline_path))?),
+        None => None
+    };
+    let outliers = options.parse_num::<usize>("outliers")?;
+    let cluster_distance = options.parse_num::<u32>("cluster-distance")?;
+    let retries = options.parse_num::<u32>("retries")?.unwrap_or(0);
+    let min_confidence = options.parse_num::<f64>("min-confidence")?.unwrap_or(0.0);
+    let cost_fp = options.parse_num::<f64>("cost-fp")?;
+    let cost_fn = options.parse_num::<f64>("cost-fn")?;
+    let consistency = options.parse_num::<u32>("consistency")?.unwrap_or(1);
+    let max_total_bytes = options.parse_num::<u64>("max-total-bytes")?;
+    let max_concurrency = options.parse_num::<usize>("max-concurrency")?.unwrap_or(DEFAULT_MAX_CONCURRENCY);
+    let concurrency = Concurrency::parse(options.get_or("concurrency", "1"), max_concurrency)?;
+    let shard = match options.get("shard") {
+        Some(spec) => Some(Shard::parse(spec)?),
+        None => None
+    };
+    if consistency == 0 {
+        return Err(String::from("--consistency must be at least 1"));
     }
-}
 
-fn run_multiple(path: PathBuf, expected_result: u8, url: &str) -> EvalReport {
-    let mut results: Vec<EvalResult> = Vec::new();
-    let client = Client::new();
+    let mut evaluator = Evaluator::new(url.to_string(), expect);
+    if let Some(auth) = auth {
+        evaluator.authenticate(auth)?;
+    }
+    let health = options.get("health-url").map(String::from).unwrap_or(health_url(url));
+    let mut run = RunMetadata::new(
+        url.to_string(),
+        argv[2].clone(),
+        options.args.clone(),
+        DetectorInfo::fetch(evaluator.client(), &health)
+    );
 
-    let file_paths = match std::fs::read_dir(&path) {
-        Ok(paths) => paths,
-        Err(_) => return EvalReport::from(Vec::new())
+    evaluator.tags = tags;
+    evaluator.retries = retries;
+    evaluator.min_confidence = min_confidence;
+    evaluator.consistency = consistency;
+    evaluator.max_total_bytes = max_total_bytes;
+    evaluator.shard = shard;
+    evaluator.limit = options.parse_num::<usize>("limit")?;
+    evaluator.concurrency = concurrency;
+    evaluator.expected_generator = options.get("expect-generator").map(String::from);
+    run.shard = shard.map(|s| s.to_string());
+    evaluator.cache = match (options.get("cache"), options.has("no-cache")) {
+        (Some(cache_path), false) => {
+            let model_version = options.get("model-version").map(String::from)
+                .or(run.detector.as_ref().and_then(|d| d.model_version.clone().or(d.version.clone()).or(d.git_sha.clone())))
+                .filter(|v| !v.trim().is_empty());
+            match model_version {
+                Some(version) => Some(ResponseCache::open(&PathBuf::from(cache_path), url.to_string(), version)?),
+                None => {
+                    println!("Not caching, the detector reported no model version, pass --model-version\n");
+                    None
+                }
+            }
+        },
+        _ => None
     };
 
-    let files_count = std::fs::read_dir(path).unwrap().count();
-    println!("Analyzing {} files", files_count);
-    for (idx, fpath) in file_paths.enumerate() {
-        let file_name = fpath.as_ref().unwrap().file_name().into_string().unwrap(); // of course
-        println!("({}/{}) Performing analysis on file {}", (idx + 1), files_count, file_name);
-
-        let file = match File::open(fpath.unwrap().path()) {
-            Ok(f) => f,
-            Err(e) => {
-                println!("{:?}\n", e.source());
-                results.push(
-                    EvalResult::new(expected_result, 0, file_name)
-                );
-                continue;
-            }
+    let mut report = evaluator.run(dataset);
+    if let Some(count) = outliers {
+        report.outliers = Outliers::from(&report.results, count);
+    }
+    if let Some(distance) = cluster_distance {
+        report.miss_clusters = cluster_misses(&report.results, distance);
+    }
+    if cost_fp.is_some() || cost_fn.is_some() {
+        report.cost = Some(CostSummary::from(&report.confusion, cost_fp.unwrap_or(1.0), cost_fn.unwrap_or(1.0)));
+    }
+    run.dataset_hash = dataset_hash(&report.results);
+    report.run = Some(run);
+    print_report(&report);
+
+    if argv.len() > 3 {
+        let write_path = &argv[3];
+        let report_json = match serde_json::to_string(&report) {
+            Ok(j) => j,
+            Err(_) => String::from("{}")
         };
+        write_report(report_json, PathBuf::from(write_path));
+    }
 
-        let eval = match upload_file(file_name.clone(), file, &client, url) {
-            Ok(val) => val,
-            Err(e) => {
-                println!("{:?}\n", e.source());
-                results.push(
-                    EvalResult::new(expected_result, 0, file_name)
-                );
-                continue;
+    let client = Client::new();
+    let webhooks = [
+        (Channel::Slack, options.get("notify-slack")),
+        (Channel::Teams, options.get("notify-teams"))
+    ];
+    for (channel, webhook) in webhooks {
+        if let Some(webhook) = webhook {
+            let summary = format_summary(&report, baseline.as_ref(), options.get("report-link"), channel);
+            if let Err(e) = notify(&client, webhook, summary) {
+                println!("Error sending notification: {}", e);
             }
-        };
+        }
+    }
+    Ok(report)
+}
 
-        println!("Analysis of file {} returned {}, expected {}\n", file_name, eval, expected_result);
-        results.push(
-            EvalResult::new(expected_result, eval, file_name)
+fn print_usage() {
+    println!("Usage: runmany-eval [expect] [url] [path] [output] [options]");
+    println!("       runmany-eval serve --config <file> [--listen <addr>] [--schedule <cron>] [--keep <n>]");
+    println!("       runmany-eval bench <path> <url> [<url>...] [--rounds <n>] [--concurrency <levels>] [--output <file>]");
+    println!("       runmany-eval merge <output> <report> [<report>...]\n");
+    println!("expect: analysis result to expect. values:\n\t(1,genuine,real)\tgenuine image\n\t(2,generated,fake)\tgenerated image\n\t(0,labels)\t\tuse the dataset's labels\n");
+    println!("url: image upload endpoint, ex. http://localhost:8080/upload\n");
+    println!("path: path containing images for analysis, hf://<repo id> for a Hugging Face dataset,");
+    println!("\tor s3://<bucket>/<prefix>, gs://<bucket>/<prefix> for an object store,");
+    println!("\tor - to read newline separated file paths from stdin\n");
+    println!("output: path to write results to. optional\n");
+    println!("options:");
+    println!("\t--split <name>\t\tHugging Face split, default train");
+    println!("\t--hf-config <name>\tHugging Face config, default default; --config is taken for it");
+    println!("\t\t\t\twhen it names no file");
+    println!("\t--image-column <name>\tHugging Face image column, default image");
+    println!("\t--label-column <name>\tHugging Face label column, used with expect 0");
+    println!("\t--label-map <map>\tlabel to expect mapping, ex. 0=generated,1=genuine; rows whose label");
+    println!("\t\t\t\tmaps to neither are skipped");
+    println!("\t--endpoint <url>\tS3 compatible endpoint, ex. http://localhost:9000 for MinIO");
+    println!("\t--region <name>\t\tS3 region, default $AWS_REGION or us-east-1");
+    println!("\t--limit <n>\t\tevaluate at most n files, the first by name for a directory, taken");
+    println!("\t\t\t\tfrom the shard with --shard");
+    println!("\t--tags <file>\t\tJSON sidecar assigning tags to file names, ex. {{\"a.jpg\": {{\"device\": \"pixel7\"}}}}");
+    println!("\t\t\t\ta \"generator\" tag is the tool expected in the file's C2PA claims");
+    println!("\t--expect-generator <g>\tgenerator expected for files without a generator tag, ex. midjourney");
+    println!("\t--notify-slack <url>\tpost a run summary to a Slack webhook");
+    println!("\t--notify-teams <url>\tpost a run summary to a Teams webhook");
+    println!("\t--baseline <file>\tearlier report to compare the summary against");
+    println!("\t--report-link <url>\tlink to the full report included in the summary");
+    println!("\t--outliers <n>\t\tnumber of slowest and largest files to list, default 5");
+    println!("\t--cluster-distance <b>\tperceptual hash bits two misses may differ by to share a cluster, default 10");
+    println!("\t--retries <n>\t\tretry failed uploads n times with the same idempotency key");
+    println!("\t--health-url <url>\tdetector health endpoint queried for build info, default <url host>/health");
+    println!("\t--min-confidence <c>\ttreat verdicts with confidence below c (0-1) as abstentions");
+    println!("\t--cost-fp <c>\t\tcost of flagging a genuine image as generated, default 1");
+    println!("\t--cost-fn <c>\t\tcost of missing a generated image, default 1");
+    println!("\t--cache <file>\t\tsqlite cache of responses by content hash, endpoint and model version");
+    println!("\t--no-cache\t\tignore --cache and upload every file");
+    println!("\t--model-version <v>\tmodel version cached responses are keyed on, default the one the");
+    println!("\t\t\t\thealth endpoint reports; without either nothing is cached");
+    println!("\t--config <file>\t\tJSON config whose \"auth\" request is sent before the run to obtain a");
+    println!("\t\t\t\tsession cookie, ex. {{\"auth\": {{\"url\": \"<login url>\", \"form\": {{\"password\": \"${{PASSWORD}}\"}}}}}}");
+    println!("\t--max-total-bytes <n>\tstop uploading once n image bytes have been sent");
+    println!("\t--concurrency <n>\tfiles analyzed in parallel, default 1, or auto to find the highest level");
+    println!("\t\t\t\tthe backend sustains without errors or latency blowups");
+    println!("\t--max-concurrency <n>\tupper bound for --concurrency auto, default 16");
+    println!("\t--shard <k>/<n>\t\tonly evaluate shard k (0 to n-1) of n, chosen by file name hash;");
+    println!("\t\t\t\tcombine the shard reports with merge");
+    println!("\t--consistency <n>\tsubmit each file n times and report verdict flips and score variance\n");
+    println!("serve options:");
+    println!("\t--config <file>\t\tJSON config listing evaluations, ex.");
+    println!("\t\t\t\t{{\"evaluations\": [{{\"name\": \"nightly\", \"args\": [\"fake\", \"<url>\", \"<path>\"], \"schedule\": \"0 3 * * *\"}}]}}");
+    println!("\t--listen <addr>\t\tHTTP API address, default 127.0.0.1:8090");
+    println!("\t--schedule <cron>\tschedule for evaluations without their own, ex. \"0 3 * * *\"");
+    println!("\t--keep <n>\t\tnumber of reports kept in memory, default 10");
+    println!("\tthe config's \"auth\" request is sent before every scheduled run");
+    println!("\tGET /reports lists kept reports, GET /reports/latest and GET /reports/<id> return one\n");
+    println!("bench options:");
+    println!("\t--rounds <n>\t\ttimes each probe image is submitted per level, default 3");
+    println!("\t--concurrency <levels>\tcomma separated parallelism levels, default 1,2,4,8");
+    println!("\t--output <file>\t\twrite latency and throughput results as JSON");
+    println!("object store credentials are read from the environment:");
+    println!("\ts3://\tAWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_SESSION_TOKEN");
+    println!("\tgs://\tGCS_HMAC_ACCESS_ID, GCS_HMAC_SECRET or GOOGLE_OAUTH_ACCESS_TOKEN");
+    println!("\twithout credentials the bucket is read anonymously");
+}
+
+fn dataset_from_args(path: &str, options: &Options) -> Result<Dataset, String> {
+    if path == "-" {
+        return Ok(Dataset::Stdin);
+    }
+    if let Some(repo_id) = path.strip_prefix("hf://") {
+        let mut hf = HfDataset::new(
+            repo_id.to_string(),
+            options.get("hf-config").or(hf_config_alias(options)).unwrap_or("default").to_string(),
+            options.get_or("split", "train").to_string(),
+            options.get_or("image-column", "image").to_string()
         );
+        hf.label_column = options.get("label-column").map(String::from);
+        if let Some(spec) = options.get("label-map") {
+            hf.label_map = parse_label_map(spec)?;
+        }
+        // Rows of other shards would be fetched in their place
+        if !options.has("shard") {
+            hf.limit = options.parse_num::<usize>("limit")?;
+        }
+        return Ok(Dataset::HuggingFace(hf));
+    }
+    match ObjectStore::from_url(path) {
+        Some(mut store) => {
+            store.endpoint = options.get("endpoint").map(String::from);
+            if let Some(region) = options.get("region") {
+                store.region = region.to_string();
+            }
+            Ok(Dataset::ObjectStore(store))
+        },
+        None => Ok(Dataset::Directory(PathBuf::from(path)))
     }
+}
 
-    let report = EvalReport::from(results);
-    print_report(&report);
-    report
+/// `--config` naming a Hugging Face config, as before `--hf-config`: for
+/// an hf:// path without `--hf-config`, when it isn't a file.
+fn hf_config_alias(options: &Options) -> Option<&str> {
+    let hf = options.positional.get(2).is_some_and(|p| p.starts_with("hf://"));
+    match (hf, options.get("hf-config"), options.get("config")) {
+        (true, None, Some(config)) if !PathBuf::from(config).is_file() => Some(config),
+        _ => None
+    }
+}
+
+fn write_report(report: String, write_path: PathBuf) {
+    let mut outfile = match File::create(write_path) {
+        Ok(f) => f,
+        Err(_) => {
+            println!("Error opening file. Report:\n{}", report);
+            return;
+        }
+    };
+    if outfile.write_all(report.as_bytes()).is_err() {
+        println!("Error writing to file. Report:\n{}", report);
+    }
 }
 
 fn print_report(report: &EvalReport) {
@@ -121,42 +315,83 @@ fn print_report(report: &EvalReport) {
     println!("hits:\t\t{}", report.hits);
     println!("misses:\t\t{}", report.misses);
     println!("fails:\t\t{}", report.fails);
+    println!("abstentions:\t{}", report.abstentions);
+    println!("bytes uploaded:\t{}", report.bytes_uploaded);
+    if let Some(attribution) = &report.attribution {
+        println!("attribution:\t{}/{} correct, {} with C2PA generators", attribution.total.correct, attribution.total.files, attribution.total.detected);
+        println!("attribution accuracy:\t{}", attribution.accuracy);
+        println!("attribution accuracy detected:\t{}", attribution.accuracy_detected);
+        for (generator, counts) in &attribution.by_generator {
+            println!("\t{}\t{}/{} correct, {} detected", generator, counts.correct, counts.files, counts.detected);
+        }
+    }
+    if let Some(concurrency) = &report.concurrency {
+        println!("concurrency:\t{}{} (peak {})", concurrency.level, if concurrency.adaptive { " adaptive" } else { "" }, concurrency.peak);
+    }
+    if report.files_skipped > 0 {
+        println!("skipped:\t{} (upload budget reached)", report.files_skipped);
+    }
     println!("accuracy:\t{}", report.accuracy);
+    println!("coverage:\t{}", report.coverage);
+    println!("accuracy answered:\t{}", report.accuracy_answered);
+    println!("tp/fp/tn/fn:\t{}/{}/{}/{}",
+        report.confusion.true_positives,
+        report.confusion.false_positives,
+        report.confusion.true_negatives,
+        report.confusion.false_negatives
+    );
+    if let Some(cost) = &report.cost {
+        println!("total cost:\t{}", cost.total_cost);
+        println!("weighted score:\t{}", cost.weighted_score);
+    }
+    for (reason, count) in &report.failures_by_reason {
+        println!("failed {}:\t{}", reason, count);
+    }
+    if let Some(consistency) = &report.consistency {
+        println!("\nconsistency:\t{} of {} files flipped", consistency.unstable_files, consistency.files);
+        println!("mean flip rate:\t{}", consistency.mean_flip_rate);
+        println!("mean score variance:\t{}", consistency.mean_score_variance);
+        println!("max score variance:\t{}", consistency.max_score_variance);
+        println!("\nflips\tvariance\tverdicts\tfile");
+        for result in &report.results {
+            if let Some(c) = result.consistency.as_ref().filter(|c| c.flip_rate > 0.0) {
+                let verdicts: Vec<String> = c.verdicts.iter().map(u8::to_string).collect();
+                println!("{}\t{}\t{}\t{}", c.flip_rate, c.score_variance.unwrap_or(0.0), verdicts.join(","), result.file_name);
+            }
+        }
+    }
+    let slices = [
+        ("tag", &report.accuracy_by_tag),
+        ("ext", &report.accuracy_by_extension),
+        ("size", &report.accuracy_by_resolution)
+    ];
+    for (name, slice) in slices {
+        if slice.is_empty() {
+            continue;
+        }
+        println!("\n{}\tfiles\thits\tmisses\tfails\tabstain\taccuracy", name);
+        for (key, breakdown) in slice {
+            println!("{}\t{}", key, breakdown.to_string());
+        }
+    }
+    if !report.miss_clusters.is_empty() {
+        println!("\nmiss clusters\nfiles\tphash\t\t\tmembers");
+        for cluster in &report.miss_clusters {
+            println!("{}\t{}\t{}", cluster.files.len(), cluster.phash, cluster.files.join(", "));
+        }
+    }
+    if !report.timing.is_empty() {
+        println!("\nstage\tfiles\ttotal ms\tmean ms\tmax ms");
+        for (stage, stats) in &report.timing {
+            println!("{}\t{}\t{:.1}\t{:.1}\t{:.1}", stage, stats.files, stats.total_ms, stats.mean_ms, stats.max_ms);
+        }
+    }
+    println!("\nslowest\nms\tbytes\texpect\tactual\tfile");
+    for outlier in &report.outliers.slowest {
+        println!("{}", outlier.to_string());
+    }
+    println!("\nlargest\nms\tbytes\texpect\tactual\tfile");
+    for outlier in &report.outliers.largest {
+        println!("{}", outlier.to_string());
+    }
 }
-
-fn upload_file(file_name: String, mut file: File, client: &Client, url: &str) -> Result<u8, std::io::Error>{
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-
-    let file_ext = file_name.split(".").last().unwrap();
-    let mime = match file_ext.to_lowercase().as_str() {
-        "png" => "image/png",
-        "jpg" | "jpeg" => "image/jpeg",
-        _ => return Err(std::io::Error::new(ErrorKind::InvalidData, "Invalid file type"))
-    };
-
-    let part = multipart::Part::bytes(buffer)
-        .file_name(file_name)
-        .mime_str(mime).unwrap();
-
-    let form = multipart::Form::new().part("image", part);
-
-    let server_response = match client.post(url).multipart(form).send() {
-        Ok(resp) => resp,
-        Err(e) => return Err(std::io::Error::new(ErrorKind::Other, e.to_string()))
-    };
-    Ok(get_verdict(server_response))
-}
-
-fn get_verdict(response: Response) -> u8 {
-    let result_plain = response.text().unwrap();
-    match result_plain.find("Analysis Failed") {
-        Some(_) => return 0,
-        None => {},
-    };
-
-    let json: Value = serde_json::from_str(result_plain.as_str()).unwrap();
-    let verdict = json["analysis"]["verdict"].to_string();
-
-    return if verdict.contains("Authentic") { 1 } else { 2 }
-}
\ No newline at end of file
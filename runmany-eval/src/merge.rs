@@ -0,0 +1,152 @@
+use std::{collections::HashSet, path::PathBuf};
+use runmany_eval::{evalresult::{CostSummary, EvalReport}, metadata::{dataset_hash, RunMetadata}};
+
+use crate::{options::Options, print_report, write_report};
+
+fn load_report(path: &str) -> Result<EvalReport, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(format!("Error reading report {}: {}", path, e))
+    };
+    match serde_json::from_str(&content) {
+        Ok(report) => Ok(report),
+        Err(e) => Err(format!("Error parsing report {}: {}", path, e))
+    }
+}
+
+/// `merge <output> <report>...`: combines the reports of a sharded run
+/// into one, recomputing every aggregate from the per-file results.
+pub fn merge(options: &Options) -> Result<EvalReport, String> {
+    let argv = &options.positional; // [0:merge, 1:output, 2..:reports]
+    if argv.len() < 3 {
+        return Err(String::from("merge needs an output path and at least one report"));
+    }
+
+    let mut reports = Vec::new();
+    for path in &argv[2..] {
+        let report = load_report(path)?;
+        println!("{}: {} files", path, report.files_analyzed);
+        reports.push((path.as_str(), report));
+    }
+    let merged = combine(reports)?;
+    print_report(&merged);
+
+    match serde_json::to_string(&merged) {
+        Ok(json) => write_report(json, PathBuf::from(&argv[1])),
+        Err(e) => println!("Error serializing merged report: {}", e)
+    }
+    Ok(merged)
+}
+
+/// The shard count of a run, 1 when it wasn't sharded.
+fn shard_count(run: &RunMetadata) -> &str {
+    run.shard.as_deref().and_then(|s| s.split_once('/')).map_or("1", |(_, count)| count)
+}
+
+/// Combines reports of the same run, refusing reports of other endpoints,
+/// datasets, shard counts or cost weights and files found in two reports.
+fn combine(reports: Vec<(&str, EvalReport)>) -> Result<EvalReport, String> {
+    let mut run: Option<RunMetadata> = None;
+    let mut cost = None;
+    let mut files_skipped = 0;
+    let mut file_names = HashSet::new();
+    let mut results = Vec::new();
+    for (i, (path, report)) in reports.into_iter().enumerate() {
+        if let (Some(first), Some(other)) = (&run, &report.run) {
+            if first.endpoint != other.endpoint {
+                return Err(format!("{} was run against {}, not {}", path, other.endpoint, first.endpoint));
+            }
+            if first.dataset != other.dataset {
+                return Err(format!("{} is of dataset {}, not {}", path, other.dataset, first.dataset));
+            }
+            if shard_count(first) != shard_count(other) {
+                return Err(format!("{} is one of {} shards, not {}", path, shard_count(other), shard_count(first)));
+            }
+        }
+        let weights = report.cost.as_ref().map(|c| (c.cost_fp, c.cost_fn));
+        if i > 0 && weights != cost {
+            return Err(format!("{} has other cost weights than the reports before it", path));
+        }
+        cost = weights;
+        if let Some(duplicate) = report.results.iter().find(|r| !file_names.insert(r.file_name.clone())) {
+            return Err(format!("{} is in more than one report, the last {}", duplicate.file_name, path));
+        }
+        run = run.or(report.run);
+        files_skipped += report.files_skipped;
+        results.extend(report.results);
+    }
+
+    let mut merged = EvalReport::from(results);
+    merged.files_skipped = files_skipped;
+    if let Some((cost_fp, cost_fn)) = cost {
+        merged.cost = Some(CostSummary::from(&merged.confusion, cost_fp, cost_fn));
+    }
+    // Metadata of the first shard, now describing the whole dataset
+    merged.run = run.map(|mut run| {
+        run.shard = None;
+        run.dataset_hash = dataset_hash(&merged.results);
+        run
+    });
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use runmany_eval::evalresult::EvalResult;
+    use super::*;
+
+    /// A report of shard `shard` with results for `files`, all hits.
+    fn report(endpoint: &str, shard: &str, files: &[&str], cost: Option<(f64, f64)>) -> EvalReport {
+        let results = files.iter().map(|f| EvalResult::new(2, 2, f.to_string())).collect();
+        let mut report = EvalReport::from(results);
+        let mut run = RunMetadata::new(endpoint.to_string(), String::from("hf://org/images"), Vec::new(), None);
+        run.shard = Some(shard.to_string());
+        report.run = Some(run);
+        report.cost = cost.map(|(fp, fn_)| CostSummary::from(&report.confusion, fp, fn_));
+        report
+    }
+
+    fn error(reports: Vec<(&str, EvalReport)>) -> String {
+        match combine(reports) {
+            Ok(_) => panic!("merged reports that disagree"),
+            Err(e) => e
+        }
+    }
+
+    #[test]
+    fn shards_are_combined() {
+        let merged = combine(vec![
+            ("0.json", report("http://a/upload", "0/2", &["a.jpg", "b.jpg"], Some((1.0, 5.0)))),
+            ("1.json", report("http://a/upload", "1/2", &["c.jpg"], Some((1.0, 5.0))))
+        ]).unwrap();
+        assert_eq!(merged.files_analyzed, 3);
+        assert_eq!(merged.cost.map(|c| (c.cost_fp, c.cost_fn)), Some((1.0, 5.0)));
+        let run = merged.run.unwrap();
+        assert_eq!(run.shard, None);
+        assert_eq!(run.dataset_hash, dataset_hash(&merged.results));
+    }
+
+    #[test]
+    fn reports_of_other_runs_are_refused() {
+        let shard = |endpoint: &str, shard: &str, files: &[&str]| report(endpoint, shard, files, None);
+        assert!(error(vec![("0.json", shard("http://a/upload", "0/2", &["a.jpg"])), ("1.json", shard("http://a/upload", "0/2", &["a.jpg"]))])
+            .contains("a.jpg is in more than one report"));
+        assert!(error(vec![("0.json", shard("http://a/upload", "0/2", &["a.jpg"])), ("1.json", shard("http://b/upload", "1/2", &["b.jpg"]))])
+            .contains("http://b/upload"));
+        assert!(error(vec![("0.json", shard("http://a/upload", "0/2", &["a.jpg"])), ("1.json", shard("http://a/upload", "1/3", &["b.jpg"]))])
+            .contains("one of 3 shards, not 2"));
+        let mut other = shard("http://a/upload", "1/2", &["b.jpg"]);
+        if let Some(run) = other.run.as_mut() {
+            run.dataset = String::from("./images");
+        }
+        assert!(error(vec![("0.json", shard("http://a/upload", "0/2", &["a.jpg"])), ("1.json", other)]).contains("dataset ./images"));
+        assert!(error(vec![
+            ("0.json", report("http://a/upload", "0/2", &["a.jpg"], Some((1.0, 5.0)))),
+            ("1.json", report("http://a/upload", "1/2", &["b.jpg"], Some((1.0, 1.0))))
+        ]).contains("1.json has other cost weights"));
+        assert!(error(vec![
+            ("0.json", report("http://a/upload", "0/2", &["a.jpg"], None)),
+            ("1.json", report("http://a/upload", "1/2", &["b.jpg"], Some((1.0, 1.0))))
+        ]).contains("other cost weights"));
+    }
+}
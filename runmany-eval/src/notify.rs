@@ -0,0 +1,100 @@
+use std::{collections::HashMap, path::Path};
+use reqwest::blocking::Client;
+use serde_json::json;
+
+use crate::evalresult::{EvalReport, EvalResult, ABSTAINED};
+
+const MAX_REGRESSIONS: usize = 5;
+
+#[derive(Clone, Copy)]
+pub enum Channel {
+    Slack,
+    Teams
+}
+
+pub fn load_baseline(path: &Path) -> Result<EvalReport, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(format!("Error reading baseline report: {}", e))
+    };
+    match serde_json::from_str(&content) {
+        Ok(report) => Ok(report),
+        Err(e) => Err(format!("Error parsing baseline report: {}", e))
+    }
+}
+
+fn result_name(code: u8) -> &'static str {
+    match code {
+        1 => "genuine",
+        2 => "generated",
+        ABSTAINED => "abstained",
+        _ => "failed"
+    }
+}
+
+fn is_hit(result: &EvalResult) -> bool {
+    !result.is_failure() && result.actual_result == result.expected_result
+}
+
+/// Files that were hits in the baseline and no longer are.
+fn regressions<'a>(report: &'a EvalReport, baseline: &EvalReport) -> Vec<&'a EvalResult> {
+    let baseline_hits: HashMap<&str, bool> = baseline.results.iter()
+        .map(|r| (r.file_name.as_str(), is_hit(r)))
+        .collect();
+    report.results.iter()
+        .filter(|r| !is_hit(r) && baseline_hits.get(r.file_name.as_str()) == Some(&true))
+        .collect()
+}
+
+/// Chat-formatted run summary. Slack and Teams both render the `text`
+/// field as markdown, but disagree on bold and link syntax.
+pub fn format_summary(report: &EvalReport, baseline: Option<&EvalReport>, report_link: Option<&str>, channel: Channel) -> String {
+    let bold = |text: &str| match channel {
+        Channel::Slack => format!("*{}*", text),
+        Channel::Teams => format!("**{}**", text)
+    };
+    let mut lines = Vec::new();
+
+    let mut headline = format!(
+        "{} {}/{} hits, accuracy {:.1}%",
+        bold("runmany-eval finished:"), report.hits, report.files_analyzed, report.accuracy * 100.0
+    );
+    if let Some(base) = baseline {
+        let delta = (report.accuracy - base.accuracy) * 100.0;
+        headline.push_str(&format!(" (baseline {:.1}%, {:+.1} pp)", base.accuracy * 100.0, delta));
+    }
+    lines.push(headline);
+    lines.push(format!("misses {}, fails {}, abstentions {}", report.misses, report.fails, report.abstentions));
+
+    if let Some(base) = baseline {
+        let regressed = regressions(report, base);
+        if !regressed.is_empty() {
+            lines.push(bold(&format!("Top regressions ({} total):", regressed.len())));
+            regressed.iter().take(MAX_REGRESSIONS).for_each(|r| {
+                lines.push(format!(
+                    "- {}: expected {}, got {}",
+                    r.file_name, result_name(r.expected_result), result_name(r.actual_result)
+                ));
+            });
+        }
+    }
+
+    if let Some(link) = report_link {
+        lines.push(match channel {
+            Channel::Slack => format!("<{}|Full report>", link),
+            Channel::Teams => format!("[Full report]({})", link)
+        });
+    }
+    match channel {
+        Channel::Slack => lines.join("\n"),
+        // Teams collapses single newlines
+        Channel::Teams => lines.join("\n\n")
+    }
+}
+
+pub fn notify(client: &Client, webhook: &str, text: String) -> Result<(), String> {
+    match client.post(webhook).json(&json!({ "text": text })).send().and_then(|r| r.error_for_status()) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string())
+    }
+}
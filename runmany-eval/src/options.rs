@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+/// Flags that don't take a value.
+const SWITCHES: &[&str] = &["no-cache"];
+
+/// Command line split into positional arguments and `--flag value` pairs.
+pub struct Options {
+    /// The arguments as given, kept for run metadata.
+    pub args: Vec<String>,
+    pub positional: Vec<String>,
+    flags: HashMap<String, Vec<String>>
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut positional = Vec::new();
+        let mut flags: HashMap<String, Vec<String>> = HashMap::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(n) if !n.is_empty() => n,
+                _ => {
+                    positional.push(arg.clone());
+                    continue;
+                }
+            };
+            if SWITCHES.contains(&name) {
+                flags.entry(name.to_string()).or_default().push(String::new());
+                continue;
+            }
+            match iter.next() {
+                Some(value) => flags.entry(name.to_string()).or_default().push(value.clone()),
+                None => return Err(format!("Missing value for --{}", name))
+            }
+        }
+        Ok(Options { args: args.to_vec(), positional, flags })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.flags.get(name).and_then(|v| v.last()).map(|v| v.as_str())
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.flags.contains_key(name)
+    }
+
+    pub fn get_or<'a>(&'a self, name: &str, default: &'a str) -> &'a str {
+        self.get(name).unwrap_or(default)
+    }
+
+    pub fn parse_num<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.get(name) {
+            Some(v) => match v.parse::<T>() {
+                Ok(n) => Ok(Some(n)),
+                Err(_) => Err(format!("Invalid value for --{}: {}", name, v))
+            },
+            None => Ok(None)
+        }
+    }
+}
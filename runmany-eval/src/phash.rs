@@ -0,0 +1,82 @@
+use image::imageops::{self, FilterType};
+use serde::{Deserialize, Serialize};
+
+use crate::evalresult::{EvalResult, ABSTAINED};
+
+/// Hamming distance up to which two hashes count as the same image family.
+pub const DEFAULT_CLUSTER_DISTANCE: u32 = 10;
+
+/// 64-bit difference hash: the image is shrunk to 9x8 grayscale and each
+/// bit records whether a pixel is brighter than its right neighbour.
+/// Survives recompression and resizing. None if the image can't be decoded.
+pub fn dhash(buffer: &[u8]) -> Option<u64> {
+    let image = image::load_from_memory(buffer).ok()?;
+    let small = imageops::resize(&image.to_luma8(), 9, 8, FilterType::Triangle);
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    Some(hash)
+}
+
+/// Misclassified files whose hashes are within the cluster distance of
+/// each other, directly or through other members.
+#[derive(Serialize, Deserialize)]
+pub struct MissCluster {
+    /// Hash of the first member, hex.
+    pub phash: String,
+    pub files: Vec<String>
+}
+
+/// Groups answered-but-wrong results with single linkage on the Hamming
+/// distance of their hashes. Only groups of two or more are returned,
+/// largest first.
+pub fn cluster_misses(results: &[EvalResult], max_distance: u32) -> Vec<MissCluster> {
+    let misses: Vec<(&EvalResult, u64)> = results.iter()
+        .filter(|r| !r.is_failure() && r.actual_result != ABSTAINED && r.actual_result != r.expected_result)
+        .filter_map(|r| r.phash.as_deref().and_then(|h| u64::from_str_radix(h, 16).ok()).map(|h| (r, h)))
+        .collect();
+
+    let mut parent: Vec<usize> = (0..misses.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..misses.len() {
+        for j in (i + 1)..misses.len() {
+            if (misses[i].1 ^ misses[j].1).count_ones() <= max_distance {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[b] = a;
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root: Vec<Option<usize>> = vec![None; misses.len()];
+    for i in 0..misses.len() {
+        let r = root(&mut parent, i);
+        match group_of_root[r] {
+            Some(g) => groups[g].push(i),
+            None => {
+                group_of_root[r] = Some(groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+
+    let mut clusters: Vec<MissCluster> = groups.into_iter()
+        .filter(|members| members.len() > 1)
+        .map(|members| MissCluster {
+            phash: format!("{:016x}", misses[members[0]].1),
+            files: members.iter().map(|&i| misses[i].0.file_name.clone()).collect()
+        })
+        .collect();
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.files.len()));
+    clusters
+}
@@ -0,0 +1,141 @@
+use std::{collections::VecDeque, path::PathBuf, str::FromStr, sync::{Arc, Mutex}, thread};
+use chrono::Local;
+use cron::Schedule;
+use serde::Serialize;
+use serde_json::json;
+use tiny_http::{Header, Method, Response, Server};
+
+use runmany_eval::{auth::AuthRequest, evalresult::EvalReport};
+
+use crate::{config::{load_config, EvaluationConfig}, evaluate, options::Options};
+
+const DEFAULT_LISTEN: &str = "127.0.0.1:8090";
+const DEFAULT_KEEP: usize = 10;
+
+#[derive(Serialize)]
+struct StoredReport {
+    id: u64,
+    name: String,
+    finished_at: String,
+    report: EvalReport
+}
+
+/// The last `keep` finished reports, oldest first.
+struct ReportStore {
+    next_id: u64,
+    keep: usize,
+    reports: VecDeque<StoredReport>
+}
+
+impl ReportStore {
+    fn push(&mut self, name: String, report: EvalReport) {
+        self.next_id += 1;
+        self.reports.push_back(StoredReport {
+            id: self.next_id,
+            name,
+            finished_at: Local::now().to_rfc3339(),
+            report
+        });
+        while self.reports.len() > self.keep {
+            self.reports.pop_front();
+        }
+    }
+}
+
+/// Accepts standard five-field cron expressions as well as the
+/// six/seven-field form with seconds.
+fn parse_schedule(expression: &str) -> Result<Schedule, String> {
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression),
+        _ => expression.to_string()
+    };
+    match Schedule::from_str(&expression) {
+        Ok(s) => Ok(s),
+        Err(e) => Err(format!("Invalid schedule '{}': {}", expression, e))
+    }
+}
+
+pub fn serve(options: &Options) -> Result<(), String> {
+    let config_path = match options.get("config") {
+        Some(p) => p,
+        None => return Err(String::from("serve needs --config"))
+    };
+    let config = load_config(&PathBuf::from(config_path))?;
+    let keep = options.parse_num::<usize>("keep")?.unwrap_or(DEFAULT_KEEP);
+    let store = Arc::new(Mutex::new(ReportStore { next_id: 0, keep, reports: VecDeque::new() }));
+
+    let mut scheduled = Vec::new();
+    for evaluation in config.evaluations {
+        let expression = match evaluation.schedule.as_deref().or(options.get("schedule")) {
+            Some(e) => e.to_string(),
+            None => return Err(format!("Evaluation '{}' has no schedule", evaluation.name))
+        };
+        let eval_options = Options::parse(&evaluation.args)?;
+        scheduled.push((parse_schedule(&expression)?, evaluation, eval_options));
+    }
+    for (schedule, evaluation, eval_options) in scheduled {
+        let (store, auth) = (store.clone(), config.auth.clone());
+        thread::spawn(move || run_scheduled(schedule, evaluation, eval_options, auth, store));
+    }
+
+    let listen = options.get_or("listen", DEFAULT_LISTEN);
+    let server = match Server::http(listen) {
+        Ok(s) => s,
+        Err(e) => return Err(format!("Error listening on {}: {}", listen, e))
+    };
+    println!("Serving reports on http://{}/reports", listen);
+    for request in server.incoming_requests() {
+        let (status, body) = handle(request.method(), request.url(), &store);
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+        if let Err(e) = request.respond(response) {
+            println!("Error sending response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn run_scheduled(schedule: Schedule, evaluation: EvaluationConfig, options: Options, auth: Option<AuthRequest>, store: Arc<Mutex<ReportStore>>) {
+    for next in schedule.upcoming(Local) {
+        println!("Next run of '{}' at {}", evaluation.name, next.to_rfc3339());
+        if let Ok(wait) = (next - Local::now()).to_std() {
+            thread::sleep(wait);
+        }
+        match evaluate(&options, auth.as_ref()) {
+            Ok(report) => store.lock().unwrap().push(evaluation.name.clone(), report),
+            Err(e) => println!("Scheduled run of '{}' failed: {}", evaluation.name, e)
+        }
+    }
+}
+
+fn handle(method: &Method, url: &str, store: &Mutex<ReportStore>) -> (u16, serde_json::Value) {
+    if *method != Method::Get {
+        return (405, json!({ "error": "method not allowed" }));
+    }
+    let store = store.lock().unwrap();
+    let path = url.split('?').next().unwrap_or("");
+    let stored = match path.trim_end_matches('/') {
+        "/health" => return (200, json!({ "healthy": true })),
+        "/reports" => {
+            let list: Vec<serde_json::Value> = store.reports.iter().rev().map(|r| json!({
+                "id": r.id,
+                "name": r.name,
+                "finished_at": r.finished_at,
+                "files_analyzed": r.report.files_analyzed,
+                "accuracy": r.report.accuracy
+            })).collect();
+            return (200, json!(list));
+        },
+        "/reports/latest" => store.reports.back(),
+        other => match other.strip_prefix("/reports/").and_then(|id| id.parse::<u64>().ok()) {
+            Some(id) => store.reports.iter().find(|r| r.id == id),
+            None => return (404, json!({ "error": "not found" }))
+        }
+    };
+    match stored.map(serde_json::to_value) {
+        Some(Ok(value)) => (200, value),
+        Some(Err(e)) => (500, json!({ "error": e.to_string() })),
+        None => (404, json!({ "error": "no such report" }))
+    }
+}
@@ -0,0 +1,42 @@
+use std::{collections::{BTreeMap, HashMap}, path::Path};
+use serde_json::Value;
+
+/// Tags per file name, e.g. campaign, collection date or device.
+pub type TagMap = HashMap<String, BTreeMap<String, String>>;
+
+/// Loads a sidecar file of the form
+/// `{"image1.jpg": {"campaign": "spring", "device": "pixel7"}, ...}`.
+/// Non-string tag values are stored in their JSON representation.
+pub fn load_tags(path: &Path) -> Result<TagMap, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(format!("Error reading tags file: {}", e))
+    };
+    let json: Value = match serde_json::from_str(&content) {
+        Ok(j) => j,
+        Err(e) => return Err(format!("Error parsing tags file: {}", e))
+    };
+    let files = match json.as_object() {
+        Some(f) => f,
+        None => return Err(String::from("Tags file must contain an object keyed by file name"))
+    };
+
+    let mut tag_map = TagMap::new();
+    for (file_name, tags) in files {
+        let tags = match tags.as_object() {
+            Some(t) => t,
+            None => return Err(format!("Tags for {} must be an object", file_name))
+        };
+        let tags = tags.iter()
+            .map(|(tag, value)| {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string()
+                };
+                (tag.clone(), value)
+            })
+            .collect();
+        tag_map.insert(file_name.clone(), tags);
+    }
+    Ok(tag_map)
+}
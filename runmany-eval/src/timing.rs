@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::evalresult::EvalResult;
+
+/// Where the time for one file went, in milliseconds.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct StageTimings {
+    /// Loading the file from disk, Hugging Face or the object store.
+    pub read_ms: f64,
+    /// Request and response on the wire, i.e. the round trip minus
+    /// `server_ms`. The whole round trip if the backend didn't report
+    /// its own duration.
+    pub transfer_ms: f64,
+    /// Analysis time reported by the backend in `metadata.analysis_duration`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_ms: Option<f64>,
+    pub parse_ms: f64
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StageStats {
+    pub files: usize,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64
+}
+
+impl StageStats {
+    fn from(values: &[f64]) -> StageStats {
+        let total_ms = values.iter().fold(0.0, |total, v| total + v);
+        StageStats {
+            files: values.len(),
+            total_ms,
+            mean_ms: match values.len() {
+                0 => 0.0,
+                n => total_ms / n as f64
+            },
+            max_ms: values.iter().copied().fold(0.0, f64::max)
+        }
+    }
+}
+
+/// Per stage aggregates keyed by `read`, `transfer`, `server` and `parse`.
+/// `server` only covers files whose backend reported a duration.
+pub fn stage_stats(results: &[EvalResult]) -> BTreeMap<String, StageStats> {
+    let timings: Vec<&StageTimings> = results.iter().map(|r| &r.timing).collect();
+    let stages: [(&str, Vec<f64>); 4] = [
+        ("read", timings.iter().map(|t| t.read_ms).collect()),
+        ("transfer", timings.iter().map(|t| t.transfer_ms).collect()),
+        ("server", timings.iter().filter_map(|t| t.server_ms).collect()),
+        ("parse", timings.iter().map(|t| t.parse_ms).collect())
+    ];
+    stages.into_iter()
+        .map(|(name, values)| (name.to_string(), StageStats::from(&values)))
+        .collect()
+}
@@ -0,0 +1,301 @@
+use std::fmt::Display;
+use serde_json::{Map, Value};
+
+/// What a verdict string means for scoring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Label {
+    Genuine,
+    Generated,
+    /// Unknown, uncertain or inconclusive answers.
+    Abstained,
+    /// The backend ran but its analysis pipeline failed.
+    Failed
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verdict {
+    pub label: Label,
+    /// Verdict string as sent by the backend.
+    pub verdict: String,
+    pub probability: Option<f64>,
+    /// Normalised to 0-1.
+    pub confidence: Option<f64>,
+    pub cache_hit: bool,
+    /// Backend side duration from `metadata.analysis_duration`, in ms.
+    pub analysis_ms: Option<f64>,
+    /// Claim generators found by the C2PA analyzer, ex. `Midjourney`.
+    pub generators: Vec<String>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    InvalidJson(String),
+    MissingVerdict,
+    UnrecognizedVerdict(String)
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidJson(e) => write!(f, "invalid JSON: {}", e),
+            ParseError::MissingVerdict => write!(f, "response has no verdict"),
+            ParseError::UnrecognizedVerdict(v) => write!(f, "unrecognized verdict '{}'", v)
+        }
+    }
+}
+
+/// Parses an upload response. Besides the current
+/// `{"analysis": {"verdict": ...}, "metadata": {"cache_hit": ...}}` layout
+/// this accepts the flat layout of older backends (`verdict` at the top
+/// level), responses wrapped in `result` or `data`, camelCase or
+/// capitalised keys and numbers sent as strings.
+pub fn parse_verdict(body: &str) -> Result<Verdict, ParseError> {
+    let root: Value = match serde_json::from_str(body) {
+        Ok(v) => v,
+        // Some proxies turn pipeline errors into plain text
+        Err(_) if body.to_lowercase().contains("analysis failed") => return Ok(failed("Analysis Failed")),
+        Err(e) => return Err(ParseError::InvalidJson(e.to_string()))
+    };
+    let root = match root.as_object() {
+        Some(o) => o,
+        None => return Err(ParseError::MissingVerdict)
+    };
+
+    let analysis = match find_analysis(root) {
+        Some(a) => a,
+        None => return match field(root, "error").and_then(Value::as_str) {
+            Some(error) => Ok(failed(error)),
+            None => Err(ParseError::MissingVerdict)
+        }
+    };
+    let verdict = match field(analysis, "verdict").and_then(Value::as_str) {
+        Some(v) => v.trim().to_string(),
+        None => return Err(ParseError::MissingVerdict)
+    };
+    let label = match classify(&verdict) {
+        Some(l) => l,
+        None => return Err(ParseError::UnrecognizedVerdict(verdict))
+    };
+    let metadata = [field(root, "metadata").and_then(Value::as_object), Some(root), Some(analysis)];
+    let cache_hit = metadata.iter()
+        .flatten()
+        .find_map(|o| field(o, "cache_hit").and_then(as_bool))
+        .unwrap_or(false);
+    let analysis_ms = metadata.iter()
+        .flatten()
+        .find_map(|o| field(o, "analysis_duration").and_then(as_f64));
+
+    Ok(Verdict {
+        label,
+        verdict,
+        probability: field(analysis, "probability").and_then(as_f64),
+        confidence: field(analysis, "confidence").and_then(as_fraction),
+        cache_hit,
+        analysis_ms,
+        generators: c2pa_generators(root)
+    })
+}
+
+fn failed(verdict: &str) -> Verdict {
+    Verdict {
+        label: Label::Failed,
+        verdict: verdict.to_string(),
+        probability: None,
+        confidence: None,
+        cache_hit: false,
+        analysis_ms: None,
+        generators: Vec::new()
+    }
+}
+
+/// `c2pa.data.claims[].claim_generator`, as produced by the c2pa-rust
+/// analyzer. The `data` wrapper is optional.
+fn c2pa_generators(root: &Map<String, Value>) -> Vec<String> {
+    let c2pa = match field(root, "c2pa").and_then(Value::as_object) {
+        Some(c) => c,
+        None => return Vec::new()
+    };
+    let report = field(c2pa, "data").and_then(Value::as_object).unwrap_or(c2pa);
+    let claims = match field(report, "claims").and_then(Value::as_array) {
+        Some(c) => c,
+        None => return Vec::new()
+    };
+    let mut generators: Vec<String> = Vec::new();
+    for claim in claims.iter().filter_map(Value::as_object) {
+        let names = match field(claim, "claim_generator") {
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            Some(Value::String(name)) => vec![name.as_str()],
+            _ => Vec::new()
+        };
+        for name in names.into_iter().map(str::trim).filter(|n| !n.is_empty()) {
+            if !generators.iter().any(|g| g == name) {
+                generators.push(name.to_string());
+            }
+        }
+    }
+    generators
+}
+
+/// The object holding `verdict`, searched in the layouts backends have used.
+fn find_analysis(root: &Map<String, Value>) -> Option<&Map<String, Value>> {
+    let data = field(root, "data").and_then(Value::as_object);
+    let candidates = [
+        field(root, "analysis").and_then(Value::as_object),
+        Some(root),
+        field(root, "result").and_then(Value::as_object),
+        data.and_then(|d| field(d, "analysis")).and_then(Value::as_object),
+        data
+    ];
+    candidates.into_iter().flatten().find(|o| field(o, "verdict").is_some())
+}
+
+/// Matches whole words of the verdict, so `ai` doesn't match `maintained`.
+/// A phrase matches consecutive words.
+fn classify(verdict: &str) -> Option<Label> {
+    let verdict = verdict.to_lowercase();
+    let words: Vec<&str> = verdict.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    let has = |phrases: &[&str]| phrases.iter().any(|phrase| {
+        let phrase: Vec<&str> = phrase.split(' ').collect();
+        words.windows(phrase.len()).any(|w| w == phrase.as_slice())
+    });
+    if has(&["failed", "error"]) {
+        Some(Label::Failed)
+    } else if has(&["unknown", "uncertain", "inconclusive", "no data"]) {
+        Some(Label::Abstained)
+    } else if has(&["authentic", "human", "genuine", "real"]) {
+        Some(Label::Genuine)
+    } else if has(&["ai", "generated", "fake", "synthetic"]) {
+        Some(Label::Generated)
+    } else {
+        None
+    }
+}
+
+/// Looks up a key ignoring case and `_`, so `cache_hit` matches `cacheHit`.
+fn field<'a>(object: &'a Map<String, Value>, name: &str) -> Option<&'a Value> {
+    let normalize = |k: &str| k.replace('_', "").to_lowercase();
+    let wanted = normalize(name);
+    object.iter().find(|(k, _)| normalize(k) == wanted).map(|(_, v)| v)
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().trim_end_matches('%').parse().ok(),
+        _ => None
+    }.filter(|n: &f64| n.is_finite())
+}
+
+/// A 0-1 value, or a percentage written with `%`, ex. `"90%"`. Other
+/// values out of range are dropped.
+fn as_fraction(value: &Value) -> Option<f64> {
+    let percent = value.as_str().is_some_and(|s| s.trim().ends_with('%'));
+    let n = as_f64(value)?;
+    let n = if percent { n / 100.0 } else { n };
+    (0.0..=1.0).contains(&n).then_some(n)
+}
+
+fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => s.trim().to_lowercase().parse().ok(),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parses_current_layout() {
+        let body = r#"{"analysis":{"verdict":"Likely AI Generated","probability":71.2,"confidence":0.8},"metadata":{"cache_hit":true}}"#;
+        let verdict = parse_verdict(body).unwrap();
+        assert_eq!(verdict.label, Label::Generated);
+        assert_eq!(verdict.probability, Some(71.2));
+        assert_eq!(verdict.confidence, Some(0.8));
+        assert!(verdict.cache_hit);
+        assert!(verdict.generators.is_empty());
+    }
+
+    #[test]
+    fn collects_c2pa_generators() {
+        let body = r#"{"analysis":{"verdict":"AI Generated"},"c2pa":{"analysis_type":"c2pa","data":{"claims":[
+            {"claim_generator":["Midjourney","Midjourney"]},{"claim_generator":"Adobe Firefly"},{"claim_generator":null}]}}}"#;
+        assert_eq!(parse_verdict(body).unwrap().generators, vec!["Midjourney", "Adobe Firefly"]);
+    }
+
+    #[test]
+    fn parses_historical_layouts() {
+        let bodies = [
+            r#"{"verdict":"Authentic","confidence":"0.9"}"#,
+            r#"{"result":{"Verdict":"authentic"},"cacheHit":"true"}"#,
+            r#"{"data":{"analysis":{"verdict":"LIKELY AUTHENTIC","confidence":"90%"}}}"#,
+            r#"{"data":{"verdict":"Likely Human"}}"#
+        ];
+        for body in bodies {
+            assert_eq!(parse_verdict(body).unwrap().label, Label::Genuine, "{}", body);
+        }
+        assert_eq!(parse_verdict(bodies[2]).unwrap().confidence, Some(0.9));
+        assert!(parse_verdict(bodies[1]).unwrap().cache_hit);
+    }
+
+    #[test]
+    fn matches_whole_words() {
+        assert_eq!(classify("AI-generated"), Some(Label::Generated));
+        assert_eq!(classify("Likely Real"), Some(Label::Genuine));
+        assert_eq!(classify("No data"), Some(Label::Abstained));
+        assert_eq!(classify("Maintained"), None);
+        assert_eq!(classify("Surreal"), None);
+        assert_eq!(classify("Terror"), None);
+        let body = r#"{"analysis":{"verdict":"Authentic","confidence":90}}"#;
+        assert_eq!(parse_verdict(body).unwrap().confidence, None);
+    }
+
+    #[test]
+    fn reports_failures_and_errors() {
+        assert_eq!(parse_verdict(r#"{"analysis":{"verdict":"Analysis Failed"}}"#).unwrap().label, Label::Failed);
+        assert_eq!(parse_verdict(r#"{"error":"Analysis failed"}"#).unwrap().label, Label::Failed);
+        assert_eq!(parse_verdict("Analysis Failed").unwrap().label, Label::Failed);
+        assert_eq!(parse_verdict(r#"{"analysis":{"verdict":"Maybe"}}"#), Err(ParseError::UnrecognizedVerdict(String::from("Maybe"))));
+        assert_eq!(parse_verdict(r#"{"analysis":{"verdict":null}}"#), Err(ParseError::MissingVerdict));
+        assert_eq!(parse_verdict("[1, 2]"), Err(ParseError::MissingVerdict));
+        assert!(matches!(parse_verdict("<html>502</html>"), Err(ParseError::InvalidJson(_))));
+    }
+
+    fn json_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<f64>().prop_map(|n| serde_json::json!(n)),
+            "[a-zA-Z _]{0,12}".prop_map(Value::String),
+            prop_oneof![Just("verdict"), Just("Authentic"), Just("AI Generated"), Just("Unknown")]
+                .prop_map(|s| Value::String(s.to_string()))
+        ];
+        leaf.prop_recursive(4, 32, 6, |inner| prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::vec((
+                prop_oneof![
+                    Just("analysis"), Just("verdict"), Just("Verdict"), Just("confidence"), Just("probability"),
+                    Just("metadata"), Just("cache_hit"), Just("cacheHit"), Just("data"), Just("result"), Just("error")
+                ].prop_map(String::from),
+                inner
+            ), 0..5).prop_map(|fields| Value::Object(fields.into_iter().collect()))
+        ])
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics_on_arbitrary_text(body in ".*") {
+            let _ = parse_verdict(&body);
+        }
+
+        #[test]
+        fn never_panics_on_arbitrary_json(value in json_value()) {
+            if let Ok(verdict) = parse_verdict(&value.to_string()) {
+                prop_assert!(verdict.confidence.is_none_or(f64::is_finite));
+            }
+        }
+    }
+}